@@ -0,0 +1,111 @@
+use crate::{
+	font::FontFileEntry,
+	render::{Atlas, Renderer},
+};
+use anyhow::{Context, Result};
+use std::{fs, path::PathBuf};
+
+/// Subcommand arguments for packing glyphs into a texture atlas.
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true)]
+/// Renders the characters of a text with a font and packs their SDF bitmaps
+/// into one texture.
+///
+/// Writes `atlas.png`, a grayscale image with all glyphs, and `atlas.json`
+/// with every glyph's rectangle in the image and its metrics, for renderers
+/// that draw text from a single sprite sheet instead of `.pbf` glyph blocks.
+///
+/// # Examples
+///
+/// ```bash
+/// versatiles_glyphs atlas --charset "0123456789" -o atlas/ my_font.ttf
+/// ```
+pub struct Subcommand {
+	/// Font file to render.
+	#[arg()]
+	input_file: PathBuf,
+
+	/// Characters to render. Every distinct character is packed once.
+	/// Defaults to printable ASCII.
+	#[arg(short, long, value_name = "TEXT")]
+	charset: Option<String>,
+
+	/// Directory to write `atlas.png` and `atlas.json` to.
+	#[arg(short, long, value_name = "DIR", default_value = ".")]
+	output_directory: PathBuf,
+}
+
+pub fn run(args: &Subcommand) -> Result<()> {
+	let data = fs::read(&args.input_file)
+		.with_context(|| format!("Failed to read {:?}", args.input_file))?;
+	let entry = FontFileEntry::new(data)
+		.with_context(|| format!("Failed to load font {:?}", args.input_file))?;
+
+	let charset = match &args.charset {
+		Some(charset) => charset.clone(),
+		None => (' '..='~').collect(),
+	};
+	let glyphs = Renderer::new_precise().render_glyphs_for_string(&entry.face, &charset);
+	let atlas = Atlas::pack(&glyphs);
+
+	fs::create_dir_all(&args.output_directory)
+		.with_context(|| format!("Failed to create {:?}", args.output_directory))?;
+	fs::write(args.output_directory.join("atlas.png"), atlas.to_png()?)?;
+	fs::write(args.output_directory.join("atlas.json"), atlas.to_json()?)?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::render::AtlasGlyph;
+	use tempfile::TempDir;
+
+	fn font() -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+	}
+
+	#[test]
+	fn test_run_atlas() -> Result<()> {
+		let dir = TempDir::new()?;
+		let args = Subcommand {
+			input_file: font(),
+			charset: Some(String::from("Ag Ag")),
+			output_directory: dir.path().join("atlas"),
+		};
+		run(&args)?;
+
+		let png = fs::read(dir.path().join("atlas/atlas.png"))?;
+		assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+		let json: serde_json::Value =
+			serde_json::from_slice(&fs::read(dir.path().join("atlas/atlas.json"))?)?;
+		let glyphs: Vec<AtlasGlyph> = serde_json::from_value(json["glyphs"].clone())?;
+		assert_eq!(
+			glyphs.iter().map(|g| g.id).collect::<Vec<_>>(),
+			[0x20, 0x41, 0x67]
+		);
+		assert_eq!(
+			png[16..20],
+			json["width"].as_u64().unwrap().to_be_bytes()[4..]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_atlas_default_charset() -> Result<()> {
+		let dir = TempDir::new()?;
+		let args = Subcommand {
+			input_file: font(),
+			charset: None,
+			output_directory: dir.path().to_path_buf(),
+		};
+		run(&args)?;
+
+		let json: serde_json::Value =
+			serde_json::from_slice(&fs::read(dir.path().join("atlas.json"))?)?;
+		assert_eq!(json["glyphs"].as_array().unwrap().len(), 95);
+		Ok(())
+	}
+}
@@ -1,8 +1,15 @@
-use crate::protobuf::PbfGlyphs;
+use crate::{
+	font::{encode_codeblocks, FontFileEntry},
+	protobuf::PbfGlyphs,
+};
 use anyhow::{bail, Context, Result};
 use clap::ValueEnum;
-use prost::Message;
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+	collections::BTreeSet,
+	fs,
+	io::Write,
+	path::{Path, PathBuf},
+};
 
 #[derive(Clone, Debug, ValueEnum)]
 enum Format {
@@ -28,15 +35,26 @@ enum Format {
 /// ```
 pub struct Subcommand {
 	/// Directories to scan for font files.
-	#[arg()]
-	glyph_directory: PathBuf,
+	#[arg(required_unless_present = "coverage")]
+	glyph_directory: Option<PathBuf>,
+
+	/// Instead of reading glyphs, compare the codepoint coverage of two font
+	/// files: their counts, intersection, union, and the codepoint blocks only
+	/// one of them covers.
+	#[arg(long, num_args = 2, value_names = ["FONT_A", "FONT_B"], conflicts_with = "glyph_directory")]
+	coverage: Vec<PathBuf>,
 
 	#[arg(short, long, default_value = "csv")]
 	format: Format,
 }
 
 pub fn run(args: &Subcommand, stdout: &mut (impl Write + Send + Sync + 'static)) -> Result<()> {
-	let glyph_directory = &args.glyph_directory;
+	if let [font_a, font_b] = args.coverage.as_slice() {
+		return print_coverage(font_a, font_b, stdout);
+	}
+	let Some(glyph_directory) = &args.glyph_directory else {
+		bail!("Either a glyph directory or --coverage is required");
+	};
 
 	if !glyph_directory.exists() {
 		bail!("Directory does not exist: {:?}", glyph_directory);
@@ -72,7 +90,7 @@ pub fn run(args: &Subcommand, stdout: &mut (impl Write + Send + Sync + 'static))
 			Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
 			Err(e) => return Err(e).with_context(|| format!("Failed to read {filename:?}")),
 		};
-		let mut glyphs = PbfGlyphs::decode(buf.as_slice())
+		let mut glyphs = PbfGlyphs::from_slice(&buf)
 			.with_context(|| format!("Failed to decode {filename:?}"))?
 			.into_glyphs();
 
@@ -97,6 +115,31 @@ pub fn run(args: &Subcommand, stdout: &mut (impl Write + Send + Sync + 'static))
 	Ok(())
 }
 
+/// Prints the codepoint counts of the fonts `font_a` and `font_b`, their
+/// intersection and union, and the codepoints only one of them covers as
+/// codeblocks (see [`encode_codeblocks`]).
+fn print_coverage(font_a: &Path, font_b: &Path, stdout: &mut impl Write) -> Result<()> {
+	let codepoints = |path: &Path| -> Result<BTreeSet<u32>> {
+		let data = fs::read(path).with_context(|| format!("Failed to read {path:?}"))?;
+		let entry =
+			FontFileEntry::new(data).with_context(|| format!("Failed to load font {path:?}"))?;
+		Ok(entry.metadata.codepoints.iter().copied().collect())
+	};
+	let a = codepoints(font_a)?;
+	let b = codepoints(font_b)?;
+	let only = |x: &BTreeSet<u32>, y: &BTreeSet<u32>| {
+		encode_codeblocks(&x.difference(y).copied().collect::<Vec<_>>())
+	};
+
+	writeln!(stdout, "A: {} codepoints in {font_a:?}", a.len())?;
+	writeln!(stdout, "B: {} codepoints in {font_b:?}", b.len())?;
+	writeln!(stdout, "intersection: {}", a.intersection(&b).count())?;
+	writeln!(stdout, "union: {}", a.union(&b).count())?;
+	writeln!(stdout, "only in A: {}", only(&a, &b))?;
+	writeln!(stdout, "only in B: {}", only(&b, &a))?;
+	Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -131,7 +174,8 @@ mod tests {
 
 		// 2) Run debug::run against the sparse output.
 		let args = Subcommand {
-			glyph_directory: glyph_dir,
+			glyph_directory: Some(glyph_dir),
+			coverage: vec![],
 			format: Format::Csv,
 		};
 		let mut stdout: Vec<u8> = Vec::new();
@@ -155,7 +199,8 @@ mod tests {
 	#[test]
 	fn test_debug_run_missing_directory_errors() {
 		let args = Subcommand {
-			glyph_directory: PathBuf::from("/nonexistent/path/that/should/not/exist"),
+			glyph_directory: Some(PathBuf::from("/nonexistent/path/that/should/not/exist")),
+			coverage: vec![],
 			format: Format::Csv,
 		};
 		let mut stdout: Vec<u8> = Vec::new();
@@ -176,7 +221,8 @@ mod tests {
 		writer.finish()?;
 
 		let args = Subcommand {
-			glyph_directory: temp.path().join("fira_sans_regular"),
+			glyph_directory: Some(temp.path().join("fira_sans_regular")),
+			coverage: vec![],
 			format: Format::Tsv,
 		};
 		let mut stdout: Vec<u8> = Vec::new();
@@ -206,7 +252,8 @@ mod tests {
 		std::fs::write(temp.path().join("0-255.pbf"), b"\xff\xff\xff not a pbf")?;
 
 		let args = Subcommand {
-			glyph_directory: temp.path().to_path_buf(),
+			glyph_directory: Some(temp.path().to_path_buf()),
+			coverage: vec![],
 			format: Format::Csv,
 		};
 		let mut stdout: Vec<u8> = Vec::new();
@@ -217,4 +264,29 @@ mod tests {
 		);
 		Ok(())
 	}
+
+	#[test]
+	fn test_debug_run_coverage() -> Result<()> {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let args = Subcommand {
+			glyph_directory: None,
+			coverage: vec![
+				testdata.join("Noto Sans/Noto Sans - Regular.ttf"),
+				testdata.join("Fira Sans - Regular.ttf"),
+			],
+			format: Format::Csv,
+		};
+		let mut stdout: Vec<u8> = Vec::new();
+		run(&args, &mut stdout)?;
+
+		let output = String::from_utf8(stdout)?;
+		let lines = output.lines().collect::<Vec<_>>();
+		assert!(lines[0].starts_with("A: 3094 codepoints in "));
+		assert!(lines[1].starts_with("B: 1686 codepoints in "));
+		assert_eq!(lines[2], "intersection: 1654");
+		assert_eq!(lines[3], "union: 3126");
+		assert!(lines[4].starts_with("only in A: 0,2B-36,"), "{}", lines[4]);
+		assert!(lines[5].starts_with("only in B: E3,"), "{}", lines[5]);
+		Ok(())
+	}
 }
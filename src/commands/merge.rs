@@ -1,8 +1,20 @@
-use crate::{font::FontManager, render::Renderer, utils::prepare_output_directory, writer::Writer};
-use anyhow::Result;
+use super::{
+	block_starts, ensure_stats_supported, new_tar, notdef_codepoints, parse_block_range,
+	parse_block_size, parse_codepoint_range, parse_max_codepoint, parse_max_memory, print_dry_run,
+	print_stats, text_codepoints, write_glyphs, BlockRange, OutputOptions,
+};
+use crate::{
+	font::{FontLayout, FontManager, GLYPH_BLOCK_SIZE},
+	render::Renderer,
+	utils::{ensure_output_directory, prepare_output_directory},
+	writer::{ChecksumAlgorithm, GzipNaming, Writer},
+};
+use anyhow::{ensure, Context, Result};
 use std::{
-	io::Write,
-	path::{self, PathBuf},
+	fs::{self, File},
+	io::{Read, Write},
+	ops::RangeInclusive,
+	path::{self, Path, PathBuf},
 };
 
 /// Subcommand arguments for merging font files.
@@ -22,10 +34,16 @@ use std::{
 /// versatiles_glyphs merge -o output font.ttf font_arabic.ttf font_chinese.ttf
 /// ```
 pub struct Subcommand {
-	/// One or more font files to merge and convert.
+	/// One or more font files to merge and convert, or "-" to read a single font from stdin.
 	#[arg(num_args=1..)]
 	input_files: Vec<PathBuf>,
 
+	/// Also read the paths of font files from FILE, or from stdin if FILE is "-",
+	/// one per line, e.g. when there are too many for the command line. Blank
+	/// lines and lines starting with "#" are ignored.
+	#[arg(long, value_name = "FILE")]
+	files_from: Option<PathBuf>,
+
 	/// Output directory for glyphs. Mutually exclusive with `tar`.
 	#[arg(long, short = 'o', conflicts_with = "tar")]
 	output_directory: Option<String>,
@@ -34,10 +52,224 @@ pub struct Subcommand {
 	#[arg(long, short = 't', conflicts_with = "output_directory")]
 	tar: bool,
 
+	/// Write every font into its own tar archive in DIR, like "DIR/fira_sans_regular.tar",
+	/// each with the font's glyph blocks and index files.
+	#[arg(
+		long,
+		conflicts_with_all = ["output_directory", "tar", "merge_into", "skip_existing"],
+		value_name = "DIR"
+	)]
+	tar_per_font: Option<PathBuf>,
+
+	/// Set the modification time of every tar entry to SECONDS since the Unix
+	/// epoch instead of the current time, so the same fonts always produce the
+	/// same archive. Only used with `tar` and `tar_per_font`.
+	#[arg(long, value_name = "SECONDS")]
+	mtime: Option<u64>,
+
+	/// Write all files below this subdirectory, like "fonts", inside the output
+	/// directory or tar.
+	#[arg(long, value_name = "PATH")]
+	prefix: Option<String>,
+
+	/// Retry writes that fail with a transient error, e.g. when streaming a tar
+	/// over the network, up to N times with a growing delay.
+	#[arg(long, value_name = "N")]
+	retries: Option<u32>,
+
+	/// Hash every written file with ALGORITHM ("sha256" or "blake3") and
+	/// list the checksums in a `manifest.json`.
+	#[arg(long, value_name = "ALGORITHM")]
+	checksums: Option<ChecksumAlgorithm>,
+
+	/// Render the `.notdef` glyph (usually a box) for codepoints missing from a font.
+	#[arg(long)]
+	include_notdef: bool,
+
+	/// Comma-separated hexadecimal codepoints or ranges, like "FFFD,4E00-9FFF",
+	/// that get the `.notdef` glyph if missing. Defaults to "FFFD".
+	#[arg(
+		long,
+		requires = "include_notdef",
+		value_delimiter = ',',
+		value_parser = parse_codepoint_range,
+		value_name = "RANGES"
+	)]
+	notdef_codepoints: Vec<RangeInclusive<u32>>,
+
+	/// Skip control characters (0x00–0x1F, 0x7F–0x9F), surrogates and
+	/// noncharacters, which fonts often map to empty glyphs.
+	#[arg(long)]
+	skip_control: bool,
+
+	/// Only render the characters used in the UTF-8 text file at PATH, e.g.
+	/// the place names of a map, as far as the fonts cover them.
+	#[arg(long, value_name = "PATH")]
+	text_file: Option<PathBuf>,
+
+	/// Skip codepoints above this one, like "0xFFFF" or "65535", e.g. for
+	/// clients that don't support blocks beyond the Basic Multilingual Plane.
+	#[arg(long, value_parser = parse_max_codepoint, value_name = "CODEPOINT")]
+	max_codepoint: Option<u32>,
+
+	/// Move the baseline of all glyphs by PX pixels relative to the glyph size,
+	/// e.g. for scripts aligned on an ideographic baseline. Positive values
+	/// lower the glyphs. A "baseline" in a "fonts.json" takes precedence.
+	#[arg(long, allow_hyphen_values = true, value_name = "PX")]
+	baseline: Option<i32>,
+
+	/// Directory naming: "id" (e.g. "fira_sans_regular/") or "family-style"
+	/// (e.g. "Fira Sans/Regular/").
+	#[arg(long, default_value_t = FontLayout::Id, value_name = "LAYOUT")]
+	layout: FontLayout,
+
+	/// Write all glyph blocks of a font into a single "glyphs.pbf", with the
+	/// byte offsets of each range in "glyphs.json".
+	#[arg(long)]
+	bundle: bool,
+
+	/// Gzip every glyph block, so a static server can send it with
+	/// `Content-Encoding: gzip`. NAMING is "pbf" to keep the file names (the
+	/// default) or "pbf.gz" to append ".gz", like `--gzip-blocks=pbf.gz`.
+	#[arg(
+		long,
+		num_args = 0..=1,
+		require_equals = true,
+		default_missing_value = "pbf",
+		conflicts_with = "bundle",
+		value_name = "NAMING"
+	)]
+	gzip_blocks: Option<GzipNaming>,
+
+	/// Also gzip the JSON files, like "index.json" and "font_families.json",
+	/// named like the blocks, so the whole output is encoded the same way.
+	#[arg(long, requires = "gzip_blocks")]
+	gzip_json: bool,
+
+	/// Write an empty but valid block for each of the 256 ranges from "0-255"
+	/// to "65280-65535" that has no glyphs, so every range a client requests exists.
+	#[arg(long)]
+	emit_empty_ranges: bool,
+
+	/// Number of codepoints per glyph block file, a power of two from 64 to 65536.
+	/// maplibre/mapbox clients expect the default of 256.
+	#[arg(long, default_value_t = GLYPH_BLOCK_SIZE, value_parser = parse_block_size, value_name = "SIZE")]
+	block_size: u32,
+
+	/// Write one file per script instead of per block of codepoints, like
+	/// "latin.pbf", "arabic.pbf" and "cjk.pbf", for clients that load all
+	/// glyphs of a script at once. Codepoints the script table doesn't cover
+	/// go to "other.pbf".
+	#[arg(
+		long,
+		conflicts_with_all = ["block_size", "emit_empty_ranges", "include_range", "exclude_range", "bundle"]
+	)]
+	by_script: bool,
+
+	/// Only write these glyph blocks, given by range like "19968-20223" or by
+	/// start index like "19968". Comma-separated or repeated.
+	#[arg(
+		long,
+		value_delimiter = ',',
+		value_parser = parse_block_range,
+		value_name = "RANGES"
+	)]
+	include_range: Vec<BlockRange>,
+
+	/// Skip these glyph blocks, given like `--include-range`.
+	#[arg(
+		long,
+		value_delimiter = ',',
+		value_parser = parse_block_range,
+		value_name = "RANGES"
+	)]
+	exclude_range: Vec<BlockRange>,
+
+	/// Fail if any glyph can't be rendered, instead of skipping it with a warning.
+	#[arg(long)]
+	strict: bool,
+
+	/// Decode every glyph block again after writing it, and fail if it's
+	/// corrupted or has a different number of glyphs than were rendered.
+	/// Directory outputs are read back from disk.
+	#[arg(long)]
+	validate: bool,
+
+	/// Keep the existing output directory and only render the glyph blocks that
+	/// are missing, e.g. to resume an interrupted run. Not available with
+	/// `--checksums` and `--bundle`, which need every block.
+	#[arg(long, conflicts_with_all = ["tar", "checksums", "bundle"])]
+	skip_existing: bool,
+
+	/// Write into the existing glyph directory DIR instead of a new one, keeping
+	/// its fonts: `index.json`, `font_families.json` and `fontstacks.json` list
+	/// both the existing and the new fonts. Mutually exclusive with
+	/// `output_directory` and `tar`, and with `checksums`, since the manifest
+	/// would only list the new files.
+	#[arg(
+		long,
+		conflicts_with_all = ["output_directory", "tar", "checksums"],
+		value_name = "DIR"
+	)]
+	merge_into: Option<String>,
+
+	/// Soft limit in MiB for rendered glyph blocks that wait to be written,
+	/// e.g. when writing to a slow disk. Fonts are held in memory regardless.
+	#[arg(long, value_parser = parse_max_memory, value_name = "MIB")]
+	max_memory: Option<u64>,
+
+	/// Print the slowest glyphs, the render time per font, the approximate
+	/// memory use and the number of glyphs whose halo the buffer cuts off to
+	/// stderr. Requires building with the "profiling" feature.
+	#[arg(long)]
+	stats: bool,
+
+	/// List the glyph block files that would be written, with their glyph
+	/// counts and estimated sizes, without rendering or writing anything.
+	#[arg(long)]
+	dry_run: bool,
+
+	/// Only write the index files, like `index.json` and `font_families.json`,
+	/// e.g. to update them in an existing glyph directory. Nothing is rendered
+	/// and the output directory is kept.
+	#[arg(long)]
+	output_index_only: bool,
+
+	/// Also write a `fontstacks.json` that maps every fontstack name, like
+	/// "Fira Sans Regular", to its font directory.
+	#[arg(long)]
+	fontstacks: bool,
+
+	/// Also write a `ranges.json` to every font directory, listing the
+	/// filenames of its non-empty glyph blocks, e.g. for cache warming.
+	#[arg(long)]
+	list_ranges: bool,
+
 	/// Skip writing the `font_families.json` file.
 	#[arg(long)]
 	no_families: bool,
 
+	/// Add the copyright notice, license and license URL of every font, as
+	/// found in its `name` table, to `font_families.json`, so the attribution
+	/// that licenses like the SIL Open Font License require can be shown.
+	#[arg(long, conflicts_with = "no_families")]
+	copyright: bool,
+
+	/// Write "font_families.json" as `{"version": 2, "families": [...]}`, so
+	/// clients can detect its schema, instead of the legacy bare array.
+	#[arg(long, conflicts_with = "no_families")]
+	versioned_families: bool,
+
+	/// Indent the JSON files, like "index.json" and "font_families.json", for
+	/// reading, e.g. when debugging. By default they are compact.
+	#[arg(long)]
+	pretty: bool,
+
+	/// Store the family, size, units per EM and ascender of the font in every
+	/// glyph block, so it describes itself. Map renderers ignore this field.
+	#[arg(long)]
+	concat_metadata: bool,
+
 	/// Skip writing the `index.json` file.
 	#[arg(long)]
 	no_index: bool,
@@ -51,49 +283,228 @@ pub struct Subcommand {
 	single_thread: bool,
 }
 
+impl Subcommand {
+	/// Returns the options for [`write_glyphs`].
+	fn output_options(&self) -> OutputOptions<'_> {
+		OutputOptions {
+			prefix: self.prefix.as_deref(),
+			retries: self.retries,
+			bundle: self.bundle,
+			gzip_blocks: self.gzip_blocks,
+			gzip_json: self.gzip_json,
+			checksums: self.checksums,
+			output_index_only: self.output_index_only,
+			stats: self.stats,
+			no_index: self.no_index,
+			no_families: self.no_families,
+			fontstacks: self.fontstacks,
+			list_ranges: self.list_ranges,
+		}
+	}
+}
+
 /// Executes the merge subcommand logic.
 ///
 /// Collects fonts, initializes a [`FontManager`], and writes glyph data
-/// either to a directory or stdout tar.
-pub fn run(args: &Subcommand, stdout: &mut (impl Write + Send + Sync + 'static)) -> Result<()> {
+/// either to a directory or stdout tar. The single input file `-` is read from `stdin`.
+pub fn run(
+	args: &Subcommand,
+	stdin: &mut impl Read,
+	stdout: &mut (impl Write + Send + Sync + 'static),
+) -> Result<()> {
 	let mut font_manager = FontManager::new(!args.single_thread);
+	font_manager.layout = args.layout;
+	font_manager.emit_empty_ranges = args.emit_empty_ranges;
+	font_manager.block_size = args.block_size;
+	font_manager.group_by_script = args.by_script;
+	font_manager.include_blocks = block_starts(&args.include_range, args.block_size)?;
+	font_manager.exclude_blocks = block_starts(&args.exclude_range, args.block_size)?;
+	ensure_stats_supported(args.stats)?;
+	font_manager.strict = args.strict;
+	font_manager.validate = args.validate;
+	font_manager.skip_existing = args.skip_existing;
+	font_manager.merge_existing = args.merge_into.is_some();
+	font_manager.include_copyright = args.copyright;
+	font_manager.versioned_families = args.versioned_families;
+	font_manager.pretty_json = args.pretty;
+	font_manager.concat_metadata = args.concat_metadata;
+	font_manager.max_memory = args.max_memory;
 
-	// Canonicalize all input paths before adding to the FontManager.
-	let input_paths: Vec<PathBuf> = args
-		.input_files
-		.iter()
-		.map(|p| Ok(path::absolute(p)?.canonicalize()?))
-		.collect::<Result<Vec<_>>>()?;
-	font_manager.add_paths(&input_paths)?;
-
-	let mut writer = if args.tar {
-		eprintln!("Rendering glyphs as tar to stdout.");
-		Writer::new_tar(stdout)
-	} else {
-		let out_dir = prepare_output_directory(args.output_directory.as_deref().unwrap_or("output"))?;
-		eprintln!("Rendering glyphs to directory: {out_dir:?}");
-		Writer::new_file(path::absolute(out_dir)?)
-	};
-
-	let renderer = Renderer::new(args.dummy);
+	let mut input_files = args.input_files.clone();
+	if let Some(list) = &args.files_from {
+		let text = if list.as_os_str() == "-" {
+			let mut text = String::new();
+			stdin
+				.read_to_string(&mut text)
+				.context("failed to read the list of input files from stdin")?;
+			text
+		} else {
+			fs::read_to_string(list).with_context(|| format!("failed to read {list:?}"))?
+		};
+		input_files.extend(parse_file_list(&text));
+	}
+	ensure!(!input_files.is_empty(), "no input files given");
 
-	// Render glyphs and optionally write index/family files.
-	font_manager.render_glyphs(&mut writer, &renderer)?;
-	if !args.no_index {
-		font_manager.write_index_json(&mut writer)?;
+	if input_files.iter().any(|p| p.as_os_str() == "-") {
+		ensure!(
+			input_files.len() == 1 && args.files_from.is_none(),
+			"\"-\" reads a single font from stdin and can't be combined with other input files"
+		);
+		let mut bytes = Vec::new();
+		stdin.read_to_end(&mut bytes)?;
+		font_manager
+			.add_bytes(bytes)
+			.context("failed to read a font from stdin")?;
+	} else {
+		// Canonicalize all input paths before adding to the FontManager.
+		let input_paths: Vec<PathBuf> = input_files
+			.iter()
+			.map(|p| Ok(path::absolute(p)?.canonicalize()?))
+			.collect::<Result<Vec<_>>>()?;
+		font_manager.add_paths(&input_paths)?;
+	}
+	if args.include_notdef {
+		font_manager.include_notdef(&notdef_codepoints(&args.notdef_codepoints));
 	}
-	if !args.no_families {
-		font_manager.write_families_json(&mut writer)?;
+	if args.skip_control {
+		font_manager.skip_non_rendering();
+	}
+	if let Some(path) = &args.text_file {
+		font_manager.restrict_charset(&text_codepoints(path)?);
+	}
+	if let Some(max_codepoint) = args.max_codepoint {
+		font_manager.limit_codepoints(max_codepoint);
+	}
+	let mut renderer = Renderer::new(args.dummy);
+	if let Some(baseline) = args.baseline {
+		renderer = renderer.with_baseline(baseline);
+	}
+	if args.dry_run {
+		return print_dry_run(&font_manager, &renderer, stdout);
 	}
 
-	writer.finish()?;
+	if let Some(dir) = &args.tar_per_font {
+		fs::create_dir_all(dir).with_context(|| format!("creating directory {dir:?}"))?;
+		for (id, font_manager) in font_manager.into_single_fonts() {
+			let path = dir.join(format!("{id}.tar"));
+			eprintln!("Rendering glyphs as tar to file: {path:?}");
+			let mut file = File::create(&path).with_context(|| format!("creating file {path:?}"))?;
+			write_glyphs(
+				&args.output_options(),
+				&font_manager,
+				&renderer,
+				new_tar(&mut file, args.mtime),
+			)?;
+		}
+	} else {
+		let writer = if args.tar {
+			eprintln!("Rendering glyphs as tar to stdout.");
+			new_tar(stdout, args.mtime)
+		} else if let Some(out_dir) = &args.merge_into {
+			ensure!(
+				Path::new(out_dir).is_dir(),
+				"the glyph directory {out_dir:?} to merge into doesn't exist"
+			);
+			eprintln!("Merging glyphs into directory: {out_dir:?}");
+			Writer::new_file(path::absolute(out_dir)?)
+		} else {
+			let out_dir = args.output_directory.as_deref().unwrap_or("output");
+			let out_dir = if args.skip_existing || args.output_index_only {
+				ensure_output_directory(out_dir)?
+			} else {
+				prepare_output_directory(out_dir)?
+			};
+			eprintln!("Rendering glyphs to directory: {out_dir:?}");
+			Writer::new_file(path::absolute(out_dir)?)
+		};
+		write_glyphs(&args.output_options(), &font_manager, &renderer, writer)?;
+	}
+	if args.stats {
+		print_stats(&renderer);
+	}
 
 	Ok(())
 }
 
+/// Parses a list of input files, one path per line, ignoring blank lines and
+/// `#` comments.
+fn parse_file_list(text: &str) -> Vec<PathBuf> {
+	text
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(PathBuf::from)
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use prost::Message;
+	use sha2::Digest;
+
+	impl Default for Subcommand {
+		fn default() -> Self {
+			Subcommand {
+				input_files: vec![],
+				files_from: None,
+				output_directory: None,
+				tar: false,
+				tar_per_font: None,
+				mtime: None,
+				prefix: None,
+				retries: None,
+				checksums: None,
+				include_notdef: false,
+				notdef_codepoints: vec![],
+				skip_control: false,
+				text_file: None,
+				max_codepoint: None,
+				baseline: None,
+				layout: FontLayout::Id,
+				bundle: false,
+				gzip_blocks: None,
+				gzip_json: false,
+				emit_empty_ranges: false,
+				block_size: GLYPH_BLOCK_SIZE,
+				by_script: false,
+				include_range: vec![],
+				exclude_range: vec![],
+				strict: false,
+				validate: false,
+				skip_existing: false,
+				merge_into: None,
+				max_memory: None,
+				stats: false,
+				dry_run: false,
+				output_index_only: false,
+				fontstacks: false,
+				list_ranges: false,
+				no_families: false,
+				copyright: false,
+				versioned_families: false,
+				pretty: false,
+				concat_metadata: false,
+				no_index: false,
+				dummy: false,
+				single_thread: false,
+			}
+		}
+	}
+
+	#[test]
+	fn test_default_matches_cli_defaults() {
+		use clap::{Args, Command, FromArgMatches};
+		let matches = Subcommand::augment_args(Command::new("merge"))
+			.try_get_matches_from(["merge", "font.ttf"])
+			.unwrap();
+		let args = Subcommand::from_arg_matches(&matches).unwrap();
+		let expected = Subcommand {
+			input_files: vec![PathBuf::from("font.ttf")],
+			..Default::default()
+		};
+		assert_eq!(format!("{args:?}"), format!("{expected:?}"));
+	}
 
 	fn get_tar_entries(data: &[u8]) -> Vec<String> {
 		let mut tar = tar::Archive::new(data);
@@ -121,15 +532,12 @@ mod tests {
 				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
 			],
 			output_directory: Some(out.to_str().unwrap().to_string()),
-			tar: false,
-			no_families: false,
-			no_index: false,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
-		run(&args, &mut stdout)?;
+		run(&args, &mut std::io::empty(), &mut stdout)?;
 
 		assert!(out.join("fira_sans_regular/0-255.pbf").is_file());
 		assert!(out.join("font_families.json").is_file());
@@ -137,6 +545,82 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_run_with_fontstacks() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			fontstacks: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let fontstacks: std::collections::BTreeMap<String, String> =
+			serde_json::from_slice(&std::fs::read(out.join("fontstacks.json"))?)?;
+		assert_eq!(
+			fontstacks,
+			[(
+				"Fira Sans Regular".to_string(),
+				"fira_sans_regular".to_string()
+			)]
+			.into()
+		);
+		assert!(out.join("index.json").is_file());
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_retries() -> Result<()> {
+		/// Fails every other write, like a full socket buffer.
+		#[derive(Default)]
+		struct FlakyStdout {
+			calls: u32,
+			data: Vec<u8>,
+		}
+		impl Write for FlakyStdout {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.calls += 1;
+				if self.calls % 2 == 1 {
+					return Err(std::io::ErrorKind::WouldBlock.into());
+				}
+				self.data.extend_from_slice(buf);
+				Ok(buf.len())
+			}
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let args = |retries: Option<u32>| Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			tar: true,
+			mtime: Some(0),
+			retries,
+			dummy: true,
+			..Default::default()
+		};
+		let mut expected = Vec::<u8>::new();
+		run(&args(None), &mut std::io::empty(), &mut expected)?;
+
+		assert!(run(
+			&args(None),
+			&mut std::io::empty(),
+			&mut FlakyStdout::default()
+		)
+		.is_err());
+		let mut stdout = FlakyStdout::default();
+		run(&args(Some(1)), &mut std::io::empty(), &mut stdout)?;
+		assert_eq!(stdout.data, expected);
+		Ok(())
+	}
+
 	#[test]
 	fn test_run_with_tar_to_stdout() -> Result<()> {
 		// Pretend we have multiple directories, but they actually reference the same testdata dir.
@@ -144,16 +628,13 @@ mod tests {
 			input_files: vec![
 				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
 			],
-			output_directory: None,
 			tar: true,
-			no_families: false,
-			no_index: false,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
-		run(&args, &mut stdout)?;
+		run(&args, &mut std::io::empty(), &mut stdout)?;
 
 		assert_eq!(
 			get_tar_entries(&stdout),
@@ -179,11 +660,821 @@ mod tests {
 				"\"fira_sans_regular/8704-8959.pbf\": 6511",
 				"\"fira_sans_regular/8960-9215.pbf\": 4375",
 				"\"fira_sans_regular/9472-9727.pbf\": 853",
-				"\"font_families.json\": 365",
-				"\"index.json\": 25"
+				"\"font_families.json\": 277",
+				"\"index.json\": 21"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_mtime() -> Result<()> {
+		let mut args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			tar: true,
+			mtime: Some(1_000_000_000),
+			dummy: true,
+			..Default::default()
+		};
+
+		// Rendered in parallel, the archive is the same on every run.
+		let run_tar = |args: &Subcommand| -> Result<Vec<u8>> {
+			let mut stdout = Vec::<u8>::new();
+			run(args, &mut std::io::empty(), &mut stdout)?;
+			Ok(stdout)
+		};
+		let first = run_tar(&args)?;
+		assert_eq!(run_tar(&args)?, first);
+		let mut archive = tar::Archive::new(&first[..]);
+		for entry in archive.entries()? {
+			assert_eq!(entry?.header().mtime()?, 1_000_000_000);
+		}
+
+		args.mtime = Some(0);
+		assert_ne!(run_tar(&args)?, first);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_checksums() -> Result<()> {
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			tar: true,
+			checksums: Some(ChecksumAlgorithm::Sha256),
+			dummy: true,
+			..Default::default()
+		};
+
+		// Returns the manifest and the raw data of "0-255.pbf".
+		let render = || -> Result<(serde_json::Value, Vec<u8>)> {
+			let mut stdout = Vec::<u8>::new();
+			run(&args, &mut std::io::empty(), &mut stdout)?;
+
+			let mut manifest = None;
+			let mut block = Vec::new();
+			for entry in tar::Archive::new(stdout.as_slice()).entries()? {
+				let mut entry = entry?;
+				let path = entry.path()?.to_str().unwrap().to_string();
+				if path == "manifest.json" {
+					manifest = Some(serde_json::from_reader(&mut entry)?);
+				} else if path == "fira_sans_regular/0-255.pbf" {
+					std::io::Read::read_to_end(&mut entry, &mut block)?;
+				}
+			}
+			Ok((manifest.unwrap(), block))
+		};
+
+		let (manifest, block) = render()?;
+		assert_eq!(manifest["algorithm"], "sha256");
+		assert_eq!(manifest["files"].as_array().unwrap().len(), 22);
+
+		let entry = &manifest["files"][0];
+		assert_eq!(entry["path"], "fira_sans_regular/0-255.pbf");
+		assert_eq!(entry["size"], 80022);
+		let expected = sha2::Sha256::digest(&block)
+			.iter()
+			.map(|b| format!("{b:02x}"))
+			.collect::<String>();
+		assert_eq!(entry["hash"], expected);
+
+		// Checksums don't depend on the tar mtime or the rendering order.
+		assert_eq!(render()?.0, manifest);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_include_notdef() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			include_notdef: true,
+			notdef_codepoints: vec![0x41..=0x41, 0xE000..=0xE001],
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let data = std::fs::read(out.join("fira_sans_regular/57344-57599.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+		assert_eq!(
+			glyphs.iter().map(|g| g.id).collect::<Vec<_>>(),
+			[0xE000, 0xE001]
+		);
+
+		// The font has an "A", so the block keeps its 192 regular glyphs.
+		let data = std::fs::read(out.join("fira_sans_regular/0-255.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+		assert_eq!(glyphs.len(), 192);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_skip_control() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let render = |skip_control: bool| -> Result<Vec<u32>> {
+			let out = temp.path().join(skip_control.to_string());
+			let args = Subcommand {
+				input_files: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+					.join("testdata/Noto Sans/Noto Sans - Regular.ttf")],
+				output_directory: Some(out.to_str().unwrap().to_string()),
+				skip_control,
+				dummy: true,
+				..Default::default()
+			};
+			run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+			let data = std::fs::read(out.join("noto_sans_regular/0-255.pbf"))?;
+			let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+			Ok(glyphs.iter().map(|g| g.id).collect())
+		};
+
+		// Noto Sans maps NULL and CARRIAGE RETURN to empty glyphs.
+		let ids = render(false)?;
+		assert!(ids.contains(&0x00) && ids.contains(&0x0D));
+
+		let filtered = render(true)?;
+		assert!(filtered
+			.iter()
+			.all(|&id| !(id < 0x20 || (0x7F..=0x9F).contains(&id))));
+		assert_eq!(filtered.len(), ids.len() - 2);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_text_file() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Noto Sans");
+		let corpus = temp.path().join("corpus.txt");
+		std::fs::write(&corpus, "Aé\r\n中\n\tA\n")?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				testdata.join("Noto Sans - Regular.ttf"),
+				testdata.join("Noto Sans SC - Regular.ttf"),
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			text_file: Some(corpus),
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		// "中" (U+4E2D) comes from Noto Sans SC, which is merged into Noto Sans.
+		let mut files = std::fs::read_dir(out.join("noto_sans_regular"))?
+			.map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+			.collect::<Result<Vec<_>>>()?;
+		files.sort_unstable();
+		assert_eq!(files, ["0-255.pbf", "19968-20223.pbf"]);
+
+		// The line breaks and tabs of the corpus aren't rendered.
+		let data = std::fs::read(out.join("noto_sans_regular/0-255.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+		assert_eq!(
+			glyphs.iter().map(|g| g.id).collect::<Vec<_>>(),
+			[0x41, 0xE9]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_baseline() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let render = |baseline: Option<i32>| -> Result<i32> {
+			let out = temp.path().join(format!("{baseline:?}"));
+			let args = Subcommand {
+				input_files: vec![
+					PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+				],
+				output_directory: Some(out.to_str().unwrap().to_string()),
+				baseline,
+				dummy: true,
+				..Default::default()
+			};
+			run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+			let data = std::fs::read(out.join("fira_sans_regular/0-255.pbf"))?;
+			let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+			Ok(glyphs.iter().find(|g| g.id == 65).unwrap().top)
+		};
+
+		// The baseline is relative to the size: 6 pixels lower "A" by 6.
+		assert_eq!(render(None)?, -7);
+		assert_eq!(render(Some(6))?, -13);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_bundle() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let render = |bundle: bool| -> Result<PathBuf> {
+			let out = temp.path().join(if bundle { "bundled" } else { "blocks" });
+			let args = Subcommand {
+				input_files: vec![
+					PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+				],
+				output_directory: Some(out.to_str().unwrap().to_string()),
+				bundle,
+				dummy: true,
+				..Default::default()
+			};
+			run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+			Ok(out.join("fira_sans_regular"))
+		};
+
+		let bundled = render(true)?;
+		let mut files = std::fs::read_dir(&bundled)?
+			.map(|e| Ok(e?.file_name().into_string().unwrap()))
+			.collect::<Result<Vec<_>>>()?;
+		files.sort_unstable();
+		assert_eq!(files, ["glyphs.json", "glyphs.pbf"]);
+
+		// Splitting the bundle yields exactly the separately written blocks.
+		let blocks = render(false)?;
+		let bundle = std::fs::read(bundled.join("glyphs.pbf"))?;
+		let index = std::fs::read(bundled.join("glyphs.json"))?;
+		let ranges = crate::writer::split_bundle(&bundle, &index)?;
+		assert_eq!(ranges.len(), 20);
+		assert_eq!(ranges[0].0, "0-255");
+		for (range, bytes) in ranges {
+			assert_eq!(bytes, std::fs::read(blocks.join(format!("{range}.pbf")))?);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_emit_empty_ranges() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			emit_empty_ranges: true,
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let dir = out.join("fira_sans_regular");
+		assert_eq!(std::fs::read_dir(&dir)?.count(), 256);
+		for start in (0..65536).step_by(256) {
+			assert!(dir.join(format!("{start}-{}.pbf", start + 255)).is_file());
+		}
+
+		// Fira Sans has no glyphs in the last range, so it gets an empty block.
+		let data = std::fs::read(dir.join("65280-65535.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?;
+		assert!(glyphs.into_glyphs().is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_block_size() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			block_size: 512,
+			exclude_range: vec![parse_block_range("512-1023")?],
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let dir = out.join("fira_sans_regular");
+		assert!(dir.join("0-511.pbf").is_file());
+		assert!(dir.join("9216-9727.pbf").is_file());
+		assert!(!dir.join("512-1023.pbf").exists());
+		assert!(!dir.join("0-255.pbf").exists());
+
+		let data = std::fs::read(dir.join("0-511.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?;
+		assert!(glyphs.into_glyphs().iter().any(|g| g.id >= 256));
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_by_script() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Noto Sans");
+		let args = Subcommand {
+			input_files: vec![
+				testdata.join("Noto Sans - Regular.ttf"),
+				testdata.join("Noto Sans Arabic - Regular.ttf"),
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			by_script: true,
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let dir = out.join("noto_sans_regular");
+		assert!(!dir.join("0-255.pbf").exists());
+		let ids = |script: &str| -> Result<Vec<u32>> {
+			let data = std::fs::read(dir.join(format!("{script}.pbf")))?;
+			let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?;
+			Ok(glyphs.into_glyphs().iter().map(|g| g.id).collect())
+		};
+		let (latin, arabic) = (ids("latin")?, ids("arabic")?);
+		assert!(latin.contains(&('A' as u32)) && !arabic.contains(&('A' as u32)));
+		assert!(arabic.contains(&0x0627) && !latin.contains(&0x0627));
+		assert!(latin
+			.iter()
+			.all(|&id| crate::font::script_of(id) == "latin"));
+		assert!(arabic
+			.iter()
+			.all(|&id| crate::font::script_of(id) == "arabic"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_skip_existing() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		std::fs::create_dir_all(out.join("fira_sans_regular"))?;
+		std::fs::write(out.join("fira_sans_regular/0-255.pbf"), b"old")?;
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			skip_existing: true,
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let dir = out.join("fira_sans_regular");
+		assert_eq!(std::fs::read(dir.join("0-255.pbf"))?, b"old");
+		assert!(dir.join("256-511.pbf").is_file());
+		assert_eq!(std::fs::read_dir(&dir)?.count(), 20);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_merge_into() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let render = |font: &str, output_directory: Option<String>, merge_into: Option<String>| {
+			let args = Subcommand {
+				input_files: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(font)],
+				output_directory,
+				merge_into,
+				fontstacks: true,
+				dummy: true,
+				..Default::default()
+			};
+			run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())
+		};
+		let out_str = out.to_str().unwrap().to_string();
+		let missing = temp.path().join("missing").to_str().unwrap().to_string();
+
+		render(
+			"testdata/Fira Sans - Regular.ttf",
+			Some(out_str.clone()),
+			None,
+		)?;
+		render(
+			"testdata/Noto Sans/Noto Sans - Regular.ttf",
+			None,
+			Some(out_str),
+		)?;
+		assert!(render("testdata/Fira Sans - Regular.ttf", None, Some(missing)).is_err());
+
+		assert!(out.join("fira_sans_regular/0-255.pbf").is_file());
+		assert!(out.join("noto_sans_regular/0-255.pbf").is_file());
+
+		let read_json = |name: &str| -> Result<serde_json::Value> {
+			Ok(serde_json::from_slice(&std::fs::read(out.join(name))?)?)
+		};
+		assert_eq!(
+			read_json("index.json")?,
+			serde_json::json!(["fira_sans_regular", "noto_sans_regular"])
+		);
+		let families = read_json("font_families.json")?;
+		let families = families
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|family| {
+				(
+					family["name"].as_str().unwrap(),
+					family["faces"][0]["id"].as_str().unwrap(),
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			families,
+			[
+				("Fira Sans", "fira_sans_regular"),
+				("Noto Sans", "noto_sans_regular")
+			]
+		);
+		assert_eq!(
+			read_json("fontstacks.json")?,
+			serde_json::json!({
+				"Fira Sans Regular": "fira_sans_regular",
+				"Noto Sans Regular": "noto_sans_regular"
+			})
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_output_index_only() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		std::fs::create_dir_all(&out)?;
+		std::fs::write(out.join("index.json"), b"[]")?;
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			output_index_only: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let mut files = std::fs::read_dir(&out)?
+			.map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+			.collect::<Result<Vec<_>>>()?;
+		files.sort_unstable();
+		assert_eq!(files, ["font_families.json", "index.json"]);
+		assert_eq!(
+			std::fs::read_to_string(out.join("index.json"))?,
+			"[\"fira_sans_regular\"]"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_list_ranges() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			list_ranges: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let ranges: Vec<String> =
+			serde_json::from_slice(&std::fs::read(out.join("fira_sans_regular/ranges.json"))?)?;
+		assert_eq!(
+			ranges,
+			[
+				"0-255.pbf",
+				"256-511.pbf",
+				"512-767.pbf",
+				"768-1023.pbf",
+				"1024-1279.pbf",
+				"1280-1535.pbf",
+				"3584-3839.pbf",
+				"7424-7679.pbf",
+				"7680-7935.pbf",
+				"7936-8191.pbf",
+				"8192-8447.pbf",
+				"8448-8703.pbf",
+				"8704-8959.pbf",
+				"8960-9215.pbf",
+				"9472-9727.pbf",
+				"11264-11519.pbf",
+				"42752-43007.pbf",
+				"43776-44031.pbf",
+				"64256-64511.pbf",
+				"65024-65279.pbf"
 			]
 		);
 
+		// Every listed block was written, and no other.
+		let mut files = std::fs::read_dir(out.join("fira_sans_regular"))?
+			.map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+			.filter(|name| !matches!(name.as_deref(), Ok("ranges.json")))
+			.collect::<Result<Vec<_>>>()?;
+		files.sort_unstable_by_key(|name| name.split('-').next().unwrap().parse::<u32>().unwrap());
+		assert_eq!(files, ranges);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_tar_per_font() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let dir = temp.path().join("tars");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf"),
+				PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+					.join("testdata/Noto Sans/Noto Sans - Regular.ttf"),
+			],
+			tar_per_font: Some(dir.clone()),
+			dummy: true,
+			..Default::default()
+		};
+		let mut stdout = Vec::<u8>::new();
+		run(&args, &mut std::io::empty(), &mut stdout)?;
+		assert!(stdout.is_empty());
+
+		let mut files = std::fs::read_dir(&dir)?
+			.map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+			.collect::<Result<Vec<_>>>()?;
+		files.sort_unstable();
+		assert_eq!(files, ["fira_sans_regular.tar", "noto_sans_regular.tar"]);
+
+		for id in ["fira_sans_regular", "noto_sans_regular"] {
+			let data = std::fs::read(dir.join(format!("{id}.tar")))?;
+			let mut archive = tar::Archive::new(data.as_slice());
+			let mut index = String::new();
+			let mut blocks = 0;
+			for entry in archive.entries()? {
+				let mut entry = entry?;
+				let path = entry.path()?.to_str().unwrap().to_string();
+				match path.as_str() {
+					"index.json" => {
+						entry.read_to_string(&mut index)?;
+					}
+					"font_families.json" => {}
+					_ => {
+						assert!(path.starts_with(&format!("{id}/")), "{path} in {id}.tar");
+						blocks += path.ends_with(".pbf") as usize;
+					}
+				}
+			}
+			assert!(blocks > 0);
+			assert_eq!(serde_json::from_str::<Vec<String>>(&index)?, [id]);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_gzip_blocks() -> Result<()> {
+		use flate2::read::GzDecoder;
+
+		let temp = tempfile::tempdir()?;
+		let render =
+			|name: &str, gzip_blocks: Option<GzipNaming>, gzip_json: bool| -> Result<PathBuf> {
+				let out = temp.path().join(name);
+				let args = Subcommand {
+					input_files: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+						.join("testdata/Fira Sans - Regular.ttf")],
+					output_directory: Some(out.to_str().unwrap().to_string()),
+					gzip_blocks,
+					gzip_json,
+					dummy: true,
+					..Default::default()
+				};
+				run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+				Ok(out)
+			};
+		let plain = render("plain", None, false)?;
+		let gzipped = render("gzipped", Some(GzipNaming::PbfGz), false)?;
+
+		let mut blocks = 0;
+		for entry in std::fs::read_dir(plain.join("fira_sans_regular"))? {
+			let file_name = entry?.file_name().into_string().unwrap();
+			let compressed = std::fs::read(gzipped.join(format!("fira_sans_regular/{file_name}.gz")))?;
+			let mut decompressed = Vec::new();
+			GzDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed)?;
+			assert_eq!(
+				decompressed,
+				std::fs::read(plain.join("fira_sans_regular").join(&file_name))?,
+				"{file_name}"
+			);
+			blocks += 1;
+		}
+		assert_eq!(blocks, 20);
+
+		// JSON files stay uncompressed.
+		assert_eq!(
+			std::fs::read(gzipped.join("font_families.json"))?,
+			std::fs::read(plain.join("font_families.json"))?
+		);
+
+		// Unless they are compressed as well.
+		let gzipped = render("gzipped_json", Some(GzipNaming::PbfGz), true)?;
+		for file_name in ["font_families.json", "index.json"] {
+			assert!(!gzipped.join(file_name).exists());
+			let compressed = std::fs::read(gzipped.join(format!("{file_name}.gz")))?;
+			let mut decompressed = Vec::new();
+			GzDecoder::new(compressed.as_slice()).read_to_end(&mut decompressed)?;
+			serde_json::from_slice::<serde_json::Value>(&decompressed)?;
+			assert_eq!(
+				decompressed,
+				std::fs::read(plain.join(file_name))?,
+				"{file_name}"
+			);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_exclude_range() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			exclude_range: vec![parse_block_range("0-255")?],
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		let dir = out.join("fira_sans_regular");
+		assert!(!dir.join("0-255.pbf").exists());
+		assert!(dir.join("256-511.pbf").is_file());
+		assert!(dir.join("9472-9727.pbf").is_file());
+		assert_eq!(std::fs::read_dir(&dir)?.count(), 19);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_stdin() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let mut args = Subcommand {
+			input_files: vec![PathBuf::from("-")],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			no_families: true,
+			dummy: true,
+			..Default::default()
+		};
+		let mut stdin =
+			std::io::Cursor::new(include_bytes!("../../testdata/Fira Sans - Regular.ttf"));
+		run(&args, &mut stdin, &mut Vec::<u8>::new())?;
+
+		assert_eq!(
+			std::fs::read_to_string(out.join("index.json"))?,
+			"[\"fira_sans_regular\"]"
+		);
+		assert!(out.join("fira_sans_regular/0-255.pbf").is_file());
+
+		// Stdin can't be combined with files.
+		args
+			.input_files
+			.push(PathBuf::from("testdata/Fira Sans - Regular.ttf"));
+		let err = run(&args, &mut std::io::empty(), &mut Vec::<u8>::new()).unwrap_err();
+		assert!(err.to_string().contains("can't be combined"), "{err}");
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_files_from() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let list = temp.path().join("fonts.txt");
+		std::fs::write(
+			&list,
+			format!(
+				"# Latin fonts\n{}\n\n  {}  \n",
+				testdata.join("Fira Sans - Regular.ttf").display(),
+				testdata.join("Noto Sans/Noto Sans - Regular.ttf").display()
+			),
+		)?;
+
+		let out = temp.path().join("glyphs");
+		let mut args = Subcommand {
+			files_from: Some(list.clone()),
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			no_families: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+		let index = || std::fs::read_to_string(out.join("index.json"));
+		assert_eq!(index()?, "[\"fira_sans_regular\",\"noto_sans_regular\"]");
+
+		// The list can come from stdin, and is combined with positional inputs.
+		std::fs::remove_dir_all(&out)?;
+		args.files_from = Some(PathBuf::from("-"));
+		args.input_files = vec![testdata.join("Noto Sans/Noto Sans Arabic - Regular.ttf")];
+		let mut stdin = std::io::Cursor::new(std::fs::read(&list)?);
+		run(&args, &mut stdin, &mut Vec::<u8>::new())?;
+		assert_eq!(index()?, "[\"fira_sans_regular\",\"noto_sans_regular\"]");
+		// Noto Sans Arabic is merged into Noto Sans, since its family is "Noto Sans".
+		assert!(out.join("noto_sans_regular/1536-1791.pbf").is_file());
+
+		// An empty list and no inputs is an error.
+		std::fs::write(&list, "# nothing yet\n")?;
+		args.files_from = Some(list);
+		args.input_files = vec![];
+		let err = run(&args, &mut std::io::empty(), &mut Vec::<u8>::new()).unwrap_err();
+		assert_eq!(err.to_string(), "no input files given");
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_uses_font_name_as_fontstack() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			no_families: true,
+			no_index: true,
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut std::io::empty(), &mut Vec::<u8>::new())?;
+
+		// The directory uses the id, the fontstack inside the block the font name.
+		let data = std::fs::read(out.join("fira_sans_regular/0-255.pbf"))?;
+		let glyphs = crate::protobuf::PbfGlyphs::decode(data.as_slice())?;
+		assert_eq!(glyphs.name(), Some("Fira Sans Regular"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_prefix() -> Result<()> {
+		let mut args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			tar: true,
+			prefix: Some(String::from("fonts/")),
+			checksums: Some(ChecksumAlgorithm::Sha256),
+			dummy: true,
+			..Default::default()
+		};
+
+		let mut stdout = Vec::<u8>::new();
+		run(&args, &mut std::io::empty(), &mut stdout)?;
+
+		let entries = get_tar_entries(&stdout);
+		assert_eq!(entries.len(), 25);
+		assert!(
+			entries.iter().all(|e| e.starts_with("\"fonts/")),
+			"{entries:?}"
+		);
+		assert_eq!(entries[0], "\"fonts/\": 0");
+		assert!(entries.contains(&String::from("\"fonts/index.json\": 21")));
+		assert!(entries
+			.iter()
+			.any(|e| e.starts_with("\"fonts/manifest.json\"")));
+
+		args.prefix = Some(String::from("../fonts"));
+		assert!(run(&args, &mut std::io::empty(), &mut Vec::<u8>::new()).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_dry_run() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_files: vec![
+				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+			],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			dry_run: true,
+			..Default::default()
+		};
+
+		let mut stdout = Vec::<u8>::new();
+		run(&args, &mut std::io::empty(), &mut stdout)?;
+
+		let lines = String::from_utf8(stdout)?;
+		let lines = lines.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 20);
+		assert!(lines[0].starts_with("fira_sans_regular/0-255.pbf: 192 glyphs, ~"));
+		assert!(lines[0].ends_with(" bytes"));
+		assert!(lines
+			.iter()
+			.any(|l| l.starts_with("fira_sans_regular/9472-9727.pbf: ")));
+		assert!(!out.exists());
 		Ok(())
 	}
 }
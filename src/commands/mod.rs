@@ -1,3 +1,380 @@
+pub mod atlas;
 pub mod debug;
 pub mod merge;
 pub mod recurse;
+pub mod sample;
+
+use crate::{
+	font::{check_block_size, FontManager, MemoryStats},
+	render::Renderer,
+	utils::get_progress_bar,
+	writer::{ChecksumAlgorithm, GzipNaming, RetryPolicy, Writer},
+};
+use anyhow::{ensure, Context, Result};
+use std::{collections::HashSet, fs, io::Write, ops::RangeInclusive, path::Path};
+
+/// Codepoint that gets the `.notdef` glyph if `--include-notdef` is set without
+/// `--notdef-codepoints`: U+FFFD REPLACEMENT CHARACTER.
+const DEFAULT_NOTDEF_CODEPOINT: u32 = 0xFFFD;
+
+/// Number of slowest glyphs listed by `--stats`.
+#[cfg(feature = "profiling")]
+const STATS_SLOWEST_GLYPHS: usize = 20;
+
+/// Parses a hexadecimal codepoint or codepoint range, like `"FFFD"`, `"U+FFFD"`
+/// or `"4E00-9FFF"`.
+fn parse_codepoint_range(text: &str) -> Result<RangeInclusive<u32>> {
+	let parse = |hex: &str| -> Result<u32> {
+		let hex = hex.trim();
+		let hex = hex
+			.strip_prefix("U+")
+			.or_else(|| hex.strip_prefix("u+"))
+			.unwrap_or(hex);
+		let codepoint = u32::from_str_radix(hex, 16)
+			.with_context(|| format!("invalid hexadecimal codepoint \"{hex}\""))?;
+		ensure!(
+			codepoint <= 0x10FFFF,
+			"codepoint {codepoint:X} is out of range"
+		);
+		Ok(codepoint)
+	};
+	let range = match text.split_once('-') {
+		Some((start, end)) => parse(start)?..=parse(end)?,
+		None => parse(text)?..=parse(text)?,
+	};
+	ensure!(!range.is_empty(), "empty codepoint range \"{text}\"");
+	Ok(range)
+}
+
+/// Parses `--max-codepoint`, either hexadecimal with a `0x` or `U+` prefix,
+/// like `"0xFFFF"`, or decimal, like `"65535"`.
+fn parse_max_codepoint(text: &str) -> Result<u32> {
+	let trimmed = text.trim();
+	let hex = ["0x", "0X", "U+", "u+"]
+		.iter()
+		.find_map(|prefix| trimmed.strip_prefix(prefix));
+	let codepoint = match hex {
+		Some(hex) => u32::from_str_radix(hex, 16),
+		None => trimmed.parse(),
+	}
+	.with_context(|| {
+		format!("invalid codepoint \"{text}\", expected e.g. \"0xFFFF\" or \"65535\"")
+	})?;
+	ensure!(
+		codepoint <= 0x10FFFF,
+		"codepoint {codepoint:X} is out of range"
+	);
+	Ok(codepoint)
+}
+
+/// Creates the tar writer for `--tar` and `--tar-per-font`, which stamps every
+/// entry with `--mtime` if it is given.
+fn new_tar<W: Write + Send + Sync + 'static>(output: &mut W, mtime: Option<u64>) -> Writer<'_> {
+	match mtime {
+		Some(mtime) => Writer::new_tar_with_mtime(output, mtime),
+		None => Writer::new_tar(output),
+	}
+}
+
+/// The output flags that `merge` and `recurse` share, see [`write_glyphs`].
+struct OutputOptions<'a> {
+	prefix: Option<&'a str>,
+	retries: Option<u32>,
+	bundle: bool,
+	gzip_blocks: Option<GzipNaming>,
+	gzip_json: bool,
+	checksums: Option<ChecksumAlgorithm>,
+	output_index_only: bool,
+	stats: bool,
+	no_index: bool,
+	no_families: bool,
+	fontstacks: bool,
+	list_ranges: bool,
+}
+
+/// Renders the glyphs of `font_manager` and writes them, and the index files
+/// unless disabled, with `writer`.
+fn write_glyphs<'a>(
+	options: &OutputOptions,
+	font_manager: &'a FontManager<'a>,
+	renderer: &Renderer,
+	mut writer: Writer,
+) -> Result<()> {
+	if let Some(retries) = options.retries {
+		writer = writer.with_retries(RetryPolicy {
+			retries,
+			..Default::default()
+		});
+	}
+	if let Some(prefix) = options.prefix {
+		writer = writer.with_prefix(prefix)?;
+	}
+	if options.bundle {
+		writer = writer.with_bundles();
+	}
+	if let Some(naming) = options.gzip_blocks {
+		writer = writer.with_gzip_blocks(naming);
+	}
+	if options.gzip_json {
+		writer = writer.with_gzip_json();
+	}
+	if let Some(algorithm) = options.checksums {
+		writer = writer.with_checksums(algorithm);
+	}
+
+	// Render glyphs and optionally write index/family files.
+	if !options.output_index_only {
+		font_manager.render_glyphs_with_progress(&mut writer, renderer, &get_progress_bar(0))?;
+		let failed = font_manager.failed_glyphs();
+		if failed > 0 {
+			eprintln!("{failed} glyph(s) could not be rendered and were skipped.");
+		}
+		if options.stats {
+			print_memory_stats(&font_manager.memory_stats());
+		}
+	}
+	if !options.no_index {
+		font_manager.write_index_json(&mut writer)?;
+	}
+	if !options.no_families {
+		font_manager.write_families_json(&mut writer)?;
+	}
+	if options.fontstacks {
+		font_manager.write_fontstacks_json(&mut writer)?;
+	}
+	if options.list_ranges {
+		font_manager.write_ranges_json(&mut writer)?;
+	}
+
+	writer.finish()?;
+
+	Ok(())
+}
+
+/// Parses `--max-memory`, given in MiB, into bytes.
+fn parse_max_memory(text: &str) -> Result<u64> {
+	let mib = text
+		.trim()
+		.parse::<u64>()
+		.with_context(|| format!("invalid size \"{text}\", expected MiB like \"512\""))?;
+	mib.checked_mul(1 << 20)
+		.with_context(|| format!("{mib} MiB is too large"))
+}
+
+/// A glyph block given by `--include-range` or `--exclude-range`, either by its
+/// range like `"0-255"` or by its start index like `"256"`.
+#[derive(Clone, Debug, PartialEq)]
+struct BlockRange {
+	start: u32,
+	end: Option<u32>,
+}
+
+/// Parses a [`BlockRange`]. It is checked against the block size by [`block_starts`].
+fn parse_block_range(text: &str) -> Result<BlockRange> {
+	let parse = |number: &str| -> Result<u32> {
+		number
+			.trim()
+			.parse()
+			.with_context(|| format!("invalid block range \"{text}\""))
+	};
+	Ok(match text.split_once('-') {
+		Some((start, end)) => BlockRange {
+			start: parse(start)?,
+			end: Some(parse(end)?),
+		},
+		None => BlockRange {
+			start: parse(text)?,
+			end: None,
+		},
+	})
+}
+
+/// Returns the start indices of `ranges`, checking that each is a block of `block_size`.
+fn block_starts(ranges: &[BlockRange], block_size: u32) -> Result<Vec<u32>> {
+	ranges
+		.iter()
+		.map(|range| {
+			let text = match range.end {
+				Some(end) => format!("{}-{end}", range.start),
+				None => range.start.to_string(),
+			};
+			ensure!(
+				range.start % block_size == 0 && range.start <= 0x10FFFF,
+				"block range \"{text}\" must start at a multiple of {block_size}"
+			);
+			if let Some(end) = range.end {
+				ensure!(
+					end == range.start.saturating_add(block_size - 1),
+					"block range \"{text}\" must span {block_size} codepoints, like \"0-{}\"",
+					block_size - 1
+				);
+			}
+			Ok(range.start)
+		})
+		.collect()
+}
+
+/// Parses `--block-size`, see [`check_block_size`].
+fn parse_block_size(text: &str) -> Result<u32> {
+	let size = text
+		.trim()
+		.parse()
+		.with_context(|| format!("invalid block size \"{text}\""))?;
+	check_block_size(size)?;
+	Ok(size)
+}
+
+/// Collects the codepoints for `--include-notdef`, falling back to
+/// [`DEFAULT_NOTDEF_CODEPOINT`] if no ranges are given.
+fn notdef_codepoints(ranges: &[RangeInclusive<u32>]) -> HashSet<u32> {
+	if ranges.is_empty() {
+		HashSet::from([DEFAULT_NOTDEF_CODEPOINT])
+	} else {
+		ranges.iter().cloned().flatten().collect()
+	}
+}
+
+/// Reads the UTF-8 text corpus for `--text-file` and returns its distinct
+/// codepoints, without control characters like line breaks and tabs.
+fn text_codepoints(path: &Path) -> Result<HashSet<u32>> {
+	let text =
+		fs::read_to_string(path).with_context(|| format!("Failed to read text file {path:?}"))?;
+	Ok(text
+		.chars()
+		.filter(|c| !c.is_control())
+		.map(|c| c as u32)
+		.collect())
+}
+
+/// Prints the glyph block files `font_manager` would write for `--dry-run`,
+/// one `filename: count` line each with the estimated size of the file when
+/// rendered with `renderer`, followed by a summary on stderr.
+fn print_dry_run(
+	font_manager: &FontManager,
+	renderer: &Renderer,
+	stdout: &mut impl Write,
+) -> Result<()> {
+	let files = font_manager.planned_files_with_size(renderer)?;
+	for (filename, glyphs, size) in &files {
+		writeln!(stdout, "{filename}: {glyphs} glyphs, ~{size} bytes")?;
+	}
+	let glyphs = files.iter().map(|(_, glyphs, _)| glyphs).sum::<usize>();
+	let size = files.iter().map(|(_, _, size)| size).sum::<u64>();
+	eprintln!(
+		"Dry run: {} fonts, {} glyph blocks, {glyphs} glyphs, ~{:.1} MiB uncompressed. Nothing was written.",
+		font_manager.fonts.len(),
+		files.len(),
+		size as f64 / (1 << 20) as f64
+	);
+	Ok(())
+}
+
+/// Fails if `--stats` is set, but render times aren't measured in this build.
+fn ensure_stats_supported(stats: bool) -> Result<()> {
+	ensure!(
+		!stats || cfg!(feature = "profiling"),
+		"--stats requires building with the \"profiling\" feature"
+	);
+	Ok(())
+}
+
+/// Prints the approximate memory use of a render for `--stats`.
+fn print_memory_stats(stats: &MemoryStats) {
+	let mib = |bytes: u64| bytes as f64 / (1 << 20) as f64;
+	eprintln!(
+		"Memory: {:.1} MiB of fonts, at most {:.1} MiB in {} rendered blocks waiting to be written",
+		mib(stats.font_bytes),
+		mib(stats.peak_block_bytes),
+		stats.peak_blocks
+	);
+}
+
+/// Prints the slowest glyphs and the render time per font for `--stats`.
+#[cfg(feature = "profiling")]
+fn print_stats(renderer: &Renderer) {
+	eprint!("{}", renderer.profiler().report(STATS_SLOWEST_GLYPHS));
+}
+
+/// Without the `profiling` feature, [`ensure_stats_supported`] rejects `--stats`.
+#[cfg(not(feature = "profiling"))]
+fn print_stats(_renderer: &Renderer) {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_codepoint_range() {
+		assert_eq!(parse_codepoint_range("FFFD").unwrap(), 0xFFFD..=0xFFFD);
+		assert_eq!(parse_codepoint_range("U+00e9").unwrap(), 0xE9..=0xE9);
+		assert_eq!(parse_codepoint_range("4E00-9FFF").unwrap(), 0x4E00..=0x9FFF);
+		assert!(parse_codepoint_range("9FFF-4E00").is_err());
+		assert!(parse_codepoint_range("110000").is_err());
+		assert!(parse_codepoint_range("xyz").is_err());
+	}
+
+	#[test]
+	fn test_parse_max_codepoint() {
+		assert_eq!(parse_max_codepoint("0xFFFF").unwrap(), 0xFFFF);
+		assert_eq!(parse_max_codepoint("U+1f600").unwrap(), 0x1F600);
+		assert_eq!(parse_max_codepoint("65535").unwrap(), 0xFFFF);
+		assert!(parse_max_codepoint("FFFF").is_err());
+		assert!(parse_max_codepoint("0x110000").is_err());
+		assert!(parse_max_codepoint("-1").is_err());
+	}
+
+	#[test]
+	fn test_parse_max_memory() {
+		assert_eq!(parse_max_memory("512").unwrap(), 512 << 20);
+		assert_eq!(parse_max_memory("0").unwrap(), 0);
+		assert!(parse_max_memory("-1").is_err());
+		assert!(parse_max_memory("1.5").is_err());
+		// Would silently wrap around with a shift.
+		assert!(parse_max_memory(&(1u64 << 44).to_string()).is_err());
+	}
+
+	#[test]
+	fn test_block_starts() {
+		let starts = |text: &str, size: u32| {
+			let ranges = text
+				.split(',')
+				.map(parse_block_range)
+				.collect::<Result<Vec<_>>>()?;
+			block_starts(&ranges, size)
+		};
+		assert_eq!(
+			starts("0-255,19968-20223,256", 256).unwrap(),
+			[0, 19968, 256]
+		);
+		assert_eq!(starts("0-511,1024", 512).unwrap(), [0, 1024]);
+		assert!(starts("0-511", 256).is_err());
+		assert!(starts("256", 512).is_err());
+		assert!(starts("100", 256).is_err());
+		assert!(starts("abc", 256).is_err());
+	}
+
+	#[test]
+	fn test_parse_block_size() {
+		assert_eq!(parse_block_size("512").unwrap(), 512);
+		assert!(parse_block_size("500").is_err());
+		assert!(parse_block_size("-1").is_err());
+	}
+
+	#[test]
+	fn test_notdef_codepoints() {
+		assert_eq!(notdef_codepoints(&[]), HashSet::from([0xFFFD]));
+		assert_eq!(
+			notdef_codepoints(&[0x41..=0x42, 0xE000..=0xE000]),
+			HashSet::from([0x41, 0x42, 0xE000])
+		);
+	}
+
+	#[test]
+	fn test_ensure_stats_supported() {
+		assert!(ensure_stats_supported(false).is_ok());
+		assert_eq!(
+			ensure_stats_supported(true).is_ok(),
+			cfg!(feature = "profiling")
+		);
+	}
+}
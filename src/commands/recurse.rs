@@ -1,10 +1,24 @@
-use crate::{font::FontManager, render::Renderer, utils::prepare_output_directory, writer::Writer};
-use anyhow::{Context, Result};
+use super::{
+	block_starts, ensure_stats_supported, new_tar, notdef_codepoints, parse_block_range,
+	parse_block_size, parse_codepoint_range, parse_max_codepoint, parse_max_memory, print_dry_run,
+	print_stats, text_codepoints, write_glyphs, BlockRange, OutputOptions,
+};
+use crate::{
+	font::{FontLayout, FontManager, FontSettings, GLYPH_BLOCK_SIZE},
+	render::Renderer,
+	utils::{ensure_output_directory, prepare_output_directory},
+	writer::{ChecksumAlgorithm, GzipNaming, Writer},
+};
+use anyhow::{bail, ensure, Context, Result};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::Deserialize;
 use std::{
-	fs,
+	collections::{BTreeMap, HashSet},
+	fs::{self, File},
 	io::Write,
+	ops::RangeInclusive,
 	path::{self, Path, PathBuf},
+	slice,
 };
 
 /// Subcommand arguments for recursively scanning font files.
@@ -13,9 +27,21 @@ use std::{
 /// Recursively scans directories for `.ttf` or `.otf` files and converts them.
 ///
 /// If a directory contains a "fonts.json" file, it will be used to configure the conversion.
-/// A "fonts.json" has the structure: { name: string, sources: string[] }[] where:
+/// A "fonts.json" has the structure: { name: string, sources: string[], ... }[] where:
 ///   - name: the name of the font, like "Noto Sans Regular".
 ///   - sources: the list of font files to merge, relative to the directory.
+///   - size (optional): glyph size in pixels per EM, default 24.
+///   - buffer (optional): SDF padding in pixels, default 3.
+///   - baseline (optional): baseline offset in pixels relative to the size,
+///     positive values lower the glyphs, default 0.
+///   - charset (optional): a string of all characters to render, default all.
+///   - style, weight (optional): override the values in "font_families.json".
+///   - overrides (optional): maps hexadecimal codepoints or ranges, like "2192"
+///     or "2190-21FF", to one of the sources, which then provides these glyphs
+///     instead of the first source that covers them.
+///
+/// Other font files next to a "fonts.json" are ignored, but its subdirectories are
+/// still scanned, unless a source of the "fonts.json" lies inside them.
 ///
 /// # Examples
 ///
@@ -36,10 +62,243 @@ pub struct Subcommand {
 	#[arg(long, short = 't', conflicts_with = "output_directory")]
 	tar: bool,
 
+	/// Write every font into its own tar archive in DIR, like "DIR/fira_sans_regular.tar",
+	/// each with the font's glyph blocks and index files.
+	#[arg(
+		long,
+		conflicts_with_all = ["output_directory", "tar", "merge_into", "skip_existing"],
+		value_name = "DIR"
+	)]
+	tar_per_font: Option<PathBuf>,
+
+	/// Set the modification time of every tar entry to SECONDS since the Unix
+	/// epoch instead of the current time, so the same fonts always produce the
+	/// same archive. Only used with `tar` and `tar_per_font`.
+	#[arg(long, value_name = "SECONDS")]
+	mtime: Option<u64>,
+
+	/// Write all files below this subdirectory, like "fonts", inside the output
+	/// directory or tar.
+	#[arg(long, value_name = "PATH")]
+	prefix: Option<String>,
+
+	/// Retry writes that fail with a transient error, e.g. when streaming a tar
+	/// over the network, up to N times with a growing delay.
+	#[arg(long, value_name = "N")]
+	retries: Option<u32>,
+
+	/// Only scan DEPTH directory levels of each input directory, e.g. 1 for
+	/// the fonts directly inside it. By default, all subdirectories are scanned.
+	#[arg(long, value_parser = clap::value_parser!(u32).range(1..), value_name = "DEPTH")]
+	max_depth: Option<u32>,
+
+	/// Only render these fonts, matched by font id (e.g. "noto_sans_regular")
+	/// or family name (e.g. "Noto Sans"). Can be repeated.
+	#[arg(long, alias = "only-family", value_name = "ID_OR_FAMILY")]
+	only: Vec<String>,
+
+	/// Skip these fonts, matched by font id or family name. Can be repeated.
+	#[arg(long, alias = "exclude-family", value_name = "ID_OR_FAMILY")]
+	exclude: Vec<String>,
+
+	/// Skip fonts with fewer than N glyphs, like stub or icon fonts, so they
+	/// don't end up in the output and index files.
+	#[arg(long, value_name = "N")]
+	min_glyphs: Option<usize>,
+
+	/// Hash every written file with ALGORITHM ("sha256" or "blake3") and
+	/// list the checksums in a `manifest.json`.
+	#[arg(long, value_name = "ALGORITHM")]
+	checksums: Option<ChecksumAlgorithm>,
+
+	/// Render the `.notdef` glyph (usually a box) for codepoints missing from a font.
+	#[arg(long)]
+	include_notdef: bool,
+
+	/// Comma-separated hexadecimal codepoints or ranges, like "FFFD,4E00-9FFF",
+	/// that get the `.notdef` glyph if missing. Defaults to "FFFD".
+	#[arg(
+		long,
+		requires = "include_notdef",
+		value_delimiter = ',',
+		value_parser = parse_codepoint_range,
+		value_name = "RANGES"
+	)]
+	notdef_codepoints: Vec<RangeInclusive<u32>>,
+
+	/// Skip control characters (0x00–0x1F, 0x7F–0x9F), surrogates and
+	/// noncharacters, which fonts often map to empty glyphs.
+	#[arg(long)]
+	skip_control: bool,
+
+	/// Only render the characters used in the UTF-8 text file at PATH, e.g.
+	/// the place names of a map, as far as the fonts cover them.
+	#[arg(long, value_name = "PATH")]
+	text_file: Option<PathBuf>,
+
+	/// Skip codepoints above this one, like "0xFFFF" or "65535", e.g. for
+	/// clients that don't support blocks beyond the Basic Multilingual Plane.
+	#[arg(long, value_parser = parse_max_codepoint, value_name = "CODEPOINT")]
+	max_codepoint: Option<u32>,
+
+	/// Move the baseline of all glyphs by PX pixels relative to the glyph size,
+	/// e.g. for scripts aligned on an ideographic baseline. Positive values
+	/// lower the glyphs. A "baseline" in a "fonts.json" takes precedence.
+	#[arg(long, allow_hyphen_values = true, value_name = "PX")]
+	baseline: Option<i32>,
+
+	/// Directory naming: "id" (e.g. "fira_sans_regular/") or "family-style"
+	/// (e.g. "Fira Sans/Regular/").
+	#[arg(long, default_value_t = FontLayout::Id, value_name = "LAYOUT")]
+	layout: FontLayout,
+
+	/// Write all glyph blocks of a font into a single "glyphs.pbf", with the
+	/// byte offsets of each range in "glyphs.json".
+	#[arg(long)]
+	bundle: bool,
+
+	/// Gzip every glyph block, so a static server can send it with
+	/// `Content-Encoding: gzip`. NAMING is "pbf" to keep the file names (the
+	/// default) or "pbf.gz" to append ".gz", like `--gzip-blocks=pbf.gz`.
+	#[arg(
+		long,
+		num_args = 0..=1,
+		require_equals = true,
+		default_missing_value = "pbf",
+		conflicts_with = "bundle",
+		value_name = "NAMING"
+	)]
+	gzip_blocks: Option<GzipNaming>,
+
+	/// Also gzip the JSON files, like "index.json" and "font_families.json",
+	/// named like the blocks, so the whole output is encoded the same way.
+	#[arg(long, requires = "gzip_blocks")]
+	gzip_json: bool,
+
+	/// Write an empty but valid block for each of the 256 ranges from "0-255"
+	/// to "65280-65535" that has no glyphs, so every range a client requests exists.
+	#[arg(long)]
+	emit_empty_ranges: bool,
+
+	/// Number of codepoints per glyph block file, a power of two from 64 to 65536.
+	/// maplibre/mapbox clients expect the default of 256.
+	#[arg(long, default_value_t = GLYPH_BLOCK_SIZE, value_parser = parse_block_size, value_name = "SIZE")]
+	block_size: u32,
+
+	/// Write one file per script instead of per block of codepoints, like
+	/// "latin.pbf", "arabic.pbf" and "cjk.pbf", for clients that load all
+	/// glyphs of a script at once. Codepoints the script table doesn't cover
+	/// go to "other.pbf".
+	#[arg(
+		long,
+		conflicts_with_all = ["block_size", "emit_empty_ranges", "include_range", "exclude_range", "bundle"]
+	)]
+	by_script: bool,
+
+	/// Only write these glyph blocks, given by range like "19968-20223" or by
+	/// start index like "19968". Comma-separated or repeated.
+	#[arg(
+		long,
+		value_delimiter = ',',
+		value_parser = parse_block_range,
+		value_name = "RANGES"
+	)]
+	include_range: Vec<BlockRange>,
+
+	/// Skip these glyph blocks, given like `--include-range`.
+	#[arg(
+		long,
+		value_delimiter = ',',
+		value_parser = parse_block_range,
+		value_name = "RANGES"
+	)]
+	exclude_range: Vec<BlockRange>,
+
+	/// Fail if any glyph can't be rendered, instead of skipping it with a warning.
+	#[arg(long)]
+	strict: bool,
+
+	/// Decode every glyph block again after writing it, and fail if it's
+	/// corrupted or has a different number of glyphs than were rendered.
+	/// Directory outputs are read back from disk.
+	#[arg(long)]
+	validate: bool,
+
+	/// Keep the existing output directory and only render the glyph blocks that
+	/// are missing, e.g. to resume an interrupted run. Not available with
+	/// `--checksums` and `--bundle`, which need every block.
+	#[arg(long, conflicts_with_all = ["tar", "checksums", "bundle"])]
+	skip_existing: bool,
+
+	/// Write into the existing glyph directory DIR instead of a new one, keeping
+	/// its fonts: `index.json`, `font_families.json` and `fontstacks.json` list
+	/// both the existing and the new fonts. Mutually exclusive with
+	/// `output_directory` and `tar`, and with `checksums`, since the manifest
+	/// would only list the new files.
+	#[arg(
+		long,
+		conflicts_with_all = ["output_directory", "tar", "checksums"],
+		value_name = "DIR"
+	)]
+	merge_into: Option<String>,
+
+	/// Soft limit in MiB for rendered glyph blocks that wait to be written,
+	/// e.g. when writing to a slow disk. Fonts are held in memory regardless.
+	#[arg(long, value_parser = parse_max_memory, value_name = "MIB")]
+	max_memory: Option<u64>,
+
+	/// Print the slowest glyphs, the render time per font, the approximate
+	/// memory use and the number of glyphs whose halo the buffer cuts off to
+	/// stderr. Requires building with the "profiling" feature.
+	#[arg(long)]
+	stats: bool,
+
+	/// List the glyph block files that would be written, with their glyph
+	/// counts and estimated sizes, without rendering or writing anything.
+	#[arg(long)]
+	dry_run: bool,
+
+	/// Only write the index files, like `index.json` and `font_families.json`,
+	/// e.g. to update them in an existing glyph directory. Nothing is rendered
+	/// and the output directory is kept.
+	#[arg(long)]
+	output_index_only: bool,
+
+	/// Also write a `fontstacks.json` that maps every fontstack name, like
+	/// "Fira Sans Regular", to its font directory.
+	#[arg(long)]
+	fontstacks: bool,
+
+	/// Also write a `ranges.json` to every font directory, listing the
+	/// filenames of its non-empty glyph blocks, e.g. for cache warming.
+	#[arg(long)]
+	list_ranges: bool,
+
 	/// Skip writing the `font_families.json` file.
 	#[arg(long)]
 	no_families: bool,
 
+	/// Add the copyright notice, license and license URL of every font, as
+	/// found in its `name` table, to `font_families.json`, so the attribution
+	/// that licenses like the SIL Open Font License require can be shown.
+	#[arg(long, conflicts_with = "no_families")]
+	copyright: bool,
+
+	/// Write "font_families.json" as `{"version": 2, "families": [...]}`, so
+	/// clients can detect its schema, instead of the legacy bare array.
+	#[arg(long, conflicts_with = "no_families")]
+	versioned_families: bool,
+
+	/// Indent the JSON files, like "index.json" and "font_families.json", for
+	/// reading, e.g. when debugging. By default they are compact.
+	#[arg(long)]
+	pretty: bool,
+
+	/// Store the family, size, units per EM and ascender of the font in every
+	/// glyph block, so it describes itself. Map renderers ignore this field.
+	#[arg(long)]
+	concat_metadata: bool,
+
 	/// Skip writing the `index.json` file.
 	#[arg(long)]
 	no_index: bool,
@@ -53,13 +312,109 @@ pub struct Subcommand {
 	single_thread: bool,
 }
 
+impl Subcommand {
+	/// Returns the options for [`write_glyphs`].
+	fn output_options(&self) -> OutputOptions<'_> {
+		OutputOptions {
+			prefix: self.prefix.as_deref(),
+			retries: self.retries,
+			bundle: self.bundle,
+			gzip_blocks: self.gzip_blocks,
+			gzip_json: self.gzip_json,
+			checksums: self.checksums,
+			output_index_only: self.output_index_only,
+			stats: self.stats,
+			no_index: self.no_index,
+			no_families: self.no_families,
+			fontstacks: self.fontstacks,
+			list_ranges: self.list_ranges,
+		}
+	}
+}
+
 /// Describes the structure of a `fonts.json` for merged font sets.
+///
+/// Unknown fields are rejected, so typos in optional overrides don't go unnoticed.
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct FontConfig {
 	/// Descriptive name for the font or set of merged fonts.
 	name: String,
 	/// Paths to `.ttf` / `.otf` files, relative to the containing folder.
 	sources: Vec<String>,
+	/// Glyph size in pixels per EM.
+	size: Option<u32>,
+	/// SDF padding in pixels on every side of each glyph.
+	buffer: Option<u32>,
+	/// Baseline offset in pixels relative to the size, defaults to 0.
+	baseline: Option<i32>,
+	/// Only render the characters contained in this string.
+	charset: Option<String>,
+	/// Style override, e.g. "italic".
+	style: Option<String>,
+	/// Weight override, e.g. 700.
+	weight: Option<u16>,
+	/// Codepoints or ranges, like "2192" or "2190-21FF", mapped to the source
+	/// that provides their glyphs.
+	overrides: Option<BTreeMap<String, String>>,
+}
+
+impl FontConfig {
+	/// Validates the optional overrides and converts them into [`FontSettings`].
+	fn settings(&self) -> Result<FontSettings> {
+		ensure!(
+			self.size != Some(0),
+			"font \"{}\": size must be greater than 0",
+			self.name
+		);
+		let mut overrides = Vec::new();
+		for (range, source) in self.overrides.iter().flatten() {
+			let Some(index) = self.sources.iter().position(|s| s == source) else {
+				bail!(
+					"font \"{}\": override source \"{source}\" is not one of the sources",
+					self.name
+				);
+			};
+			let range = parse_codepoint_range(range)
+				.with_context(|| format!("font \"{}\": invalid override", self.name))?;
+			overrides.push((range, index));
+		}
+		Ok(FontSettings {
+			size: self.size,
+			buffer: self.buffer,
+			baseline: self.baseline,
+			charset: self
+				.charset
+				.as_ref()
+				.map(|chars| chars.chars().map(|c| c as u32).collect()),
+			style: self.style.clone(),
+			weight: self.weight,
+			overrides,
+			..Default::default()
+		})
+	}
+}
+
+/// Counts the font files [`scan`] loaded and the ones it had to skip.
+#[derive(Debug, Default)]
+struct ScanStats {
+	/// Number of font files added to the [`FontManager`].
+	loaded: usize,
+	/// Number of unreadable files, directories or fonts that were skipped.
+	failed: usize,
+}
+
+impl ScanStats {
+	/// Counts `result` as loaded or failed. Failures are logged to stderr with `path`.
+	fn record(&mut self, path: &Path, result: Result<()>) {
+		match result {
+			Ok(()) => self.loaded += 1,
+			Err(err) => {
+				eprintln!("Skipping {path:?}: {err:#}");
+				self.failed += 1;
+			}
+		}
+	}
 }
 
 /// Executes the recurse subcommand logic.
@@ -67,76 +422,434 @@ struct FontConfig {
 /// Scans specified directories, reading `fonts.json` if present, and
 /// merges fonts into a [`FontManager`]. The glyph data is written
 /// either to a directory or stdout tar.
+///
+/// Unreadable files and invalid fonts are skipped with a warning. The command
+/// only fails if no font could be loaded at all.
 pub fn run(args: &Subcommand, stdout: &mut (impl Write + Send + Sync + 'static)) -> Result<()> {
 	let mut font_manager = FontManager::new(!args.single_thread);
+	font_manager.layout = args.layout;
+	font_manager.emit_empty_ranges = args.emit_empty_ranges;
+	font_manager.block_size = args.block_size;
+	font_manager.group_by_script = args.by_script;
+	font_manager.include_blocks = block_starts(&args.include_range, args.block_size)?;
+	font_manager.exclude_blocks = block_starts(&args.exclude_range, args.block_size)?;
+	ensure_stats_supported(args.stats)?;
+	font_manager.strict = args.strict;
+	font_manager.validate = args.validate;
+	font_manager.skip_existing = args.skip_existing;
+	font_manager.merge_existing = args.merge_into.is_some();
+	font_manager.include_copyright = args.copyright;
+	font_manager.versioned_families = args.versioned_families;
+	font_manager.pretty_json = args.pretty;
+	font_manager.concat_metadata = args.concat_metadata;
+	font_manager.max_memory = args.max_memory;
+	let mut stats = ScanStats::default();
 
 	for dir in &args.input_directories {
 		let canonical = path::absolute(dir)?.canonicalize()?;
 		eprintln!("Scanning directory: {canonical:?}");
-		scan(&canonical, &mut font_manager)?;
+		scan(&canonical, args.max_depth, &mut font_manager, &mut stats)?;
 	}
 
-	let mut writer = if args.tar {
-		eprintln!("Rendering glyphs as tar to stdout.");
-		Writer::new_tar(stdout)
-	} else {
-		let out_dir = prepare_output_directory(args.output_directory.as_deref().unwrap_or("output"))?;
-		eprintln!("Rendering glyphs to directory: {out_dir:?}");
-		Writer::new_file(path::absolute(out_dir)?)
-	};
-
-	let renderer = Renderer::new(args.dummy);
+	if stats.failed > 0 {
+		eprintln!("{} font file(s) could not be loaded.", stats.failed);
+		if stats.loaded == 0 {
+			bail!("No font could be loaded.");
+		}
+	}
 
-	font_manager.render_glyphs(&mut writer, &renderer)?;
-	if !args.no_index {
-		font_manager.write_index_json(&mut writer)?;
+	font_manager.filter_fonts(&args.only, &args.exclude);
+	if let Some(min_glyphs) = args.min_glyphs {
+		for (id, count) in font_manager.remove_small_fonts(min_glyphs) {
+			eprintln!("Skipping font {id:?}: only {count} glyph(s), fewer than {min_glyphs}");
+		}
+	}
+	if args.include_notdef {
+		font_manager.include_notdef(&notdef_codepoints(&args.notdef_codepoints));
+	}
+	if args.skip_control {
+		font_manager.skip_non_rendering();
+	}
+	if let Some(path) = &args.text_file {
+		font_manager.restrict_charset(&text_codepoints(path)?);
+	}
+	if let Some(max_codepoint) = args.max_codepoint {
+		font_manager.limit_codepoints(max_codepoint);
 	}
-	if !args.no_families {
-		font_manager.write_families_json(&mut writer)?;
+	let mut renderer = Renderer::new(args.dummy);
+	if let Some(baseline) = args.baseline {
+		renderer = renderer.with_baseline(baseline);
+	}
+	if args.dry_run {
+		return print_dry_run(&font_manager, &renderer, stdout);
 	}
 
-	writer.finish()?;
+	if let Some(dir) = &args.tar_per_font {
+		fs::create_dir_all(dir).with_context(|| format!("creating directory {dir:?}"))?;
+		for (id, font_manager) in font_manager.into_single_fonts() {
+			let path = dir.join(format!("{id}.tar"));
+			eprintln!("Rendering glyphs as tar to file: {path:?}");
+			let mut file = File::create(&path).with_context(|| format!("creating file {path:?}"))?;
+			write_glyphs(
+				&args.output_options(),
+				&font_manager,
+				&renderer,
+				new_tar(&mut file, args.mtime),
+			)?;
+		}
+	} else {
+		let writer = if args.tar {
+			eprintln!("Rendering glyphs as tar to stdout.");
+			new_tar(stdout, args.mtime)
+		} else if let Some(out_dir) = &args.merge_into {
+			ensure!(
+				Path::new(out_dir).is_dir(),
+				"the glyph directory {out_dir:?} to merge into doesn't exist"
+			);
+			eprintln!("Merging glyphs into directory: {out_dir:?}");
+			Writer::new_file(path::absolute(out_dir)?)
+		} else {
+			let out_dir = args.output_directory.as_deref().unwrap_or("output");
+			let out_dir = if args.skip_existing || args.output_index_only {
+				ensure_output_directory(out_dir)?
+			} else {
+				prepare_output_directory(out_dir)?
+			};
+			eprintln!("Rendering glyphs to directory: {out_dir:?}");
+			Writer::new_file(path::absolute(out_dir)?)
+		};
+		write_glyphs(&args.output_options(), &font_manager, &renderer, writer)?;
+	}
+	if args.stats {
+		print_stats(&renderer);
+	}
 
 	Ok(())
 }
 
-/// Recursively scans directories and adds matching font files to the [`FontManager`].
-fn scan(path: &Path, font_manager: &mut FontManager) -> Result<()> {
-	if path.is_file() {
-		let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-		if extension == "ttf" || extension == "otf" {
-			font_manager.add_path(path)?;
+/// A font found by [`collect_sources`], to be loaded by [`scan`].
+enum Source {
+	/// A single `.ttf` or `.otf` file, named after its metadata.
+	File(PathBuf),
+	/// A font configured in the `fonts.json` at `config`.
+	Config {
+		config: PathBuf,
+		name: String,
+		sources: Vec<PathBuf>,
+		settings: Box<FontSettings>,
+	},
+}
+
+impl Source {
+	/// Returns the font files of this source.
+	fn paths(&self) -> &[PathBuf] {
+		match self {
+			Source::File(path) => slice::from_ref(path),
+			Source::Config { sources, .. } => sources,
 		}
-	} else if path.is_dir() {
-		let font_file = path.join("fonts.json");
-		if font_file.exists() {
-			let data =
-				fs::read(&font_file).with_context(|| format!("Failed to read {font_file:?}"))?;
-			let configs = serde_json::from_slice::<Vec<FontConfig>>(&data)?;
-
-			for c in configs {
-				font_manager.add_font_with_name(
-					&c.name,
-					&c.sources
-						.iter()
-						.map(|src| path.join(src))
-						.collect::<Vec<_>>(),
-				)?;
+	}
+}
+
+/// Scans directories up to `max_depth` levels deep (see [`collect_sources`])
+/// and adds matching font files to the [`FontManager`].
+///
+/// A directory containing a `fonts.json` is handled by that config: font files
+/// directly inside it are not added on their own. Its subdirectories are still
+/// scanned, except those containing any of the config's sources, so referenced
+/// fonts are never added twice.
+///
+/// All fonts are found first, then read and parsed in parallel if
+/// [`FontManager::parallel`] is set, and finally added in the order they were
+/// found, so the grouping of files into fonts doesn't depend on the thread timing.
+///
+/// Unreadable files or directories and fonts that fail to parse are counted in
+/// `stats` and skipped. Only an invalid `fonts.json` aborts the scan.
+fn scan(
+	path: &Path,
+	max_depth: Option<u32>,
+	font_manager: &mut FontManager,
+	stats: &mut ScanStats,
+) -> Result<()> {
+	let sources = collect_sources(path, max_depth, font_manager.parallel, stats)?;
+
+	let paths = sources
+		.iter()
+		.flat_map(|source| source.paths().iter().cloned())
+		.collect::<Vec<_>>();
+	let mut files = font_manager.read_files(&paths).into_iter();
+
+	for source in sources {
+		// Always take all files of the source, so the next source starts at its own.
+		let source_files = files
+			.by_ref()
+			.take(source.paths().len())
+			.collect::<Vec<_>>()
+			.into_iter()
+			.collect::<Result<Vec<_>, _>>();
+		match source {
+			Source::File(path) => {
+				let result = source_files.map(|files| {
+					for file in files {
+						font_manager.add_file(file);
+					}
+				});
+				stats.record(&path, result.map_err(Into::into));
 			}
-		} else {
-			for entry in fs::read_dir(path)? {
-				scan(&entry?.path(), font_manager)?;
+			Source::Config {
+				config,
+				name,
+				settings,
+				..
+			} => {
+				let result = source_files
+					.map(|files| font_manager.add_files_with_settings(&name, files, *settings))
+					.with_context(|| format!("loading font \"{name}\""));
+				stats.record(&config, result);
 			}
 		}
 	}
 	Ok(())
 }
 
+/// Collects the fonts below `path` for [`scan`], without reading them.
+///
+/// The directories are walked level by level instead of recursively, so deep
+/// nesting can't overflow the stack, and the directories of each level are
+/// read in parallel if `parallel`. With `max_depth`, only that many levels are
+/// read: `Some(1)` only finds the fonts directly inside `path`.
+///
+/// Entries are sorted by path, so the fonts are found in the same order on
+/// every file system. Every directory is read once, by its canonical path, so
+/// symlinks that point back up the tree can't cause an endless walk.
+fn collect_sources(
+	path: &Path,
+	max_depth: Option<u32>,
+	parallel: bool,
+	stats: &mut ScanStats,
+) -> Result<Vec<Source>> {
+	if !path.is_dir() {
+		return Ok(font_file_source(path).into_iter().collect());
+	}
+
+	let mut sources = Vec::new();
+	let mut visited = HashSet::from([path
+		.canonicalize()
+		.with_context(|| format!("Failed to resolve {path:?}"))?]);
+	let mut level = vec![path.to_path_buf()];
+	let mut depth = 1;
+	while !level.is_empty() {
+		let contents = if parallel {
+			level
+				.par_iter()
+				.map(|dir| read_directory(dir))
+				.collect::<Vec<_>>()
+		} else {
+			level.iter().map(|dir| read_directory(dir)).collect()
+		};
+		level = Vec::new();
+		for content in contents {
+			let content = content?;
+			for (path, err) in content.failures {
+				stats.record(&path, Err(err));
+			}
+			sources.extend(content.sources);
+			for dir in content.subdirectories {
+				match dir.canonicalize() {
+					Ok(canonical) => {
+						if visited.insert(canonical) {
+							level.push(dir);
+						}
+					}
+					Err(err) => stats.record(&dir, Err(err.into())),
+				}
+			}
+		}
+		if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+			break;
+		}
+		depth += 1;
+	}
+	Ok(sources)
+}
+
+/// Returns a [`Source::File`] if `path` is a `.ttf` or `.otf` file.
+fn font_file_source(path: &Path) -> Option<Source> {
+	let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+	(path.is_file() && (extension == "ttf" || extension == "otf"))
+		.then(|| Source::File(path.to_path_buf()))
+}
+
+/// The fonts and subdirectories that [`read_directory`] found in a directory.
+#[derive(Default)]
+struct DirectoryContent {
+	sources: Vec<Source>,
+	/// Subdirectories to scan next, sorted by path.
+	subdirectories: Vec<PathBuf>,
+	/// Unreadable entries, to be counted in [`ScanStats`].
+	failures: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Reads the fonts of the directory `path` for [`collect_sources`], from its
+/// `fonts.json` if it has one, or from its font files otherwise.
+///
+/// Subdirectories containing a source of the `fonts.json` are skipped, so
+/// referenced fonts are never added twice. Only an invalid `fonts.json` is an error.
+fn read_directory(path: &Path) -> Result<DirectoryContent> {
+	let mut content = DirectoryContent::default();
+	let font_file = path.join("fonts.json");
+	let mut referenced = Vec::new();
+	let has_config = font_file.exists();
+	if has_config {
+		let data = fs::read(&font_file).with_context(|| format!("Failed to read {font_file:?}"))?;
+		let configs = serde_json::from_slice::<Vec<FontConfig>>(&data)
+			.with_context(|| format!("Failed to parse {font_file:?}"))?;
+
+		for c in configs {
+			let font_sources = c
+				.sources
+				.iter()
+				.map(|src| path.join(src))
+				.collect::<Vec<_>>();
+			referenced.extend(font_sources.iter().cloned());
+			content.sources.push(Source::Config {
+				config: font_file.clone(),
+				settings: Box::new(c.settings()?),
+				name: c.name,
+				sources: font_sources,
+			});
+		}
+	}
+
+	let entries = match fs::read_dir(path) {
+		Ok(entries) => entries,
+		Err(err) => {
+			content.failures.push((path.to_path_buf(), err.into()));
+			return Ok(content);
+		}
+	};
+	let mut sub_paths = Vec::new();
+	for entry in entries {
+		match entry {
+			Ok(entry) => sub_paths.push(entry.path()),
+			Err(err) => content.failures.push((path.to_path_buf(), err.into())),
+		}
+	}
+	sub_paths.sort_unstable();
+
+	for sub_path in sub_paths {
+		if sub_path.is_dir() {
+			if !referenced.iter().any(|src| src.starts_with(&sub_path)) {
+				content.subdirectories.push(sub_path);
+			}
+		} else if !has_config {
+			content.sources.extend(font_file_source(&sub_path));
+		}
+	}
+	Ok(content)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::font::FontWrapper;
 
+	impl Default for Subcommand {
+		fn default() -> Self {
+			Subcommand {
+				input_directories: vec![],
+				output_directory: None,
+				tar: false,
+				tar_per_font: None,
+				mtime: None,
+				prefix: None,
+				retries: None,
+				max_depth: None,
+				only: vec![],
+				exclude: vec![],
+				min_glyphs: None,
+				checksums: None,
+				include_notdef: false,
+				notdef_codepoints: vec![],
+				skip_control: false,
+				text_file: None,
+				max_codepoint: None,
+				baseline: None,
+				layout: FontLayout::Id,
+				bundle: false,
+				gzip_blocks: None,
+				gzip_json: false,
+				emit_empty_ranges: false,
+				block_size: GLYPH_BLOCK_SIZE,
+				by_script: false,
+				include_range: vec![],
+				exclude_range: vec![],
+				strict: false,
+				validate: false,
+				skip_existing: false,
+				merge_into: None,
+				max_memory: None,
+				stats: false,
+				dry_run: false,
+				output_index_only: false,
+				fontstacks: false,
+				list_ranges: false,
+				no_families: false,
+				copyright: false,
+				versioned_families: false,
+				pretty: false,
+				concat_metadata: false,
+				no_index: false,
+				dummy: false,
+				single_thread: false,
+			}
+		}
+	}
+
+	#[test]
+	fn test_default_matches_cli_defaults() {
+		use clap::{Args, Command, FromArgMatches};
+		let matches = Subcommand::augment_args(Command::new("recurse"))
+			.try_get_matches_from(["recurse", "fonts"])
+			.unwrap();
+		let args = Subcommand::from_arg_matches(&matches).unwrap();
+		let expected = Subcommand {
+			input_directories: vec![PathBuf::from("fonts")],
+			..Default::default()
+		};
+		assert_eq!(format!("{args:?}"), format!("{expected:?}"));
+	}
+
+	#[test]
+	fn test_skip_existing_conflicts() {
+		use clap::{Args, Command};
+		let parse = |args: &[&str]| {
+			Subcommand::augment_args(Command::new("recurse"))
+				.try_get_matches_from([&["recurse", "fonts", "-o", "glyphs"], args].concat())
+		};
+		assert!(parse(&["--skip-existing"]).is_ok());
+		assert!(parse(&["--checksums", "sha256", "--bundle"]).is_ok());
+		for args in [&["--checksums", "sha256"][..], &["--bundle"]] {
+			let err = parse(&[&["--skip-existing"], args].concat()).unwrap_err();
+			assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+		}
+	}
+
+	#[test]
+	fn test_merge_into_conflicts_with_checksums() {
+		use clap::{Args, Command};
+		let err = Subcommand::augment_args(Command::new("recurse"))
+			.try_get_matches_from([
+				"recurse",
+				"fonts",
+				"--merge-into",
+				"glyphs",
+				"--checksums",
+				"sha256",
+			])
+			.unwrap_err();
+		assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+	}
+
 	fn get_names(font: &FontWrapper) -> Vec<String> {
 		let mut names = font
 			.files
@@ -151,7 +864,12 @@ mod tests {
 	fn test_scan() -> Result<()> {
 		let dir_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
 		let mut font_manager = FontManager::new(false);
-		scan(&dir_path, &mut font_manager)?;
+		scan(
+			&dir_path,
+			None,
+			&mut font_manager,
+			&mut ScanStats::default(),
+		)?;
 
 		let mut keys = font_manager.fonts.keys().collect::<Vec<_>>();
 		keys.sort_unstable();
@@ -220,11 +938,8 @@ mod tests {
 				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
 			],
 			output_directory: Some(out.to_str().unwrap().to_string()),
-			tar: false,
-			no_families: false,
-			no_index: false,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
@@ -251,11 +966,10 @@ mod tests {
 				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
 			],
 			output_directory: Some(out.to_str().unwrap().to_string()),
-			tar: false,
 			no_families: true,
 			no_index: true,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
@@ -288,11 +1002,8 @@ mod tests {
 		let args = Subcommand {
 			input_directories: vec![font_dir],
 			output_directory: Some(out.to_str().unwrap().to_string()),
-			tar: false,
-			no_families: false,
-			no_index: false,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
@@ -304,6 +1015,268 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_run_with_fonts_json_overrides() -> Result<()> {
+		use crate::protobuf::PbfGlyphs;
+		use prost::Message;
+
+		let temp = tempfile::tempdir()?;
+		let font_dir = temp.path().join("input");
+		std::fs::create_dir(&font_dir)?;
+		std::fs::copy(
+			PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf"),
+			font_dir.join("font.ttf"),
+		)?;
+		std::fs::write(
+			font_dir.join("fonts.json"),
+			r#"[
+				{"name": "Default Sans", "sources": ["font.ttf"]},
+				{"name": "Big Sans", "sources": ["font.ttf"], "size": 32, "charset": "AB", "weight": 700}
+			]"#,
+		)?;
+
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_directories: vec![font_dir],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut Vec::<u8>::new())?;
+
+		let get_glyphs = |id: &str| -> Result<Vec<crate::protobuf::PbfGlyph>> {
+			let data = std::fs::read(out.join(id).join("0-255.pbf"))?;
+			let mut glyphs = PbfGlyphs::decode(data.as_slice())?.into_glyphs();
+			glyphs.sort_unstable();
+			Ok(glyphs)
+		};
+
+		let default_glyphs = get_glyphs("default_sans")?;
+		let big_glyphs = get_glyphs("big_sans")?;
+		let default_a = default_glyphs.iter().find(|g| g.id == 65).unwrap();
+
+		// The charset limits "Big Sans" to two glyphs, rendered larger.
+		assert_eq!(
+			big_glyphs.iter().map(|g| g.id).collect::<Vec<_>>(),
+			[65, 66]
+		);
+		assert_eq!((default_a.width, default_a.height), (14, 17));
+		assert_eq!((big_glyphs[0].width, big_glyphs[0].height), (19, 23));
+		assert!(!out.join("big_sans/256-511.pbf").exists());
+
+		let families = std::fs::read_to_string(out.join("font_families.json"))?;
+		assert!(families.contains("\"weight\":700"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_font_config_overrides() -> Result<()> {
+		let config = |json: &str| serde_json::from_str::<FontConfig>(json).unwrap().settings();
+
+		let settings = config(
+			r#"{"name": "A", "sources": ["text.ttf", "arrows.ttf"], "overrides": {"2190-2192": "arrows.ttf", "U+41": "text.ttf"}}"#,
+		)?;
+		assert_eq!(settings.overrides, [(0x2190..=0x2192, 1), (0x41..=0x41, 0)]);
+
+		// The whole codespace is a single entry, and the last matching range wins.
+		let settings = config(
+			r#"{"name": "A", "sources": ["text.ttf", "arrows.ttf"], "overrides": {"0-10FFFF": "text.ttf", "2192": "arrows.ttf"}}"#,
+		)?;
+		assert_eq!(
+			settings.overrides,
+			[(0..=0x10FFFF, 0), (0x2192..=0x2192, 1)]
+		);
+		assert_eq!(settings.override_index(0x2191), Some(0));
+		assert_eq!(settings.override_index(0x2192), Some(1));
+
+		let err =
+			config(r#"{"name": "A", "sources": ["text.ttf"], "overrides": {"2192": "arrows.ttf"}}"#)
+				.unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("\"arrows.ttf\" is not one of the sources"));
+		let err =
+			config(r#"{"name": "A", "sources": ["text.ttf"], "overrides": {"arrow": "text.ttf"}}"#)
+				.unwrap_err();
+		assert_eq!(err.to_string(), "font \"A\": invalid override");
+		Ok(())
+	}
+
+	#[test]
+	fn test_scan_in_parallel_matches_serial() -> Result<()> {
+		let dir_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let scan_fonts = |parallel: bool| -> Result<Vec<(String, Vec<String>)>> {
+			let mut font_manager = FontManager::new(parallel);
+			scan(
+				&dir_path,
+				None,
+				&mut font_manager,
+				&mut ScanStats::default(),
+			)?;
+			let mut fonts = font_manager
+				.fonts
+				.iter()
+				.map(|(id, font)| {
+					let names = font.files.iter().map(|f| f.metadata.name.clone());
+					(id.clone(), names.collect())
+				})
+				.collect::<Vec<_>>();
+			fonts.sort_unstable();
+			Ok(fonts)
+		};
+
+		let serial = scan_fonts(false)?;
+		assert_eq!(serial.len(), 2);
+		assert_eq!(serial[1].1.len(), 23);
+		assert_eq!(scan_fonts(true)?, serial);
+		Ok(())
+	}
+
+	#[test]
+	fn test_scan_deeply_nested_directories() -> Result<()> {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let temp = tempfile::tempdir()?;
+		std::fs::copy(
+			testdata.join("Fira Sans - Regular.ttf"),
+			temp.path().join("top.ttf"),
+		)?;
+		let mut deep = temp.path().to_path_buf();
+		for _ in 0..300 {
+			deep.push("d");
+		}
+		std::fs::create_dir_all(&deep)?;
+		std::fs::copy(
+			testdata.join("Noto Sans/Noto Sans - Regular.ttf"),
+			deep.join("deep.ttf"),
+		)?;
+
+		for (parallel, max_depth, expected) in [
+			(false, None, vec!["fira_sans_regular", "noto_sans_regular"]),
+			(true, None, vec!["fira_sans_regular", "noto_sans_regular"]),
+			(false, Some(300), vec!["fira_sans_regular"]),
+			(true, Some(1), vec!["fira_sans_regular"]),
+		] {
+			let mut manager = FontManager::new(parallel);
+			scan(
+				temp.path(),
+				max_depth,
+				&mut manager,
+				&mut ScanStats::default(),
+			)?;
+			let mut keys = manager.fonts.keys().collect::<Vec<_>>();
+			keys.sort_unstable();
+			assert_eq!(keys, expected);
+		}
+		Ok(())
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_scan_symlink_cycle() -> Result<()> {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let temp = tempfile::tempdir()?;
+		let fonts = temp.path().join("fonts");
+		std::fs::create_dir(&fonts)?;
+		std::fs::copy(
+			testdata.join("Fira Sans - Regular.ttf"),
+			fonts.join("fira.ttf"),
+		)?;
+		// "fonts/loop" points back to the top, so the tree is endless.
+		std::os::unix::fs::symlink(temp.path(), fonts.join("loop"))?;
+
+		for parallel in [false, true] {
+			let mut manager = FontManager::new(parallel);
+			let mut stats = ScanStats::default();
+			scan(temp.path(), None, &mut manager, &mut stats)?;
+			assert_eq!(
+				manager.fonts.keys().collect::<Vec<_>>(),
+				["fira_sans_regular"]
+			);
+			assert_eq!(manager.fonts["fira_sans_regular"].files.len(), 1);
+			assert_eq!((stats.loaded, stats.failed), (1, 0));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_scan_rejects_unknown_fonts_json_fields() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		std::fs::write(
+			temp.path().join("fonts.json"),
+			r#"[{"name": "X", "sources": [], "sise": 32}]"#,
+		)?;
+
+		let mut manager = FontManager::new(false);
+		let err = scan(temp.path(), None, &mut manager, &mut ScanStats::default()).unwrap_err();
+		assert!(format!("{err:#}").contains("unknown field `sise`"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_scan_fonts_json_with_subdirectories() -> Result<()> {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
+		let temp = tempfile::tempdir()?;
+
+		// The top-level fonts.json references a font in "referenced/"; "extra/"
+		// holds an unrelated font that must still be found.
+		std::fs::create_dir(temp.path().join("referenced"))?;
+		std::fs::create_dir(temp.path().join("extra"))?;
+		std::fs::copy(
+			testdata.join("Fira Sans - Regular.ttf"),
+			temp.path().join("referenced/font.ttf"),
+		)?;
+		std::fs::copy(
+			testdata.join("Noto Sans/Noto Sans - Regular.ttf"),
+			temp.path().join("extra/noto.ttf"),
+		)?;
+		std::fs::write(
+			temp.path().join("fonts.json"),
+			r#"[{"name": "Custom Sans", "sources": ["referenced/font.ttf"]}]"#,
+		)?;
+
+		let mut manager = FontManager::new(false);
+		scan(temp.path(), None, &mut manager, &mut ScanStats::default())?;
+
+		let mut keys = manager.fonts.keys().collect::<Vec<_>>();
+		keys.sort_unstable();
+		assert_eq!(keys, ["custom_sans", "noto_sans_regular"]);
+		// "referenced/" was not scanned again, so the font was added only once.
+		assert_eq!(manager.fonts["custom_sans"].files.len(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_skips_invalid_fonts() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let font_dir = temp.path().join("input");
+		std::fs::create_dir(&font_dir)?;
+		std::fs::copy(
+			PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf"),
+			font_dir.join("good.ttf"),
+		)?;
+		std::fs::write(font_dir.join("bad.ttf"), b"\x00\x01\x00\x00 truncated")?;
+
+		let mut stats = ScanStats::default();
+		scan(&font_dir, None, &mut FontManager::new(false), &mut stats)?;
+		assert_eq!((stats.loaded, stats.failed), (1, 1));
+
+		let out = temp.path().join("glyphs");
+		let args = Subcommand {
+			input_directories: vec![font_dir.clone()],
+			output_directory: Some(out.to_str().unwrap().to_string()),
+			dummy: true,
+			..Default::default()
+		};
+		run(&args, &mut Vec::<u8>::new())?;
+		assert!(out.join("fira_sans_regular/0-255.pbf").is_file());
+
+		// Without any valid font, the command fails.
+		std::fs::remove_file(font_dir.join("good.ttf"))?;
+		let err = run(&args, &mut Vec::<u8>::new()).unwrap_err();
+		assert_eq!(err.to_string(), "No font could be loaded.");
+		Ok(())
+	}
+
 	#[test]
 	fn test_scan_skips_non_font_files() -> Result<()> {
 		let temp = tempfile::tempdir()?;
@@ -314,7 +1287,7 @@ mod tests {
 		)?;
 
 		let mut manager = FontManager::new(false);
-		scan(temp.path(), &mut manager)?;
+		scan(temp.path(), None, &mut manager, &mut ScanStats::default())?;
 		// Only the .ttf file was added; README.txt was skipped.
 		assert_eq!(manager.fonts.len(), 1);
 		Ok(())
@@ -327,12 +1300,9 @@ mod tests {
 			input_directories: vec![
 				PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
 			],
-			output_directory: None,
 			tar: true,
-			no_families: false,
-			no_index: false,
 			dummy: true,
-			single_thread: false,
+			..Default::default()
 		};
 
 		let mut stdout = Vec::<u8>::new();
@@ -362,11 +1332,73 @@ mod tests {
 				"\"fira_sans_regular/8704-8959.pbf\": 6511",
 				"\"fira_sans_regular/8960-9215.pbf\": 4375",
 				"\"fira_sans_regular/9472-9727.pbf\": 853",
-				"\"font_families.json\": 365",
-				"\"index.json\": 25"
+				"\"font_families.json\": 277",
+				"\"index.json\": 21"
 			]
 		);
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_run_with_only_filter() -> Result<()> {
+		let args = Subcommand {
+			input_directories: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata")],
+			tar: true,
+			only: vec!["fira_sans_regular".to_string()],
+			dummy: true,
+			..Default::default()
+		};
+
+		let mut stdout = Vec::<u8>::new();
+		run(&args, &mut stdout)?;
+
+		let entries = get_tar_entries(&stdout);
+		assert_eq!(entries.len(), 23);
+		assert!(entries
+			.iter()
+			.all(|e| e.starts_with("\"fira_sans_regular/") || e.contains(".json")));
+
+		// The index only lists the selected font.
+		let mut tar = tar::Archive::new(stdout.as_slice());
+		let mut index = tar
+			.entries()?
+			.map(|e| e.unwrap())
+			.find(|e| e.path().unwrap().to_str() == Some("index.json"))
+			.unwrap();
+		let mut content = String::new();
+		std::io::Read::read_to_string(&mut index, &mut content)?;
+		assert_eq!(content, "[\"fira_sans_regular\"]");
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_with_min_glyphs() -> Result<()> {
+		let args = Subcommand {
+			input_directories: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata")],
+			tar: true,
+			only: vec![
+				"fira_sans_regular".to_string(),
+				"noto_sans_regular".to_string(),
+			],
+			min_glyphs: Some(2000),
+			dummy: true,
+			..Default::default()
+		};
+
+		let mut stdout = Vec::<u8>::new();
+		run(&args, &mut stdout)?;
+
+		// Fira Sans has 1686 glyphs, Noto Sans more than 3000.
+		let mut tar = tar::Archive::new(stdout.as_slice());
+		let mut index = tar
+			.entries()?
+			.map(|e| e.unwrap())
+			.find(|e| e.path().unwrap().to_str() == Some("index.json"))
+			.unwrap();
+		let mut content = String::new();
+		std::io::Read::read_to_string(&mut index, &mut content)?;
+		assert_eq!(content, "[\"noto_sans_regular\"]");
+		Ok(())
+	}
 }
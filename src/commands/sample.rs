@@ -0,0 +1,88 @@
+use crate::{
+	font::FontFileEntry, protobuf::PbfGlyph, render::Renderer, utils::bitmap_as_ascii_art,
+};
+use anyhow::{Context, Result};
+use std::{fs, io::Write, path::PathBuf};
+
+/// Subcommand arguments for previewing a font as ASCII art.
+#[derive(clap::Args, Debug)]
+#[command(arg_required_else_help = true)]
+/// Renders a sample text with a font and prints every glyph as ASCII art.
+///
+/// This is a quick check that a font renders correctly, e.g. that it isn't
+/// garbled or missing characters, before generating a whole glyph set.
+///
+/// # Examples
+///
+/// ```bash
+/// versatiles_glyphs sample --sample "Ag" my_font.ttf
+/// ```
+pub struct Subcommand {
+	/// Font file to render.
+	#[arg()]
+	input_file: PathBuf,
+
+	/// Text to render. Every distinct character is printed once.
+	#[arg(short, long, value_name = "TEXT")]
+	sample: String,
+}
+
+pub fn run(args: &Subcommand, stdout: &mut impl Write) -> Result<()> {
+	let data = fs::read(&args.input_file)
+		.with_context(|| format!("Failed to read {:?}", args.input_file))?;
+	let entry = FontFileEntry::new(data)
+		.with_context(|| format!("Failed to load font {:?}", args.input_file))?;
+
+	let glyphs = Renderer::new_precise().render_glyphs_for_string(&entry.face, &args.sample);
+	for glyph in glyphs {
+		let c = char::from_u32(glyph.id).unwrap_or(char::REPLACEMENT_CHARACTER);
+		writeln!(
+			stdout,
+			"{c:?} U+{:04X}, advance {}",
+			glyph.id, glyph.advance
+		)?;
+		for line in glyph_art(&glyph) {
+			writeln!(stdout, "{line}")?;
+		}
+		writeln!(stdout)?;
+	}
+
+	Ok(())
+}
+
+/// Returns the SDF bitmap of `glyph`, including its buffer, as ASCII art.
+/// Glyphs without a bitmap, like spaces, have no lines.
+fn glyph_art(glyph: &PbfGlyph) -> Vec<String> {
+	match (&glyph.bitmap, glyph.bitmap_dimensions()) {
+		(Some(bitmap), Some((width, _))) => bitmap_as_ascii_art(bitmap, width as usize),
+		_ => vec![],
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_run_sample() -> Result<()> {
+		let args = Subcommand {
+			input_file: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+				.join("testdata/Fira Sans - Regular.ttf"),
+			sample: String::from("Ag"),
+		};
+		let mut stdout = Vec::new();
+		run(&args, &mut stdout)?;
+
+		let output = String::from_utf8(stdout)?;
+		let blocks = output.trim_end().split("\n\n").collect::<Vec<_>>();
+		assert_eq!(blocks.len(), 2);
+		assert!(blocks[0].starts_with("'A' U+0041, advance "));
+		assert!(blocks[1].starts_with("'g' U+0067, advance "));
+		for block in blocks {
+			let art = block.lines().skip(1).collect::<Vec<_>>();
+			assert!(art.len() > 10, "{block}");
+			assert!(art.iter().any(|line| line.contains('▓')), "{block}");
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,119 @@
+//! Typed errors returned by the public [`font`](crate::font), [`render`](crate::render)
+//! and [`writer`](crate::writer) APIs.
+//!
+//! Internally the crate uses [`anyhow`], so any failure that isn't one of the
+//! specific kinds below is passed through as [`GlyphError::Other`]. Since
+//! [`GlyphError`] implements [`std::error::Error`], it converts back into an
+//! [`anyhow::Error`] with `?`.
+
+use std::io;
+
+/// The kinds of failures a caller may want to handle differently.
+#[derive(Debug, thiserror::Error)]
+pub enum GlyphError {
+	/// The font data isn't a valid TrueType or OpenType font, e.g. because it is truncated.
+	#[error("Could not parse font data")]
+	FontParse(#[from] ttf_parser::FaceParsingError),
+
+	/// The font has no `cmap` table, so its codepoints are unknown.
+	#[error("Font has no cmap table")]
+	NoCmap,
+
+	/// The data is a font in a format that can't be rendered, like `"WOFF2"`.
+	#[error("unsupported font format {0}, expected TrueType or OpenType")]
+	UnsupportedFormat(String),
+
+	/// Reading or writing a file failed.
+	#[error("{context}")]
+	Io {
+		/// What was being done, e.g. `reading font file "font.ttf"`.
+		context: String,
+		/// The underlying error.
+		#[source]
+		source: io::Error,
+	},
+
+	/// Any other failure.
+	#[error(transparent)]
+	Other(anyhow::Error),
+}
+
+impl GlyphError {
+	/// Creates a [`GlyphError::Io`] with a description of what failed.
+	pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+		GlyphError::Io {
+			context: context.into(),
+			source,
+		}
+	}
+}
+
+impl GlyphError {
+	/// Converts the failure of an I/O operation, e.g. of a writer. A
+	/// [`GlyphError`] passed through [`anyhow`] keeps its kind, and an
+	/// [`io::Error`] becomes a [`GlyphError::Io`] described by the outermost
+	/// context of `err`, or by `context` if it has none. Everything else
+	/// becomes [`GlyphError::Other`].
+	pub(crate) fn from_io(err: anyhow::Error, context: impl FnOnce() -> String) -> Self {
+		let message = err.to_string();
+		match err.downcast::<GlyphError>() {
+			Ok(err) => err,
+			Err(err) => match err.downcast::<io::Error>() {
+				Ok(source) if message == source.to_string() => GlyphError::io(context(), source),
+				Ok(source) => GlyphError::io(message, source),
+				Err(err) => GlyphError::Other(err),
+			},
+		}
+	}
+}
+
+impl From<io::Error> for GlyphError {
+	fn from(source: io::Error) -> Self {
+		GlyphError::io("I/O error", source)
+	}
+}
+
+impl From<anyhow::Error> for GlyphError {
+	/// Unwraps a [`GlyphError`] that was passed through [`anyhow`] without added
+	/// context, so its kind survives. Everything else becomes [`GlyphError::Other`].
+	fn from(err: anyhow::Error) -> Self {
+		match err.downcast::<GlyphError>() {
+			Ok(err) => err,
+			Err(err) => GlyphError::Other(err),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_anyhow_round_trip_keeps_kind() {
+		let err = GlyphError::from(anyhow::Error::from(GlyphError::NoCmap));
+		assert!(matches!(err, GlyphError::NoCmap));
+
+		let err = GlyphError::from(anyhow::anyhow!("something else"));
+		assert!(matches!(err, GlyphError::Other(_)));
+		assert_eq!(err.to_string(), "something else");
+	}
+
+	#[test]
+	fn test_from_io() {
+		let source = || io::Error::new(io::ErrorKind::NotFound, "gone");
+		let err = GlyphError::from_io(source().into(), || String::from("writing \"a\""));
+		assert!(
+			matches!(&err, GlyphError::Io { context, source } if context == "writing \"a\"" && source.kind() == io::ErrorKind::NotFound)
+		);
+
+		// The context added with `anyhow` is kept.
+		let err = anyhow::Error::from(source()).context("creating file \"b\"");
+		let err = GlyphError::from_io(err, || String::from("writing \"b\""));
+		assert!(matches!(&err, GlyphError::Io { context, .. } if context == "creating file \"b\""));
+
+		let err = GlyphError::from_io(GlyphError::NoCmap.into(), String::new);
+		assert!(matches!(err, GlyphError::NoCmap));
+		let err = GlyphError::from_io(anyhow::anyhow!("invalid path"), String::new);
+		assert!(matches!(err, GlyphError::Other(_)));
+	}
+}
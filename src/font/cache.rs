@@ -0,0 +1,202 @@
+//! A thread-safe cache of rendered glyph blocks, to serve glyphs on demand.
+
+use super::FontManager;
+use crate::{error::GlyphError, render::Renderer};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+/// The encoded block of one `(font_id, start_index)` key, `None` until it is rendered.
+type Slot = Arc<Mutex<Option<Arc<[u8]>>>>;
+
+/// Renders the glyph blocks of a [`FontManager`] on demand and keeps the
+/// encoded `.pbf` bytes, e.g. for a tile or label server.
+///
+/// The cache is `Send + Sync`, so a single cache can serve all request threads.
+/// Concurrent requests for the same block wait for one render, while different
+/// blocks are rendered in parallel. Failed renders aren't cached, so the next
+/// request tries again.
+///
+/// The cache is unbounded: every requested block stays in memory until
+/// [`Self::clear`], there is no eviction. Only valid keys are cached (see
+/// [`FontManager::check_block_start`]), and with the default block size a font
+/// has at most 4352 blocks, so a server with a fixed set of fonts stays bounded.
+pub struct GlyphCache<'a> {
+	manager: FontManager<'a>,
+	renderer: Renderer,
+	blocks: Mutex<HashMap<(String, u32), Slot>>,
+}
+
+impl<'a> GlyphCache<'a> {
+	/// Creates an empty cache that renders the fonts of `manager` with `renderer`.
+	pub fn new(manager: FontManager<'a>, renderer: Renderer) -> Self {
+		Self {
+			manager,
+			renderer,
+			blocks: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Returns the managed fonts.
+	pub fn manager(&self) -> &FontManager<'a> {
+		&self.manager
+	}
+
+	/// Returns the block of the font `font_id` that starts at `start_index`,
+	/// like `0` for `0-255.pbf`, rendering it with [`FontManager::render_block`]
+	/// on the first request.
+	///
+	/// Returns `None` if the font isn't managed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the block can't be rendered, see [`FontManager::render_block`].
+	pub fn get(&self, font_id: &str, start_index: u32) -> Result<Option<Arc<[u8]>>, GlyphError> {
+		if !self.manager.fonts.contains_key(font_id) {
+			return Ok(None);
+		}
+		// Invalid keys get no slot, so requests can't grow the map without bound.
+		self.manager.check_block_start(start_index)?;
+		// Only the lookup holds the map's lock, the render only holds the slot's.
+		// A panic while holding either leaves it consistent: the map only gains
+		// slots, and a slot stays `None` until its render succeeds.
+		let slot = self
+			.blocks
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.entry((font_id.to_string(), start_index))
+			.or_default()
+			.clone();
+		let mut block = slot.lock().unwrap_or_else(PoisonError::into_inner);
+		// `None` means the block isn't rendered yet, or its last render failed.
+		if let Some(bytes) = &*block {
+			return Ok(Some(bytes.clone()));
+		}
+		let bytes = Arc::<[u8]>::from(self.manager.render_block(
+			font_id,
+			start_index,
+			&self.renderer,
+		)?);
+		*block = Some(bytes.clone());
+		Ok(Some(bytes))
+	}
+
+	/// Drops all cached blocks, e.g. after the fonts changed.
+	pub fn clear(&self) {
+		self
+			.blocks
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::protobuf::PbfGlyphs;
+	use std::{path::PathBuf, thread};
+
+	fn new_cache() -> Result<GlyphCache<'static>, GlyphError> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[
+			PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Fira Sans - Regular.ttf")
+		])?;
+		Ok(GlyphCache::new(manager, Renderer::new_recording()))
+	}
+
+	#[test]
+	fn test_cache_is_send_and_sync() {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<GlyphCache>();
+	}
+
+	#[test]
+	fn test_concurrent_requests_render_once() -> anyhow::Result<()> {
+		let cache = new_cache()?;
+		let blocks = thread::scope(|scope| {
+			let handles = (0..8)
+				.map(|_| scope.spawn(|| cache.get("fira_sans_regular", 0)))
+				.collect::<Vec<_>>();
+			handles
+				.into_iter()
+				.map(|handle| handle.join().unwrap())
+				.collect::<Result<Vec<_>, _>>()
+		})?;
+
+		let first = blocks[0].clone().unwrap();
+		assert!(blocks
+			.iter()
+			.all(|block| Arc::ptr_eq(block.as_ref().unwrap(), &first)));
+
+		// Every glyph of the block was rendered exactly once.
+		let recorder = cache.renderer.recorder().unwrap();
+		let glyphs = PbfGlyphs::from_slice(&first)?.into_glyphs();
+		assert!(!glyphs.is_empty());
+		assert_eq!(recorder.calls().len(), glyphs.len());
+
+		// Cleared blocks are rendered again.
+		cache.clear();
+		cache.get("fira_sans_regular", 0)?;
+		assert_eq!(recorder.calls().len(), 2 * glyphs.len());
+		Ok(())
+	}
+
+	#[test]
+	fn test_poisoned_locks_are_recovered() -> anyhow::Result<()> {
+		let cache = new_cache()?;
+		let key = (String::from("fira_sans_regular"), 0);
+		let slot = cache.blocks.lock().unwrap().entry(key).or_default().clone();
+		thread::scope(|scope| {
+			let map = scope.spawn(|| {
+				let _map = cache.blocks.lock().unwrap();
+				panic!("poison the map");
+			});
+			assert!(map.join().is_err());
+			let block = scope.spawn(|| {
+				let _block = slot.lock().unwrap();
+				panic!("poison the slot");
+			});
+			assert!(block.join().is_err());
+		});
+		assert!(cache.blocks.is_poisoned() && slot.is_poisoned());
+
+		// The empty slot is rendered as usual.
+		let block = cache.get("fira_sans_regular", 0)?.unwrap();
+		assert!(!PbfGlyphs::from_slice(&block)?.into_glyphs().is_empty());
+		cache.clear();
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_missing_and_invalid_blocks() -> anyhow::Result<()> {
+		let cache = new_cache()?;
+		assert!(cache.get("noto_sans_regular", 0)?.is_none());
+
+		// A range without glyphs is a valid, empty block.
+		let empty = cache.get("fira_sans_regular", 0xF000)?.unwrap();
+		assert!(PbfGlyphs::from_slice(&empty)?.into_glyphs().is_empty());
+
+		let err = cache.get("fira_sans_regular", 100).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"block start 100 is not a multiple of the block size 256"
+		);
+
+		// The last block is valid, anything beyond U+10FFFF isn't.
+		let last = cache.get("fira_sans_regular", 0x10FF00)?.unwrap();
+		assert!(PbfGlyphs::from_slice(&last)?.into_glyphs().is_empty());
+		for start_index in [0x110000, u32::MAX - 255] {
+			let err = cache.get("fira_sans_regular", start_index).unwrap_err();
+			assert_eq!(
+				err.to_string(),
+				format!("block start {start_index} is beyond the last codepoint U+10FFFF")
+			);
+		}
+
+		// Only the valid blocks have a slot.
+		assert_eq!(cache.blocks.lock().unwrap().len(), 2);
+		Ok(())
+	}
+}
@@ -0,0 +1,249 @@
+//! Glyph substitution with a single OpenType feature from the `GSUB` table,
+//! like `smcp` (small capitals) or `liga` (standard ligatures).
+//!
+//! This is not a shaping engine. Only single substitution (type 1) and ligature
+//! substitution (type 4) lookups are applied, including those wrapped in
+//! extension lookups. The feature's lookups are collected from every script and
+//! language system, and contextual, multiple and alternate substitutions are
+//! ignored. Fonts that rely on those, e.g. for Arabic or Indic scripts, will
+//! not look like shaped text.
+
+use ttf_parser::{
+	gsub::{LigatureSubstitution, SingleSubstitution, SubstitutionSubtable},
+	opentype_layout::{LayoutTable, Lookup},
+	Face, GlyphId, Tag,
+};
+
+/// The `GSUB` lookups of one OpenType feature of a font.
+pub struct FeatureSubstitution<'a> {
+	gsub: LayoutTable<'a>,
+	/// Indices of the feature's lookups, sorted, since lookups are applied in
+	/// the order of the lookup list.
+	lookups: Vec<u16>,
+}
+
+impl<'a> FeatureSubstitution<'a> {
+	/// Collects the lookups of `feature`, like `Tag::from_bytes(b"smcp")`.
+	///
+	/// Returns [`None`] if `face` has no `GSUB` table or doesn't support `feature`.
+	pub fn new(face: &Face<'a>, feature: Tag) -> Option<Self> {
+		let gsub = face.tables().gsub?;
+		let mut lookups = gsub
+			.features
+			.into_iter()
+			.filter(|f| f.tag == feature)
+			.flat_map(|f| f.lookup_indices)
+			.collect::<Vec<_>>();
+		if lookups.is_empty() {
+			return None;
+		}
+		lookups.sort_unstable();
+		lookups.dedup();
+		Some(FeatureSubstitution { gsub, lookups })
+	}
+
+	/// Iterates over the lookups of the feature, in lookup order.
+	fn lookups(&self) -> impl Iterator<Item = Lookup<'a>> + '_ {
+		self
+			.lookups
+			.iter()
+			.filter_map(|&index| self.gsub.lookups.get(index))
+	}
+
+	/// Returns the glyph that replaces `glyph` under this feature, applying every
+	/// single substitution lookup in turn, or `glyph` itself if none applies.
+	pub fn substitute(&self, glyph: GlyphId) -> GlyphId {
+		self.lookups().fold(glyph, |glyph, lookup| {
+			subtables(&lookup)
+				.find_map(|subtable| match subtable {
+					SubstitutionSubtable::Single(single) => single_substitute(&single, glyph),
+					_ => None,
+				})
+				.unwrap_or(glyph)
+		})
+	}
+
+	/// Returns the ligature glyph that replaces a sequence starting at the first
+	/// of `glyphs`, and the number of glyphs it replaces, if there is one.
+	pub fn ligature(&self, glyphs: &[GlyphId]) -> Option<(GlyphId, usize)> {
+		self.lookups().find_map(|lookup| {
+			subtables(&lookup).find_map(|subtable| match subtable {
+				SubstitutionSubtable::Ligature(ligatures) => ligature_substitute(&ligatures, glyphs),
+				_ => None,
+			})
+		})
+	}
+
+	/// Applies the feature to a sequence of glyphs: every lookup in lookup order
+	/// passes over the whole sequence from left to right, like in a shaper, so
+	/// a ligature lookup sees the output of the single substitutions before it.
+	pub fn apply(&self, glyphs: &[GlyphId]) -> Vec<GlyphId> {
+		let mut glyphs = glyphs.to_vec();
+		for lookup in self.lookups() {
+			let mut result = Vec::with_capacity(glyphs.len());
+			let mut i = 0;
+			while i < glyphs.len() {
+				match apply_lookup(&lookup, &glyphs[i..]) {
+					Some((glyph, count)) => {
+						result.push(glyph);
+						i += count;
+					}
+					None => {
+						result.push(glyphs[i]);
+						i += 1;
+					}
+				}
+			}
+			glyphs = result;
+		}
+		glyphs
+	}
+}
+
+/// Iterates over the subtables of `lookup`.
+fn subtables<'a>(lookup: &Lookup<'a>) -> impl Iterator<Item = SubstitutionSubtable<'a>> {
+	lookup.subtables.into_iter::<SubstitutionSubtable>()
+}
+
+/// Applies `lookup` at the start of `glyphs`, returning the replacement and
+/// the number of glyphs it replaces.
+///
+/// Like in OpenType, only the first subtable that applies is used, even if
+/// later ones cover the same glyph.
+fn apply_lookup(lookup: &Lookup, glyphs: &[GlyphId]) -> Option<(GlyphId, usize)> {
+	let first = *glyphs.first()?;
+	subtables(lookup).find_map(|subtable| match subtable {
+		SubstitutionSubtable::Single(single) => {
+			single_substitute(&single, first).map(|glyph| (glyph, 1))
+		}
+		SubstitutionSubtable::Ligature(ligatures) => ligature_substitute(&ligatures, glyphs),
+		_ => None,
+	})
+}
+
+/// Looks up `glyph` in a single substitution subtable.
+fn single_substitute(single: &SingleSubstitution, glyph: GlyphId) -> Option<GlyphId> {
+	match single {
+		SingleSubstitution::Format1 { coverage, delta } => {
+			coverage.get(glyph)?;
+			// The delta is added modulo 65536.
+			Some(GlyphId(glyph.0.wrapping_add(*delta as u16)))
+		}
+		SingleSubstitution::Format2 {
+			coverage,
+			substitutes,
+		} => substitutes.get(coverage.get(glyph)?),
+	}
+}
+
+/// Finds the first ligature of `ligatures` whose components match the start of `glyphs`.
+fn ligature_substitute(
+	ligatures: &LigatureSubstitution,
+	glyphs: &[GlyphId],
+) -> Option<(GlyphId, usize)> {
+	let (first, rest) = glyphs.split_first()?;
+	let set = ligatures
+		.ligature_sets
+		.get(ligatures.coverage.get(*first)?)?;
+	set.into_iter().find_map(|ligature| {
+		let count = ligature.components.len() as usize;
+		let matches = rest.len() >= count
+			&& ligature
+				.components
+				.into_iter()
+				.zip(rest)
+				.all(|(component, glyph)| component == *glyph);
+		matches.then_some((ligature.glyph, count + 1))
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FIRA: &[u8] = include_bytes!("../../testdata/Fira Sans - Regular.ttf");
+
+	#[test]
+	fn test_liga() {
+		let face = Face::parse(FIRA, 0).unwrap();
+		let liga = FeatureSubstitution::new(&face, Tag::from_bytes(b"liga")).unwrap();
+		let glyphs = "fix"
+			.chars()
+			.map(|c| face.glyph_index(c).unwrap())
+			.collect::<Vec<_>>();
+
+		let result = liga.apply(&glyphs);
+		assert_eq!(result.len(), 2);
+		assert_ne!(result[0], glyphs[0]);
+		assert_eq!(result[1], glyphs[2]);
+		assert!(FeatureSubstitution::new(&face, Tag::from_bytes(b"xxxx")).is_none());
+	}
+
+	/// Builds the `GSUB` table of the feature `test` with two lookups:
+	///
+	/// 1. single substitutions in two subtables that both cover glyph 3: the
+	///    first maps 1 → 2 and 3 → 4, the second 3 → 5 and 4 → 6,
+	/// 2. the ligature 2 + 4 → 9.
+	fn test_gsub() -> Vec<u8> {
+		let mut gsub = Vec::new();
+		let mut push = |words: &[u16]| {
+			for word in words {
+				gsub.extend_from_slice(&word.to_be_bytes());
+			}
+		};
+		// Header with the offsets of the script, feature and lookup lists.
+		push(&[1, 0, 10, 12, 28]);
+		// Script list without scripts.
+		push(&[0]);
+		// Feature list with the feature `test`, which uses both lookups.
+		push(&[1, u16::from_be_bytes(*b"te"), u16::from_be_bytes(*b"st"), 8]);
+		push(&[0, 2, 0, 1]);
+		// Lookup list.
+		push(&[2, 6, 44]);
+		// Lookup 0: two single substitution subtables, each with a delta and a
+		// coverage table.
+		push(&[1, 0, 2, 10, 24]);
+		push(&[1, 6, 1, 1, 2, 1, 3]);
+		push(&[1, 6, 2, 1, 2, 3, 4]);
+		// Lookup 1: a ligature substitution subtable with a coverage table, a
+		// ligature set, and the ligature.
+		push(&[4, 0, 1, 8]);
+		push(&[1, 8, 1, 14]);
+		push(&[1, 1, 2]);
+		push(&[1, 4]);
+		push(&[9, 2, 4]);
+		gsub
+	}
+
+	#[test]
+	fn test_first_subtable_and_lookup_order() {
+		let mut head = [0u8; 54];
+		head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+		let maxp = [0, 0, 0x50, 0, 0, 10];
+		let gsub = test_gsub();
+		let face = Face::from_raw_tables(ttf_parser::RawFaceTables {
+			head: &head,
+			hhea: &[0; 36],
+			maxp: &maxp,
+			gsub: Some(&gsub),
+			..Default::default()
+		})
+		.unwrap();
+		let feature = FeatureSubstitution::new(&face, Tag::from_bytes(b"test")).unwrap();
+
+		// Only the first subtable that covers glyph 3 applies.
+		assert_eq!(feature.substitute(GlyphId(3)), GlyphId(4));
+		assert_eq!(feature.substitute(GlyphId(4)), GlyphId(6));
+
+		// The single substitutions come first, so the ligature matches their result.
+		assert_eq!(feature.apply(&[GlyphId(1), GlyphId(3)]), [GlyphId(9)]);
+		assert_eq!(
+			feature.apply(&[GlyphId(3), GlyphId(1)]),
+			[GlyphId(4), GlyphId(2)]
+		);
+		assert_eq!(
+			feature.ligature(&[GlyphId(2), GlyphId(4)]),
+			Some((GlyphId(9), 2))
+		);
+	}
+}
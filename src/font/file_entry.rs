@@ -1,6 +1,10 @@
-use super::metadata::FontMetadata;
-use anyhow::{Context, Result};
-use std::{marker::PhantomPinned, pin::Pin, slice};
+use super::{kerning, metadata::FontMetadata};
+use crate::{
+	error::GlyphError,
+	render::{glyph_advance, RenderOptions},
+};
+use anyhow::Result;
+use std::{marker::PhantomPinned, path::Path, pin::Pin, slice};
 use ttf_parser::Face;
 
 /// A font file entry that holds raw font bytes, a parsed [`Face`], and font metadata.
@@ -13,7 +17,8 @@ pub struct FontFileEntry<'a> {
 	/// The metadata extracted from the font, such as name, style, and other descriptors.
 	pub metadata: FontMetadata,
 
-	/// Pinned backing storage for `face`'s borrowed slice.
+	/// Pinned backing storage for `face`'s borrowed slice, or empty if `face`
+	/// borrows the caller's data (see [`Self::from_face`]).
 	///
 	/// Load-bearing despite never being read directly: dropping or moving it would
 	/// invalidate the `&'a [u8]` that `face` holds. Hence `#[allow(dead_code)]`.
@@ -28,8 +33,15 @@ impl<'a> FontFileEntry<'a> {
 	/// Creates a new [`FontFileEntry`] from raw bytes.
 	///
 	/// # Errors
-	/// Returns an error if the font data fails to parse.
-	pub fn new(data: Vec<u8>) -> Result<Self> {
+	/// Returns [`GlyphError::UnsupportedFormat`] for WOFF and WOFF2 data,
+	/// [`GlyphError::FontParse`] if the font data fails to parse, and
+	/// [`GlyphError::NoCmap`] if the font has no `cmap` table.
+	pub fn new(data: Vec<u8>) -> Result<Self, GlyphError> {
+		match data.get(..4) {
+			Some(b"wOFF") => return Err(GlyphError::UnsupportedFormat(String::from("WOFF"))),
+			Some(b"wOF2") => return Err(GlyphError::UnsupportedFormat(String::from("WOFF2"))),
+			_ => {}
+		}
 		let data = Pin::new(data);
 		// SAFETY: This builds a self-referential struct. The slice we hand to
 		// `Face::parse` borrows from the bytes owned by `data`. The borrow is
@@ -45,7 +57,7 @@ impl<'a> FontFileEntry<'a> {
 		// the struct provides it; it exists so `Face<'a>` can borrow the
 		// internal slice.
 		let slice: &'a [u8] = unsafe { slice::from_raw_parts(data.as_ptr(), data.len()) };
-		let face = Face::parse(slice, 0).context("Could not parse font data")?;
+		let face = Face::parse(slice, 0)?;
 		let metadata = FontMetadata::try_from(&face)?;
 		Ok(FontFileEntry {
 			data,
@@ -54,6 +66,69 @@ impl<'a> FontFileEntry<'a> {
 			_pin: PhantomPinned,
 		})
 	}
+
+	/// Creates a new [`FontFileEntry`] from a [`Face`] the caller has already
+	/// parsed, without parsing the font again.
+	///
+	/// This keeps any changes made to `face`, like variation coordinates set
+	/// with [`Face::set_variation`], or the choice of a face in a font collection.
+	/// Unlike [`Self::new`], the entry doesn't own the font data: it borrows
+	/// the bytes `face` was parsed from for `'a`, so it can't outlive them.
+	///
+	/// # Errors
+	/// Returns [`GlyphError::NoCmap`] if the font has no `cmap` table.
+	#[allow(dead_code)] // Public API; the CLI always reads font files.
+	pub fn from_face(face: Face<'a>) -> Result<Self, GlyphError> {
+		let metadata = FontMetadata::try_from(&face)?;
+		Ok(FontFileEntry {
+			data: Pin::new(Vec::new()),
+			face,
+			metadata,
+			_pin: PhantomPinned,
+		})
+	}
+
+	/// Reads and parses the font file at `path`.
+	///
+	/// # Errors
+	/// Returns [`GlyphError::Io`] if the file can't be read, or the error of
+	/// [`Self::new`] if it can't be parsed.
+	pub fn from_path(path: &Path) -> Result<Self, GlyphError> {
+		let data = std::fs::read(path)
+			.map_err(|err| GlyphError::io(format!("reading font file \"{path:?}\""), err))?;
+		Self::new(data)
+	}
+
+	/// Returns the horizontal kerning between the characters `left` and `right`
+	/// in font units, or [`None`] if either is missing or the pair isn't kerned.
+	///
+	/// Reads the `kern` table and `GPOS` pair adjustment lookups. Complex `GPOS`
+	/// positioning (contextual lookups, script and feature selection) is not
+	/// applied, so shaped text may be spaced differently.
+	#[allow(dead_code)] // Public API; the CLI doesn't lay out text.
+	pub fn kerning(&self, left: char, right: char) -> Option<i16> {
+		let left = self.face.glyph_index(left)?;
+		let right = self.face.glyph_index(right)?;
+		kerning::glyph_kerning(&self.face, left, right)
+	}
+
+	/// Returns the horizontal advance of `codepoint` in pixels at the default
+	/// render size, the same value as the [`PbfGlyph::advance`](crate::protobuf::PbfGlyph::advance)
+	/// of the rendered glyph, or [`None`] if the font doesn't cover it.
+	///
+	/// This reads only the `hmtx` table, so it's far cheaper than rendering,
+	/// e.g. to measure text.
+	#[allow(dead_code)] // Public API; the CLI doesn't lay out text.
+	pub fn advance(&self, codepoint: u32) -> Option<u32> {
+		self.advance_with_size(codepoint, RenderOptions::default().size)
+	}
+
+	/// Like [`Self::advance`], but at `size` pixels per EM.
+	#[allow(dead_code)] // Public API; the CLI doesn't lay out text.
+	pub fn advance_with_size(&self, codepoint: u32, size: u32) -> Option<u32> {
+		let glyph_id = self.face.glyph_index(char::from_u32(codepoint)?)?;
+		Some(glyph_advance(&self.face, glyph_id, size).round() as u32)
+	}
 }
 
 #[cfg(test)]
@@ -76,4 +151,68 @@ mod tests {
 		let result = FontFileEntry::new(invalid_data);
 		assert_eq!(result.unwrap_err().to_string(), "Could not parse font data");
 	}
+
+	#[test]
+	fn test_font_file_entry_new_with_truncated_font() {
+		// Keeps the header, but cuts off the tables it points to.
+		let result = FontFileEntry::new(FIRA[..64].to_vec());
+		assert!(matches!(result, Err(GlyphError::FontParse(_))));
+	}
+
+	/// Returns a copy of `FIRA` with `unitsPerEm` in the `head` table set to `units_per_em`.
+	fn fira_with_units_per_em(units_per_em: u16) -> Vec<u8> {
+		let mut data = FIRA.to_vec();
+		let num_tables = u16::from_be_bytes([data[4], data[5]]) as usize;
+		let record = (0..num_tables)
+			.map(|i| 12 + i * 16)
+			.find(|&record| &data[record..record + 4] == b"head")
+			.unwrap();
+		let offset = u32::from_be_bytes(data[record + 8..record + 12].try_into().unwrap()) as usize;
+		data[offset + 18..offset + 20].copy_from_slice(&units_per_em.to_be_bytes());
+		data
+	}
+
+	#[test]
+	fn test_font_file_entry_new_with_invalid_units_per_em() {
+		// ttf-parser rejects a `head` table with `unitsPerEm` outside 16..=16384,
+		// so the renderer never divides by zero or scales by an absurd factor.
+		for units_per_em in [0, 1, 0xFFFF] {
+			let result = FontFileEntry::new(fira_with_units_per_em(units_per_em));
+			assert!(
+				matches!(result, Err(GlyphError::FontParse(_))),
+				"unitsPerEm {units_per_em}"
+			);
+		}
+
+		let entry = FontFileEntry::new(fira_with_units_per_em(2048)).unwrap();
+		assert_eq!(entry.face.units_per_em(), 2048);
+	}
+
+	#[test]
+	fn test_font_file_entry_new_with_woff2() {
+		let result = FontFileEntry::new(b"wOF2\0\x01\0\0".to_vec());
+		assert!(matches!(result, Err(GlyphError::UnsupportedFormat(format)) if format == "WOFF2"));
+	}
+
+	#[test]
+	fn test_kerning() {
+		let entry = FontFileEntry::new(FIRA.to_vec()).unwrap();
+		assert!(entry.metadata.has_kerning);
+		assert!(entry.kerning('A', 'V').is_some_and(|k| k < 0));
+		assert!(entry.kerning('T', 'o').is_some_and(|k| k < 0));
+		assert_eq!(entry.kerning('A', '\u{10FFFF}'), None);
+	}
+
+	#[test]
+	fn test_advance() {
+		let entry = FontFileEntry::new(FIRA.to_vec()).unwrap();
+		let renderer = crate::render::Renderer::new_precise();
+		for c in ['A', 'i', 'W', ' '] {
+			let glyph = renderer.render_glyph(&entry.face, c as u32).unwrap();
+			assert_eq!(entry.advance(c as u32), Some(glyph.advance), "{c:?}");
+		}
+		assert_eq!(entry.advance('A' as u32), Some(13));
+		assert_eq!(entry.advance_with_size('A' as u32, 48), Some(26));
+		assert_eq!(entry.advance(0x10FFFF), None);
+	}
 }
@@ -1,18 +1,61 @@
 use super::file_entry::FontFileEntry;
-use crate::{protobuf::PbfGlyphs, render::Renderer};
-use anyhow::Result;
-use std::collections::HashMap;
+use crate::{
+	error::GlyphError,
+	protobuf::{PbfFontMetadata, PbfGlyphs},
+	render::Renderer,
+};
+use anyhow::{ensure, Result};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
 
-/// The number of glyphs in each block, corresponding to a range of 256 codepoints.
+/// The default number of glyphs in each block, corresponding to a range of 256
+/// codepoints as requested by maplibre/mapbox clients.
 pub const GLYPH_BLOCK_SIZE: u32 = 256;
 
-/// Represents a block of glyphs (up to 256) that can be rendered into a `.pbf` file.
+/// The smallest block size accepted by [`check_block_size`].
+pub const MIN_GLYPH_BLOCK_SIZE: u32 = 64;
+
+/// The largest block size accepted by [`check_block_size`], a whole Unicode plane.
+pub const MAX_GLYPH_BLOCK_SIZE: u32 = 65536;
+
+/// Checks that `size` can be used as a block size: a power of two from
+/// [`MIN_GLYPH_BLOCK_SIZE`] to [`MAX_GLYPH_BLOCK_SIZE`].
+///
+/// # Errors
+///
+/// Returns an error describing the accepted sizes otherwise.
+pub fn check_block_size(size: u32) -> Result<()> {
+	ensure!(
+		size.is_power_of_two() && (MIN_GLYPH_BLOCK_SIZE..=MAX_GLYPH_BLOCK_SIZE).contains(&size),
+		"invalid block size {size}: expected a power of two from {MIN_GLYPH_BLOCK_SIZE} to {MAX_GLYPH_BLOCK_SIZE}"
+	);
+	Ok(())
+}
+
+/// A block rendered by [`GlyphBlock::render_skipping_failures`]: the encoded
+/// block, the number of glyphs in it, and the codepoints that failed to render.
+pub type RenderedBlock = (Vec<u8>, usize, Vec<(u32, GlyphError)>);
+
+/// Represents a block of glyphs (up to [`Self::size`]) that can be rendered into a `.pbf` file.
 /// Each block tracks which font file is responsible for each character.
 pub struct GlyphBlock<'a> {
 	/// The start of the codepoint range for this block.
 	pub start_index: u32,
-	/// A map from the codepoint offset (`0..=255`) to the [`FontFileEntry`] that provides the glyph.
-	pub glyphs: HashMap<u8, &'a FontFileEntry<'a>>,
+	/// The number of codepoints covered by this block, [`GLYPH_BLOCK_SIZE`] by default.
+	pub size: u32,
+	/// A map from the codepoint offset (`0..size`) to the [`FontFileEntry`] that provides the glyph.
+	/// Ordered, so glyphs are always rendered in codepoint order and the output is reproducible.
+	pub glyphs: BTreeMap<u32, &'a FontFileEntry<'a>>,
+	/// Codepoint offsets that are rendered with the font's `.notdef` glyph.
+	pub notdef: BTreeSet<u32>,
+	/// Codepoint offsets that are rendered from a fallback font, with the id of
+	/// that font, see [`FontManager::fallbacks`](super::FontManager::fallbacks).
+	pub fallbacks: BTreeMap<u32, &'a str>,
+	/// The script of all codepoints in a block created by [`Self::for_script`],
+	/// which names the block instead of its range.
+	pub script: Option<&'static str>,
+	/// Metrics of the font that are stored in the rendered block, see
+	/// [`PbfGlyphs::set_metadata`].
+	pub metadata: Option<PbfFontMetadata>,
 }
 
 impl<'a> GlyphBlock<'a> {
@@ -20,68 +63,218 @@ impl<'a> GlyphBlock<'a> {
 	///
 	/// The block will cover the codepoints from `start_index` to
 	/// `start_index + GLYPH_BLOCK_SIZE - 1`.
+	#[allow(dead_code)] // Public API; the CLI creates blocks of the configured size.
 	pub fn new(start_index: u32) -> Self {
+		GlyphBlock::with_size(start_index, GLYPH_BLOCK_SIZE)
+	}
+
+	/// Creates a new, empty [`GlyphBlock`] covering the `size` codepoints from
+	/// `start_index` to `start_index + size - 1`.
+	pub fn with_size(start_index: u32, size: u32) -> Self {
 		GlyphBlock {
 			start_index,
-			glyphs: HashMap::new(),
+			size,
+			glyphs: BTreeMap::new(),
+			notdef: BTreeSet::new(),
+			fallbacks: BTreeMap::new(),
+			script: None,
+			metadata: None,
 		}
 	}
 
-	/// Associates a single character index (0–255) with a particular font file.
+	/// Creates a new, empty [`GlyphBlock`] for the codepoints of `script`, see
+	/// [`script_of`](super::script_of), like `"latin"` for `latin.pbf`.
+	///
+	/// It starts at `0` and spans all of Unicode, so character indices are
+	/// codepoints.
+	pub fn for_script(script: &'static str) -> Self {
+		GlyphBlock {
+			script: Some(script),
+			..GlyphBlock::with_size(0, 0x110000)
+		}
+	}
+
+	/// Associates a single character index (`0..size`) with a particular font file.
 	///
 	/// This indicates that the specified codepoint (based on `start_index + char_index`)
 	/// will be rendered using the provided font face data.
-	pub fn set_glyph_font(&mut self, char_index: u8, font: &'a FontFileEntry<'a>) {
+	pub fn set_glyph_font(&mut self, char_index: u32, font: &'a FontFileEntry<'a>) {
 		self.glyphs.entry(char_index).or_insert(font);
 	}
 
+	/// Associates the character index with `font`, replacing the font file it
+	/// was associated with, see [`FontSettings::overrides`](super::FontSettings::overrides).
+	pub fn override_glyph_font(&mut self, char_index: u32, font: &'a FontFileEntry<'a>) {
+		self.glyphs.insert(char_index, font);
+	}
+
+	/// Renders the `.notdef` glyph of `font` for the character index, unless
+	/// a font has already been assigned to it.
+	pub fn set_notdef_font(&mut self, char_index: u32, font: &'a FontFileEntry<'a>) {
+		if let Entry::Vacant(e) = self.glyphs.entry(char_index) {
+			e.insert(font);
+			self.notdef.insert(char_index);
+		}
+	}
+
+	/// Renders the character index with `font` of the fallback font `id`, unless
+	/// a font has already been assigned to it.
+	pub fn set_fallback_font(&mut self, char_index: u32, font: &'a FontFileEntry<'a>, id: &'a str) {
+		if let Entry::Vacant(e) = self.glyphs.entry(char_index) {
+			e.insert(font);
+			self.fallbacks.insert(char_index, id);
+		}
+	}
+
 	/// Returns the number of codepoints within this block that are mapped to a font.
 	pub fn len(&self) -> usize {
 		self.glyphs.len()
 	}
 
 	/// Returns `true` if no characters in this block are mapped to a font.
-	///
-	/// Pairs with [`Self::len`] (clippy's `len_without_is_empty` convention).
-	/// Currently only called from tests, hence the `#[allow(dead_code)]`.
-	#[allow(dead_code)]
 	pub fn is_empty(&self) -> bool {
 		self.glyphs.is_empty()
 	}
 
-	/// Provides a string representation of this block's codepoint range.
+	/// Provides a string representation of this block's codepoint range, or
+	/// its script.
 	fn range(&self) -> String {
-		format!(
-			"{}-{}",
-			self.start_index,
-			self.start_index + GLYPH_BLOCK_SIZE - 1
-		)
+		match self.script {
+			Some(script) => script.to_string(),
+			None => format!(
+				"{}-{}",
+				self.start_index,
+				self.start_index + (self.size - 1)
+			),
+		}
 	}
 
 	/// Renders all glyphs in this block using the provided [`Renderer`].
 	///
+	/// With the `profiling` feature, the render time of every glyph is recorded
+	/// in the renderer's [`Profiler`](crate::render::Profiler). Every glyph is
+	/// also recorded in the renderer's [`RenderRecorder`](crate::render::RenderRecorder),
+	/// if it has one.
+	///
 	/// A [`PbfGlyphs`] structure is created to store the glyph data, which is then serialized
 	/// into a `Vec<u8>`.
 	///
+	/// Codepoints that aren't valid [`char`]s, like surrogates, are skipped.
+	///
 	/// # Errors
 	///
 	/// Returns an error if glyph rendering fails.
-	pub fn render(&self, font_name: String, renderer: &Renderer) -> Result<Vec<u8>> {
+	#[allow(dead_code)] // Public API; the CLI renders with `render_with_count`.
+	pub fn render(&self, font_name: String, renderer: &Renderer) -> Result<Vec<u8>, GlyphError> {
+		Ok(self.render_with_count(font_name, renderer)?.0)
+	}
+
+	/// Like [`Self::render`], but also returns the number of glyphs in the block,
+	/// e.g. to check the written block against it.
+	pub fn render_with_count(
+		&self,
+		font_name: String,
+		renderer: &Renderer,
+	) -> Result<(Vec<u8>, usize), GlyphError> {
+		let (data, count, _) = self.render_glyphs(font_name, renderer, false)?;
+		Ok((data, count))
+	}
+
+	/// Like [`Self::render_with_count`], but glyphs that fail to render are left
+	/// out of the block instead of failing it. Their codepoints and errors are
+	/// returned as well.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the block can't be encoded.
+	pub fn render_skipping_failures(
+		&self,
+		font_name: String,
+		renderer: &Renderer,
+	) -> Result<RenderedBlock, GlyphError> {
+		self.render_glyphs(font_name, renderer, true)
+	}
+
+	/// Implements [`Self::render_with_count`] and [`Self::render_skipping_failures`].
+	fn render_glyphs(
+		&self,
+		font_name: String,
+		renderer: &Renderer,
+		skip_failures: bool,
+	) -> Result<RenderedBlock, GlyphError> {
 		let mut glyphs = PbfGlyphs::new(font_name, self.range());
+		if let Some(metadata) = &self.metadata {
+			glyphs.set_metadata(metadata.clone());
+		}
+		let mut count = 0;
+		let mut failures = Vec::new();
 
 		for (char_index, font_entry) in &self.glyphs {
-			let codepoint = self.start_index + (*char_index as u32);
-			if let Some(glyph) = renderer.render_glyph(&font_entry.face, codepoint) {
-				glyphs.push(glyph);
+			let codepoint = self.start_index + char_index;
+			if char::from_u32(codepoint).is_none() {
+				continue;
 			}
+			#[cfg(feature = "profiling")]
+			let start = std::time::Instant::now();
+
+			let notdef = self.notdef.contains(char_index);
+			match renderer.render_block_glyph(&font_entry.face, codepoint, notdef) {
+				Ok(Some(glyph)) => {
+					glyphs.push(glyph);
+					count += 1;
+				}
+				Ok(None) => {}
+				Err(err) if skip_failures => failures.push((codepoint, err)),
+				Err(err) => return Err(err),
+			}
+			if let Some(recorder) = renderer.recorder() {
+				recorder.record(glyphs.name().unwrap_or_default(), codepoint);
+			}
+
+			#[cfg(feature = "profiling")]
+			renderer.profiler().record(
+				glyphs.name().unwrap_or_default(),
+				codepoint,
+				start.elapsed(),
+			);
 		}
 
-		glyphs.into_vec()
+		Ok((glyphs.into_vec()?, count, failures))
+	}
+
+	/// Estimates the encoded size in bytes of this block, as rendered by
+	/// [`Self::render`], without rendering it, e.g. for a dry run.
+	///
+	/// See [`Renderer::estimate_block_glyph_size`] for how glyphs are
+	/// estimated. Compression isn't taken into account.
+	pub fn estimate_size(&self, font_name: &str, renderer: &Renderer) -> u64 {
+		// Tags and lengths of the fontstack and its name, range and metadata.
+		const BLOCK_OVERHEAD: u64 = 16;
+
+		let metadata = self
+			.metadata
+			.as_ref()
+			.map_or(0, |metadata| metadata.family.len() as u64 + 12);
+		let glyphs = self
+			.glyphs
+			.iter()
+			.filter(|(char_index, _)| char::from_u32(self.start_index + *char_index).is_some())
+			.map(|(char_index, font_entry)| {
+				let notdef = self.notdef.contains(char_index);
+				renderer.estimate_block_glyph_size(
+					&font_entry.face,
+					self.start_index + char_index,
+					notdef,
+				)
+			})
+			.sum::<u64>();
+		BLOCK_OVERHEAD + font_name.len() as u64 + self.range().len() as u64 + metadata + glyphs
 	}
 
 	/// Generates a filename for the `.pbf` file representing this block.
 	///
-	/// For instance, if the block covers `0–255`, the filename would be `0-255.pbf`.
+	/// For instance, if the block covers `0–255`, the filename would be `0-255.pbf`,
+	/// and for the Latin script `latin.pbf`.
 	pub fn filename(&self) -> String {
 		format!("{}.pbf", self.range())
 	}
@@ -120,6 +313,35 @@ mod tests {
 		let expected_range = format!("{}-{}", start_index, start_index + GLYPH_BLOCK_SIZE - 1);
 		assert_eq!(block.range(), expected_range);
 		assert_eq!(block.filename(), format!("{expected_range}.pbf"));
+
+		let block = GlyphBlock::with_size(512, 512);
+		assert_eq!(block.filename(), "512-1023.pbf");
+
+		let block = GlyphBlock::for_script("latin");
+		assert_eq!(block.filename(), "latin.pbf");
+	}
+
+	#[test]
+	fn test_check_block_size() {
+		assert!(check_block_size(256).is_ok());
+		assert!(check_block_size(64).is_ok());
+		assert!(check_block_size(65536).is_ok());
+		assert!(check_block_size(0).is_err());
+		assert!(check_block_size(300).is_err());
+		assert!(check_block_size(32).is_err());
+		assert!(check_block_size(131072).is_err());
+	}
+
+	#[test]
+	fn test_set_notdef_font() {
+		let mut block = GlyphBlock::new(0);
+		let font_entry = create_font_file_entry();
+		block.set_glyph_font(65, &font_entry);
+		block.set_notdef_font(65, &font_entry);
+		block.set_notdef_font(66, &font_entry);
+
+		assert_eq!(block.len(), 2);
+		assert_eq!(block.notdef.iter().copied().collect::<Vec<_>>(), [66]);
 	}
 
 	#[test]
@@ -133,4 +355,65 @@ mod tests {
 		let out_data = render_result.unwrap();
 		assert!(!out_data.is_empty());
 	}
+
+	#[test]
+	fn test_render_from_face() -> Result<()> {
+		// The caller owns the data and the parsed face.
+		let data = VALID_FONT.to_vec();
+		let face = ttf_parser::Face::parse(&data, 0)?;
+		let font_entry = FontFileEntry::from_face(face)?;
+		assert_eq!(font_entry.metadata.generate_name(), "Fira Sans Regular");
+
+		let mut block = GlyphBlock::new(0);
+		block.set_glyph_font(65, &font_entry);
+		let data = block.render("TestFont".to_string(), &Renderer::new_precise())?;
+		let glyphs = PbfGlyphs::from_slice(&data)?.into_glyphs();
+		assert_eq!(glyphs.len(), 1);
+
+		let face = ttf_parser::Face::parse(VALID_FONT, 0)?;
+		let expected = Renderer::new_precise().render_glyph(&face, 65).unwrap();
+		assert_eq!(glyphs[0], expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_skips_surrogates() -> Result<()> {
+		let mut block = GlyphBlock::new(0xD800);
+		let font_entry = create_font_file_entry();
+		block.set_glyph_font(0, &font_entry);
+		block.set_glyph_font(65, &font_entry);
+		block.set_notdef_font(66, &font_entry);
+
+		for renderer in [Renderer::new_precise(), Renderer::new_dummy()] {
+			let data = block.render("TestFont".to_string(), &renderer)?;
+			let glyphs = PbfGlyphs::from_slice(&data)?;
+			assert_eq!(glyphs.name(), Some("TestFont"));
+			assert!(glyphs.into_glyphs().is_empty());
+		}
+		Ok(())
+	}
+	#[test]
+	fn test_estimate_size() -> Result<()> {
+		let font_entry = create_font_file_entry();
+		let mut block = GlyphBlock::new(0);
+		for codepoint in font_entry.metadata.codepoints.iter().filter(|c| **c < 256) {
+			block.set_glyph_font(*codepoint, &font_entry);
+		}
+		block.set_notdef_font(1, &font_entry);
+
+		// Within 10% of the rendered size.
+		let renderer = Renderer::new_precise();
+		let size = block.render("TestFont".to_string(), &renderer)?.len() as f64;
+		let estimate = block.estimate_size("TestFont", &renderer) as f64;
+		assert!(
+			(estimate - size).abs() < size * 0.1,
+			"{estimate} vs. {size}"
+		);
+
+		// Surrogates aren't rendered, so their block is only the header.
+		let mut block = GlyphBlock::new(0xD800);
+		block.set_notdef_font(0, &font_entry);
+		assert_eq!(block.estimate_size("TestFont", &renderer), 16 + 8 + 11);
+		Ok(())
+	}
 }
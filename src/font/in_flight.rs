@@ -0,0 +1,108 @@
+//! Bounds the memory held by rendered glyph blocks that wait to be written.
+
+use std::sync::{Condvar, Mutex};
+
+/// Approximate memory use of a render, see
+/// [`FontManager::memory_stats`](super::FontManager::memory_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+	/// Size of all loaded font files in bytes.
+	pub font_bytes: u64,
+	/// Most bytes of rendered blocks held in memory at once during the last render.
+	pub peak_block_bytes: u64,
+	/// Most rendered blocks held in memory at once during the last render.
+	pub peak_blocks: usize,
+}
+
+#[derive(Default)]
+struct State {
+	bytes: u64,
+	blocks: usize,
+	peak_bytes: u64,
+	peak_blocks: usize,
+	/// Index of the next block the writer needs, see [`InFlight::advance`].
+	next: usize,
+	closed: bool,
+}
+
+/// Counts the rendered blocks between the render threads and the writer, and
+/// makes render threads wait while the blocks would exceed `limit` bytes.
+///
+/// The writer writes the blocks in order of their index, so it holds the
+/// blocks that are rendered early until all previous ones arrived. To bound
+/// them, render threads also wait while their block is `window` or more
+/// blocks ahead of the next one the writer needs.
+pub struct InFlight {
+	limit: Option<u64>,
+	window: usize,
+	state: Mutex<State>,
+	changed: Condvar,
+}
+
+impl InFlight {
+	/// Creates a counter that admits blocks up to `limit` bytes, or any number
+	/// of blocks if `limit` is `None`, that are less than `window` blocks ahead
+	/// of the next one the writer needs.
+	pub fn new(limit: Option<u64>, window: usize) -> Self {
+		InFlight {
+			limit,
+			window,
+			state: Mutex::new(State::default()),
+			changed: Condvar::new(),
+		}
+	}
+
+	/// Waits until the block `index` of `size` bytes fits within the limit and
+	/// the window, and counts it.
+	///
+	/// The next block the writer needs is always admitted, so neither a block
+	/// larger than the limit nor the blocks held back for it can stall the
+	/// render. Returns `false` without counting the block if [`Self::close`]
+	/// was called.
+	pub fn acquire(&self, index: usize, size: u64) -> bool {
+		let mut state = self.state.lock().unwrap();
+		while !state.closed
+			&& index != state.next
+			&& (index >= state.next + self.window
+				|| (state.blocks > 0 && self.limit.is_some_and(|limit| state.bytes + size > limit)))
+		{
+			state = self.changed.wait(state).unwrap();
+		}
+		if state.closed {
+			return false;
+		}
+		state.bytes += size;
+		state.blocks += 1;
+		state.peak_bytes = state.peak_bytes.max(state.bytes);
+		state.peak_blocks = state.peak_blocks.max(state.blocks);
+		true
+	}
+
+	/// Stops counting a block of `size` bytes, e.g. once it was written.
+	pub fn release(&self, size: u64) {
+		let mut state = self.state.lock().unwrap();
+		state.bytes -= size;
+		state.blocks -= 1;
+		self.changed.notify_all();
+	}
+
+	/// Sets the index of the next block the writer needs, once it wrote or
+	/// skipped all blocks before it.
+	pub fn advance(&self, next: usize) {
+		self.state.lock().unwrap().next = next;
+		self.changed.notify_all();
+	}
+
+	/// Wakes all waiting threads and makes every further [`Self::acquire`] fail,
+	/// e.g. because the writer stopped.
+	pub fn close(&self) {
+		self.state.lock().unwrap().closed = true;
+		self.changed.notify_all();
+	}
+
+	/// Returns the most bytes and blocks that were in flight at once.
+	pub fn peak(&self) -> (u64, usize) {
+		let state = self.state.lock().unwrap();
+		(state.peak_bytes, state.peak_blocks)
+	}
+}
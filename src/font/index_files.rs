@@ -1,22 +1,37 @@
-use super::{wrapper::FontWrapper, FontMetadata};
-use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use super::{wrapper::FontWrapper, FontMetadata, FontSettings};
+use anyhow::{bail, ensure, Context, Result};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	ops::RangeInclusive,
+};
 
 /// Data structure representing a single font face within a family.
 /// This includes the unique `id`, as well as styling attributes like
 /// `style`, `weight`, and `width`.
-#[derive(serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 struct FontFace {
 	id: String,
 	style: String,
 	weight: u16,
 	width: String,
 	codeblocks: String,
+	/// Attribution from the font's `name` table, only written on request, see
+	/// [`FontFamiliesOptions::copyright`].
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	copyright: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	license: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	license_url: Option<String>,
 }
 
+/// Schema version of the versioned `font_families.json`, see
+/// [`FontFamiliesOptions::versioned`]. The legacy bare array counts as version 1.
+pub const FONT_FAMILIES_VERSION: u32 = 2;
+
 /// Data structure representing a font family, which can contain
 /// one or more [`FontFace`] entries.
-#[derive(serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 struct FontFamily {
 	/// Name of the font family, e.g., "Noto Sans".
 	name: String,
@@ -24,6 +39,55 @@ struct FontFamily {
 	faces: Vec<FontFace>,
 }
 
+/// The content of `font_families.json`, either versioned or as the legacy
+/// bare array of families.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum FontFamilies {
+	Versioned {
+		version: u32,
+		families: Vec<FontFamily>,
+	},
+	Legacy(Vec<FontFamily>),
+}
+
+impl FontFamilies {
+	/// Wraps `families` in the current [`FONT_FAMILIES_VERSION`] if `versioned`.
+	fn new(families: Vec<FontFamily>, versioned: bool) -> Self {
+		if versioned {
+			FontFamilies::Versioned {
+				version: FONT_FAMILIES_VERSION,
+				families,
+			}
+		} else {
+			FontFamilies::Legacy(families)
+		}
+	}
+
+	/// Parses either shape, rejecting versions other than [`FONT_FAMILIES_VERSION`].
+	fn from_slice(bytes: &[u8]) -> Result<Self> {
+		let families =
+			serde_json::from_slice::<FontFamilies>(bytes).context("invalid font_families.json")?;
+		if let FontFamilies::Versioned { version, .. } = families {
+			ensure!(
+				version == FONT_FAMILIES_VERSION,
+				"unsupported font_families.json version {version}"
+			);
+		}
+		Ok(families)
+	}
+
+	fn is_versioned(&self) -> bool {
+		matches!(self, FontFamilies::Versioned { .. })
+	}
+
+	fn into_families(self) -> Vec<FontFamily> {
+		match self {
+			FontFamilies::Versioned { families, .. } | FontFamilies::Legacy(families) => families,
+		}
+	}
+}
+
 impl FontFamily {
 	/// Creates a new font family with the given name.
 	fn new(name: String) -> Self {
@@ -33,14 +97,32 @@ impl FontFamily {
 		}
 	}
 
-	/// Adds a new [`FontFace`] to this family.
-	fn add_font(&mut self, id: String, meta: &FontMetadata) {
+	/// Adds a new [`FontFace`] to this family, applying the style/weight
+	/// overrides and charset filter from `settings`. With `with_copyright`, the
+	/// face carries the copyright and license of the font.
+	fn add_font(
+		&mut self,
+		id: String,
+		meta: &FontMetadata,
+		settings: &FontSettings,
+		with_copyright: bool,
+	) {
+		let attribution = |value: &Option<String>| value.clone().filter(|_| with_copyright);
+		let codepoints = meta
+			.codepoints
+			.iter()
+			.copied()
+			.filter(|&cp| settings.includes(cp))
+			.collect::<Vec<_>>();
 		self.faces.push(FontFace {
 			id,
-			style: meta.style.clone(),
-			weight: meta.weight,
+			style: settings.style.clone().unwrap_or_else(|| meta.style.clone()),
+			weight: settings.weight.unwrap_or(meta.weight),
 			width: meta.width.clone(),
-			codeblocks: encode_codeblocks(&meta.codepoints),
+			codeblocks: encode_codeblocks(&codepoints),
+			copyright: attribution(&meta.copyright),
+			license: attribution(&meta.license),
+			license_url: attribution(&meta.license_url),
 		});
 	}
 }
@@ -62,7 +144,7 @@ impl FontFamily {
 /// # Return Value
 /// A string of comma-separated hexadecimal block ranges, or an empty string if
 /// no codepoints are provided.
-fn encode_codeblocks(codepoints: &[u32]) -> String {
+pub fn encode_codeblocks(codepoints: &[u32]) -> String {
 	let blocks = HashSet::<u32>::from_iter(codepoints.iter().map(|&cp| cp >> 4));
 	let mut blocks: Vec<u32> = blocks.into_iter().collect();
 	blocks.sort_unstable();
@@ -98,7 +180,47 @@ fn encode_codeblocks(codepoints: &[u32]) -> String {
 		.join(",")
 }
 
-/// Builds an index (list) of all font IDs, returning JSON-encoded bytes.
+/// Decodes a string produced by [`encode_codeblocks`] into the codepoint ranges
+/// it covers.
+///
+/// Each block `b` expands to the 16 codepoints `b << 4 ..= (b << 4) | 0xF`, so the
+/// result covers every encoded codepoint, plus its neighbors within the same block.
+/// An empty string yields no ranges.
+///
+/// # Errors
+///
+/// Returns an error if a block is not valid hexadecimal or a range is reversed.
+#[allow(dead_code)] // Public API; the CLI only encodes codeblocks.
+pub fn decode_codeblocks(codeblocks: &str) -> Result<Vec<RangeInclusive<u32>>> {
+	let parse = |hex: &str| {
+		u32::from_str_radix(hex, 16).with_context(|| format!("invalid codeblock \"{hex}\""))
+	};
+	codeblocks
+		.split(',')
+		.filter(|part| !part.is_empty())
+		.map(|part| {
+			let (start, end) = match part.split_once('-') {
+				Some((start, end)) => (parse(start)?, parse(end)?),
+				None => (parse(part)?, parse(part)?),
+			};
+			ensure!(start <= end, "invalid codeblock range \"{part}\"");
+			Ok((start << 4)..=((end << 4) | 0xF))
+		})
+		.collect()
+}
+
+/// Encodes `value` as JSON, indented if `pretty`, e.g. for debugging, or
+/// compact otherwise, which keeps the index files of large font sets small.
+fn to_json(value: &impl serde::Serialize, pretty: bool) -> Result<Vec<u8>> {
+	Ok(if pretty {
+		serde_json::to_vec_pretty(value)?
+	} else {
+		serde_json::to_vec(value)?
+	})
+}
+
+/// Builds an index (list) of all font IDs, returning JSON-encoded bytes,
+/// indented if `pretty`.
 ///
 /// The iterator should yield `(id, FontWrapper)` pairs. The resulting JSON
 /// is an array of sorted string IDs.
@@ -106,24 +228,100 @@ fn encode_codeblocks(codepoints: &[u32]) -> String {
 /// # Errors
 ///
 /// Returns an error if the encoding process fails.
-pub fn build_index_json<'a>(iter: impl Iterator<Item = &'a String>) -> Result<Vec<u8>> {
+pub fn build_index_json<'a>(
+	iter: impl Iterator<Item = &'a String>,
+	pretty: bool,
+) -> Result<Vec<u8>> {
 	let mut list = iter.collect::<Vec<_>>();
 	list.sort();
-	Ok(serde_json::to_vec_pretty(&list)?)
+	to_json(&list, pretty)
+}
+
+/// Builds a list of glyph block filenames, like `["0-255.pbf", "256-511.pbf"]`,
+/// returning JSON-encoded bytes, indented if `pretty`. The filenames are kept
+/// in the given order.
+///
+/// # Errors
+///
+/// Returns an error if the encoding process fails.
+pub fn build_ranges_json(filenames: &[String], pretty: bool) -> Result<Vec<u8>> {
+	to_json(&filenames, pretty)
+}
+
+/// Builds a map from every fontstack name (see [`FontWrapper::display_name`])
+/// to its font id, returning JSON-encoded bytes, indented if `pretty`.
+///
+/// Clients that request glyphs by display name, like `"Fira Sans Regular"`,
+/// can use it to find the font's directory. The names are sorted.
+///
+/// # Errors
+///
+/// Returns an error if a font has no files, if two fonts have the same name,
+/// so clients couldn't tell them apart, or if the encoding process fails.
+pub fn build_fontstacks_json<'a>(
+	iter: impl Iterator<Item = (&'a String, &'a FontWrapper<'a>)>,
+	pretty: bool,
+) -> Result<Vec<u8>> {
+	let mut fontstacks = BTreeMap::<String, &String>::new();
+	for (id, font) in iter {
+		let name = font.display_name()?;
+		if let Some(other) = fontstacks.insert(name.clone(), id) {
+			let (first, second) = if other < id { (other, id) } else { (id, other) };
+			bail!("the fonts {first:?} and {second:?} have the same fontstack name {name:?}");
+		}
+	}
+	to_json(&fontstacks, pretty)
+}
+
+/// Options for [`build_font_families_json_with_options`]. All are off by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FontFamiliesOptions {
+	/// Whether every face also has the `copyright`, `license` and `license_url`
+	/// of its font, as far as the font's `name` table has them.
+	pub copyright: bool,
+	/// Whether the array is wrapped as `{"version": 2, "families": [...]}`
+	/// (see [`FONT_FAMILIES_VERSION`]), so clients can detect the schema.
+	/// Otherwise it is the legacy bare array.
+	pub versioned: bool,
+	/// Whether the JSON is indented.
+	pub pretty: bool,
 }
 
 /// Builds a list of font families, each containing one or more font faces,
-/// returning JSON-encoded bytes.
+/// returning indented JSON-encoded bytes.
+///
+/// This is [`build_font_families_json_with_options`] with the legacy bare
+/// array, without attribution.
+///
+/// # Errors
+///
+/// Returns an error if the encoding process fails.
+#[allow(dead_code)] // Public API; the CLI uses `build_font_families_json_with_options`.
+pub fn build_font_families_json<'a>(
+	iter: impl Iterator<Item = (&'a String, &'a FontWrapper<'a>)>,
+) -> Result<Vec<u8>> {
+	let options = FontFamiliesOptions {
+		pretty: true,
+		..Default::default()
+	};
+	build_font_families_json_with_options(iter, &options)
+}
+
+/// Builds a list of font families, each containing one or more font faces,
+/// returning JSON-encoded bytes, shaped by `options`.
 ///
 /// The iterator should yield `(id, FontWrapper)` pairs. Each font's
 /// metadata is examined, and faces with the same family name are grouped together.
-/// The JSON contains a sorted array of families, each with an array of faces.
+/// The JSON contains an array of families sorted by name, each with an array of
+/// faces sorted by weight, width, style and id, so the output doesn't depend on
+/// the iteration order.
 ///
 /// # Errors
 ///
 /// Returns an error if the encoding process fails.
-pub fn build_font_families_json<'a>(
+pub fn build_font_families_json_with_options<'a>(
 	iter: impl Iterator<Item = (&'a String, &'a FontWrapper<'a>)>,
+	options: &FontFamiliesOptions,
 ) -> Result<Vec<u8>> {
 	let mut family_map = HashMap::<String, FontFamily>::new();
 	for (id, font) in iter {
@@ -131,17 +329,93 @@ pub fn build_font_families_json<'a>(
 		family_map
 			.entry(meta.family.to_string())
 			.or_insert_with(|| FontFamily::new(meta.family.to_string()))
-			.add_font(id.to_string(), meta);
+			.add_font(id.to_string(), meta, &font.settings, options.copyright);
 	}
 	let mut families = family_map.into_values().collect::<Vec<_>>();
+	sort_families(&mut families);
+	to_json(
+		&FontFamilies::new(families, options.versioned),
+		options.pretty,
+	)
+}
+
+/// Sorts `families` by name, and their faces by weight, width, style and id.
+fn sort_families(families: &mut [FontFamily]) {
 	families.sort_by(|a, b| a.name.cmp(&b.name));
-	Ok(serde_json::to_vec_pretty(&families)?)
+	for family in families {
+		family.faces.sort_by(|a, b| {
+			(a.weight, &a.width, &a.style, &a.id).cmp(&(b.weight, &b.width, &b.style, &b.id))
+		});
+	}
+}
+
+/// Merges two `index.json` files, e.g. an existing one and one built by
+/// [`build_index_json`], into a sorted list without duplicates, indented if `pretty`.
+///
+/// # Errors
+///
+/// Returns an error if either isn't a JSON list of strings.
+pub fn merge_index_json(existing: &[u8], new: &[u8], pretty: bool) -> Result<Vec<u8>> {
+	let mut list = serde_json::from_slice::<Vec<String>>(existing)?;
+	list.extend(serde_json::from_slice::<Vec<String>>(new)?);
+	list.sort();
+	list.dedup();
+	to_json(&list, pretty)
+}
+
+/// Merges two `font_families.json` files, e.g. an existing one and one built
+/// by [`build_font_families_json`], indented if `pretty`.
+///
+/// Faces of `new` replace the faces with the same id in `existing`, even if
+/// their family changed. Families are sorted like in [`build_font_families_json`].
+/// Either file may be versioned or legacy, the result has the shape of `new`.
+///
+/// # Errors
+///
+/// Returns an error if either isn't a valid list of font families, or has an
+/// unsupported version.
+pub fn merge_font_families_json(existing: &[u8], new: &[u8], pretty: bool) -> Result<Vec<u8>> {
+	let mut families = FontFamilies::from_slice(existing)?.into_families();
+	let new = FontFamilies::from_slice(new)?;
+	let versioned = new.is_versioned();
+	let new = new.into_families();
+
+	let new_ids = new
+		.iter()
+		.flat_map(|family| family.faces.iter().map(|face| face.id.clone()))
+		.collect::<HashSet<_>>();
+	for family in &mut families {
+		family.faces.retain(|face| !new_ids.contains(&face.id));
+	}
+	for family in new {
+		match families.iter_mut().find(|f| f.name == family.name) {
+			Some(existing) => existing.faces.extend(family.faces),
+			None => families.push(family),
+		}
+	}
+	families.retain(|family| !family.faces.is_empty());
+
+	sort_families(&mut families);
+	to_json(&FontFamilies::new(families, versioned), pretty)
+}
+
+/// Merges two `fontstacks.json` files, e.g. an existing one and one built by
+/// [`build_fontstacks_json`], indented if `pretty`. Names in `new` replace
+/// those in `existing`.
+///
+/// # Errors
+///
+/// Returns an error if either isn't a JSON object of strings.
+pub fn merge_fontstacks_json(existing: &[u8], new: &[u8], pretty: bool) -> Result<Vec<u8>> {
+	let mut fontstacks = serde_json::from_slice::<BTreeMap<String, String>>(existing)?;
+	fontstacks.extend(serde_json::from_slice::<BTreeMap<String, String>>(new)?);
+	to_json(&fontstacks, pretty)
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::font::FontManager;
+	use crate::font::{FontManager, FontSettings};
 	use std::path::PathBuf;
 
 	#[test]
@@ -152,7 +426,7 @@ mod tests {
 			PathBuf::from("./testdata/Noto Sans/Noto Sans - Regular.ttf"),
 		])?;
 
-		let json_bytes = build_index_json(manager.fonts.keys())?;
+		let json_bytes = build_index_json(manager.fonts.keys(), true)?;
 		assert_eq!(
 			String::from_utf8(json_bytes)?
 				.split('\n')
@@ -212,6 +486,253 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_build_json_compact() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[
+			PathBuf::from("./testdata/Fira Sans - Regular.ttf"),
+			PathBuf::from("./testdata/Noto Sans/Noto Sans - Regular.ttf"),
+		])?;
+
+		let parse = |bytes: &[u8]| serde_json::from_slice::<serde_json::Value>(bytes).unwrap();
+		for build in [
+			|m: &FontManager, pretty| build_index_json(m.fonts.keys(), pretty),
+			|m: &FontManager, pretty| {
+				let options = FontFamiliesOptions {
+					pretty,
+					..Default::default()
+				};
+				build_font_families_json_with_options(m.fonts.iter(), &options)
+			},
+		] {
+			let compact = build(&manager, false)?;
+			let pretty = build(&manager, true)?;
+			assert!(!compact.contains(&b'\n'));
+			assert!(compact.len() < pretty.len());
+			assert_eq!(parse(&compact), parse(&pretty));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_font_families_json_with_copyright() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[PathBuf::from("./testdata/Fira Sans - Regular.ttf")])?;
+
+		let json =
+			serde_json::from_slice::<serde_json::Value>(&build_font_families_json_with_options(
+				manager.fonts.iter(),
+				&FontFamiliesOptions {
+					copyright: true,
+					..Default::default()
+				},
+			)?)?;
+		let face = &json[0]["faces"][0];
+		assert!(face["copyright"]
+			.as_str()
+			.unwrap()
+			.contains("The Mozilla Foundation"));
+		assert_eq!(
+			face["license"],
+			"Licensed under the Open Font License, version 1.1 or later"
+		);
+		assert_eq!(face["license_url"], "http://scripts.sil.org/OFL");
+
+		// Faces written with and without attribution can be merged.
+		let without = build_font_families_json(manager.fonts.iter())?;
+		let merged = merge_font_families_json(&without, &serde_json::to_vec(&json)?, false)?;
+		let merged = serde_json::from_slice::<serde_json::Value>(&merged)?;
+		assert_eq!(merged[0]["faces"][0], *face);
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_font_families_json_versioned() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[
+			PathBuf::from("./testdata/Fira Sans - Regular.ttf"),
+			PathBuf::from("./testdata/Noto Sans/Noto Sans - Regular.ttf"),
+		])?;
+		let parse = |json: &[u8]| serde_json::from_slice::<serde_json::Value>(json).unwrap();
+		let legacy = build_font_families_json_with_options(
+			manager.fonts.iter(),
+			&FontFamiliesOptions::default(),
+		)?;
+		let versioned = build_font_families_json_with_options(
+			manager.fonts.iter(),
+			&FontFamiliesOptions {
+				versioned: true,
+				..Default::default()
+			},
+		)?;
+		assert_eq!(parse(&versioned)["version"], FONT_FAMILIES_VERSION);
+		assert_eq!(parse(&versioned)["families"], parse(&legacy));
+		assert_eq!(parse(&versioned).as_object().unwrap().len(), 2);
+
+		// Merging keeps the shape of the new file, and rejects unknown versions.
+		assert_eq!(
+			merge_font_families_json(&legacy, &versioned, false)?,
+			versioned
+		);
+		assert_eq!(
+			merge_font_families_json(&versioned, &legacy, false)?,
+			legacy
+		);
+		let future = br#"{"version":3,"families":[]}"#;
+		assert_eq!(
+			merge_font_families_json(future, &legacy, false)
+				.unwrap_err()
+				.to_string(),
+			"unsupported font_families.json version 3"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_font_families_json_sorts_faces() -> Result<()> {
+		let fira = [PathBuf::from("./testdata/Fira Sans - Regular.ttf")];
+		for _ in 0..5 {
+			// Every manager iterates its fonts in a different, random order.
+			let mut manager = FontManager::new(false);
+			for (name, weight) in [("Fira Sans Bold", 700), ("Fira Sans Light", 300)] {
+				let settings = FontSettings {
+					weight: Some(weight),
+					..Default::default()
+				};
+				manager.add_font_with_settings(name, &fira, settings)?;
+			}
+			let italic = FontSettings {
+				style: Some(String::from("italic")),
+				..Default::default()
+			};
+			manager.add_font_with_settings("Fira Sans Italic", &fira, italic)?;
+			manager.add_font_with_name("Fira Sans Regular", &fira)?;
+
+			let json =
+				serde_json::from_slice::<serde_json::Value>(&build_font_families_json_with_options(
+					manager.fonts.iter(),
+					&FontFamiliesOptions::default(),
+				)?)?;
+			let ids = json[0]["faces"]
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|face| face["id"].as_str().unwrap())
+				.collect::<Vec<_>>();
+			assert_eq!(
+				ids,
+				[
+					"fira_sans_light",
+					"fira_sans_italic",
+					"fira_sans_regular",
+					"fira_sans_bold"
+				]
+			);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_merge_index_json() -> Result<()> {
+		let merged = merge_index_json(br#"["b","c"]"#, br#"["a","b"]"#, false)?;
+		assert_eq!(
+			serde_json::from_slice::<Vec<String>>(&merged)?,
+			["a", "b", "c"]
+		);
+		assert!(merge_index_json(b"{}", b"[]", false).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_merge_font_families_json() -> Result<()> {
+		let face = |id: &str, weight: u16| {
+			format!(
+				r#"{{"id":"{id}","style":"normal","weight":{weight},"width":"normal","codeblocks":"0"}}"#
+			)
+		};
+		let existing = format!(
+			r#"[{{"name":"Noto Sans","faces":[{}]}},{{"name":"Fira","faces":[{},{}]}}]"#,
+			face("noto_sans_regular", 400),
+			face("fira_sans_regular", 400),
+			face("fira_sans_bold", 700)
+		);
+		let new = format!(
+			r#"[{{"name":"Fira Sans","faces":[{},{}]}}]"#,
+			face("fira_sans_light", 300),
+			face("fira_sans_regular", 400)
+		);
+
+		let merged = serde_json::from_slice::<serde_json::Value>(&merge_font_families_json(
+			existing.as_bytes(),
+			new.as_bytes(),
+			false,
+		)?)?;
+		let families = merged
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|family| {
+				let ids = family["faces"]
+					.as_array()
+					.unwrap()
+					.iter()
+					.map(|face| face["id"].as_str().unwrap())
+					.collect::<Vec<_>>();
+				(family["name"].as_str().unwrap(), ids)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			families,
+			[
+				("Fira", vec!["fira_sans_bold"]),
+				("Fira Sans", vec!["fira_sans_light", "fira_sans_regular"]),
+				("Noto Sans", vec!["noto_sans_regular"]),
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_fontstacks_json() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[
+			PathBuf::from("./testdata/Fira Sans - Regular.ttf"),
+			PathBuf::from("./testdata/Noto Sans/Noto Sans - Regular.ttf"),
+		])?;
+
+		let json_bytes = build_fontstacks_json(manager.fonts.iter(), true)?;
+		assert_eq!(
+			String::from_utf8(json_bytes)?
+				.split('\n')
+				.collect::<Vec<_>>(),
+			[
+				"{",
+				"  \"Fira Sans Regular\": \"fira_sans_regular\",",
+				"  \"Noto Sans Regular\": \"noto_sans_regular\"",
+				"}"
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_fontstacks_json_rejects_duplicate_names() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&[
+			PathBuf::from("./testdata/Fira Sans - Regular.ttf"),
+			PathBuf::from("./testdata/Noto Sans/Noto Sans - Regular.ttf"),
+		])?;
+		manager.fonts.get_mut("noto_sans_regular").unwrap().name =
+			Some(String::from("Fira Sans Regular"));
+
+		let err = build_fontstacks_json(manager.fonts.iter(), false).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"the fonts \"fira_sans_regular\" and \"noto_sans_regular\" have the same fontstack name \"Fira Sans Regular\""
+		);
+		Ok(())
+	}
+
 	#[test]
 	fn empty_input_returns_empty_string() {
 		assert_eq!(encode_codeblocks(&[]), "");
@@ -231,4 +752,33 @@ mod tests {
 	fn disjoint_blocks_produce_multiple_ranges() {
 		assert_eq!(encode_codeblocks(&[0x0, 0x2, 0x1F, 0x40, 0xA0]), "0-1,4,A");
 	}
+
+	#[test]
+	fn decode_inverts_encode() -> Result<()> {
+		let codepoints = [0x41, 0x42, 0xE9, 0x100, 0x11F, 0x20AC];
+		let encoded = encode_codeblocks(&codepoints);
+		assert_eq!(encoded, "4,E,10-11,20A");
+
+		let ranges = decode_codeblocks(&encoded)?;
+		assert_eq!(
+			ranges,
+			[0x40..=0x4F, 0xE0..=0xEF, 0x100..=0x11F, 0x20A0..=0x20AF]
+		);
+		assert!(codepoints
+			.iter()
+			.all(|cp| ranges.iter().any(|r| r.contains(cp))));
+
+		// Every codepoint of the decoded ranges encodes to the same string.
+		let expanded = ranges.into_iter().flatten().collect::<Vec<_>>();
+		assert_eq!(encode_codeblocks(&expanded), encoded);
+		Ok(())
+	}
+
+	#[test]
+	fn decode_rejects_invalid_input() -> Result<()> {
+		assert!(decode_codeblocks("")?.is_empty());
+		assert!(decode_codeblocks("G").is_err());
+		assert!(decode_codeblocks("5-3").is_err());
+		Ok(())
+	}
 }
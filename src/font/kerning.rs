@@ -0,0 +1,68 @@
+//! Simple pair kerning from the `kern` and `GPOS` tables.
+//!
+//! Only horizontal pair adjustments are supported: format 0, 2 and 3 subtables
+//! of the legacy `kern` table, and pair adjustment lookups (including those
+//! wrapped in extension lookups) of `GPOS`. `GPOS` lookups are read regardless
+//! of script, language and feature, and contextual positioning is ignored, so
+//! the result can differ from a full shaping engine.
+
+use ttf_parser::{
+	gpos::{PairAdjustment, PositioningSubtable},
+	Face, GlyphId,
+};
+
+/// Returns `true` if `face` has a `kern` table or a `GPOS` pair adjustment lookup.
+pub fn has_kerning(face: &Face) -> bool {
+	face.tables().kern.is_some() || gpos_pairs(face).next().is_some()
+}
+
+/// Returns the horizontal kerning between the glyphs `left` and `right` in font
+/// units, or [`None`] if the font doesn't adjust this pair.
+///
+/// The `kern` table is preferred. Otherwise the first `GPOS` pair adjustment
+/// covering the pair is used.
+pub fn glyph_kerning(face: &Face, left: GlyphId, right: GlyphId) -> Option<i16> {
+	kern_table_kerning(face, left, right)
+		.or_else(|| gpos_pairs(face).find_map(|pair| gpos_pair_kerning(&pair, left, right)))
+}
+
+/// Looks the pair up in the horizontal, non-variable subtables of the `kern` table.
+fn kern_table_kerning(face: &Face, left: GlyphId, right: GlyphId) -> Option<i16> {
+	face
+		.tables()
+		.kern?
+		.subtables
+		.into_iter()
+		.filter(|subtable| subtable.horizontal && !subtable.variable && !subtable.has_cross_stream)
+		.find_map(|subtable| subtable.glyphs_kerning(left, right))
+}
+
+/// Iterates over all pair adjustment subtables of every `GPOS` lookup.
+fn gpos_pairs<'a>(face: &Face<'a>) -> impl Iterator<Item = PairAdjustment<'a>> {
+	face
+		.tables()
+		.gpos
+		.into_iter()
+		.flat_map(|gpos| gpos.lookups.into_iter())
+		.flat_map(|lookup| lookup.subtables.into_iter::<PositioningSubtable>())
+		.filter_map(|subtable| match subtable {
+			PositioningSubtable::Pair(pair) => Some(pair),
+			_ => None,
+		})
+}
+
+/// Returns the advance adjustment of the first glyph, if `pair` covers the pair.
+fn gpos_pair_kerning(pair: &PairAdjustment, left: GlyphId, right: GlyphId) -> Option<i16> {
+	let records = match pair {
+		PairAdjustment::Format1 { coverage, sets } => sets.get(coverage.get(left)?)?.get(right)?,
+		PairAdjustment::Format2 {
+			coverage,
+			classes,
+			matrix,
+		} => {
+			coverage.get(left)?;
+			matrix.get((classes.0.get(left), classes.1.get(right)))?
+		}
+	};
+	Some(records.0.x_advance).filter(|&advance| advance != 0)
+}
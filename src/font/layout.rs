@@ -0,0 +1,114 @@
+//! Directory naming schemes for the rendered glyph files.
+
+use super::FontWrapper;
+use anyhow::{bail, Result};
+use std::{fmt, str::FromStr};
+
+/// Determines the directory a font's glyph blocks are written to, and how the
+/// font is referenced in `index.json` and `font_families.json`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FontLayout {
+	/// One directory per normalized font id, e.g. `fira_sans_regular/0-255.pbf`.
+	#[default]
+	Id,
+	/// Nested family and style directories, e.g. `Fira Sans/Regular/0-255.pbf`.
+	///
+	/// Spaces are kept, but characters that are unsafe in file names or URLs
+	/// (like `/`, `\`, `:`, `?`, `#` or `%`) are replaced by `_`.
+	FamilyStyle,
+}
+
+impl FontLayout {
+	/// Returns the directory path (without trailing slash) of the font stored under `id`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `font` has no files, so its metadata is unknown.
+	pub fn path(&self, id: &str, font: &FontWrapper) -> Result<String> {
+		Ok(match self {
+			FontLayout::Id => id.to_string(),
+			FontLayout::FamilyStyle => {
+				let meta = font.get_metadata()?;
+				let name = meta.generate_name();
+				let style = name.strip_prefix(&meta.family).unwrap_or(&name).trim();
+				format!("{}/{}", escape(&meta.family), escape(style))
+			}
+		})
+	}
+}
+
+impl fmt::Display for FontLayout {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			FontLayout::Id => "id",
+			FontLayout::FamilyStyle => "family-style",
+		})
+	}
+}
+
+impl FromStr for FontLayout {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		Ok(match s {
+			"id" => FontLayout::Id,
+			"family-style" => FontLayout::FamilyStyle,
+			_ => bail!("unknown layout \"{s}\", expected \"id\" or \"family-style\""),
+		})
+	}
+}
+
+/// Makes `name` safe to use as a single path segment.
+fn escape(name: &str) -> String {
+	let escaped = name
+		.chars()
+		.map(|c| match c {
+			'/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '%' => '_',
+			c if c.is_control() => '_',
+			c => c,
+		})
+		.collect::<String>();
+	match escaped.trim() {
+		"" | "." | ".." => String::from("_"),
+		trimmed => trimmed.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::font::FontFileEntry;
+
+	#[test]
+	fn test_path() -> Result<()> {
+		let font = FontWrapper::from(FontFileEntry::new(
+			include_bytes!("../../testdata/Fira Sans - Regular.ttf").to_vec(),
+		)?);
+		assert_eq!(
+			FontLayout::Id.path("fira_sans_regular", &font)?,
+			"fira_sans_regular"
+		);
+		assert_eq!(
+			FontLayout::FamilyStyle.path("fira_sans_regular", &font)?,
+			"Fira Sans/Regular"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_escape() {
+		assert_eq!(escape("Noto Sans"), "Noto Sans");
+		assert_eq!(escape("A/B: C?"), "A_B_ C_");
+		assert_eq!(escape(".."), "_");
+	}
+
+	#[test]
+	fn test_parse() {
+		assert_eq!("id".parse::<FontLayout>().unwrap(), FontLayout::Id);
+		assert_eq!(
+			"family-style".parse::<FontLayout>().unwrap(),
+			FontLayout::FamilyStyle
+		);
+		assert!("flat".parse::<FontLayout>().is_err());
+	}
+}
@@ -1,18 +1,39 @@
-use super::index_files::{build_font_families_json, build_index_json};
+use super::in_flight::{InFlight, MemoryStats};
+use super::index_files::{
+	build_font_families_json_with_options, build_fontstacks_json, build_index_json,
+	build_ranges_json, merge_font_families_json, merge_fontstacks_json, merge_index_json,
+	FontFamiliesOptions,
+};
 use crate::{
-	font::{FontFileEntry, FontWrapper, GlyphBlock},
+	error::GlyphError,
+	font::{FontFileEntry, FontLayout, FontSettings, FontWrapper, GlyphBlock, GLYPH_BLOCK_SIZE},
+	protobuf::{PbfFontMetadata, PbfGlyphs},
 	render::Renderer,
-	utils::get_progress_bar,
+	utils::{NoProgress, ProgressSink},
 	writer::Writer,
 };
-use anyhow::{anyhow, Result};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use regex_lite::Regex;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::{
-	collections::{hash_map::Entry, HashMap},
+	collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
 	path::{Path, PathBuf},
-	sync::{Mutex, OnceLock},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		mpsc, Mutex, PoisonError,
+	},
+	thread,
 };
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Maximum number of rendered blocks waiting to be written.
+///
+/// Bounds memory use when rendering outpaces the writer, or when blocks wait
+/// for a previous one to be written first.
+const WRITE_QUEUE_SIZE: usize = 64;
+
+/// Combines an existing index file with a new one, like [`merge_index_json`],
+/// indented if the flag is set.
+type MergeFn = fn(&[u8], &[u8], bool) -> Result<Vec<u8>>;
 
 /// Manages a collection of fonts and provides methods to render glyphs
 /// and write metadata (index/families) files.
@@ -21,6 +42,67 @@ pub struct FontManager<'a> {
 	pub fonts: HashMap<String, FontWrapper<'a>>,
 	/// Whether to parallelize rendering operations.
 	pub parallel: bool,
+	/// Directory naming scheme for the rendered fonts.
+	pub layout: FontLayout,
+	/// Whether to write an empty block for every range of the Basic Multilingual
+	/// Plane (`0-255.pbf` to `65280-65535.pbf` with the default [`Self::block_size`])
+	/// a font has no glyphs in.
+	pub emit_empty_ranges: bool,
+	/// Number of codepoints per glyph block, [`GLYPH_BLOCK_SIZE`] by default.
+	/// Should pass [`check_block_size`](super::check_block_size).
+	pub block_size: u32,
+	/// Whether to write one file per script, like `latin.pbf` and `arabic.pbf`
+	/// (see [`script_of`](super::script_of)), instead of blocks of
+	/// [`Self::block_size`] codepoints. [`Self::emit_empty_ranges`],
+	/// [`Self::include_blocks`] and [`Self::exclude_blocks`] are ignored then.
+	pub group_by_script: bool,
+	/// Start indices of the only glyph blocks to render, like `0` for `0-255.pbf`.
+	/// All blocks are rendered if empty.
+	pub include_blocks: Vec<u32>,
+	/// Start indices of glyph blocks to skip, applied after [`Self::include_blocks`].
+	pub exclude_blocks: Vec<u32>,
+	/// Whether a glyph that fails to render aborts [`Self::render_glyphs`].
+	/// Otherwise the glyph is skipped with a warning and counted in
+	/// [`Self::failed_glyphs`].
+	pub strict: bool,
+	/// Whether to skip glyph blocks whose file already exists in the output (see
+	/// [`Writer::file_exists`]), e.g. to resume an interrupted render.
+	pub skip_existing: bool,
+	/// Whether to merge the index files already in the output (see
+	/// [`Writer::read_file`]) with the managed fonts, instead of replacing them,
+	/// e.g. to add a font to an existing glyph directory.
+	pub merge_existing: bool,
+	/// Whether `font_families.json` lists the copyright notice and license of
+	/// every face (see [`FontMetadata::copyright`](super::FontMetadata::copyright)),
+	/// so the attribution that font licenses require can be shown.
+	pub include_copyright: bool,
+	/// Whether `font_families.json` is wrapped with its schema version, like
+	/// `{"version": 2, "families": [...]}`, instead of the legacy bare array,
+	/// see [`FontFamiliesOptions::versioned`].
+	pub versioned_families: bool,
+	/// Whether the JSON index files are indented for reading, e.g. when
+	/// debugging. They are compact by default, to keep them small.
+	pub pretty_json: bool,
+	/// Whether every glyph block stores the metrics of its font, like the
+	/// family, size and ascender (see [`PbfFontMetadata`]), so it describes itself.
+	pub concat_metadata: bool,
+	/// Whether to decode every glyph block again after writing it, and fail if
+	/// it doesn't parse or has fewer or more glyphs than were rendered. The
+	/// encoded bytes are checked, and the blocks are also read back from the
+	/// output if it supports that (see [`Writer::read_block`]).
+	pub validate: bool,
+	/// Soft limit in bytes for the rendered blocks that wait to be written.
+	/// Render threads wait while it's exceeded, but always keep at least one
+	/// block in flight, and every thread holds the block it just rendered.
+	/// Unlimited if `None`, apart from a queue of 64 blocks.
+	pub max_memory: Option<u64>,
+	/// Mapping from a font id to the ids of other managed fonts that provide the
+	/// glyphs it lacks, in priority order, see [`Self::set_fallbacks`].
+	pub fallbacks: HashMap<String, Vec<String>>,
+	/// Peak bytes and number of blocks in flight during the last render.
+	peak_in_flight: Mutex<(u64, usize)>,
+	/// Number of glyphs skipped because they failed to render, see [`Self::failed_glyphs`].
+	failed_glyphs: AtomicUsize,
 }
 
 impl<'a> FontManager<'a> {
@@ -29,6 +111,24 @@ impl<'a> FontManager<'a> {
 		Self {
 			fonts: HashMap::new(),
 			parallel,
+			layout: FontLayout::default(),
+			emit_empty_ranges: false,
+			block_size: GLYPH_BLOCK_SIZE,
+			group_by_script: false,
+			include_blocks: Vec::new(),
+			exclude_blocks: Vec::new(),
+			strict: false,
+			skip_existing: false,
+			merge_existing: false,
+			include_copyright: false,
+			versioned_families: false,
+			pretty_json: false,
+			concat_metadata: false,
+			validate: false,
+			max_memory: None,
+			fallbacks: HashMap::new(),
+			peak_in_flight: Mutex::new((0, 0)),
+			failed_glyphs: AtomicUsize::new(0),
 		}
 	}
 
@@ -36,9 +136,32 @@ impl<'a> FontManager<'a> {
 	///
 	/// The font name is normalized to form a key used in [`Self::fonts`].
 	/// If the key already exists, the file is appended to that font.
-	pub fn add_path(&mut self, path: &Path) -> Result<()> {
-		let file_data = std::fs::read(path)?;
-		let file = FontFileEntry::new(file_data)?;
+	///
+	/// # Errors
+	///
+	/// Returns the error of [`FontFileEntry::from_path`] if the file can't be
+	/// read or parsed.
+	#[allow(dead_code)] // Public API; the CLI loads files in bulk with `read_files`.
+	pub fn add_path(&mut self, path: &Path) -> Result<(), GlyphError> {
+		self.add_file(FontFileEntry::from_path(path)?);
+		Ok(())
+	}
+
+	/// Adds a single font file from memory, e.g. one read from stdin.
+	///
+	/// The font is stored under its normalized name, like [`Self::add_path`].
+	pub fn add_bytes(&mut self, file_data: Vec<u8>) -> Result<(), GlyphError> {
+		self.add_file(FontFileEntry::new(file_data)?);
+		Ok(())
+	}
+
+	/// Adds an already parsed font file, stored under its normalized name like
+	/// [`Self::add_path`].
+	///
+	/// Files whose names normalize to the same id are merged into one font in a fixed order (see
+	/// [`FontWrapper::insert_file`]), so the file providing a shared codepoint
+	/// doesn't depend on the order they were added in.
+	pub fn add_file(&mut self, file: FontFileEntry<'a>) {
 		let id = name_to_id(&file.metadata.generate_name());
 
 		match self.fonts.entry(id) {
@@ -46,109 +169,892 @@ impl<'a> FontManager<'a> {
 				e.insert(FontWrapper::from(file));
 			}
 			Entry::Occupied(mut e) => {
-				e.get_mut().add_file(file);
+				e.get_mut().insert_file(file);
 			}
 		}
-		Ok(())
 	}
 
 	/// Adds multiple font files to the manager.
-	pub fn add_paths(&mut self, paths: &[PathBuf]) -> Result<()> {
-		for p in paths {
-			self.add_path(p)?;
+	///
+	/// If [`Self::parallel`] is set, the files are read and parsed in parallel
+	/// (see [`Self::read_files`]). They are added in the order of `paths`, so
+	/// the result is the same either way.
+	///
+	/// # Errors
+	///
+	/// Returns the first error in the order of `paths`. The files before it are
+	/// still added.
+	pub fn add_paths(&mut self, paths: &[PathBuf]) -> Result<(), GlyphError> {
+		for file in self.read_files(paths) {
+			self.add_file(file?);
 		}
 		Ok(())
 	}
 
+	/// Reads and parses the font files at `paths` without adding them, returning
+	/// one result per path in the same order.
+	///
+	/// The files are processed on the `rayon` thread pool if [`Self::parallel`] is set.
+	pub fn read_files(&self, paths: &[PathBuf]) -> Vec<Result<FontFileEntry<'a>, GlyphError>> {
+		if self.parallel {
+			paths
+				.par_iter()
+				.map(|path| FontFileEntry::from_path(path))
+				.collect()
+		} else {
+			paths
+				.iter()
+				.map(|path| FontFileEntry::from_path(path))
+				.collect()
+		}
+	}
+
 	/// Adds multiple sources for a single named font family.
 	///
-	/// Useful for merging multiple `.ttf` files under one key.
-	pub fn add_font_with_name(&mut self, name: &str, sources: &[PathBuf]) -> Result<()> {
-		let id = name_to_id(name);
-		match self.fonts.entry(id) {
-			Entry::Occupied(mut e) => e.get_mut().add_paths(sources)?,
-			Entry::Vacant(e) => {
-				e.insert(FontWrapper::try_from(sources)?);
+	/// Useful for merging multiple `.ttf` files under one key. The rendered
+	/// blocks and `fontstacks.json` use `name` as the fontstack name.
+	#[allow(dead_code)] // Public API; the `recurse` command uses `add_files_with_settings`.
+	pub fn add_font_with_name(&mut self, name: &str, sources: &[PathBuf]) -> Result<(), GlyphError> {
+		self.add_font_with_settings(name, sources, FontSettings::default())
+	}
+
+	/// Like [`Self::add_font_with_name`], but also sets per-font [`FontSettings`].
+	///
+	/// If the font already exists, its sources are extended and its settings
+	/// are replaced by `settings`.
+	pub fn add_font_with_settings(
+		&mut self,
+		name: &str,
+		sources: &[PathBuf],
+		settings: FontSettings,
+	) -> Result<(), GlyphError> {
+		let files = self
+			.read_files(sources)
+			.into_iter()
+			.collect::<Result<Vec<_>, _>>()?;
+		self.add_files_with_settings(name, files, settings);
+		Ok(())
+	}
+
+	/// Like [`Self::add_font_with_settings`], but with already parsed font files.
+	pub fn add_files_with_settings(
+		&mut self,
+		name: &str,
+		files: Vec<FontFileEntry<'a>>,
+		settings: FontSettings,
+	) {
+		let font = self.fonts.entry(name_to_id(name)).or_default();
+		for file in files {
+			font.add_file(file);
+		}
+		font.settings = settings;
+		font.name = Some(name.to_string());
+	}
+
+	/// Restricts the managed fonts to a selection.
+	///
+	/// If `only` is non-empty, fonts not matched by any of its patterns are removed.
+	/// Fonts matched by any pattern in `exclude` are removed afterwards. A pattern
+	/// matches a font if it normalizes to the font's id (e.g. `"fira_sans_regular"`
+	/// or `"Fira Sans Regular"`) or equals its family name, ignoring case.
+	pub fn filter_fonts(&mut self, only: &[String], exclude: &[String]) {
+		let matches_any = |id: &str, font: &FontWrapper, patterns: &[String]| {
+			patterns.iter().any(|p| font_matches(id, font, p))
+		};
+		self.fonts.retain(|id, font| {
+			(only.is_empty() || matches_any(id, font, only)) && !matches_any(id, font, exclude)
+		});
+	}
+
+	/// Removes the fonts that cover fewer than `min_glyphs` codepoints (see
+	/// [`FontWrapper::codepoints`]), like stub or icon fonts found in a scanned
+	/// directory, so they don't show up in the output and index files.
+	///
+	/// Returns the ids of the removed fonts with their number of codepoints,
+	/// sorted by id, e.g. to report them.
+	pub fn remove_small_fonts(&mut self, min_glyphs: usize) -> Vec<(String, usize)> {
+		let mut removed = Vec::new();
+		self.fonts.retain(|id, font| {
+			let count = font.codepoints().len();
+			if count >= min_glyphs {
+				return true;
 			}
+			removed.push((id.clone(), count));
+			false
+		});
+		removed.sort_unstable();
+		removed
+	}
+
+	/// Renders the codepoints that font `name` lacks from the fonts `fallbacks`,
+	/// consulted in order, e.g. a symbol or script font for a text font.
+	///
+	/// Unlike the files of one font, which are merged into one logical font,
+	/// the fallbacks stay separate fonts: they are still rendered on their own,
+	/// and the index files only list the codepoints of `name` itself. Their
+	/// glyphs are tagged in [`GlyphBlock::fallbacks`]. The fallbacks of a
+	/// fallback are not consulted. Names are normalized like in
+	/// [`Self::add_font_with_name`], and replace any previous fallbacks.
+	#[allow(dead_code)] // Public API; the CLI merges fonts instead.
+	pub fn set_fallbacks(&mut self, name: &str, fallbacks: &[&str]) {
+		self.fallbacks.insert(
+			name_to_id(name),
+			fallbacks
+				.iter()
+				.map(|fallback| name_to_id(fallback))
+				.collect(),
+		);
+	}
+
+	/// Splits this manager into one manager per font, sorted by font id, e.g.
+	/// to write every font into its own archive. All settings except
+	/// [`Self::fallbacks`] are kept, since each manager holds only one font.
+	pub fn into_single_fonts(self) -> Vec<(String, FontManager<'a>)> {
+		let mut fonts = self.fonts.into_iter().collect::<Vec<_>>();
+		fonts.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+		fonts
+			.into_iter()
+			.map(|(id, font)| {
+				let manager = FontManager {
+					fonts: HashMap::from([(id.clone(), font)]),
+					parallel: self.parallel,
+					layout: self.layout,
+					emit_empty_ranges: self.emit_empty_ranges,
+					block_size: self.block_size,
+					group_by_script: self.group_by_script,
+					include_blocks: self.include_blocks.clone(),
+					exclude_blocks: self.exclude_blocks.clone(),
+					strict: self.strict,
+					skip_existing: self.skip_existing,
+					merge_existing: self.merge_existing,
+					include_copyright: self.include_copyright,
+					versioned_families: self.versioned_families,
+					pretty_json: self.pretty_json,
+					concat_metadata: self.concat_metadata,
+					validate: self.validate,
+					max_memory: self.max_memory,
+					fallbacks: HashMap::new(),
+					peak_in_flight: Mutex::new((0, 0)),
+					failed_glyphs: AtomicUsize::new(0),
+				};
+				(id, manager)
+			})
+			.collect()
+	}
+
+	/// Renders the `.notdef` glyph of every font for those `codepoints` it doesn't cover.
+	pub fn include_notdef(&mut self, codepoints: &HashSet<u32>) {
+		for font in self.fonts.values_mut() {
+			font.settings.notdef.extend(codepoints);
+		}
+	}
+
+	/// Skips control characters and other codepoints that never render in every
+	/// font, see [`is_non_rendering`](super::is_non_rendering).
+	pub fn skip_non_rendering(&mut self) {
+		for font in self.fonts.values_mut() {
+			font.settings.skip_non_rendering = true;
 		}
-		Ok(())
+	}
+
+	/// Skips all codepoints above `max_codepoint` in every font, e.g. `0xFFFF`
+	/// for clients that don't support blocks beyond the Basic Multilingual Plane.
+	pub fn limit_codepoints(&mut self, max_codepoint: u32) {
+		for font in self.fonts.values_mut() {
+			let max = font
+				.settings
+				.max_codepoint
+				.map_or(max_codepoint, |max| max.min(max_codepoint));
+			font.settings.max_codepoint = Some(max);
+		}
+	}
+
+	/// Restricts every font to `codepoints`, like the characters of a text corpus,
+	/// on top of its own [`FontSettings::charset`], so only glyphs that are used
+	/// are rendered and indexed.
+	pub fn restrict_charset(&mut self, codepoints: &HashSet<u32>) {
+		for font in self.fonts.values_mut() {
+			let charset = match font.settings.charset.take() {
+				Some(charset) => charset.intersection(codepoints).copied().collect(),
+				None => codepoints.clone(),
+			};
+			font.settings.charset = Some(charset);
+		}
+	}
+
+	/// Returns the number of distinct codepoints covered by all managed fonts together.
+	#[allow(dead_code)] // Public API; for diagnostics.
+	pub fn total_codepoints(&self) -> usize {
+		self
+			.fonts
+			.values()
+			.flat_map(|font| font.codepoints())
+			.collect::<HashSet<_>>()
+			.len()
+	}
+
+	/// Returns the number of codepoints covered by both fonts `id1` and `id2`,
+	/// or [`None`] if one of them isn't managed.
+	#[allow(dead_code)] // Public API; for diagnostics.
+	pub fn codepoint_overlap(&self, id1: &str, id2: &str) -> Option<usize> {
+		let codepoints1 = self.fonts.get(id1)?.codepoints();
+		let codepoints2 = self.fonts.get(id2)?.codepoints();
+		Some(codepoints1.intersection(&codepoints2).count())
+	}
+
+	/// Returns the codepoint overlap of every pair of managed fonts as
+	/// `(id1, id2, count)`, sorted by id, with `id1 < id2`.
+	///
+	/// Fonts that share most of their codepoints gain little from being merged.
+	#[allow(dead_code)] // Public API; for diagnostics.
+	pub fn codepoint_overlaps(&self) -> Vec<(String, String, usize)> {
+		let mut fonts = self
+			.fonts
+			.iter()
+			.map(|(id, font)| (id, font.codepoints()))
+			.collect::<Vec<_>>();
+		fonts.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+		let mut overlaps = Vec::new();
+		for (i, (id1, codepoints1)) in fonts.iter().enumerate() {
+			for (id2, codepoints2) in &fonts[i + 1..] {
+				let count = codepoints1.intersection(codepoints2).count();
+				overlaps.push((id1.to_string(), id2.to_string(), count));
+			}
+		}
+		overlaps
 	}
 
 	/// Renders glyphs from all managed fonts via the provided renderer,
 	/// writing each glyph block to the supplied writer.
 	///
-	/// Rendering is parallelized with `rayon` for performance.
-	pub fn render_glyphs(&'a self, writer: &mut Writer, renderer: &Renderer) -> Result<()> {
+	/// Each font is rendered with `renderer`, adjusted by the font's
+	/// [`FontSettings`]. Rendering is parallelized with `rayon` for performance. Rendered blocks
+	/// are sent through a bounded channel to a single writer thread, so IO
+	/// overlaps with rendering and writes stay sequential (as tar requires).
+	///
+	/// # Order
+	///
+	/// Fonts are rendered in order of their id, and the blocks of each font in
+	/// order of their start index. The files are written in exactly this order,
+	/// also with [`Self::parallel`], so a tar archive with a fixed modification
+	/// time (see [`Writer::new_tar_with_mtime`]) is identical on every run.
+	///
+	/// # Memory
+	///
+	/// Each block is written and dropped as soon as all blocks before it are
+	/// written. Render threads wait while their block is `WRITE_QUEUE_SIZE` (64)
+	/// or more blocks ahead of the next one to write, so at most that many
+	/// rendered blocks are held back, plus one per render thread, and the memory
+	/// used for rendered data stays constant no matter how many fonts are managed. The task list only holds codepoint to
+	/// font references, and the font files themselves are kept in memory.
+	///
+	/// Doesn't report progress, see [`Self::render_glyphs_with_progress`].
+	///
+	/// # Errors
+	///
+	/// Returns the first error of the writer. Glyphs that fail to render are
+	/// skipped with a warning and counted in [`Self::failed_glyphs`], unless
+	/// [`Self::strict`] is set.
+	///
+	/// With [`Self::skip_existing`], blocks already in the output are neither
+	/// rendered nor counted as progress.
+	#[allow(dead_code)] // Public API; the CLI reports progress with `render_glyphs_with_progress`.
+	pub fn render_glyphs(
+		&'a self,
+		writer: &mut Writer,
+		renderer: &Renderer,
+	) -> Result<(), GlyphError> {
+		self.render_glyphs_with_progress(writer, renderer, &NoProgress)
+	}
+
+	/// Like [`Self::render_glyphs`], but reports the number of rendered glyphs to
+	/// `progress`, e.g. a progress bar from [`get_progress_bar`](crate::utils::get_progress_bar).
+	pub fn render_glyphs_with_progress(
+		&'a self,
+		writer: &mut Writer,
+		renderer: &Renderer,
+		progress: &dyn ProgressSink,
+	) -> Result<(), GlyphError> {
+		Ok(self.render_glyphs_with_queue(writer, renderer, progress, WRITE_QUEUE_SIZE)?)
+	}
+
+	/// Implements [`Self::render_glyphs_with_progress`] with a configurable channel capacity.
+	fn render_glyphs_with_queue(
+		&'a self,
+		writer: &mut Writer,
+		renderer: &Renderer,
+		progress: &dyn ProgressSink,
+		queue_size: usize,
+	) -> Result<()> {
 		struct Todo<'block> {
+			/// Fontstack name stored in the block, e.g. "Fira Sans Regular".
 			name: String,
+			path: String,
 			block: GlyphBlock<'block>,
+			renderer: Renderer,
 		}
 
 		// Collect all blocks from every font.
 		let mut tasks = Vec::new();
-		for (name, font) in &self.fonts {
-			writer.write_directory(&format!("{name}/"))?;
-			for block in font.get_blocks() {
+		let mut directories = HashSet::new();
+		for (path, id, font) in self.font_paths()? {
+			// Clients request glyphs by the human-readable fontstack name, so the
+			// blocks carry that name, while the directory uses the id.
+			let name = font.display_name()?;
+
+			// Write every directory level once, parents first.
+			let mut dir = String::new();
+			for segment in path.split('/') {
+				dir = format!("{dir}{segment}/");
+				if directories.insert(dir.clone()) {
+					writer.write_directory(&dir)?;
+				}
+			}
+			let font_renderer = font.settings.apply_to(renderer);
+			let metadata = self.block_metadata(font, &font_renderer)?;
+			for mut block in self.font_blocks(id, font)? {
+				if self.skip_existing && writer.file_exists(&format!("{path}/{}", block.filename())) {
+					continue;
+				}
+				block.metadata = metadata.clone();
 				tasks.push(Todo {
 					name: name.clone(),
+					path: path.clone(),
 					block,
+					renderer: font_renderer.clone(),
 				});
 			}
 		}
 
-		// Progress bar across all glyph blocks.
-		let total_glyphs = tasks.iter().map(|t| t.block.len() as u64).sum();
-		let progress = get_progress_bar(total_glyphs);
-		let writer_mutex = Mutex::new(writer);
+		// Progress across all glyph blocks, counted in glyphs.
+		progress.set_total(tasks.iter().map(|t| t.block.len() as u64).sum());
 
-		let op = |todo: &Todo| -> Result<()> {
-			let file_name = format!("{}/{}", todo.name, todo.block.filename());
-			let data = todo.block.render(todo.name.clone(), renderer)?;
+		// Every task sends its index and its rendered block as `(file name, data,
+		// glyph count)`, or `None` if it was skipped.
+		type Rendered = (usize, Option<(String, Vec<u8>, usize)>);
+		let (sender, receiver) = mpsc::sync_channel::<Rendered>(queue_size);
+		let in_flight = InFlight::new(self.max_memory, queue_size);
 
-			writer_mutex
-				.lock()
-				.map_err(|_| anyhow!("writer mutex poisoned"))?
-				.write_file(&file_name, &data)?;
+		thread::scope(|scope| -> Result<()> {
+			// Single consumer: writes the rendered blocks in the order of the tasks,
+			// holding back those that arrive early.
+			// Returning early drops `receiver`, which makes every pending `send` fail.
+			let in_flight = &in_flight;
+			let validate = self.validate;
+			// Compressing at the best level is slow, so it's done by the render threads.
+			let compress = writer.compresses_blocks();
+			let consumer = scope.spawn(move || -> Result<()> {
+				let mut pending = BTreeMap::new();
+				let mut next = 0;
+				let written = receiver.into_iter().try_for_each(|(index, rendered)| {
+					pending.insert(index, rendered);
+					while let Some(rendered) = pending.remove(&next) {
+						next += 1;
+						if let Some((file_name, data, count)) = rendered {
+							if compress {
+								writer.write_compressed_block(&file_name, &data)
+							} else {
+								writer.write_file(&file_name, &data)
+							}
+							.with_context(|| format!("failed to write {file_name:?}"))?;
+							if validate {
+								if let Some(written) = writer.read_block(&file_name)? {
+									validate_block(&written, &file_name, count)?;
+								}
+							}
+							in_flight.release(data.len() as u64);
+						}
+						in_flight.advance(next);
+					}
+					Ok(())
+				});
+				// Wake render threads waiting for memory, so they see the stop.
+				in_flight.close();
+				written
+			});
 
-			progress.inc(todo.block.len() as u64);
-			Ok(())
-		};
+			let op = |(index, todo): (usize, &Todo)| -> Result<()> {
+				let file_name = format!("{}/{}", todo.path, todo.block.filename());
+				let rendered = if self.strict {
+					todo
+						.block
+						.render_with_count(todo.name.clone(), &todo.renderer)
+						.map(|(data, count)| (data, count, Vec::new()))
+				} else {
+					todo
+						.block
+						.render_skipping_failures(todo.name.clone(), &todo.renderer)
+				};
+				let (data, count, failures) = match rendered {
+					Ok(rendered) => rendered,
+					Err(err) if self.strict => {
+						return Err(
+							anyhow::Error::from(err).context(format!("failed to render {file_name:?}")),
+						)
+					}
+					Err(err) => {
+						eprintln!("Skipping {file_name:?}: {err:#}");
+						self
+							.failed_glyphs
+							.fetch_add(todo.block.len(), Ordering::Relaxed);
+						progress.inc(todo.block.len() as u64);
+						return sender
+							.send((index, None))
+							.map_err(|_| anyhow!("writer thread stopped"));
+					}
+				};
+				for (codepoint, err) in &failures {
+					eprintln!("Skipping glyph U+{codepoint:04X} in {file_name:?}: {err:#}");
+				}
+				self
+					.failed_glyphs
+					.fetch_add(failures.len(), Ordering::Relaxed);
 
-		if self.parallel {
-			tasks.par_iter().try_for_each(op)?;
-		} else {
-			tasks.iter().try_for_each(op)?;
-		}
+				if self.validate {
+					validate_block(&data, &file_name, count)?;
+				}
+				let data = if compress {
+					Writer::compress(&data)?
+				} else {
+					data
+				};
+
+				if !in_flight.acquire(index, data.len() as u64) {
+					bail!("writer thread stopped");
+				}
+				sender
+					.send((index, Some((file_name, data, count))))
+					.map_err(|_| anyhow!("writer thread stopped"))?;
+
+				progress.inc(todo.block.len() as u64);
+				Ok(())
+			};
+
+			let rendered = if self.parallel {
+				tasks.par_iter().enumerate().try_for_each(op)
+			} else {
+				tasks.iter().enumerate().try_for_each(op)
+			};
+
+			// Close the channel so the consumer finishes once the queue is drained.
+			drop(sender);
+			let written = consumer
+				.join()
+				.map_err(|_| anyhow!("writer thread panicked"))?;
 
+			// A writer error is the root cause of any "writer thread stopped" render error.
+			written?;
+			rendered
+		})?;
+
+		*self
+			.peak_in_flight
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner) = in_flight.peak();
 		progress.finish();
 		Ok(())
 	}
 
-	/// Writes an index of all font IDs to `index.json`.
-	pub fn write_index_json(&self, writer: &mut Writer) -> Result<()> {
-		let content = build_index_json(self.fonts.keys())?;
-		writer.write_file("index.json", &content)
+	/// Returns the number of glyphs that failed to render and were skipped by
+	/// all calls of [`Self::render_glyphs`] so far, see [`Self::strict`].
+	pub fn failed_glyphs(&self) -> usize {
+		self.failed_glyphs.load(Ordering::Relaxed)
+	}
+
+	/// Returns the approximate memory use: the size of the loaded font files,
+	/// and the peak of the rendered blocks waiting to be written during the
+	/// last [`Self::render_glyphs`], which [`Self::max_memory`] limits.
+	pub fn memory_stats(&self) -> MemoryStats {
+		let (peak_block_bytes, peak_blocks) = *self
+			.peak_in_flight
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		MemoryStats {
+			font_bytes: self
+				.fonts
+				.values()
+				.flat_map(|font| &font.files)
+				.map(|file| file.face.raw_face().data.len() as u64)
+				.sum(),
+			peak_block_bytes,
+			peak_blocks,
+		}
+	}
+
+	/// Lists the glyph block files [`Self::render_glyphs`] would write, without
+	/// rendering anything, as `(filename, glyph count)` sorted by filename,
+	/// like `("fira_sans_regular/0-255.pbf", 192)`.
+	#[allow(dead_code)] // Public API; the CLI uses `planned_files_with_size`.
+	pub fn planned_files(&'a self) -> Result<Vec<(String, usize)>, GlyphError> {
+		let mut files = Vec::new();
+		for (path, id, font) in self.font_paths()? {
+			for block in self.font_blocks(id, font)? {
+				files.push((format!("{path}/{}", block.filename()), block.len()));
+			}
+		}
+		files.sort_unstable();
+		Ok(files)
+	}
+
+	/// Like [`Self::planned_files`], but also estimates the size in bytes of
+	/// every file rendered with `renderer`, see [`GlyphBlock::estimate_size`],
+	/// as `(filename, glyph count, bytes)`.
+	pub fn planned_files_with_size(
+		&'a self,
+		renderer: &Renderer,
+	) -> Result<Vec<(String, usize, u64)>, GlyphError> {
+		let mut files = Vec::new();
+		for (path, id, font) in self.font_paths()? {
+			let name = font.display_name()?;
+			let renderer = font.settings.apply_to(renderer);
+			let metadata = self.block_metadata(font, &renderer)?;
+			for mut block in self.font_blocks(id, font)? {
+				block.metadata = metadata.clone();
+				let size = block.estimate_size(&name, &renderer);
+				files.push((format!("{path}/{}", block.filename()), block.len(), size));
+			}
+		}
+		files.sort_unstable();
+		Ok(files)
+	}
+
+	/// Returns the codepoints [`Self::render_glyphs`] would render a glyph for in
+	/// the font `font_id`, sorted: those of its files and [`Self::fallbacks`]
+	/// that pass its [`FontSettings`] filters, like the charset and
+	/// [`FontSettings::skip_non_rendering`], and the block filters
+	/// [`Self::include_blocks`] and [`Self::exclude_blocks`].
+	///
+	/// Codepoints that only get the `.notdef` glyph are not included.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `font_id` or one of its fallbacks isn't managed.
+	#[allow(dead_code)] // Public API; for checking filters before rendering.
+	pub fn effective_codepoints(&'a self, font_id: &str) -> Result<Vec<u32>, GlyphError> {
+		let (id, font) = self
+			.fonts
+			.get_key_value(font_id)
+			.ok_or_else(|| anyhow!("font \"{font_id}\" not found"))?;
+		Ok(self
+			.font_blocks(id, font)?
+			.iter()
+			.flat_map(|block| {
+				block
+					.glyphs
+					.keys()
+					.filter(|index| !block.notdef.contains(index))
+					.map(|index| block.start_index + index)
+			})
+			.filter(|&codepoint| char::from_u32(codepoint).is_some())
+			.collect())
+	}
+
+	/// Renders the single glyph block of the font `font_id` that starts at
+	/// `start_index`, like [`Self::render_glyphs`] would write it, e.g. to serve
+	/// glyphs on demand, see [`GlyphCache`](super::GlyphCache).
+	///
+	/// A block without glyphs, or one excluded by [`Self::include_blocks`] and
+	/// [`Self::exclude_blocks`], is rendered empty, so every range a client
+	/// requests is valid.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the font isn't managed, `start_index` isn't a valid
+	/// block start (see [`Self::check_block_start`]), or rendering fails.
+	#[allow(dead_code)] // Public API; the CLI renders all blocks with `render_glyphs`.
+	pub fn render_block(
+		&'a self,
+		font_id: &str,
+		start_index: u32,
+		renderer: &Renderer,
+	) -> Result<Vec<u8>, GlyphError> {
+		let (id, font) = self
+			.fonts
+			.get_key_value(font_id)
+			.ok_or_else(|| anyhow!("font \"{font_id}\" not found"))?;
+		self.check_block_start(start_index)?;
+		let mut block = self
+			.font_blocks(id, font)?
+			.into_iter()
+			.find(|block| block.start_index == start_index)
+			.unwrap_or_else(|| GlyphBlock::with_size(start_index, self.block_size));
+		let name = font.display_name()?;
+		let renderer = font.settings.apply_to(renderer);
+		block.metadata = self.block_metadata(font, &renderer)?;
+		block.render(name, &renderer)
+	}
+
+	/// Checks that [`Self::render_block`] can render the block starting at
+	/// `start_index`, so there are at most `0x110000 / block_size` valid
+	/// starts per font.
+	///
+	/// # Errors
+	///
+	/// Returns an error if blocks are grouped by script (see
+	/// [`Self::group_by_script`]), or `start_index` isn't a multiple of
+	/// [`Self::block_size`] up to U+10FFFF.
+	#[allow(dead_code)] // Public API; the CLI renders all blocks with `render_glyphs`.
+	pub fn check_block_start(&self, start_index: u32) -> Result<(), GlyphError> {
+		if self.group_by_script {
+			return Err(GlyphError::Other(anyhow!(
+				"blocks grouped by script can't be rendered by start index"
+			)));
+		}
+		if start_index % self.block_size != 0 {
+			return Err(GlyphError::Other(anyhow!(
+				"block start {start_index} is not a multiple of the block size {}",
+				self.block_size
+			)));
+		}
+		if start_index > 0x10FFFF {
+			return Err(GlyphError::Other(anyhow!(
+				"block start {start_index} is beyond the last codepoint U+10FFFF"
+			)));
+		}
+		Ok(())
+	}
+
+	/// Returns the metrics stored in every glyph block of `font` if
+	/// [`Self::concat_metadata`] is set, taken from its first file and the size
+	/// of `renderer`, which has the font's settings applied.
+	fn block_metadata(
+		&self,
+		font: &FontWrapper,
+		renderer: &Renderer,
+	) -> Result<Option<PbfFontMetadata>> {
+		if !self.concat_metadata {
+			return Ok(None);
+		}
+		let face = &font
+			.files
+			.first()
+			.ok_or_else(|| anyhow!("FontWrapper has no files"))?
+			.face;
+		Ok(Some(PbfFontMetadata {
+			family: font.get_metadata()?.family.clone(),
+			size: renderer.options().size,
+			units_per_em: face.units_per_em() as u32,
+			ascender: face.ascender() as i32,
+		}))
+	}
+
+	/// Returns the blocks to render for the font `id`, sorted by start index and
+	/// including empty blocks if [`Self::emit_empty_ranges`] is set. Blocks are
+	/// filtered by [`Self::include_blocks`] and [`Self::exclude_blocks`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if one of the [`Self::fallbacks`] of `id` isn't managed.
+	fn font_blocks(&'a self, id: &str, font: &'a FontWrapper<'a>) -> Result<Vec<GlyphBlock<'a>>> {
+		let fallbacks = self
+			.fallbacks
+			.get(id)
+			.into_iter()
+			.flatten()
+			.map(|fallback| match self.fonts.get_key_value(fallback) {
+				Some((fallback, font)) => Ok((fallback.as_str(), font)),
+				None => Err(anyhow!(
+					"fallback font \"{fallback}\" of \"{id}\" not found"
+				)),
+			})
+			.collect::<Result<Vec<_>>>()?;
+		if self.group_by_script {
+			return Ok(font.get_script_blocks_with_fallbacks(&fallbacks));
+		}
+		let mut blocks = font.get_blocks_with_fallbacks(self.block_size, &fallbacks);
+		if self.emit_empty_ranges {
+			let starts = blocks.iter().map(|b| b.start_index).collect::<HashSet<_>>();
+			blocks.extend(
+				(0..0x10000)
+					.step_by(self.block_size as usize)
+					.filter(|start| !starts.contains(start))
+					.map(|start| GlyphBlock::with_size(start, self.block_size)),
+			);
+			blocks.sort_unstable_by_key(|block| block.start_index);
+		}
+		blocks.retain(|block| {
+			(self.include_blocks.is_empty() || self.include_blocks.contains(&block.start_index))
+				&& !self.exclude_blocks.contains(&block.start_index)
+		});
+		Ok(blocks)
+	}
+
+	/// Writes an index of all font directories (see [`Self::layout`]) to `index.json`.
+	pub fn write_index_json(&self, writer: &mut Writer) -> Result<(), GlyphError> {
+		let fonts = self.font_paths()?;
+		let content = build_index_json(fonts.iter().map(|(path, _, _)| path), self.pretty_json)?;
+		self.write_index_file(writer, "index.json", content, merge_index_json)
+	}
+
+	/// Writes a map from every fontstack name, like `"Fira Sans Regular"`, to its
+	/// font directory (see [`Self::layout`]) to `fontstacks.json`.
+	pub fn write_fontstacks_json(&self, writer: &mut Writer) -> Result<(), GlyphError> {
+		let fonts = self.font_paths()?;
+		let content = build_fontstacks_json(
+			fonts.iter().map(|(path, _, font)| (path, *font)),
+			self.pretty_json,
+		)?;
+		self.write_index_file(writer, "fontstacks.json", content, merge_fontstacks_json)
 	}
 
 	/// Writes a list of font families and their styles/weights to `font_families.json`.
-	pub fn write_families_json(&self, writer: &mut Writer) -> Result<()> {
-		let content = build_font_families_json(self.fonts.iter())?;
-		writer.write_file("font_families.json", &content)
+	///
+	/// Each face's `id` is the font's directory (see [`Self::layout`]).
+	pub fn write_families_json(&self, writer: &mut Writer) -> Result<(), GlyphError> {
+		let fonts = self.font_paths()?;
+		let options = FontFamiliesOptions {
+			copyright: self.include_copyright,
+			versioned: self.versioned_families,
+			pretty: self.pretty_json,
+		};
+		let content = build_font_families_json_with_options(
+			fonts.iter().map(|(path, _, font)| (path, *font)),
+			&options,
+		)?;
+		self.write_index_file(
+			writer,
+			"font_families.json",
+			content,
+			merge_font_families_json,
+		)
+	}
+
+	/// Writes the filenames of the non-empty glyph blocks of every font, like
+	/// `["0-255.pbf", "256-511.pbf"]`, to `ranges.json` in the font's directory,
+	/// e.g. for cache warming or CDN prefetch manifests.
+	///
+	/// These are the blocks [`Self::render_glyphs`] writes, sorted by start
+	/// index, without the empty ones of [`Self::emit_empty_ranges`].
+	pub fn write_ranges_json(&'a self, writer: &mut Writer) -> Result<(), GlyphError> {
+		for (path, id, font) in self.font_paths()? {
+			let filenames = self
+				.font_blocks(id, font)?
+				.iter()
+				.filter(|block| !block.is_empty())
+				.map(|block| block.filename())
+				.collect::<Vec<_>>();
+			writer.write_file(
+				&format!("{path}/ranges.json"),
+				&build_ranges_json(&filenames, self.pretty_json)?,
+			)?;
+		}
+		Ok(())
+	}
+
+	/// Writes `content` to `filename`. With [`Self::merge_existing`], an existing
+	/// `filename` is read back and combined with `content` by `merge` first.
+	fn write_index_file(
+		&self,
+		writer: &mut Writer,
+		filename: &str,
+		content: Vec<u8>,
+		merge: MergeFn,
+	) -> Result<(), GlyphError> {
+		let existing = if self.merge_existing {
+			writer.read_file(filename)?
+		} else {
+			None
+		};
+		let content = match existing {
+			Some(existing) => merge(&existing, &content, self.pretty_json)
+				.with_context(|| format!("merging the existing \"{filename}\""))?,
+			None => content,
+		};
+		writer.write_file(filename, &content)
+	}
+
+	/// Returns the directory path from [`Self::layout`], the id and the font
+	/// for every managed font, sorted by id, so everything derived from the
+	/// fonts is written in the same order on every run.
+	///
+	/// # Errors
+	///
+	/// Returns an error if two fonts would be written to the same directory.
+	fn font_paths(&self) -> Result<Vec<(String, &String, &FontWrapper<'a>)>> {
+		let mut ids_by_path = HashMap::<String, &String>::new();
+		let mut fonts = Vec::new();
+		for (id, font) in &self.fonts {
+			let path = self.layout.path(id, font)?;
+			if let Some(other) = ids_by_path.insert(path.clone(), id) {
+				bail!("fonts \"{other}\" and \"{id}\" would both be written to \"{path}/\"");
+			}
+			fonts.push((path, id, font));
+		}
+		fonts.sort_unstable_by(|a, b| a.1.cmp(b.1));
+		Ok(fonts)
 	}
 }
 
-/// Normalizes a font name into a lowercase, underscore-delimited string.
+/// Checks whether `pattern` selects the font stored under `id`, either by
+/// normalized id or by (case-insensitive) family name.
+fn font_matches(id: &str, font: &FontWrapper, pattern: &str) -> bool {
+	name_to_id(pattern) == id
+		|| font
+			.get_metadata()
+			.is_ok_and(|meta| meta.family.eq_ignore_ascii_case(pattern.trim()))
+}
+
+/// Normalizes a font name into a lowercase, underscore-delimited ASCII id
+/// that is safe to use in URLs and file names.
+///
+/// - Diacritics are stripped (`"é"` → `"e"`) and ligature-like letters are
+///   transliterated (`"ß"` → `"ss"`, `"Æ"` → `"ae"`).
+/// - Runs of whitespace, `-` and `_` become a single `_`; other punctuation is dropped.
+/// - Remaining non-ASCII letters or digits (e.g. CJK) are encoded as `u{hex}`.
+///
+/// The mapping is lossy: distinct names can share an id (`"Café"` and
+/// `"Cafe"`, `"A.B"` and `"AB"`, or `"u6e90"` and `"源"`), and fonts whose
+/// names do are merged into one.
 fn name_to_id(name: &str) -> String {
-	static RE: OnceLock<Regex> = OnceLock::new();
-	let re = RE.get_or_init(|| Regex::new(r"[-_\s]+").expect("valid regex"));
-	let lower = name.to_lowercase();
-	let collapsed = re.replace_all(&lower, " ").trim().to_string();
-	collapsed.replace(' ', "_")
+	let mut id = String::new();
+	let mut separator = false;
+	for c in name.nfkd().filter(|c| !is_combining_mark(*c)) {
+		if c.is_whitespace() || c == '-' || c == '_' {
+			separator = true;
+			continue;
+		}
+		let ascii = match c {
+			'ß' => "ss".to_string(),
+			'Æ' | 'æ' => "ae".to_string(),
+			'Œ' | 'œ' => "oe".to_string(),
+			'Þ' | 'þ' => "th".to_string(),
+			'Ø' | 'ø' => "o".to_string(),
+			'Đ' | 'đ' => "d".to_string(),
+			'Ł' | 'ł' => "l".to_string(),
+			'ı' => "i".to_string(),
+			c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase().to_string(),
+			c if c.is_alphanumeric() => format!("u{:x}", c as u32),
+			_ => continue,
+		};
+		if separator && !id.is_empty() {
+			id.push('_');
+		}
+		separator = false;
+		id.push_str(&ascii);
+	}
+	id
+}
+
+/// Checks that `data` of the glyph block `file_name` decodes and holds `count`
+/// glyphs, see [`FontManager::validate`].
+fn validate_block(data: &[u8], file_name: &str, count: usize) -> Result<()> {
+	let glyphs = PbfGlyphs::from_slice(data)
+		.with_context(|| format!("validation failed: {file_name:?} doesn't decode"))?
+		.into_glyphs();
+	ensure!(
+		glyphs.len() == count,
+		"validation failed: {file_name:?} has {} glyphs, but {count} were rendered",
+		glyphs.len()
+	);
+	Ok(())
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::writer::GzipNaming;
+	use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 	fn get_test_paths() -> Vec<PathBuf> {
 		let d = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata");
@@ -239,6 +1145,756 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_render_glyphs_to_tar_in_parallel() -> Result<()> {
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths())?;
+
+		let mut output = Vec::<u8>::new();
+		let mut writer = Writer::new_tar(&mut output);
+		manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+		writer.finish()?;
+		drop(writer);
+
+		// Every entry decodes, and each font directory precedes its block files.
+		let mut archive = tar::Archive::new(output.as_slice());
+		let mut seen_dirs = Vec::<String>::new();
+		let mut files = Vec::<String>::new();
+		for entry in archive.entries()? {
+			let entry = entry?;
+			let path = entry.path()?.to_str().unwrap().to_string();
+			if path.ends_with('/') {
+				seen_dirs.push(path);
+			} else {
+				let dir = format!("{}/", path.split('/').next().unwrap());
+				assert!(seen_dirs.contains(&dir), "{path} written before {dir}");
+				files.push(format!("{path} ({})", entry.size()));
+			}
+		}
+		files.sort_unstable();
+
+		assert_eq!(files.len(), 57);
+		assert_eq!(files[0], "fira_sans_regular/0-255.pbf (80022)");
+		assert_eq!(files[56], "noto_sans_regular/9472-9727.pbf (394)");
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_to_tar_is_reproducible() -> Result<()> {
+		let render = || -> Result<Vec<u8>> {
+			let mut manager = FontManager::new(false);
+			manager.add_paths(&get_test_paths()[..2])?;
+
+			let mut output = Vec::<u8>::new();
+			let mut writer = Writer::new_tar_with_mtime(&mut output, 1_700_000_000);
+			manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+			manager.write_index_json(&mut writer)?;
+			writer.finish()?;
+			drop(writer);
+			Ok(output)
+		};
+		let entries = |output: &[u8]| -> Result<Vec<String>> {
+			let mut archive = tar::Archive::new(output);
+			let mut paths = Vec::new();
+			for entry in archive.entries()? {
+				let entry = entry?;
+				assert_eq!(entry.header().mtime()?, 1_700_000_000);
+				paths.push(entry.path()?.to_str().unwrap().to_string());
+			}
+			Ok(paths)
+		};
+
+		let output = render()?;
+		let paths = entries(&output)?;
+		for _ in 0..3 {
+			let again = render()?;
+			assert_eq!(entries(&again)?, paths);
+			assert!(again == output, "the archives differ");
+		}
+
+		// The font directories first, then the blocks of every font in order of
+		// the font id, each in ascending order.
+		assert_eq!(
+			paths[..3],
+			[
+				"fira_sans_regular/",
+				"noto_sans_regular/",
+				"fira_sans_regular/0-255.pbf"
+			]
+		);
+		let noto = paths
+			.iter()
+			.position(|p| p == "noto_sans_regular/0-255.pbf")
+			.unwrap();
+		assert_eq!(paths[noto - 1], "fira_sans_regular/65024-65279.pbf");
+		assert!(paths[noto..paths.len() - 1]
+			.iter()
+			.all(|p| p.starts_with("noto_sans_regular/")));
+		assert_eq!(paths.last().unwrap(), "index.json");
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_to_tar_with_single_slot_queue() -> Result<()> {
+		let render = |queue_size: usize| -> Result<Vec<String>> {
+			let mut manager = FontManager::new(true);
+			manager.add_font_with_name("Noto Sans", &get_test_paths()[1..])?;
+
+			let mut output = Vec::<u8>::new();
+			let mut writer = Writer::new_tar(&mut output);
+			manager.render_glyphs_with_queue(
+				&mut writer,
+				&Renderer::new_dummy(),
+				&NoProgress,
+				queue_size,
+			)?;
+			writer.finish()?;
+			drop(writer);
+
+			let mut archive = tar::Archive::new(output.as_slice());
+			let mut entries = Vec::new();
+			for entry in archive.entries()? {
+				let entry = entry?;
+				entries.push(format!("{} ({})", entry.path()?.display(), entry.size()));
+			}
+			entries.sort_unstable();
+			Ok(entries)
+		};
+
+		// Back pressure from a single slot must not lose or corrupt any block.
+		let entries = render(1)?;
+		assert_eq!(entries, render(WRITE_QUEUE_SIZE)?);
+		assert_eq!(entries.len(), 38);
+		assert_eq!(entries[0], "noto_sans/ (0)");
+		Ok(())
+	}
+
+	#[test]
+	fn test_fallbacks() -> Result<()> {
+		let paths = get_test_paths();
+		let mut manager = FontManager::new(false);
+		manager.add_font_with_name("Fira Sans", &paths[0..1])?;
+		manager.add_font_with_name("Noto Sans Arabic", &paths[2..3])?;
+		manager.set_fallbacks("Fira Sans", &["Noto Sans Arabic"]);
+
+		// Fira Sans has no Arabic letters, so alef comes from the fallback.
+		let alef = 0x0627;
+		let fira = &manager.fonts["fira_sans"];
+		assert!(!fira.codepoints().contains(&alef));
+		let blocks = manager.font_blocks("fira_sans", fira)?;
+		let arabic = blocks.iter().find(|b| b.start_index == 1536).unwrap();
+		assert_eq!(
+			arabic.fallbacks.get(&(alef - 1536)),
+			Some(&"noto_sans_arabic")
+		);
+		let file = arabic.glyphs[&(alef - 1536)];
+		assert!(std::ptr::eq(
+			file,
+			&manager.fonts["noto_sans_arabic"].files[0]
+		));
+
+		// Glyphs the primary font has are never replaced.
+		let latin = blocks.iter().find(|b| b.start_index == 0).unwrap();
+		assert!(!latin.fallbacks.contains_key(&65));
+		assert!(std::ptr::eq(latin.glyphs[&65], &fira.files[0]));
+
+		let renderer = Renderer::new_precise();
+		let data = arabic.render(String::from("Fira Sans Regular"), &renderer)?;
+		let glyphs = crate::protobuf::PbfGlyphs::from_slice(&data)?.into_glyphs();
+		let glyph = glyphs.into_iter().find(|g| g.id == alef).unwrap();
+		assert_eq!(Some(glyph), renderer.render_glyph(&file.face, alef));
+
+		// The fallback is still rendered on its own.
+		let files = manager.planned_files()?;
+		assert!(files.iter().any(|(f, _)| f == "fira_sans/1536-1791.pbf"));
+		assert!(files
+			.iter()
+			.any(|(f, _)| f == "noto_sans_arabic/1536-1791.pbf"));
+
+		manager.set_fallbacks("Fira Sans", &["Missing"]);
+		let err = manager.planned_files().unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"fallback font \"missing\" of \"fira_sans\" not found"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_max_memory() -> Result<()> {
+		let render = |max_memory: Option<u64>| -> Result<(Vec<String>, MemoryStats)> {
+			let mut manager = FontManager::new(true);
+			manager.add_font_with_name("Noto Sans", &get_test_paths()[1..])?;
+			manager.max_memory = max_memory;
+
+			let mut writer = Writer::new_dummy();
+			manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+			let mut files = writer.get_inner().unwrap().to_vec();
+			files.sort_unstable();
+			Ok((files, manager.memory_stats()))
+		};
+
+		let (files, unlimited) = render(None)?;
+		let (capped_files, capped) = render(Some(1))?;
+		assert_eq!(capped_files, files);
+
+		// Every block exceeds the cap, so only one is in flight at a time.
+		assert_eq!(capped.peak_blocks, 1);
+		assert!(capped.peak_block_bytes <= unlimited.peak_block_bytes);
+		assert!(unlimited.peak_blocks >= 1);
+		let file_bytes = get_test_paths()[1..]
+			.iter()
+			.map(|path| std::fs::metadata(path).map(|m| m.len()))
+			.sum::<std::io::Result<u64>>()?;
+		assert_eq!(capped.font_bytes, file_bytes);
+		assert_eq!(capped.font_bytes, unlimited.font_bytes);
+		Ok(())
+	}
+
+	#[test]
+	fn test_add_paths_in_parallel_keeps_order() -> Result<()> {
+		let paths = get_test_paths();
+		let names = |manager: &FontManager| {
+			let mut fonts = manager
+				.fonts
+				.iter()
+				.map(|(id, font)| {
+					let names = font.files.iter().map(|f| f.metadata.name.clone());
+					(id.clone(), names.collect::<Vec<_>>())
+				})
+				.collect::<Vec<_>>();
+			fonts.sort_unstable();
+			fonts
+		};
+
+		let mut serial = FontManager::new(false);
+		serial.add_paths(&paths)?;
+		let mut parallel = FontManager::new(true);
+		parallel.add_paths(&paths)?;
+		assert_eq!(names(&parallel), names(&serial));
+		assert_eq!(
+			names(&parallel)[1].1,
+			["Noto Sans", "Noto Sans Arabic", "Noto Sans Tamil"]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_codepoint_overlap() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[0..2])?;
+
+		let overlap = manager
+			.codepoint_overlap("fira_sans_regular", "noto_sans_regular")
+			.unwrap();
+		// Both cover Latin, Greek and Cyrillic, but neither covers the other completely.
+		assert!((1000..1686).contains(&overlap), "{overlap}");
+		assert_eq!(manager.total_codepoints(), 1686 + 3094 - overlap);
+		assert_eq!(
+			manager.codepoint_overlaps(),
+			[(
+				String::from("fira_sans_regular"),
+				String::from("noto_sans_regular"),
+				overlap
+			)]
+		);
+		assert_eq!(
+			manager.codepoint_overlap("fira_sans_regular", "unknown"),
+			None
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_progress() -> Result<()> {
+		#[derive(Default)]
+		struct CountingSink {
+			total: AtomicU64,
+			count: AtomicU64,
+			finished: AtomicBool,
+		}
+		impl ProgressSink for CountingSink {
+			fn set_total(&self, total: u64) {
+				self.total.store(total, Ordering::SeqCst);
+			}
+			fn inc(&self, n: u64) {
+				self.count.fetch_add(n, Ordering::SeqCst);
+			}
+			fn finish(&self) {
+				self.finished.store(true, Ordering::SeqCst);
+			}
+		}
+
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths())?;
+		let total_glyphs = manager
+			.fonts
+			.values()
+			.flat_map(|font| font.get_blocks())
+			.map(|block| block.len() as u64)
+			.sum::<u64>();
+
+		let sink = CountingSink::default();
+		let mut writer = Writer::new_dummy();
+		manager.render_glyphs_with_progress(&mut writer, &Renderer::new_dummy(), &sink)?;
+
+		assert_eq!(sink.total.load(Ordering::SeqCst), total_glyphs);
+		assert_eq!(sink.count.load(Ordering::SeqCst), total_glyphs);
+		assert!(sink.finished.load(Ordering::SeqCst));
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_failing_glyph() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths()[0..1])?;
+		let renderer = Renderer::new_failing(65);
+
+		// Lenient: "A" is left out of its block and counted, the rest is written.
+		let mut writer = Writer::new_file(temp.path().to_path_buf());
+		manager.render_glyphs(&mut writer, &renderer)?;
+		assert_eq!(manager.failed_glyphs(), 1);
+		let data = std::fs::read(temp.path().join("fira_sans_regular/0-255.pbf"))?;
+		let ids = PbfGlyphs::from_slice(&data)?
+			.into_glyphs()
+			.iter()
+			.map(|glyph| glyph.id)
+			.collect::<Vec<_>>();
+		assert!(!ids.contains(&65));
+		assert!(ids.contains(&66));
+
+		// Strict: the failure aborts the render.
+		manager.strict = true;
+		let err = manager
+			.render_glyphs(&mut Writer::new_dummy(), &renderer)
+			.unwrap_err();
+		assert_eq!(format!("{err:#}"), "failed to render glyph 65");
+		assert_eq!(manager.failed_glyphs(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_failing_writer() -> Result<()> {
+		/// Accepts the first `budget` bytes, then fails every write.
+		struct FailingWrite {
+			budget: usize,
+		}
+		impl std::io::Write for FailingWrite {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				if self.budget < buf.len() {
+					return Err(std::io::Error::other("disk full"));
+				}
+				self.budget -= buf.len();
+				Ok(buf.len())
+			}
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths())?;
+		manager.strict = true;
+
+		// Enough for the directory headers, but not for the first glyph block.
+		let mut output = FailingWrite { budget: 4096 };
+		let mut writer = Writer::new_tar(&mut output);
+		let err = manager
+			.render_glyphs_with_queue(&mut writer, &Renderer::new_dummy(), &NoProgress, 1)
+			.unwrap_err();
+		assert!(format!("{err:#}").contains("disk full"), "{err:#}");
+		assert!(err.to_string().starts_with("failed to write \""), "{err}");
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_skip_existing() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		std::fs::create_dir(temp.path().join("fira_sans_regular"))?;
+		std::fs::write(temp.path().join("fira_sans_regular/0-255.pbf"), b"old")?;
+
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[0..1])?;
+		manager.include_blocks = vec![0, 256];
+		manager.skip_existing = true;
+
+		let mut writer = Writer::new_file(temp.path().to_path_buf());
+		manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+		writer.finish()?;
+
+		let dir = temp.path().join("fira_sans_regular");
+		assert_eq!(std::fs::read(dir.join("0-255.pbf"))?, b"old");
+		assert_eq!(std::fs::read(dir.join("256-511.pbf"))?.len(), 130750);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_concat_metadata() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[0..1])?;
+		manager.include_blocks = vec![0];
+
+		let render = |manager: &FontManager| -> Result<PbfGlyphs> {
+			let mut writer = Writer::new_file(temp.path().to_path_buf());
+			manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+			writer.finish()?;
+			PbfGlyphs::from_slice(&std::fs::read(
+				temp.path().join("fira_sans_regular/0-255.pbf"),
+			)?)
+		};
+		assert_eq!(render(&manager)?.metadata(), None);
+
+		manager.concat_metadata = true;
+		let glyphs = render(&manager)?;
+		let metadata = PbfFontMetadata {
+			family: String::from("Fira Sans"),
+			size: 24,
+			units_per_em: 1000,
+			ascender: 935,
+		};
+		assert_eq!(glyphs.metadata(), Some(&metadata));
+		assert!(!glyphs.into_glyphs().is_empty());
+
+		let block = manager.render_block("fira_sans_regular", 0, &Renderer::new_dummy())?;
+		assert_eq!(PbfGlyphs::from_slice(&block)?.metadata(), Some(&metadata));
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_in_parallel_is_deterministic() -> Result<()> {
+		let render = |parallel: bool| -> Result<Vec<u8>> {
+			let mut manager = FontManager::new(parallel);
+			manager.add_paths(&get_test_paths())?;
+			let mut output = Vec::new();
+			let mut writer = Writer::new_tar_with_mtime(&mut output, 0);
+			manager.render_glyphs_with_queue(&mut writer, &Renderer::new_dummy(), &NoProgress, 2)?;
+			writer.finish()?;
+			drop(writer);
+			Ok(output)
+		};
+
+		let expected = render(false)?;
+		assert_eq!(render(true)?, expected);
+		assert_eq!(render(true)?, expected);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_display_name() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let mut manager = FontManager::new(true);
+		manager.add_font_with_name("My Font", &get_test_paths()[0..1])?;
+
+		let block = manager.render_block("my_font", 0, &Renderer::new_dummy())?;
+		assert_eq!(PbfGlyphs::from_slice(&block)?.name(), Some("My Font"));
+
+		let mut writer = Writer::new_file(temp.path().to_path_buf());
+		manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+		let data = std::fs::read(temp.path().join("my_font/0-255.pbf"))?;
+		assert_eq!(PbfGlyphs::from_slice(&data)?.name(), Some("My Font"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_validate() -> Result<()> {
+		let temp = tempfile::tempdir()?;
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths())?;
+		manager.validate = true;
+
+		// Blocks are read back from disk and decompressed.
+		let mut writer =
+			Writer::new_file(temp.path().to_path_buf()).with_gzip_blocks(GzipNaming::PbfGz);
+		manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+		writer.finish()?;
+		assert!(temp.path().join("fira_sans_regular/0-255.pbf.gz").is_file());
+
+		// Outputs that can't be read back check the encoded blocks.
+		let mut output = Vec::new();
+		manager.render_glyphs(&mut Writer::new_tar(&mut output), &Renderer::new_dummy())?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_validate_detects_corruption() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[0..1])?;
+		manager.include_blocks = vec![0];
+
+		// Without validation, the corruption goes unnoticed.
+		manager.render_glyphs(&mut Writer::new_corrupting(), &Renderer::new_dummy())?;
+
+		manager.validate = true;
+		let err = manager
+			.render_glyphs(&mut Writer::new_corrupting(), &Renderer::new_dummy())
+			.unwrap_err();
+		assert!(
+			err.to_string()
+				.contains("validation failed: \"fira_sans_regular/0-255.pbf\""),
+			"{err:#}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_charset() -> Result<()> {
+		let mut manager = FontManager::new(true);
+		manager.add_paths(&get_test_paths()[0..2])?;
+		let charset = HashSet::from([0x41, 0x42, 0xE9, 0x3A9, 0x10FFFF]);
+		manager
+			.fonts
+			.get_mut("fira_sans_regular")
+			.unwrap()
+			.settings
+			.charset = Some(charset);
+		let noto_codepoints = manager.fonts["noto_sans_regular"].codepoints().len();
+
+		let renderer = Renderer::new_recording();
+		manager.render_glyphs(&mut Writer::new_dummy(), &renderer)?;
+
+		let calls = renderer.recorder().unwrap().calls();
+		let fira = calls
+			.iter()
+			.filter(|(font, _)| font == "Fira Sans Regular")
+			.map(|(_, codepoint)| *codepoint)
+			.collect::<Vec<_>>();
+		// The font doesn't cover U+10FFFF.
+		assert_eq!(fira, [0x41, 0x42, 0xE9, 0x3A9]);
+		assert_eq!(calls.len() - fira.len(), noto_codepoints);
+		Ok(())
+	}
+
+	#[test]
+	fn test_effective_codepoints() -> Result<()> {
+		let paths = get_test_paths();
+		let mut manager = FontManager::new(false);
+		manager.add_font_with_name("Fira Sans", &paths[0..1])?;
+		manager.add_font_with_name("Noto Sans Arabic", &paths[2..3])?;
+		let charset = HashSet::from([0x0D, 0x41, 0xE9, 0x3A9, 0x627, 0xE000, 0x10FFFF]);
+		let fira = manager.fonts.get_mut("fira_sans").unwrap();
+		fira.settings.charset = Some(charset.clone());
+		fira.settings.notdef.insert(0xE000);
+
+		// The intersection of the charset and the font's cmap.
+		let mut expected = fira.files[0]
+			.metadata
+			.codepoints
+			.iter()
+			.copied()
+			.filter(|codepoint| charset.contains(codepoint))
+			.collect::<Vec<_>>();
+		expected.sort_unstable();
+		assert_eq!(expected, [0x0D, 0x41, 0xE9, 0x3A9]);
+		assert_eq!(manager.effective_codepoints("fira_sans")?, expected);
+
+		// Besides the `.notdef` glyph of U+E000, it matches what is rendered.
+		let renderer = Renderer::new_recording();
+		manager.render_glyphs(&mut Writer::new_dummy(), &renderer)?;
+		let mut rendered = renderer
+			.recorder()
+			.unwrap()
+			.calls()
+			.into_iter()
+			.filter(|(font, _)| font == "Fira Sans")
+			.map(|(_, codepoint)| codepoint)
+			.collect::<Vec<_>>();
+		rendered.sort_unstable();
+		assert_eq!(rendered.pop(), Some(0xE000));
+		assert_eq!(rendered, expected);
+
+		// Fallbacks add codepoints, the control character filter removes them.
+		manager.set_fallbacks("Fira Sans", &["Noto Sans Arabic"]);
+		manager.skip_non_rendering();
+		assert_eq!(
+			manager.effective_codepoints("fira_sans")?,
+			[0x41, 0xE9, 0x3A9, 0x627]
+		);
+		manager.exclude_blocks = vec![0];
+		assert_eq!(manager.effective_codepoints("fira_sans")?, [0x3A9, 0x627]);
+
+		assert!(manager.effective_codepoints("unknown").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_limit_codepoints() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[1..2])?;
+		let starts = |manager: &FontManager| {
+			manager.fonts["noto_sans_regular"]
+				.get_blocks()
+				.iter()
+				.map(|block| block.start_index)
+				.collect::<Vec<_>>()
+		};
+		assert!(starts(&manager).iter().any(|&start| start >= 0x10000));
+
+		manager.limit_codepoints(0xFFFF);
+		let capped = starts(&manager);
+		assert!(!capped.is_empty());
+		assert!(capped.iter().all(|&start| start < 0x10000));
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_glyphs_with_family_style_layout() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths()[0..2])?;
+		manager.layout = FontLayout::FamilyStyle;
+
+		let mut writer = Writer::new_dummy();
+		manager.render_glyphs(&mut writer, &Renderer::new_dummy())?;
+		manager.write_index_json(&mut writer)?;
+
+		let entries = writer.get_inner().unwrap();
+		let mut dirs = entries
+			.iter()
+			.filter(|e| e.ends_with('/'))
+			.collect::<Vec<_>>();
+		dirs.sort_unstable();
+		assert_eq!(
+			dirs,
+			[
+				"Fira Sans/",
+				"Fira Sans/Regular/",
+				"Noto Sans/",
+				"Noto Sans/Regular/"
+			]
+		);
+		assert!(entries.contains(&String::from("Fira Sans/Regular/0-255.pbf (80022)")));
+		assert_eq!(
+			entries.last().unwrap(),
+			r#"index.json: ["Fira Sans/Regular","Noto Sans/Regular"]"#
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_font_paths_reject_collisions() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		let fira = &get_test_paths()[0..1];
+		manager.add_font_with_name("Fira A", fira)?;
+		manager.add_font_with_name("Fira B", fira)?;
+		assert!(manager.font_paths().is_ok());
+
+		manager.layout = FontLayout::FamilyStyle;
+		let err = manager.font_paths().unwrap_err().to_string();
+		assert!(
+			err.contains("would both be written to \"Fira Sans/Regular/\""),
+			"{err}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_name_to_id() {
+		assert_eq!(name_to_id("Noto Sans  Regular"), "noto_sans_regular");
+		assert_eq!(name_to_id(" Fira-Sans__Bold "), "fira_sans_bold");
+		assert_eq!(name_to_id("Noto Naskh Arabic UI"), "noto_naskh_arabic_ui");
+	}
+
+	#[test]
+	fn test_name_to_id_strips_diacritics() {
+		assert_eq!(name_to_id("Crimson Prò Médium"), "crimson_pro_medium");
+		assert_eq!(name_to_id("Straße Æther Łódź"), "strasse_aether_lodz");
+	}
+
+	#[test]
+	fn test_name_to_id_drops_punctuation() {
+		assert_eq!(name_to_id("M PLUS 1p"), "m_plus_1p");
+		assert_eq!(name_to_id("Foo's (Sans) Bold!"), "foos_sans_bold");
+		assert_eq!(name_to_id("ＷＩＤＥ 源"), "wide_u6e90");
+
+		// The mapping is lossy.
+		assert_eq!(name_to_id("Café"), name_to_id("Cafe"));
+		assert_eq!(name_to_id("A.B"), name_to_id("AB"));
+		assert_eq!(name_to_id("u6e90"), name_to_id("源"));
+	}
+
+	#[test]
+	fn test_into_single_fonts() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths())?;
+		manager.block_size = 512;
+
+		let managers = manager.into_single_fonts();
+		assert_eq!(managers.len(), 2);
+		for ((id, manager), expected) in managers
+			.iter()
+			.zip(["fira_sans_regular", "noto_sans_regular"])
+		{
+			assert_eq!(id, expected);
+			assert_eq!(manager.fonts.keys().collect::<Vec<_>>(), [expected]);
+			assert_eq!(manager.block_size, 512);
+			assert!(!manager.parallel);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_filter_fonts() -> Result<()> {
+		let load = || -> Result<FontManager> {
+			let mut manager = FontManager::new(false);
+			manager.add_paths(&get_test_paths())?;
+			Ok(manager)
+		};
+		let ids = |manager: &FontManager| {
+			let mut ids = manager.fonts.keys().cloned().collect::<Vec<_>>();
+			ids.sort_unstable();
+			ids
+		};
+
+		let mut manager = load()?;
+		manager.filter_fonts(&[], &[]);
+		assert_eq!(ids(&manager), ["fira_sans_regular", "noto_sans_regular"]);
+
+		let mut manager = load()?;
+		manager.filter_fonts(&["fira_sans_regular".to_string()], &[]);
+		assert_eq!(ids(&manager), ["fira_sans_regular"]);
+
+		let mut manager = load()?;
+		manager.filter_fonts(&["Noto Sans Regular".to_string()], &[]);
+		assert_eq!(ids(&manager), ["noto_sans_regular"]);
+
+		let mut manager = load()?;
+		manager.filter_fonts(&[], &["noto sans".to_string()]);
+		assert_eq!(ids(&manager), ["fira_sans_regular"]);
+
+		let mut manager = load()?;
+		manager.filter_fonts(
+			&["Fira Sans".to_string()],
+			&["fira_sans_regular".to_string()],
+		);
+		assert!(manager.fonts.is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn test_remove_small_fonts() -> Result<()> {
+		let mut manager = FontManager::new(false);
+		manager.add_paths(&get_test_paths())?;
+		// A stub font with only three glyphs.
+		let settings = FontSettings {
+			charset: Some(HashSet::from(['a' as u32, 'b' as u32, 'c' as u32])),
+			..Default::default()
+		};
+		manager.add_font_with_settings("Stub Icons", &get_test_paths()[0..1], settings)?;
+
+		assert_eq!(manager.remove_small_fonts(3), []);
+		assert_eq!(manager.fonts.len(), 3);
+
+		assert_eq!(
+			manager.remove_small_fonts(100),
+			[(String::from("stub_icons"), 3)]
+		);
+		let mut ids = manager.fonts.keys().cloned().collect::<Vec<_>>();
+		ids.sort_unstable();
+		assert_eq!(ids, ["fira_sans_regular", "noto_sans_regular"]);
+		Ok(())
+	}
+
 	#[test]
 	fn test_write_families_json() -> Result<()> {
 		let mut manager = FontManager::new(false);
@@ -254,7 +1910,7 @@ mod tests {
 		assert_eq!(files.len(), 1);
 		assert_eq!(
 			&files[0][0..64],
-			"font_families.json: [{\"name\": \"Fira Sans\",\"faces\": [{\"id\": \"fira"
+			"font_families.json: [{\"name\":\"Fira Sans\",\"faces\":[{\"id\":\"fira_sa"
 		);
 		Ok(())
 	}
@@ -4,23 +4,19 @@
 //! gathering name, family, style, weight, width, and codepoint coverage
 //! information from a [`ttf_parser::Face`].
 
-use anyhow::Result;
+use crate::error::GlyphError;
 use std::{
 	collections::{HashMap, HashSet},
 	fmt::Debug,
 };
 use ttf_parser::{name_id, Face};
 
-use super::parse_font_name;
+use super::{kerning, parse_font_name};
 
 /// Stores extracted font properties such as `family`, `style`, and `weight`,
 /// along with a set of all supported codepoints.
 pub struct FontMetadata {
 	/// The raw font name (may include style and other descriptors).
-	///
-	/// Read by the `commands::recurse` module in the bin target; the lib never
-	/// reads it, hence the `#[allow(dead_code)]`.
-	#[allow(dead_code)]
 	pub name: String,
 	/// The family portion of the font name (e.g. "Noto Sans").
 	pub family: String,
@@ -32,6 +28,15 @@ pub struct FontMetadata {
 	pub weight: u16,
 	/// Width descriptor, often "normal", "condensed", or "expanded".
 	pub width: String,
+	/// Whether the font has pair kerning, either in a `kern` table or as a
+	/// `GPOS` pair adjustment lookup. See [`FontFileEntry::kerning`](super::FontFileEntry::kerning).
+	pub has_kerning: bool,
+	/// Copyright notice from the `name` table (name ID 0), if the font has one.
+	pub copyright: Option<String>,
+	/// License description (name ID 13), e.g. a summary of the SIL Open Font License.
+	pub license: Option<String>,
+	/// URL of the license (name ID 14).
+	pub license_url: Option<String>,
 }
 
 impl FontMetadata {
@@ -39,6 +44,10 @@ impl FontMetadata {
 	///
 	/// For example, a font with `family = "Noto Sans"`, `weight = 400`,
 	/// `width = "normal"`, and `style = "normal"` produces `"Noto Sans Regular"`.
+	///
+	/// Weights that aren't a multiple of 100 (common in variable fonts) keep their
+	/// numeric value, e.g. `"Noto Sans Weight 350"`, so they never collide with
+	/// the standard weights.
 	pub fn generate_name(&self) -> String {
 		let mut name = self.family.clone();
 		if self.width != "normal" {
@@ -46,16 +55,16 @@ impl FontMetadata {
 		}
 
 		let weight = match self.weight {
-			100 => "Thin",
-			200 => "ExtraLight",
-			300 => "Light",
-			400 => "Regular",
-			500 => "Medium",
-			600 => "SemiBold",
-			700 => "Bold",
-			800 => "ExtraBold",
-			900 => "Black",
-			_ => "Unknown",
+			100 => "Thin".to_string(),
+			200 => "ExtraLight".to_string(),
+			300 => "Light".to_string(),
+			400 => "Regular".to_string(),
+			500 => "Medium".to_string(),
+			600 => "SemiBold".to_string(),
+			700 => "Bold".to_string(),
+			800 => "ExtraBold".to_string(),
+			900 => "Black".to_string(),
+			weight => format!("Weight {weight}"),
 		};
 		name = format!("{name} {weight}");
 
@@ -71,22 +80,23 @@ impl Debug for FontMetadata {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(
 			f,
-			"FontMetadata {{ family: {}, style: {}, weight: {}, width: {}, codepoints: {} }}",
+			"FontMetadata {{ family: {}, style: {}, weight: {}, width: {}, codepoints: {}, has_kerning: {} }}",
 			self.family,
 			self.style,
 			self.weight,
 			self.width,
-			self.codepoints.len()
+			self.codepoints.len(),
+			self.has_kerning
 		)
 	}
 }
 
 impl TryFrom<&Face<'_>> for FontMetadata {
-	type Error = anyhow::Error;
+	type Error = GlyphError;
 
 	/// Attempts to build a [`FontMetadata`] from a [`ttf_parser::Face`],
 	/// extracting properties like family name, style, weight, width, and codepoints.
-	fn try_from(face: &Face) -> Result<Self> {
+	fn try_from(face: &Face) -> Result<Self, GlyphError> {
 		let map = HashMap::<u16, String>::from_iter(
 			face
 				.names()
@@ -95,16 +105,18 @@ impl TryFrom<&Face<'_>> for FontMetadata {
 		);
 
 		let get = |id: u16| map.get(&id).unwrap_or(&String::from("")).to_owned();
+		let get_optional = |id: u16| {
+			map.get(&id)
+				.map(|value| value.trim().to_string())
+				.filter(|value| !value.is_empty())
+		};
 
 		let name = get(name_id::FAMILY);
 		let (family, style, weight, width) =
 			parse_font_name(name.clone(), get(name_id::POST_SCRIPT_NAME));
 
 		let mut codepoints = HashSet::<u32>::new();
-		let table = face
-			.tables()
-			.cmap
-			.ok_or_else(|| anyhow::anyhow!("Font has no cmap table"))?;
+		let table = face.tables().cmap.ok_or(GlyphError::NoCmap)?;
 		for subtable in table.subtables.into_iter() {
 			if subtable.is_unicode() {
 				subtable.codepoints(|cp| {
@@ -124,6 +136,10 @@ impl TryFrom<&Face<'_>> for FontMetadata {
 			style,
 			weight,
 			width,
+			has_kerning: kerning::has_kerning(face),
+			copyright: get_optional(name_id::COPYRIGHT_NOTICE),
+			license: get_optional(name_id::LICENSE),
+			license_url: get_optional(name_id::LICENSE_URL),
 		})
 	}
 }
@@ -140,6 +156,19 @@ mod tests {
 		assert_eq!(metadata.family, "Fira Sans");
 		assert_eq!(metadata.generate_name(), "Fira Sans Regular");
 		assert_eq!(metadata.codepoints.len(), 1686);
+		assert!(metadata.has_kerning);
+		assert_eq!(
+			metadata.copyright.as_deref(),
+			Some("Digitized data copyright 2012-2016, The Mozilla Foundation and Telefonica S.A.")
+		);
+		assert_eq!(
+			metadata.license.as_deref(),
+			Some("Licensed under the Open Font License, version 1.1 or later")
+		);
+		assert_eq!(
+			metadata.license_url.as_deref(),
+			Some("http://scripts.sil.org/OFL")
+		);
 	}
 
 	#[test]
@@ -150,5 +179,31 @@ mod tests {
 		assert_eq!(metadata.family, "Noto Sans");
 		assert_eq!(metadata.generate_name(), "Noto Sans Regular");
 		assert_eq!(metadata.codepoints.len(), 3094);
+		assert!(metadata
+			.copyright
+			.unwrap()
+			.starts_with("Copyright 2022 The Noto Project Authors"));
+	}
+
+	#[test]
+	fn test_generate_name_with_intermediate_weights() {
+		let name = |weight: u16, style: &str| {
+			FontMetadata {
+				name: String::new(),
+				family: String::from("Noto Sans"),
+				codepoints: vec![],
+				style: String::from(style),
+				weight,
+				width: String::from("normal"),
+				has_kerning: false,
+				copyright: None,
+				license: None,
+				license_url: None,
+			}
+			.generate_name()
+		};
+		assert_eq!(name(350, "normal"), "Noto Sans Weight 350");
+		assert_eq!(name(450, "italic"), "Noto Sans Weight 450 italic");
+		assert_eq!(name(700, "normal"), "Noto Sans Bold");
 	}
 }
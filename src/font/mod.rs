@@ -4,24 +4,56 @@
 //!
 //! - Low-level constructs for reading and parsing individual font files ([`FontFileEntry`]).  
 //! - Structures to represent glyph blocks ([`GlyphBlock`]) and build indices ([`build_index_json`], [`build_font_families_json`]).  
+//! - [`encode_codeblocks`] and [`decode_codeblocks`] to summarize codepoint coverage compactly.  
 //! - A high-level [`FontManager`] for orchestrating multiple fonts, rendering, and metadata.  
 //! - Metadata extraction utilities ([`FontMetadata`] and [`parse_font_name`]) to identify the font’s
 //!   family, style, weight, width, and codepoints.  
 //! - A [`FontWrapper`] to combine multiple files into one logical font (e.g., different languages).  
+//! - A [`FontLayout`] choosing the output directory names.  
+//! - [`FeatureSubstitution`] to apply an OpenType feature, like small caps, to glyph ids.  
+//! - Pair kerning lookups from the `kern` and `GPOS` tables ([`FontFileEntry::kerning`]).  
+//! - Per-font [`FontSettings`] overriding render size, buffer, charset, style and weight.  
+//! - A script classification of codepoints ([`script_of`]) to group glyphs by script.  
+//! - A thread-safe [`GlyphCache`] rendering glyph blocks on demand, e.g. for a server.  
 
+#[allow(dead_code)] // Public API; the CLI writes all blocks to an output.
+mod cache;
+#[allow(dead_code)] // Public API; the CLI renders the default glyphs.
+mod features;
 mod file_entry;
 mod glyph_block;
+mod in_flight;
 mod index_files;
+mod kerning;
+mod layout;
 mod manager;
 mod metadata;
 mod parse_font_name;
+mod script;
+mod settings;
 mod wrapper;
 
+#[allow(unused_imports)]
+pub use cache::GlyphCache;
+pub use features::FeatureSubstitution;
 pub use file_entry::FontFileEntry;
-pub use glyph_block::{GlyphBlock, GLYPH_BLOCK_SIZE};
+pub use glyph_block::{check_block_size, GlyphBlock, GLYPH_BLOCK_SIZE};
+#[allow(unused_imports)]
+pub use glyph_block::{MAX_GLYPH_BLOCK_SIZE, MIN_GLYPH_BLOCK_SIZE};
+pub use in_flight::MemoryStats;
 #[allow(unused_imports)]
-pub use index_files::{build_font_families_json, build_index_json};
+pub use index_files::{
+	build_font_families_json, build_font_families_json_with_options, build_index_json,
+	decode_codeblocks, encode_codeblocks, FontFamiliesOptions, FONT_FAMILIES_VERSION,
+};
+pub use layout::FontLayout;
 pub use manager::FontManager;
 pub use metadata::FontMetadata;
 pub use parse_font_name::parse_font_name;
+pub use script::script_of;
+#[allow(unused_imports)]
+pub use script::OTHER_SCRIPT;
+#[allow(unused_imports)]
+pub use settings::is_non_rendering;
+pub use settings::FontSettings;
 pub use wrapper::FontWrapper;
@@ -0,0 +1,210 @@
+//! A coarse classification of codepoints by script, for writing one glyph file
+//! per script instead of per block, see [`FontManager::group_by_script`](super::FontManager::group_by_script).
+//!
+//! The table is derived from the Unicode 15.1 `Blocks.txt` of the Unicode
+//! Character Database (<https://www.unicode.org/Public/15.1.0/ucd/Blocks.txt>):
+//! every block is assigned to the script it was made for, e.g. "Arabic
+//! Presentation Forms-A" to `arabic`. Blocks shared by many scripts, like
+//! "General Punctuation" or emoji, are `common`. Unlike the per-codepoint
+//! `Scripts.txt`, the digits and punctuation of "Basic Latin" are therefore
+//! `latin`, so a Latin text needs a single file. Codepoints outside the table
+//! are `other`.
+
+/// Script of codepoints outside the table.
+pub const OTHER_SCRIPT: &str = "other";
+
+/// Start of every range, and the script of the codepoints up to the next start.
+/// Sorted by start; gaps are marked with [`OTHER_SCRIPT`].
+const SCRIPT_RANGES: &[(u32, &str)] = &[
+	(0x0000, "latin"),
+	(0x0370, "greek"),
+	(0x0400, "cyrillic"),
+	(0x0530, "armenian"),
+	(0x0590, "hebrew"),
+	(0x0600, "arabic"),
+	(0x0700, "syriac"),
+	(0x0750, "arabic"),
+	(0x0780, "thaana"),
+	(0x07C0, "nko"),
+	(0x0800, "samaritan"),
+	(0x0840, "mandaic"),
+	(0x0860, "syriac"),
+	(0x0870, "arabic"),
+	(0x0900, "devanagari"),
+	(0x0980, "bengali"),
+	(0x0A00, "gurmukhi"),
+	(0x0A80, "gujarati"),
+	(0x0B00, "oriya"),
+	(0x0B80, "tamil"),
+	(0x0C00, "telugu"),
+	(0x0C80, "kannada"),
+	(0x0D00, "malayalam"),
+	(0x0D80, "sinhala"),
+	(0x0E00, "thai"),
+	(0x0E80, "lao"),
+	(0x0F00, "tibetan"),
+	(0x1000, "myanmar"),
+	(0x10A0, "georgian"),
+	(0x1100, "hangul"),
+	(0x1200, "ethiopic"),
+	(0x13A0, "cherokee"),
+	(0x1400, "canadian_aboriginal"),
+	(0x1680, "ogham"),
+	(0x16A0, "runic"),
+	(0x1700, "philippine"),
+	(0x1780, "khmer"),
+	(0x1800, "mongolian"),
+	(0x18B0, "canadian_aboriginal"),
+	(0x1900, "limbu"),
+	(0x1950, "tai_le"),
+	(0x1980, "new_tai_lue"),
+	(0x19E0, "khmer"),
+	(0x1A00, "buginese"),
+	(0x1A20, "tai_tham"),
+	(0x1AB0, "latin"),
+	(0x1B00, "balinese"),
+	(0x1B80, "sundanese"),
+	(0x1BC0, "batak"),
+	(0x1C00, "lepcha"),
+	(0x1C50, "ol_chiki"),
+	(0x1C80, "cyrillic"),
+	(0x1C90, "georgian"),
+	(0x1CC0, "sundanese"),
+	(0x1CD0, "devanagari"),
+	(0x1D00, "latin"),
+	(0x1F00, "greek"),
+	(0x2000, "common"),
+	(0x2800, "braille"),
+	(0x2900, "common"),
+	(0x2C00, "glagolitic"),
+	(0x2C60, "latin"),
+	(0x2C80, "coptic"),
+	(0x2D00, "georgian"),
+	(0x2D30, "tifinagh"),
+	(0x2D80, "ethiopic"),
+	(0x2DE0, "cyrillic"),
+	(0x2E00, "common"),
+	(0x2E80, "cjk"),
+	(0x3040, "kana"),
+	(0x3100, "bopomofo"),
+	(0x3130, "hangul"),
+	(0x3190, "cjk"),
+	(0x31A0, "bopomofo"),
+	(0x31C0, "cjk"),
+	(0x31F0, "kana"),
+	(0x3200, "cjk"),
+	(0x4DC0, "common"),
+	(0x4E00, "cjk"),
+	(0xA000, "yi"),
+	(0xA4D0, "lisu"),
+	(0xA500, "vai"),
+	(0xA640, "cyrillic"),
+	(0xA6A0, "bamum"),
+	(0xA700, "common"),
+	(0xA720, "latin"),
+	(0xA800, "syloti_nagri"),
+	(0xA830, "common"),
+	(0xA840, "phags_pa"),
+	(0xA880, "saurashtra"),
+	(0xA8E0, "devanagari"),
+	(0xA900, "kayah_li"),
+	(0xA930, "rejang"),
+	(0xA960, "hangul"),
+	(0xA980, "javanese"),
+	(0xA9E0, "myanmar"),
+	(0xAA00, "cham"),
+	(0xAA60, "myanmar"),
+	(0xAA80, "tai_viet"),
+	(0xAAE0, "meetei_mayek"),
+	(0xAB00, "ethiopic"),
+	(0xAB30, "latin"),
+	(0xAB70, "cherokee"),
+	(0xABC0, "meetei_mayek"),
+	(0xAC00, "hangul"),
+	(0xD800, OTHER_SCRIPT),
+	(0xE000, "private_use"),
+	(0xF900, "cjk"),
+	(0xFB00, "latin"),
+	(0xFB13, "armenian"),
+	(0xFB1D, "hebrew"),
+	(0xFB50, "arabic"),
+	(0xFE00, "common"),
+	(0xFE10, "cjk"),
+	(0xFE20, "common"),
+	(0xFE30, "cjk"),
+	(0xFE50, "common"),
+	(0xFE70, "arabic"),
+	(0xFF00, "cjk"),
+	(0xFFF0, "common"),
+	(0x10000, OTHER_SCRIPT),
+	(0x1D400, "common"),
+	(0x1D800, OTHER_SCRIPT),
+	(0x1F000, "common"),
+	(0x1FB00, OTHER_SCRIPT),
+	(0x20000, "cjk"),
+	(0x323B0, OTHER_SCRIPT),
+	(0xF0000, "private_use"),
+];
+
+/// Returns the script of `codepoint`, a lowercase name like `"latin"` or
+/// `"arabic"` that can be used as a file name.
+///
+/// # Examples
+///
+/// ```
+/// use versatiles_glyphs::font::script_of;
+///
+/// assert_eq!(script_of('A' as u32), "latin");
+/// assert_eq!(script_of(0x0627), "arabic");
+/// assert_eq!(script_of('中' as u32), "cjk");
+/// ```
+pub fn script_of(codepoint: u32) -> &'static str {
+	let index = SCRIPT_RANGES.partition_point(|&(start, _)| start <= codepoint);
+	SCRIPT_RANGES[index - 1].1
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_script_ranges_are_sorted() {
+		assert_eq!(SCRIPT_RANGES[0].0, 0);
+		for pair in SCRIPT_RANGES.windows(2) {
+			assert!(pair[0].0 < pair[1].0, "{pair:X?}");
+			assert_ne!(pair[0].1, pair[1].1, "{pair:X?}");
+		}
+	}
+
+	#[test]
+	fn test_script_of() {
+		let cases = [
+			(0x0041, "latin"),
+			(0x0031, "latin"),
+			(0x00E9, "latin"),
+			(0x1E9E, "latin"),
+			(0x03A9, "greek"),
+			(0x0416, "cyrillic"),
+			(0x05D0, "hebrew"),
+			(0x0627, "arabic"),
+			(0xFEFB, "arabic"),
+			(0x0915, "devanagari"),
+			(0x0E01, "thai"),
+			(0x2014, "common"),
+			(0x3042, "kana"),
+			(0x4E2D, "cjk"),
+			(0xAC00, "hangul"),
+			(0xE000, "private_use"),
+			(0x1F600, "common"),
+			(0x20000, "cjk"),
+			(0x31350, "cjk"),
+			(0x323AF, "cjk"),
+			(0x323B0, OTHER_SCRIPT),
+			(0x10330, OTHER_SCRIPT),
+			(0x10FFFF, "private_use"),
+		];
+		for (codepoint, script) in cases {
+			assert_eq!(script_of(codepoint), script, "{codepoint:#X}");
+		}
+	}
+}
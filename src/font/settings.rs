@@ -0,0 +1,134 @@
+//! Per-font settings that override the defaults used when rendering and indexing.
+
+use crate::render::Renderer;
+use std::{collections::HashSet, ops::RangeInclusive};
+
+/// Optional overrides for a single logical font ([`FontWrapper`](super::FontWrapper)).
+///
+/// Every field left as `None` falls back to the global default: the
+/// [`Renderer`] passed to [`FontManager::render_glyphs`](super::FontManager::render_glyphs)
+/// for `size`/`buffer`/`baseline`, and the font's own [`FontMetadata`](super::FontMetadata)
+/// for `style`/`weight`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FontSettings {
+	/// Glyph size in pixels per EM.
+	pub size: Option<u32>,
+	/// SDF padding in pixels on every side of each glyph.
+	pub buffer: Option<u32>,
+	/// Baseline offset in pixels relative to the size, see [`Renderer::with_baseline`].
+	pub baseline: Option<i32>,
+	/// If set, only these codepoints are rendered and indexed.
+	pub charset: Option<HashSet<u32>>,
+	/// If `true`, codepoints that never render a glyph, like control characters,
+	/// are skipped, see [`is_non_rendering`].
+	pub skip_non_rendering: bool,
+	/// If set, codepoints above it are skipped, e.g. `0xFFFF` to stay within the
+	/// Basic Multilingual Plane.
+	pub max_codepoint: Option<u32>,
+	/// Style reported in `font_families.json`, e.g. `"italic"`.
+	pub style: Option<String>,
+	/// Weight reported in `font_families.json`, e.g. `700`.
+	pub weight: Option<u16>,
+	/// Codepoints that get the `.notdef` glyph if none of the font's files covers them.
+	/// They are not listed in the `codeblocks` of `font_families.json`.
+	pub notdef: HashSet<u32>,
+	/// Codepoint ranges that are rendered from the file at this index of
+	/// [`FontWrapper::files`](super::FontWrapper::files), instead of the first
+	/// file that covers them, e.g. a custom arrow. If ranges overlap, the last
+	/// one wins. An override is ignored if that file doesn't cover the codepoint.
+	pub overrides: Vec<(RangeInclusive<u32>, usize)>,
+}
+
+impl FontSettings {
+	/// Returns a copy of `renderer` with the `size`, `buffer` and `baseline` overrides applied.
+	pub fn apply_to(&self, renderer: &Renderer) -> Renderer {
+		let mut renderer = renderer.clone();
+		if let Some(size) = self.size {
+			renderer = renderer.with_size(size);
+		}
+		if let Some(buffer) = self.buffer {
+			renderer = renderer.with_buffer(buffer);
+		}
+		if let Some(baseline) = self.baseline {
+			renderer = renderer.with_baseline(baseline);
+		}
+		renderer
+	}
+
+	/// Returns `true` if `codepoint` passes the `charset` filter (or no filter is
+	/// set), and isn't skipped by `skip_non_rendering` or `max_codepoint`.
+	pub fn includes(&self, codepoint: u32) -> bool {
+		!(self.skip_non_rendering && is_non_rendering(codepoint))
+			&& self.max_codepoint.is_none_or(|max| codepoint <= max)
+			&& self
+				.charset
+				.as_ref()
+				.is_none_or(|charset| charset.contains(&codepoint))
+	}
+
+	/// Returns the file index that `overrides` assigns to `codepoint`, if any.
+	pub fn override_index(&self, codepoint: u32) -> Option<usize> {
+		self
+			.overrides
+			.iter()
+			.rev()
+			.find(|(range, _)| range.contains(&codepoint))
+			.map(|&(_, index)| index)
+	}
+}
+
+/// Returns `true` if `codepoint` is never drawn as a glyph: a C0 or C1 control
+/// character (`0x00–0x1F`, `0x7F–0x9F`), a surrogate, or a noncharacter like
+/// `U+FFFF`.
+///
+/// Fonts often map some of these, like `U+000D`, to empty glyphs. Format
+/// characters like the zero width joiner are kept, since they affect shaping.
+pub fn is_non_rendering(codepoint: u32) -> bool {
+	matches!(codepoint, 0x00..=0x1F | 0x7F..=0x9F | 0xD800..=0xDFFF | 0xFDD0..=0xFDEF)
+		|| codepoint & 0xFFFE == 0xFFFE
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_includes() {
+		let settings = FontSettings::default();
+		assert!(settings.includes(65));
+
+		let settings = FontSettings {
+			charset: Some(HashSet::from(['A' as u32, 'B' as u32])),
+			..Default::default()
+		};
+		assert!(settings.includes(65));
+		assert!(!settings.includes(67));
+
+		let settings = FontSettings {
+			max_codepoint: Some(0xFFFF),
+			..Default::default()
+		};
+		assert!(settings.includes(0xFFFF));
+		assert!(!settings.includes(0x10000));
+	}
+
+	#[test]
+	fn test_includes_skip_non_rendering() {
+		let settings = FontSettings {
+			skip_non_rendering: true,
+			..Default::default()
+		};
+		for codepoint in [
+			0x00, 0x0D, 0x1F, 0x7F, 0x85, 0x9F, 0xD800, 0xFDD0, 0xFFFE, 0x10FFFF,
+		] {
+			assert!(!settings.includes(codepoint), "{codepoint:#X}");
+			assert!(
+				FontSettings::default().includes(codepoint),
+				"{codepoint:#X}"
+			);
+		}
+		for codepoint in [0x20, 0x41, 0x7E, 0xA0, 0x200D, 0xFFFD, 0x1F600] {
+			assert!(settings.includes(codepoint), "{codepoint:#X}");
+		}
+	}
+}
@@ -4,73 +4,268 @@
 //! (for example, different languages). It provides methods to load font
 //! data from file paths, retrieve metadata, and generate glyph blocks for rendering.
 
-use super::{FontFileEntry, FontMetadata, GlyphBlock, GLYPH_BLOCK_SIZE};
-use anyhow::{Context, Result};
-use std::{collections::HashMap, path::PathBuf};
+use super::{script_of, FontFileEntry, FontMetadata, FontSettings, GlyphBlock, GLYPH_BLOCK_SIZE};
+use crate::{error::GlyphError, render::RenderOptions};
+use anyhow::{anyhow, Result};
+use std::{
+	cmp::Reverse,
+	collections::{BTreeMap, HashSet},
+	path::PathBuf,
+};
 
 /// A wrapper around one or more [`FontFileEntry`] instances.
 /// Each [`FontWrapper`] is effectively a "logical" font that can span
 /// multiple font files (e.g., for different languages).
+///
+/// If several files cover a codepoint, the first of them in [`Self::files`]
+/// provides its glyph, unless [`FontSettings::overrides`] picks another one.
+/// Files added with [`Self::insert_file`] are kept sorted by their raw name
+/// ([`FontMetadata::name`]), then by their number of codepoints, most first,
+/// so the winner doesn't depend on the order in which the files were found
+/// or loaded. Files added with [`Self::add_file`] keep their order, like the
+/// sources of a `fonts.json`.
 #[derive(Debug, Default)]
 pub struct FontWrapper<'a> {
 	/// Collection of all font files that share the same logical font identity.
 	pub files: Vec<FontFileEntry<'a>>,
+	/// Overrides applied when rendering and indexing this font.
+	pub settings: FontSettings,
+	/// Display name configured for this font, e.g. in a `fonts.json`. If unset,
+	/// the name is generated from the first file, see [`Self::display_name`].
+	pub name: Option<String>,
 }
 
 impl<'a> FontWrapper<'a> {
-	/// Adds a single [`FontFileEntry`] to this wrapper.
+	/// Adds a single [`FontFileEntry`] to this wrapper, after all other files.
 	pub fn add_file(&mut self, file: FontFileEntry<'a>) {
 		self.files.push(file);
 	}
 
+	/// Inserts a single [`FontFileEntry`] at its sorted position, see
+	/// [`FontWrapper`], e.g. for files that are grouped by their family
+	/// automatically. Equal files keep the order in which they were inserted.
+	pub fn insert_file(&mut self, file: FontFileEntry<'a>) {
+		let key = |file: &FontFileEntry| {
+			(
+				file.metadata.name.clone(),
+				Reverse(file.metadata.codepoints.len()),
+			)
+		};
+		let file_key = key(&file);
+		let index = self.files.partition_point(|f| key(f) <= file_key);
+		self.files.insert(index, file);
+	}
+
 	/// Adds multiple font files by reading them from the given file paths.
 	///
 	/// # Errors
 	///
-	/// Returns an error if reading or parsing any of the font files fails.
-	pub fn add_paths(&mut self, sources: &[PathBuf]) -> Result<()> {
+	/// Returns [`GlyphError::Io`] if reading any of the font files fails, or the
+	/// error of [`FontFileEntry::new`] if parsing fails.
+	pub fn add_paths(&mut self, sources: &[PathBuf]) -> Result<(), GlyphError> {
 		for path in sources {
-			let data =
-				std::fs::read(path).with_context(|| format!("reading font file \"{path:?}\""))?;
-			self.files.push(FontFileEntry::new(data)?);
+			self.files.push(FontFileEntry::from_path(path)?);
 		}
 		Ok(())
 	}
 
+	/// Returns all codepoints covered by any of the font files that pass the
+	/// [`FontSettings::charset`] filter. `.notdef` codepoints are not included.
+	pub fn codepoints(&self) -> HashSet<u32> {
+		self
+			.files
+			.iter()
+			.flat_map(|file| file.metadata.codepoints.iter().copied())
+			.filter(|&codepoint| self.settings.includes(codepoint))
+			.collect()
+	}
+
 	/// Gathers all codepoints from every contained [`FontFileEntry`], grouping them
-	/// into [`GlyphBlock`]s of size [`GLYPH_BLOCK_SIZE`].
+	/// into [`GlyphBlock`]s of size [`GLYPH_BLOCK_SIZE`]. A codepoint that several
+	/// files cover is taken from the first of them, see [`FontWrapper`].
 	///
 	/// This is essential for rendering, as each block corresponds to a `.pbf` file
-	/// covering a particular range of Unicode codepoints.
+	/// covering a particular range of Unicode codepoints. Codepoints outside the
+	/// [`FontSettings::charset`] are skipped, and missing [`FontSettings::notdef`]
+	/// codepoints are assigned to the first file's `.notdef` glyph.
+	///
+	/// The blocks are sorted by ascending [`GlyphBlock::start_index`].
+	#[allow(dead_code)] // Public API; the CLI uses `get_blocks_with_size`.
 	pub fn get_blocks(&'a self) -> Vec<GlyphBlock<'a>> {
-		let mut blocks = HashMap::<u32, GlyphBlock<'a>>::new();
+		self.get_blocks_with_size(GLYPH_BLOCK_SIZE)
+	}
+
+	/// Like [`Self::get_blocks`], but groups the codepoints into blocks of `size`
+	/// codepoints, e.g. `512` for files like `0-511.pbf`.
+	///
+	/// # Panics
+	///
+	/// Panics if `size` is zero. Use [`check_block_size`](super::check_block_size)
+	/// to validate sizes from user input.
+	pub fn get_blocks_with_size(&'a self, size: u32) -> Vec<GlyphBlock<'a>> {
+		self.get_blocks_with_fallbacks(size, &[])
+	}
+
+	/// Like [`Self::get_blocks_with_size`], but codepoints that none of the files
+	/// cover are taken from the `fallbacks` in order, as `(id, font)`. Those are
+	/// tagged with the fallback's id in [`GlyphBlock::fallbacks`].
+	///
+	/// Only codepoints that pass the `charset` filters of both fonts are used.
+	/// `.notdef` codepoints get the `.notdef` glyph only if no fallback covers them.
+	pub fn get_blocks_with_fallbacks(
+		&'a self,
+		size: u32,
+		fallbacks: &[(&'a str, &'a FontWrapper<'a>)],
+	) -> Vec<GlyphBlock<'a>> {
+		assert!(size > 0, "block size must not be zero");
+		self.get_blocks_grouped(fallbacks, |codepoint| {
+			GlyphBlock::with_size(codepoint / size * size, size)
+		})
+	}
+
+	/// Like [`Self::get_blocks_with_fallbacks`], but groups the codepoints by
+	/// script, see [`script_of`], into one block per script, like `latin.pbf`.
+	///
+	/// The blocks are sorted by script name.
+	pub fn get_script_blocks_with_fallbacks(
+		&'a self,
+		fallbacks: &[(&'a str, &'a FontWrapper<'a>)],
+	) -> Vec<GlyphBlock<'a>> {
+		self.get_blocks_grouped(fallbacks, |codepoint| {
+			GlyphBlock::for_script(script_of(codepoint))
+		})
+	}
+
+	/// Places every codepoint into the block returned by `group`, which
+	/// identifies it by its start index and script.
+	fn get_blocks_grouped(
+		&'a self,
+		fallbacks: &[(&'a str, &'a FontWrapper<'a>)],
+		group: impl Fn(u32) -> GlyphBlock<'a>,
+	) -> Vec<GlyphBlock<'a>> {
+		type Blocks<'a> = BTreeMap<(u32, Option<&'static str>), GlyphBlock<'a>>;
+		/// Returns the stored block equal to `block`, inserting it first if needed.
+		fn entry<'b, 'a>(
+			blocks: &'b mut Blocks<'a>,
+			block: GlyphBlock<'a>,
+		) -> &'b mut GlyphBlock<'a> {
+			blocks
+				.entry((block.start_index, block.script))
+				.or_insert(block)
+		}
+
+		let mut blocks = Blocks::new();
 
 		// For each file, for each codepoint, place the codepoint into its corresponding block.
 		for font_file in &self.files {
 			for &codepoint in &font_file.metadata.codepoints {
-				let block_index = codepoint / GLYPH_BLOCK_SIZE;
-				let char_index = (codepoint % GLYPH_BLOCK_SIZE) as u8;
-				let block = blocks
-					.entry(block_index)
-					.or_insert_with(|| GlyphBlock::new(block_index * GLYPH_BLOCK_SIZE));
-				block.set_glyph_font(char_index, font_file);
+				if !self.settings.includes(codepoint) {
+					continue;
+				}
+				let block = entry(&mut blocks, group(codepoint));
+				block.set_glyph_font(codepoint - block.start_index, font_file);
+			}
+		}
+
+		// Only visit the codepoints the override's file covers, not the whole range.
+		for (range, index) in &self.settings.overrides {
+			let Some(codepoints) = self.files.get(*index).map(|f| &f.metadata.codepoints) else {
+				continue;
+			};
+			let start = codepoints.partition_point(|c| c < range.start());
+			for &codepoint in codepoints[start..].iter().take_while(|&c| c <= range.end()) {
+				if let Some(font_file) = self.override_file(codepoint) {
+					let block = entry(&mut blocks, group(codepoint));
+					block.override_glyph_font(codepoint - block.start_index, font_file);
+				}
+			}
+		}
+
+		for &(id, fallback) in fallbacks {
+			for font_file in &fallback.files {
+				for &codepoint in &font_file.metadata.codepoints {
+					if !self.settings.includes(codepoint) || !fallback.settings.includes(codepoint) {
+						continue;
+					}
+					let block = entry(&mut blocks, group(codepoint));
+					block.set_fallback_font(codepoint - block.start_index, font_file, id);
+				}
+			}
+		}
+
+		if let Some(font_file) = self.files.first() {
+			for &codepoint in &self.settings.notdef {
+				if !self.settings.includes(codepoint) {
+					continue;
+				}
+				let block = entry(&mut blocks, group(codepoint));
+				block.set_notdef_font(codepoint - block.start_index, font_file);
 			}
 		}
 
 		blocks.into_values().collect()
 	}
 
+	/// Returns the advances of those `chars` that any of the files covers, by
+	/// codepoint, at this font's render size: [`FontSettings::size`] or the
+	/// default. See [`FontFileEntry::advance`].
+	///
+	/// Like [`Self::get_blocks`], the first file that covers a character is used,
+	/// unless [`FontSettings::overrides`] picks another one, and characters
+	/// outside the [`FontSettings::charset`] are skipped.
+	#[allow(dead_code)] // Public API; the CLI doesn't lay out text.
+	pub fn advances(&self, chars: impl IntoIterator<Item = char>) -> BTreeMap<u32, u32> {
+		let size = self.settings.size.unwrap_or(RenderOptions::default().size);
+		chars
+			.into_iter()
+			.map(|c| c as u32)
+			.filter(|&codepoint| self.settings.includes(codepoint))
+			.filter_map(|codepoint| {
+				let advance = match self.override_file(codepoint) {
+					Some(file) => file.advance_with_size(codepoint, size),
+					None => self
+						.files
+						.iter()
+						.find_map(|file| file.advance_with_size(codepoint, size)),
+				}?;
+				Some((codepoint, advance))
+			})
+			.collect()
+	}
+
+	/// Returns the file that [`FontSettings::overrides`] assigns to `codepoint`,
+	/// if it exists and covers `codepoint`, and `codepoint` isn't filtered out.
+	fn override_file(&self, codepoint: u32) -> Option<&FontFileEntry<'a>> {
+		let file = self.files.get(self.settings.override_index(codepoint)?)?;
+		(self.settings.includes(codepoint)
+			&& file.metadata.codepoints.binary_search(&codepoint).is_ok())
+		.then_some(file)
+	}
+
 	/// Returns the [`FontMetadata`] of the first font file in this wrapper.
 	///
 	/// # Errors
 	///
 	/// Returns an error if this wrapper has no files.
-	pub fn get_metadata(&self) -> Result<&FontMetadata> {
-		Ok(&self
-			.files
-			.first()
-			.context("FontWrapper has no files")?
-			.metadata)
+	pub fn get_metadata(&self) -> Result<&FontMetadata, GlyphError> {
+		match self.files.first() {
+			Some(file) => Ok(&file.metadata),
+			None => Err(GlyphError::Other(anyhow!("FontWrapper has no files"))),
+		}
+	}
+
+	/// Returns the fontstack name stored in the rendered blocks: the configured
+	/// [`Self::name`] if set, otherwise [`FontMetadata::generate_name`] of the
+	/// first file.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no name is configured and there are no files.
+	pub fn display_name(&self) -> Result<String, GlyphError> {
+		match &self.name {
+			Some(name) => Ok(name.clone()),
+			None => Ok(self.get_metadata()?.generate_name()),
+		}
 	}
 }
 
@@ -84,14 +279,14 @@ impl<'a> From<FontFileEntry<'a>> for FontWrapper<'a> {
 }
 
 impl TryFrom<&[PathBuf]> for FontWrapper<'_> {
-	type Error = anyhow::Error;
+	type Error = GlyphError;
 
 	/// Attempts to create a new [`FontWrapper`] from a slice of file paths.
 	///
 	/// # Errors
 	///
 	/// Returns an error if reading or parsing any of the font files fails.
-	fn try_from(paths: &[PathBuf]) -> Result<Self> {
+	fn try_from(paths: &[PathBuf]) -> Result<Self, GlyphError> {
 		let mut font = FontWrapper::default();
 		font.add_paths(paths)?;
 		Ok(font)
@@ -101,6 +296,8 @@ impl TryFrom<&[PathBuf]> for FontWrapper<'_> {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{protobuf::PbfGlyphs, render::Renderer};
+	use std::collections::HashSet;
 
 	// Helper function to create a FontFileEntry from a known valid test font.
 	fn create_test_font_file_entry<'a>() -> FontFileEntry<'a> {
@@ -113,10 +310,24 @@ mod tests {
 		let metadata = wrapper.get_metadata().unwrap();
 		assert_eq!(
             format!("{metadata:?}", ),
-            "FontMetadata { family: Fira Sans, style: normal, weight: 400, width: normal, codepoints: 1686 }"
+            "FontMetadata { family: Fira Sans, style: normal, weight: 400, width: normal, codepoints: 1686, has_kerning: true }"
         );
 	}
 
+	#[test]
+	fn test_advances() {
+		let mut wrapper = FontWrapper::from(create_test_font_file_entry());
+		let advances = wrapper.advances("AA W\u{10FFFF}".chars());
+		assert_eq!(
+			advances.into_iter().collect::<Vec<_>>(),
+			[(0x20, 6), (0x41, 13), (0x57, 19)]
+		);
+
+		wrapper.settings.size = Some(48);
+		wrapper.settings.charset = Some(HashSet::from([0x41]));
+		assert_eq!(wrapper.advances("AW".chars()), BTreeMap::from([(0x41, 26)]));
+	}
+
 	#[test]
 	fn test_get_metadata_empty_wrapper_errors() {
 		let wrapper = FontWrapper::default();
@@ -131,6 +342,10 @@ mod tests {
 			.add_paths(&[PathBuf::from("/nonexistent.ttf")])
 			.unwrap_err();
 		assert!(err.to_string().contains("reading font file"));
+		assert!(matches!(
+			err,
+			GlyphError::Io { source, .. } if source.kind() == std::io::ErrorKind::NotFound
+		));
 	}
 
 	#[test]
@@ -143,6 +358,7 @@ mod tests {
 		let mut wrapper = FontWrapper::default();
 		let err = wrapper.add_paths(&[bad]).unwrap_err();
 		assert!(err.to_string().contains("Could not parse font data"));
+		assert!(matches!(err, GlyphError::FontParse(_)));
 	}
 
 	#[test]
@@ -160,8 +376,9 @@ mod tests {
 	}
 
 	#[test]
-	fn test_get_blocks() {
-		let wrapper = FontWrapper::from(create_test_font_file_entry());
+	fn test_get_blocks_with_charset() {
+		let mut wrapper = FontWrapper::from(create_test_font_file_entry());
+		wrapper.settings.charset = Some("Aé€".chars().map(|c| c as u32).collect());
 		let blocks = wrapper.get_blocks();
 
 		let mut list = blocks
@@ -170,6 +387,114 @@ mod tests {
 			.collect::<Vec<_>>();
 		list.sort_unstable();
 
+		assert_eq!(list, [(0, 2), (8192, 1)]);
+	}
+
+	#[test]
+	fn test_get_blocks_with_notdef() {
+		let mut wrapper = FontWrapper::from(create_test_font_file_entry());
+		wrapper.settings.notdef = HashSet::from([65, 0xE000, 0xE001]);
+		let blocks = wrapper.get_blocks();
+
+		let block = blocks.iter().find(|b| b.start_index == 0xE000).unwrap();
+		assert_eq!(block.notdef.iter().copied().collect::<Vec<_>>(), [0, 1]);
+
+		// "A" exists in the font, so it keeps its own glyph.
+		let block = blocks.iter().find(|b| b.start_index == 0).unwrap();
+		assert_eq!(block.len(), 192);
+		assert!(block.notdef.is_empty());
+	}
+
+	#[test]
+	fn test_get_blocks_with_overrides() -> Result<()> {
+		let mut wrapper = FontWrapper::from(create_test_font_file_entry());
+		wrapper.add_paths(&[PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("testdata/Noto Sans/Noto Sans - Regular.ttf")])?;
+		// "A" comes from the second file, "é" isn't covered by a third one.
+		wrapper.settings.overrides = vec![(0x41..=0x41, 1), (0xE9..=0xE9, 2)];
+
+		let blocks = wrapper.get_blocks();
+		let block = blocks.iter().find(|b| b.start_index == 0).unwrap();
+		assert!(std::ptr::eq(block.glyphs[&0x41], &wrapper.files[1]));
+		assert!(std::ptr::eq(block.glyphs[&0x42], &wrapper.files[0]));
+		assert!(std::ptr::eq(block.glyphs[&0xE9], &wrapper.files[0]));
+
+		let renderer = Renderer::new_precise();
+		let glyphs = PbfGlyphs::from_slice(&block.render(String::from("Test"), &renderer)?)?;
+		let glyph = glyphs.into_glyphs().into_iter().find(|g| g.id == 0x41);
+		assert_eq!(glyph, renderer.render_glyph(&wrapper.files[1].face, 0x41));
+		assert_ne!(glyph, renderer.render_glyph(&wrapper.files[0].face, 0x41));
+
+		assert_eq!(
+			wrapper.advances("A".chars())[&0x41],
+			wrapper.files[1].advance(0x41).unwrap()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_insert_file_picks_the_same_winner_in_any_order() -> Result<()> {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Noto Sans");
+		let load = |name: &str| FontFileEntry::from_path(&testdata.join(name));
+		let regular = "Noto Sans - Regular.ttf";
+		let arabic = "Noto Sans Arabic - Regular.ttf";
+
+		// A codepoint that both files cover, like the space.
+		let covered = load(regular)?.metadata.codepoints;
+		let shared = *load(arabic)?
+			.metadata
+			.codepoints
+			.iter()
+			.find(|cp| covered.contains(cp))
+			.unwrap();
+		let winner = |first: &str, second: &str| -> Result<String> {
+			let mut wrapper = FontWrapper::from(load(first)?);
+			wrapper.insert_file(load(second)?);
+			let blocks = wrapper.get_blocks();
+			let block = blocks
+				.iter()
+				.find(|b| b.start_index == shared / 256 * 256)
+				.unwrap();
+			Ok(block.glyphs[&(shared % 256)].metadata.name.clone())
+		};
+
+		let name = winner(regular, arabic)?;
+		assert_eq!(winner(arabic, regular)?, name);
+		assert_eq!(name, load(regular)?.metadata.name);
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_blocks_are_sorted() {
+		let testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/Noto Sans");
+		let wrapper = FontWrapper::try_from(
+			&[
+				testdata.join("Noto Sans Arabic - Regular.ttf"),
+				testdata.join("Noto Sans - Regular.ttf"),
+			][..],
+		)
+		.unwrap();
+
+		let starts = wrapper
+			.get_blocks()
+			.iter()
+			.map(|b| b.start_index)
+			.collect::<Vec<_>>();
+		assert!(starts.len() > 20);
+		assert!(starts.windows(2).all(|w| w[0] < w[1]), "{starts:?}");
+	}
+
+	#[test]
+	fn test_get_blocks() {
+		let wrapper = FontWrapper::from(create_test_font_file_entry());
+		let blocks = wrapper.get_blocks();
+
+		// Already in ascending order, without sorting.
+		let list = blocks
+			.iter()
+			.map(|b| (b.start_index, b.glyphs.len()))
+			.collect::<Vec<_>>();
+
 		assert_eq!(
 			list,
 			[
@@ -3,7 +3,7 @@ use super::Point;
 /// A bounding box that can expand to include points and merge with other bounding boxes.
 /// Primarily used in spatial data structures (e.g., an R-tree) to track minimum and maximum
 /// coordinates.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct BBox {
 	/// The minimum (x, y) coordinates in this bounding box.
 	pub min: Point,
@@ -90,6 +90,28 @@ impl BBox {
 		self.max.x = self.max.x.round();
 		self.max.y = self.max.y.round();
 	}
+
+	/// Returns a copy of this bounding box grown by `amount` on every side.
+	///
+	/// A negative `amount` shrinks it.
+	#[allow(dead_code)] // Public API; the renderer uses `pad_snapped`.
+	pub fn pad(&self, amount: f64) -> BBox {
+		BBox {
+			min: Point::new(self.min.x - amount, self.min.y - amount),
+			max: Point::new(self.max.x + amount, self.max.y + amount),
+		}
+	}
+
+	/// Like [`Self::pad`], but first snaps the box outward to whole pixels,
+	/// `floor` on `min` and `ceil` on `max`, so the result always contains the
+	/// original box and has integer coordinates for an integer `amount`.
+	pub fn pad_snapped(&self, amount: f64) -> BBox {
+		BBox {
+			min: Point::new(self.min.x.floor(), self.min.y.floor()),
+			max: Point::new(self.max.x.ceil(), self.max.y.ceil()),
+		}
+		.pad(amount)
+	}
 }
 
 #[cfg(test)]
@@ -184,6 +206,30 @@ mod tests {
 		assert_eq!(bbox.max.y, -1.0);
 	}
 
+	#[test]
+	fn test_bbox_pad() {
+		let unit = BBox {
+			min: Point::new(0.0, 0.0),
+			max: Point::new(1.0, 1.0),
+		};
+		let padded = unit.pad(3.0);
+		assert_eq!(padded.min, Point::new(-3.0, -3.0));
+		assert_eq!(padded.max, Point::new(4.0, 4.0));
+		assert_eq!(padded.width(), 7.0);
+		assert_eq!(unit.pad(-0.25).max, Point::new(0.75, 0.75));
+
+		// Snapping rounds outward before padding.
+		let bbox = BBox {
+			min: Point::new(0.3, -1.5),
+			max: Point::new(1.2, 2.0),
+		};
+		let snapped = bbox.pad_snapped(3.0);
+		assert_eq!(snapped.min, Point::new(-3.0, -5.0));
+		assert_eq!(snapped.max, Point::new(5.0, 5.0));
+		assert_eq!(unit.pad_snapped(3.0).min, padded.min);
+		assert_eq!(unit.pad_snapped(3.0).max, padded.max);
+	}
+
 	#[test]
 	fn test_new_bbox_is_empty() {
 		let mut bbox = BBox::new();
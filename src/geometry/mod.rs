@@ -6,7 +6,7 @@
 //! - **[`Ring`]:** A sequence of [`Point`]s that can form a closed loop, used for polygonal outlines.
 //! - **[`Rings`]:** A collection of multiple [`Ring`] objects, representing complex or multi-part shapes.
 //! - **[`BBox`]:** An axis-aligned bounding box that expands to include additional points or boxes.
-//! - **[`Segment`]:** A line segment defined by two [`Point`] references, with operations like projection.
+//! - **[`Segment`]:** A line segment between two borrowed or owned [`Point`]s, with operations like projection.
 //!
 //! These types are commonly used throughout the glyph rendering pipeline for outline calculations,
 //! geometric transformations, intersection checks, and more.
@@ -20,5 +20,7 @@ mod segment;
 pub use bbox::BBox;
 pub use point::Point;
 pub use ring::Ring;
+#[allow(unused_imports)]
+pub use ring::CLOSE_EPSILON;
 pub use rings::Rings;
 pub use segment::Segment;
@@ -1,8 +1,13 @@
+use std::ops::Sub;
+
 /// A simple 2D point with `x` and `y` coordinates.
 ///
 /// This struct includes convenience methods for geometric transformations
 /// (translation, inversion, scaling) and measuring distances.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// It is (de)serialized as a compact `[x, y]` array.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "(f64, f64)", into = "(f64, f64)")]
 pub struct Point {
 	/// The x-coordinate.
 	pub x: f64,
@@ -41,6 +46,66 @@ impl Point {
 		dx * dx + dy * dy
 	}
 
+	/// Returns the dot product of `self` and `other`, treated as vectors.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// assert_eq!(Point::new(1.0, 2.0).dot(&Point::new(3.0, 4.0)), 11.0);
+	/// ```
+	#[inline(always)]
+	pub fn dot(&self, other: &Point) -> f64 {
+		self.x * other.x + self.y * other.y
+	}
+
+	/// Returns the z component of the cross product of `self` and `other`,
+	/// treated as vectors. It is positive if `other` points counterclockwise
+	/// of `self`, negative if clockwise, and zero if both are parallel.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// assert_eq!(Point::new(1.0, 0.0).cross(&Point::new(0.0, 1.0)), 1.0);
+	/// ```
+	#[inline(always)]
+	pub fn cross(&self, other: &Point) -> f64 {
+		self.x * other.y - self.y * other.x
+	}
+
+	/// Returns the Euclidean distance between `self` and another [`Point`].
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// let d = Point::new(1.0, 1.0).distance_to(&Point::new(4.0, 5.0));
+	/// assert_eq!(d, 5.0);
+	/// ```
+	#[allow(dead_code)] // Public API; the renderer compares squared distances.
+	pub fn distance_to(&self, other: &Point) -> f64 {
+		self.squared_distance_to(other).sqrt()
+	}
+
+	/// Returns the length of the vector from the origin to this [`Point`].
+	#[allow(dead_code)] // Public API; the renderer compares squared distances.
+	pub fn length(&self) -> f64 {
+		self.x.hypot(self.y)
+	}
+
+	/// Returns the unit vector pointing in the same direction as this [`Point`],
+	/// or [`None`] for the zero vector (or non-finite coordinates).
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// let unit = Point::new(3.0, 4.0).normalized().unwrap();
+	/// assert!((unit.x - 0.6).abs() < 1e-12 && (unit.y - 0.8).abs() < 1e-12);
+	/// assert!(Point::new(0.0, 0.0).normalized().is_none());
+	/// ```
+	#[allow(dead_code)] // Public API; not used by the renderer pipeline today.
+	pub fn normalized(&self) -> Option<Point> {
+		let length = self.length();
+		if length == 0.0 || !length.is_finite() {
+			return None;
+		}
+		Some(Point::new(self.x / length, self.y / length))
+	}
+
 	/// Returns a copy of this [`Point`] with its coordinates inverted (`-x`, `-y`).
 	///
 	/// ```
@@ -98,6 +163,19 @@ impl Point {
 		self.y *= scale;
 	}
 
+	/// Rounds both coordinates of this [`Point`] to the nearest integer.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// let mut p = Point::new(2.4, -3.6);
+	/// p.snap_to_grid();
+	/// assert_eq!(p, Point::new(2.0, -4.0));
+	/// ```
+	pub fn snap_to_grid(&mut self) {
+		self.x = self.x.round();
+		self.y = self.y.round();
+	}
+
 	/// Returns the coordinates of this [`Point`] as a tuple.
 	#[allow(dead_code)] // Public API; used heavily by geometry-module tests.
 	pub fn as_tuple(&self) -> (f64, f64) {
@@ -105,6 +183,22 @@ impl Point {
 	}
 }
 
+impl Sub for &Point {
+	type Output = Point;
+
+	/// Returns the vector from `other` to `self`.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// let v = &Point::new(4.0, 6.0) - &Point::new(1.0, 2.0);
+	/// assert_eq!(v, Point::new(3.0, 4.0));
+	/// ```
+	#[inline(always)]
+	fn sub(self, other: &Point) -> Point {
+		Point::new(self.x - other.x, self.y - other.y)
+	}
+}
+
 impl From<(f32, f32)> for Point {
 	fn from(t: (f32, f32)) -> Self {
 		Point::new(t.0 as f64, t.1 as f64)
@@ -117,6 +211,12 @@ impl From<(f64, f64)> for Point {
 	}
 }
 
+impl From<Point> for (f64, f64) {
+	fn from(p: Point) -> Self {
+		(p.x, p.y)
+	}
+}
+
 impl From<(i32, i32)> for Point {
 	fn from(t: (i32, i32)) -> Self {
 		Point::new(t.0 as f64, t.1 as f64)
@@ -149,6 +249,50 @@ mod tests {
 		assert_eq!(p2.squared_distance_to(&p1), 25.0);
 	}
 
+	#[test]
+	fn test_point_dot() {
+		let a = Point::new(1.0, 2.0);
+		assert_eq!(a.dot(&Point::new(3.0, 4.0)), 11.0);
+		assert_eq!(a.dot(&Point::new(-2.0, 1.0)), 0.0);
+		assert_eq!(a.dot(&a), a.squared_distance_to(&Point::new(0.0, 0.0)));
+	}
+
+	#[test]
+	fn test_point_cross() {
+		let x = Point::new(1.0, 0.0);
+		let y = Point::new(0.0, 1.0);
+		assert_eq!(x.cross(&y), 1.0);
+		assert_eq!(y.cross(&x), -1.0);
+		assert_eq!(x.cross(&Point::new(-3.0, 0.0)), 0.0);
+		assert_eq!(Point::new(2.0, 3.0).cross(&Point::new(4.0, 5.0)), -2.0);
+	}
+
+	#[test]
+	fn test_point_sub() {
+		let v = &Point::new(4.0, 6.0) - &Point::new(1.0, 2.0);
+		assert_eq!(v.as_tuple(), (3.0, 4.0));
+	}
+
+	#[test]
+	fn test_point_distance_to() {
+		let p1 = Point::new(0.0, 0.0);
+		let p2 = Point::new(3.0, 4.0);
+		assert_eq!(p1.distance_to(&p2), 5.0);
+		assert_eq!(p2.distance_to(&p1), 5.0);
+		assert_eq!(p2.length(), 5.0);
+		assert_eq!(Point::new(-3.0, -4.0).length(), 5.0);
+	}
+
+	#[test]
+	fn test_point_normalized() {
+		let unit = Point::new(3.0, 4.0).normalized().unwrap();
+		assert!((unit.x - 0.6).abs() < 1e-12);
+		assert!((unit.y - 0.8).abs() < 1e-12);
+		assert!((unit.length() - 1.0).abs() < 1e-12);
+		assert_eq!(Point::new(0.0, 0.0).normalized(), None);
+		assert_eq!(Point::new(f64::NAN, 1.0).normalized(), None);
+	}
+
 	#[test]
 	fn test_point_inverted() {
 		let p = Point::new(2.0, -3.0).inverted();
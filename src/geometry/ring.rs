@@ -5,10 +5,21 @@
 //! It also includes a winding number calculation for point-in-polygon tests.
 
 use super::{BBox, Point, Segment};
+use std::{ops::Index, slice};
+
+/// Default relative tolerance of [`Ring::close`]: coordinates closer than this
+/// times their magnitude (at least `1`) are considered equal.
+///
+/// Outlines are closed in font units (up to a few thousand) and then scaled,
+/// so this is far below a pixel but above the rounding error of `f64`.
+pub const CLOSE_EPSILON: f64 = 1e-9;
 
 /// A ring is essentially a list of [`Point`] instances that optionally end where they began,
 /// forming a closed polygonal chain.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// It is (de)serialized as an array of points.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct Ring {
 	/// The ordered collection of [`Point`]s.
 	pub points: Vec<Point>,
@@ -26,6 +37,15 @@ impl Ring {
 		Ring { points: Vec::new() }
 	}
 
+	/// Creates a [`Ring`] with copies of `points`, without first collecting
+	/// them into a [`Vec`] of convertible items like [`From<Vec<T>>`](Ring::from).
+	#[allow(dead_code)] // Public API; the renderer builds rings point by point.
+	pub fn from_points(points: &[Point]) -> Self {
+		Ring {
+			points: points.to_vec(),
+		}
+	}
+
 	/// Returns `true` if there are no points in this ring.
 	pub fn is_empty(&self) -> bool {
 		self.points.is_empty()
@@ -48,17 +68,29 @@ impl Ring {
 
 	/// Ensures the ring is closed by appending the first point to the end, if needed.
 	///
-	/// If the last point is already the same as the first (within floating-point epsilon),
-	/// no additional point is appended.
+	/// If the last point is already the same as the first, within
+	/// [`CLOSE_EPSILON`], no point is appended, see [`Self::close_with_epsilon`].
 	pub fn close(&mut self) {
-		if self.points.is_empty() {
-			return;
-		}
+		self.close_with_epsilon(CLOSE_EPSILON);
+	}
 
-		let first = self.points.first().unwrap();
-		let last = self.points.last().unwrap();
-		if (first.x - last.x).abs() > f64::EPSILON || (first.y - last.y).abs() > f64::EPSILON {
-			self.points.push(first.clone());
+	/// Like [`Self::close`], but considers the first and last point equal if
+	/// each coordinate differs by at most `epsilon` times its magnitude, or by
+	/// `epsilon` for magnitudes below `1`.
+	///
+	/// A last point that is equal but not identical is replaced by the first,
+	/// so the ring closes exactly without a near-zero-length closing segment.
+	pub fn close_with_epsilon(&mut self, epsilon: f64) {
+		let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+			return;
+		};
+
+		let near = |a: f64, b: f64| (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0);
+		let first = first.clone();
+		if near(first.x, last.x) && near(first.y, last.y) {
+			*self.points.last_mut().unwrap() = first;
+		} else {
+			self.points.push(first);
 		}
 	}
 
@@ -71,6 +103,29 @@ impl Ring {
 		bbox
 	}
 
+	/// Computes a circle, as center and radius, that encloses all points in
+	/// this ring, e.g. to skip the whole ring when a point is farther than
+	/// `radius` plus a search distance from the center.
+	///
+	/// The center is the middle of the [bounding box](Self::get_bbox), so the
+	/// circle is at most about 41% larger than the smallest enclosing circle.
+	/// An empty ring has a circle of radius 0 at the origin.
+	#[allow(dead_code)] // Public API; the renderer culls segments with an R-tree.
+	pub fn bounding_circle(&self) -> (Point, f64) {
+		if self.points.is_empty() {
+			return (Point::new(0.0, 0.0), 0.0);
+		}
+		let bbox = self.get_bbox();
+		let center = bbox.min.midpoint(&bbox.max);
+		let radius = self
+			.points
+			.iter()
+			.map(|point| point.squared_distance_to(&center))
+			.fold(0.0, f64::max)
+			.sqrt();
+		(center, radius)
+	}
+
 	/// Translates (moves) every point in this ring by a given offset.
 	pub fn translate(&mut self, offset: &Point) {
 		for point in &mut self.points {
@@ -85,11 +140,63 @@ impl Ring {
 		}
 	}
 
+	/// Rounds every point in this ring to whole pixels and drops consecutive
+	/// points that become identical.
+	pub fn snap_to_grid(&mut self) {
+		for point in &mut self.points {
+			point.snap_to_grid();
+		}
+		self.points.dedup();
+	}
+
+	/// Drops every point that lies within `epsilon` of the straight line
+	/// between its neighbors, like the runs of nearly collinear points that
+	/// flattening a Bezier curve produces for an almost straight section.
+	///
+	/// A whole run collapses into its two ends, as long as every dropped point
+	/// of the run stays within `epsilon` of the segment that replaces it, so
+	/// the outline doesn't drift on a gentle curve. The first and last point
+	/// are always kept, so a closed ring stays closed.
+	///
+	/// Every new point re-checks the whole run so far, so a run of `n` points
+	/// takes `O(n²)` distance checks.
+	#[allow(dead_code)] // Public API; the renderer keeps all flattened points.
+	pub fn remove_collinear_points(&mut self, epsilon: f64) {
+		if self.points.len() < 3 {
+			return;
+		}
+		let epsilon_sq = epsilon * epsilon;
+		let points = &self.points;
+		let mut kept = Vec::with_capacity(points.len());
+		kept.push(points[0].clone());
+		// Index of the last kept point, where the current run starts.
+		let mut start = 0;
+		for index in 1..points.len() - 1 {
+			// The distance to the segment, not the infinite line, so spikes that
+			// turn back along the line are kept.
+			let segment = Segment::new(&points[start], &points[index + 1]);
+			let fits = points[start + 1..=index]
+				.iter()
+				.all(|point| segment.squared_distance_to_point(point) <= epsilon_sq);
+			if !fits {
+				kept.push(points[index].clone());
+				start = index;
+			}
+		}
+		kept.push(points[points.len() - 1].clone());
+		self.points = kept;
+	}
+
 	/// Returns the last point in this ring, if it exists.
 	pub fn last(&self) -> Option<&Point> {
 		self.points.last()
 	}
 
+	/// Returns an iterator over the points in this ring.
+	pub fn iter(&self) -> slice::Iter<'_, Point> {
+		self.points.iter()
+	}
+
 	/// Returns a list of [`Segment`] instances connecting consecutive points in this ring.
 	///
 	/// This does not automatically close the ring. Therefore, if you want a fully closed
@@ -219,6 +326,32 @@ impl Ring {
 		}
 		winding_number
 	}
+
+	/// Returns `true` if `pt` lies inside this ring, i.e. its
+	/// [winding number](Self::winding_number) is non-zero.
+	///
+	/// Like [`Rings::contains_point`](super::Rings::contains_point) for a single
+	/// ring, so it doesn't depend on the direction of the ring. The ring should
+	/// be closed.
+	#[allow(dead_code)] // Public API; the renderer uses a scanline winding sweep.
+	pub fn contains_point(&self, pt: &Point) -> bool {
+		self.winding_number(pt) != 0
+	}
+
+	/// Computes the signed area enclosed by this ring (shoelace formula).
+	///
+	/// The area is positive if the points run counterclockwise (with the y axis
+	/// pointing up) and negative if they run clockwise. The ring is treated as
+	/// closed, whether or not its last point repeats the first.
+	pub fn signed_area(&self) -> f64 {
+		let points = &self.points;
+		let sum: f64 = points
+			.iter()
+			.zip(points.iter().cycle().skip(1))
+			.map(|(p1, p2)| p1.cross(p2))
+			.sum();
+		sum / 2.0
+	}
 }
 
 /// Calculates the cross product of the vectors `(p0 -> p1)` and `(p0 -> p2)`.
@@ -228,7 +361,7 @@ impl Ring {
 #[inline(always)]
 #[allow(dead_code)] // Reachable only through `winding_number`, which is currently dormant.
 fn cross_product(p0: &Point, p1: &Point, p2: &Point) -> f64 {
-	(p1.x - p0.x) * (p2.y - p0.y) - (p2.x - p0.x) * (p1.y - p0.y)
+	(p1 - p0).cross(&(p2 - p0))
 }
 
 impl<T> From<Vec<T>> for Ring
@@ -251,6 +384,52 @@ where
 	}
 }
 
+impl From<&[Point]> for Ring {
+	/// Creates a new [`Ring`] with copies of `points`, see [`Ring::from_points`].
+	fn from(points: &[Point]) -> Self {
+		Ring::from_points(points)
+	}
+}
+
+impl FromIterator<Point> for Ring {
+	/// Collects points into a [`Ring`], e.g. after transforming another ring's points.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::{Point, Ring};
+	/// let square = Ring::from(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+	/// let offset = Point::new(2.0, 0.0);
+	/// let shifted: Ring = square.iter().map(|p| p.clone().translated(&offset)).collect();
+	/// assert_eq!(shifted[1], Point::new(3.0, 0.0));
+	/// ```
+	fn from_iter<I: IntoIterator<Item = Point>>(points: I) -> Self {
+		Ring {
+			points: points.into_iter().collect(),
+		}
+	}
+}
+
+impl Index<usize> for Ring {
+	type Output = Point;
+
+	/// Returns the point at `index`.
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of bounds.
+	fn index(&self, index: usize) -> &Point {
+		&self.points[index]
+	}
+}
+
+impl<'a> IntoIterator for &'a Ring {
+	type Item = &'a Point;
+	type IntoIter = slice::Iter<'a, Point>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -300,6 +479,48 @@ mod tests {
 		assert_eq!(ring.points.len(), 3);
 	}
 
+	#[test]
+	fn test_ring_from_points() {
+		let points = [
+			Point::new(0.0, 0.0),
+			Point::new(2.0, 0.0),
+			Point::new(1.0, 3.0),
+		];
+		let ring = Ring::from_points(&points);
+		assert_eq!(ring.points, points);
+		assert_eq!(Ring::from(&points[..]), ring);
+		assert_eq!(points.iter().cloned().collect::<Ring>(), ring);
+		assert!(Ring::from_points(&[]).is_empty());
+	}
+
+	#[test]
+	fn test_ring_close_ignores_rounding_errors() {
+		let mut ring = Ring::from(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (1e-12, -1e-12)]);
+		ring.close();
+		assert_eq!(ring.len(), 4);
+		assert_eq!(ring.points[3], ring.points[0]);
+		// No near-zero-length closing segment.
+		assert!(ring
+			.get_segments()
+			.iter()
+			.all(|segment| segment.start.distance_to(&segment.end) > 0.5));
+
+		// The tolerance grows with the coordinates, like font units before scaling.
+		let mut ring = Ring::from(vec![(2000.0, 1000.0), (0.0, 0.0), (2000.0 + 1e-9, 1000.0)]);
+		ring.close();
+		assert_eq!(ring.len(), 3);
+		assert_eq!(ring.points[2], ring.points[0]);
+
+		// Points that are close but distinct still get a closing segment.
+		let mut ring = Ring::from(vec![(0.0, 0.0), (1.0, 0.0), (1e-3, 0.0)]);
+		ring.close();
+		assert_eq!(ring.len(), 4);
+
+		let mut ring = Ring::from(vec![(0.0, 0.0), (1.0, 0.0), (1e-3, 0.0)]);
+		ring.close_with_epsilon(1e-2);
+		assert_eq!(ring.len(), 3);
+	}
+
 	#[test]
 	fn test_ring_get_bbox() {
 		let mut ring = Ring::new();
@@ -323,6 +544,70 @@ mod tests {
 		assert_eq!(ring.points[1].as_tuple(), (4.0, 6.0));
 	}
 
+	#[test]
+	fn test_ring_snap_to_grid() {
+		let mut ring = Ring::new();
+		ring.add_point(Point::new(0.2, 0.1));
+		ring.add_point(Point::new(0.4, -0.3));
+		ring.add_point(Point::new(2.6, 1.5));
+		ring.close();
+
+		ring.snap_to_grid();
+		let points = ring.points.iter().map(Point::as_tuple).collect::<Vec<_>>();
+		assert_eq!(points, [(0.0, 0.0), (3.0, 2.0), (0.0, 0.0)]);
+	}
+
+	#[test]
+	fn test_ring_remove_collinear_points() {
+		let mut ring = Ring::from(vec![
+			(0.0, 0.0),
+			(1.0, 0.0),
+			(2.0, 0.0),
+			(2.0, 2.0),
+			(0.0, 0.0),
+		]);
+		ring.remove_collinear_points(1e-9);
+		let points = ring.points.iter().map(Point::as_tuple).collect::<Vec<_>>();
+		assert_eq!(points, [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 0.0)]);
+
+		// Points farther than `epsilon` from the line are kept, and so is the
+		// spike at (3, 0), which turns back along the line.
+		let mut ring = Ring::from(vec![(0.0, 0.0), (1.0, 0.1), (3.0, 0.0), (2.0, 0.0)]);
+		ring.remove_collinear_points(0.05);
+		assert_eq!(ring.len(), 4);
+		ring.remove_collinear_points(0.2);
+		let points = ring.points.iter().map(Point::as_tuple).collect::<Vec<_>>();
+		assert_eq!(points, [(0.0, 0.0), (3.0, 0.0), (2.0, 0.0)]);
+	}
+
+	#[test]
+	fn test_ring_remove_collinear_points_on_arc() {
+		let epsilon = 0.02;
+		let arc = (0..=180)
+			.map(|step| {
+				let angle = (step as f64 * 0.5).to_radians();
+				(100.0 * angle.cos(), 100.0 * angle.sin())
+			})
+			.collect::<Vec<_>>();
+		let mut ring = Ring::from(arc.clone());
+		ring.remove_collinear_points(epsilon);
+		assert!(ring.len() < arc.len());
+
+		// Every original point stays within `epsilon` of the simplified outline.
+		let segments = ring.get_segments();
+		let max_deviation = arc
+			.into_iter()
+			.map(|point| {
+				let point = Point::from(point);
+				segments
+					.iter()
+					.map(|segment| segment.squared_distance_to_point(&point).sqrt())
+					.fold(f64::INFINITY, f64::min)
+			})
+			.fold(0.0, f64::max);
+		assert!(max_deviation <= epsilon, "{max_deviation}");
+	}
+
 	#[test]
 	fn test_ring_scale() {
 		let mut ring = Ring::new();
@@ -473,6 +758,39 @@ mod tests {
 		assert_eq!(wn_outside, 0);
 	}
 
+	#[test]
+	fn test_ring_contains_point() {
+		let mut ring: Ring = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)].into();
+		ring.close();
+		let mut reversed: Ring = ring.iter().rev().cloned().collect::<Vec<_>>().into();
+		reversed.close();
+
+		for (point, inside) in [
+			((5.0, 5.0), true),
+			((11.0, 5.0), false),
+			((5.0, -1.0), false),
+		] {
+			let point = Point::from(point);
+			let rings = crate::geometry::Rings::from(vec![ring.clone()]);
+			assert_eq!(ring.contains_point(&point), inside, "{point:?}");
+			assert_eq!(reversed.contains_point(&point), inside, "{point:?}");
+			assert_eq!(rings.contains_point(&point), inside, "{point:?}");
+		}
+	}
+
+	#[test]
+	fn test_ring_signed_area() {
+		let ccw: Ring = vec![(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)].into();
+		assert_eq!(ccw.signed_area(), 12.0);
+
+		let mut cw: Ring = vec![(0.0, 0.0), (0.0, 3.0), (4.0, 3.0), (4.0, 0.0)].into();
+		assert_eq!(cw.signed_area(), -12.0);
+		cw.close();
+		assert_eq!(cw.signed_area(), -12.0);
+
+		assert_eq!(Ring::new().signed_area(), 0.0);
+	}
+
 	#[test]
 	fn test_cross_product_function() {
 		// Just to be explicit, though it's tested indirectly by winding_number
@@ -490,4 +808,32 @@ mod tests {
 		let p2_on_line = Point::new(0.5, 0.0);
 		assert_eq!(cross_product(&p0, &p1, &p2_on_line), 0.0);
 	}
+
+	#[test]
+	fn test_bounding_circle() {
+		let ring = Ring::from(vec![
+			(0.0, 0.0),
+			(4.0, 0.0),
+			(4.0, 2.0),
+			(1.0, 3.0),
+			(0.0, 0.0),
+		]);
+		let (center, radius) = ring.bounding_circle();
+		assert_eq!(center, Point::new(2.0, 1.5));
+		assert_eq!(radius, 2.5);
+		for point in &ring {
+			assert!(point.distance_to(&center) <= radius);
+		}
+
+		assert_eq!(Ring::new().bounding_circle(), (Point::new(0.0, 0.0), 0.0));
+	}
+
+	#[test]
+	fn test_ring_index_and_iter() {
+		let ring = Ring::from(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+		assert_eq!(ring[0], Point::new(0.0, 0.0));
+		assert_eq!(ring[2], Point::new(1.0, 1.0));
+		assert_eq!(ring.iter().map(|p| p.x).sum::<f64>(), 2.0);
+		assert_eq!((&ring).into_iter().count(), 3);
+	}
 }
@@ -7,10 +7,15 @@
 //! and point-in-polygon tests.
 
 use super::{BBox, Point, Ring, Segment};
+use std::{ops::Index, slice};
 
 /// A wrapper around multiple [`Ring`]s, enabling operations over
 /// all rings simultaneously (e.g., bounding box calculation, translation).
-#[derive(Clone, Debug, PartialEq)]
+///
+/// It is (de)serialized as an array of rings, like
+/// `[[[0, 0], [1, 0], [0, 1], [0, 0]]]`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct Rings {
 	/// The individual [`Ring`]s that compose this collection.
 	pub rings: Vec<Ring>,
@@ -28,6 +33,16 @@ impl Rings {
 		Rings { rings: Vec::new() }
 	}
 
+	/// Returns an iterator over the rings in this collection.
+	pub fn iter(&self) -> slice::Iter<'_, Ring> {
+		self.rings.iter()
+	}
+
+	/// Returns an iterator over the points of all rings, ring by ring.
+	pub fn iter_points(&self) -> impl Iterator<Item = &Point> {
+		self.rings.iter().flat_map(Ring::iter)
+	}
+
 	/// Returns the number of [`Ring`]s in this collection.
 	#[allow(dead_code)] // Public API; pairs with `is_empty` per clippy convention.
 	pub fn len(&self) -> usize {
@@ -39,6 +54,15 @@ impl Rings {
 		self.rings.is_empty()
 	}
 
+	/// Returns `true` if at least one [`Ring`] encloses a nonzero area.
+	///
+	/// Unlike [`Self::is_empty`], which only counts the rings, this is `false`
+	/// for rings that are all degenerate, like single points or lines, which
+	/// leave nothing to fill, e.g. after [`Self::snap_to_grid`].
+	pub fn has_renderable_area(&self) -> bool {
+		self.rings.iter().any(|ring| ring.signed_area() != 0.0)
+	}
+
 	/// Adds a [`Ring`] to this collection.
 	pub fn add_ring(&mut self, ring: Ring) {
 		self.rings.push(ring);
@@ -69,6 +93,17 @@ impl Rings {
 		}
 	}
 
+	/// Rounds all points in every [`Ring`] to whole pixels, see [`Ring::snap_to_grid`].
+	///
+	/// Edges then start and end on pixel boundaries, which keeps thin stems
+	/// equally sharp wherever they are placed, at the cost of slightly angular
+	/// curves.
+	pub fn snap_to_grid(&mut self) {
+		for ring in &mut self.rings {
+			ring.snap_to_grid();
+		}
+	}
+
 	/// Returns all [`Segment`]s from all [`Ring`]s in this collection.
 	///
 	/// Consecutive points in each ring form a segment, and the rings are processed in order.
@@ -97,6 +132,16 @@ impl Rings {
 		}
 		winding_number != 0
 	}
+
+	/// Computes the filled area of all [`Ring`]s, e.g. to detect near-empty glyphs.
+	///
+	/// Sums the [signed areas](Ring::signed_area), so holes (running opposite to
+	/// their outer ring, as in TrueType and CFF outlines) are subtracted. Returns
+	/// the absolute value, regardless of the direction of the outer rings.
+	#[allow(dead_code)] // Public API; not needed for rendering.
+	pub fn area(&self) -> f64 {
+		self.rings.iter().map(Ring::signed_area).sum::<f64>().abs()
+	}
 }
 
 impl<T> From<Vec<T>> for Rings
@@ -125,6 +170,28 @@ where
 	}
 }
 
+impl Index<usize> for Rings {
+	type Output = Ring;
+
+	/// Returns the ring at `index`.
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of bounds.
+	fn index(&self, index: usize) -> &Ring {
+		&self.rings[index]
+	}
+}
+
+impl<'a> IntoIterator for &'a Rings {
+	type Item = &'a Ring;
+	type IntoIter = slice::Iter<'a, Ring>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -297,4 +364,81 @@ mod tests {
 		let outside_all = Point::new(10.0, 10.0);
 		assert!(!rings.contains_point(&outside_all));
 	}
+
+	#[test]
+	fn test_area_with_hole() {
+		// A 10×10 square with a 4×4 hole running in the opposite direction.
+		let rings = Rings::from(vec![
+			vec![
+				(0.0, 0.0),
+				(10.0, 0.0),
+				(10.0, 10.0),
+				(0.0, 10.0),
+				(0.0, 0.0),
+			],
+			vec![(3.0, 3.0), (3.0, 7.0), (7.0, 7.0), (7.0, 3.0), (3.0, 3.0)],
+		]);
+		assert_eq!(rings.area(), 100.0 - 16.0);
+
+		// The result doesn't depend on the direction of the outer ring.
+		let reversed = Rings::from(
+			rings
+				.rings
+				.iter()
+				.map(|ring| ring.points.iter().rev().cloned().collect::<Vec<_>>())
+				.collect::<Vec<_>>(),
+		);
+		assert_eq!(reversed.area(), 84.0);
+		assert_eq!(Rings::new().area(), 0.0);
+	}
+
+	#[test]
+	fn test_has_renderable_area() {
+		// A ring collapsed onto a line has points, but no area.
+		let mut rings = Rings::from(vec![vec![(0.0, 0.0), (4.0, 0.0), (2.0, 0.0), (0.0, 0.0)]]);
+		assert!(!rings.is_empty());
+		assert!(!rings.has_renderable_area());
+		assert!(!Rings::new().has_renderable_area());
+
+		rings.add_ring(Ring::from(vec![
+			(0.0, 0.0),
+			(1.0, 0.0),
+			(0.0, 1.0),
+			(0.0, 0.0),
+		]));
+		assert!(rings.has_renderable_area());
+	}
+
+	#[test]
+	fn test_iter_points() {
+		let rings = Rings::from(vec![
+			vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)],
+			vec![(2.0, 2.0), (3.0, 2.0), (2.0, 2.0)],
+		]);
+		assert_eq!(rings.iter_points().count(), 7);
+		assert_eq!(rings.iter().map(Ring::len).collect::<Vec<_>>(), [4, 3]);
+		assert_eq!(rings[1][1], Point::new(3.0, 2.0));
+
+		let mut count = 0;
+		for ring in &rings {
+			for _point in ring {
+				count += 1;
+			}
+		}
+		assert_eq!(count, 7);
+	}
+
+	#[test]
+	fn test_serde_round_trip() {
+		let rings = Rings::from(vec![
+			vec![(0.0, 0.0), (1.5, 0.0), (0.0, 1.0), (0.0, 0.0)],
+			vec![(2.0, 2.0), (3.0, -2.25)],
+		]);
+		let json = serde_json::to_string(&rings).unwrap();
+		assert_eq!(
+			json,
+			"[[[0.0,0.0],[1.5,0.0],[0.0,1.0],[0.0,0.0]],[[2.0,2.0],[3.0,-2.25]]]"
+		);
+		assert_eq!(serde_json::from_str::<Rings>(&json).unwrap(), rings);
+	}
 }
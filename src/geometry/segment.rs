@@ -1,18 +1,23 @@
-//! Defines a line segment between two [`Point`] references, providing methods for projecting
+//! Defines a line segment between two [`Point`]s, providing methods for projecting
 //! external points onto the segment and computing distance metrics.
 
 use super::Point;
+use std::borrow::Cow;
 
-/// A line segment defined by references to two [`Point`]s (`start` and `end`).
+/// A line segment between two [`Point`]s (`start` and `end`).
+///
+/// The points are either borrowed, e.g. from a [`Ring`](super::Ring) without
+/// copying them, or owned, for segments that are constructed on their own
+/// and returned from functions.
 ///
 /// This structure is useful for geometric operations such as point projection
 /// and distance calculations.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Segment<'a> {
 	/// The start point of this segment.
-	pub start: &'a Point,
+	pub start: Cow<'a, Point>,
 	/// The end point of this segment.
-	pub end: &'a Point,
+	pub end: Cow<'a, Point>,
 }
 
 impl<'a> Segment<'a> {
@@ -28,7 +33,37 @@ impl<'a> Segment<'a> {
 	/// assert_eq!(segment.end.y, 4.0);
 	/// ```
 	pub fn new(start: &'a Point, end: &'a Point) -> Self {
-		Segment { start, end }
+		Segment {
+			start: Cow::Borrowed(start),
+			end: Cow::Borrowed(end),
+		}
+	}
+
+	/// Creates a new line segment that owns its points, so it doesn't borrow
+	/// from anything.
+	///
+	/// ```
+	/// # use versatiles_glyphs::geometry::Point;
+	/// # use versatiles_glyphs::geometry::Segment;
+	/// let segment = Segment::owned(Point::new(1.0, 2.0), Point::new(3.0, 4.0));
+	/// assert_eq!(segment.end.as_tuple(), (3.0, 4.0));
+	/// ```
+	#[allow(dead_code)] // Public API; the renderer borrows the points of its rings.
+	pub fn owned(start: Point, end: Point) -> Segment<'static> {
+		Segment {
+			start: Cow::Owned(start),
+			end: Cow::Owned(end),
+		}
+	}
+
+	/// Returns a copy of this segment that owns its points, e.g. to keep it
+	/// after the [`Ring`](super::Ring) it was borrowed from is dropped.
+	#[allow(dead_code)] // Public API; the renderer borrows the points of its rings.
+	pub fn into_owned(self) -> Segment<'static> {
+		Segment {
+			start: Cow::Owned(self.start.into_owned()),
+			end: Cow::Owned(self.end.into_owned()),
+		}
 	}
 
 	/// Projects a given point `p` onto this segment and returns the resulting [`Point`].
@@ -52,15 +87,16 @@ impl<'a> Segment<'a> {
 	/// assert_eq!(projected.as_tuple(), (5.0, 0.0));
 	/// ```
 	pub fn project_point_on(&self, p: &Point) -> Point {
-		let v = self.start;
-		let w = self.end;
+		let v: &Point = &self.start;
+		let w: &Point = &self.end;
 		let l2 = v.squared_distance_to(w);
 		// If segment length is zero, return the single point.
 		if l2 == 0.0 {
 			return v.clone();
 		}
 		// Parametric t for the projection on the infinite line
-		let t = ((p.x - v.x) * (w.x - v.x) + (p.y - v.y) * (w.y - v.y)) / l2;
+		let vw = w - v;
+		let t = (p - v).dot(&vw) / l2;
 		// If t < 0 or t > 1, it projects outside the segment, clamp to endpoints.
 		if t < 0.0 {
 			return v.clone();
@@ -68,7 +104,7 @@ impl<'a> Segment<'a> {
 			return w.clone();
 		}
 		// Otherwise, project onto the interior
-		Point::new(v.x + t * (w.x - v.x), v.y + t * (w.y - v.y))
+		Point::new(v.x + t * vw.x, v.y + t * vw.y)
 	}
 
 	/// Returns the squared distance from a given point `p` to this segment.
@@ -114,6 +150,40 @@ mod tests {
 		assert_eq!(segment.end.y, 4.0);
 	}
 
+	#[test]
+	fn test_segment_owned() {
+		fn horizontal(y: f64) -> Segment<'static> {
+			Segment::owned(Point::new(0.0, y), Point::new(4.0, y))
+		}
+
+		let segment = horizontal(2.0);
+		assert!(matches!(segment.start, Cow::Owned(_)));
+		assert_eq!(segment.start.as_tuple(), (0.0, 2.0));
+		assert_eq!(segment.end.as_tuple(), (4.0, 2.0));
+		assert_eq!(
+			segment.squared_distance_to_point(&Point::new(1.0, 5.0)),
+			9.0
+		);
+	}
+
+	#[test]
+	fn test_segment_into_owned() {
+		let segment = {
+			let ring = crate::geometry::Ring::from(vec![(0.0, 0.0), (3.0, 4.0)]);
+			let segments = ring.get_segments();
+			assert!(matches!(segments[0].start, Cow::Borrowed(_)));
+			segments[0].clone().into_owned()
+		};
+		assert_eq!(
+			segment,
+			Segment::owned(Point::new(0.0, 0.0), Point::new(3.0, 4.0))
+		);
+		assert_eq!(
+			segment.project_point_on(&Point::new(6.0, 8.0)).as_tuple(),
+			(3.0, 4.0)
+		);
+	}
+
 	#[test]
 	fn test_project_point_on_zero_length_segment() {
 		// If start == end, the segment is a degenerate line (a single point).
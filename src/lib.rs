@@ -5,9 +5,13 @@
 //! generating metadata for fonts, making it easier to work with multiple font files
 //! in a single pipeline.
 
+pub mod error;
 pub mod font;
 pub mod geometry;
 pub mod protobuf;
 pub mod render;
+mod render_fonts;
 pub mod utils;
 pub mod writer;
+
+pub use render_fonts::{render_fonts, RenderFontsOptions};
@@ -1,9 +1,11 @@
 //! Command-line interface for VersaTiles glyph generation.
 //!
 //! This binary provides subcommands for merging or recursively scanning
-//! font files into a directory or tar archive of glyphs.
+//! font files into a directory or tar archive of glyphs, and for inspecting
+//! fonts and glyphs.
 
 mod commands;
+mod error;
 mod font;
 mod geometry;
 mod protobuf;
@@ -32,14 +34,22 @@ enum Commands {
 	Recurse(commands::recurse::Subcommand),
 	/// Merge subcommand.
 	Debug(commands::debug::Subcommand),
+	/// Sample subcommand.
+	Sample(commands::sample::Subcommand),
+	/// Atlas subcommand.
+	Atlas(commands::atlas::Subcommand),
 }
 
 fn main() -> Result<()> {
 	let cli = Cli::parse();
 	match &cli.command {
 		Commands::Debug(args) => commands::debug::run(args, &mut std::io::stdout())?,
-		Commands::Merge(args) => commands::merge::run(args, &mut std::io::stdout())?,
+		Commands::Merge(args) => {
+			commands::merge::run(args, &mut std::io::stdin(), &mut std::io::stdout())?
+		}
 		Commands::Recurse(args) => commands::recurse::run(args, &mut std::io::stdout())?,
+		Commands::Sample(args) => commands::sample::run(args, &mut std::io::stdout())?,
+		Commands::Atlas(args) => commands::atlas::run(args)?,
 	};
 	Ok(())
 }
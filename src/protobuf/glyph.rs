@@ -12,16 +12,17 @@ pub struct PbfGlyph {
 	#[prost(uint32, required, tag = "1")]
 	pub id: u32,
 
-	/// Optional Signed Distance Field for the glyph, potentially including a
-	/// 3-pixel border to capture smoothing data.
+	/// Optional Signed Distance Field for the glyph, including a border of
+	/// [`Self::buffer`] pixels on every side (3 by default) for the halo, so it
+	/// has [`Self::bitmap_dimensions`] pixels.
 	#[prost(bytes = "vec", optional, tag = "2")]
 	pub bitmap: Option<alloc::vec::Vec<u8>>,
 
-	/// The width of the glyph bitmap, in pixels.
+	/// The width of the glyph, in pixels, without the bitmap's border.
 	#[prost(uint32, required, tag = "3")]
 	pub width: u32,
 
-	/// The height of the glyph bitmap, in pixels.
+	/// The height of the glyph, in pixels, without the bitmap's border.
 	#[prost(uint32, required, tag = "4")]
 	pub height: u32,
 
@@ -68,6 +69,64 @@ impl PbfGlyph {
 			advance,
 		}
 	}
+
+	/// Returns the padding in pixels on every side of the bitmap, derived from
+	/// the bitmap size and `width`/`height`, or [`None`] if there is no bitmap
+	/// or its size doesn't fit any padding.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use versatiles_glyphs::protobuf::PbfGlyph;
+	///
+	/// let mut glyph = PbfGlyph::empty(65, 10);
+	/// (glyph.width, glyph.height) = (2, 4);
+	/// glyph.bitmap = Some(vec![0; 8 * 10]);
+	/// assert_eq!(glyph.buffer(), Some(3));
+	/// ```
+	pub fn buffer(&self) -> Option<u32> {
+		let len = self.bitmap.as_ref()?.len() as u64;
+		let (width, height) = (self.width as u64, self.height as u64);
+		(0..)
+			.map(|buffer| (buffer, (width + 2 * buffer) * (height + 2 * buffer)))
+			.take_while(|&(_, size)| size <= len)
+			.find(|&(_, size)| size == len)
+			.map(|(buffer, _)| buffer as u32)
+	}
+
+	/// Returns the true width and height of the bitmap, including the border:
+	/// `(width + 2·buffer, height + 2·buffer)`.
+	///
+	/// The glyph format has no field for the border, so consumers that assume
+	/// MapLibre's fixed 3 pixels misplace glyphs rendered with another buffer.
+	/// This derives it from the bitmap size instead, see [`Self::buffer`].
+	/// Returns [`None`] under the same conditions.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use versatiles_glyphs::protobuf::PbfGlyph;
+	///
+	/// let mut glyph = PbfGlyph::empty(65, 10);
+	/// (glyph.width, glyph.height) = (2, 4);
+	/// glyph.bitmap = Some(vec![0; 12 * 14]);
+	/// assert_eq!(glyph.bitmap_dimensions(), Some((12, 14)));
+	/// ```
+	pub fn bitmap_dimensions(&self) -> Option<(u32, u32)> {
+		let buffer = self.buffer()?;
+		Some((self.width + 2 * buffer, self.height + 2 * buffer))
+	}
+
+	/// Returns the stored SDF bitmap, including its padding, as a grayscale
+	/// image of [`Self::bitmap_dimensions`] pixels, e.g. to write previews or
+	/// compare renderings.
+	///
+	/// Returns [`None`] for glyphs without a bitmap, like spaces.
+	#[allow(dead_code)] // Public API; the CLI never decodes bitmaps.
+	pub fn to_luma_image(&self) -> Option<image::GrayImage> {
+		let (width, height) = self.bitmap_dimensions()?;
+		image::GrayImage::from_raw(width, height, self.bitmap.clone()?)
+	}
 }
 
 impl Ord for PbfGlyph {
@@ -142,4 +201,53 @@ mod tests {
 			"PbfGlyph { id: 1, bitmap: None, width: 12, height: 24, left: 1, top: 2, advance: 10 }"
 		);
 	}
+
+	#[test]
+	fn test_to_luma_image() {
+		use crate::render::Renderer;
+		use ttf_parser::Face;
+
+		let font = include_bytes!("../../testdata/Fira Sans - Regular.ttf");
+		let face = Face::parse(font, 0).unwrap();
+		let renderer = Renderer::new_precise();
+
+		let glyph = renderer.render_glyph(&face, 65).unwrap();
+		assert_eq!(glyph.buffer(), Some(3));
+		let image = glyph.to_luma_image().unwrap();
+		assert_eq!(image.dimensions(), (glyph.width + 6, glyph.height + 6));
+		assert_eq!(image.as_raw(), glyph.bitmap.as_ref().unwrap());
+		// Row-major with the top row first, like the stored bitmap.
+		assert_eq!(image.get_pixel(0, 0).0, [glyph.bitmap.as_ref().unwrap()[0]]);
+
+		let space = renderer.render_glyph(&face, 32).unwrap();
+		assert!(space.to_luma_image().is_none());
+	}
+
+	#[test]
+	fn test_bitmap_dimensions() {
+		use crate::render::Renderer;
+		use ttf_parser::Face;
+
+		let font = include_bytes!("../../testdata/Fira Sans - Regular.ttf");
+		let face = Face::parse(font, 0).unwrap();
+
+		for buffer in [3, 0, 5, 8] {
+			let renderer = Renderer::new_precise().with_buffer(buffer);
+			let glyph = renderer.render_glyph(&face, 65).unwrap();
+			let (width, height) = glyph.bitmap_dimensions().unwrap();
+			assert_eq!(glyph.buffer(), Some(buffer));
+			assert_eq!(
+				(width, height),
+				(glyph.width + 2 * buffer, glyph.height + 2 * buffer)
+			);
+			assert_eq!(
+				glyph.bitmap.as_ref().unwrap().len() as u32,
+				width * height,
+				"buffer {buffer}"
+			);
+		}
+
+		let space = Renderer::new_precise().render_glyph(&face, 32).unwrap();
+		assert_eq!(space.bitmap_dimensions(), None);
+	}
 }
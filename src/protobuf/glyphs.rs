@@ -1,4 +1,4 @@
-use super::{fontstack::Fontstack, PbfGlyph};
+use super::{fontstack::Fontstack, PbfFontMetadata, PbfGlyph};
 use anyhow::Result;
 use prost::{alloc, Message};
 
@@ -13,6 +13,11 @@ pub struct PbfGlyphs {
 	/// corresponds to a unique font name or ID range.
 	#[prost(message, repeated, tag = "1")]
 	stacks: alloc::vec::Vec<Fontstack>,
+
+	/// Optional metrics of the font, see [`Self::set_metadata`]. The tag lies
+	/// in the extension range of the `glyphs` message.
+	#[prost(message, optional, tag = "16")]
+	metadata: Option<PbfFontMetadata>,
 }
 
 impl PbfGlyphs {
@@ -28,6 +33,7 @@ impl PbfGlyphs {
 	pub fn new(name: String, range: String) -> Self {
 		Self {
 			stacks: vec![Fontstack::new(name, range)],
+			metadata: None,
 		}
 	}
 
@@ -45,6 +51,58 @@ impl PbfGlyphs {
 		self.stacks[0].glyphs.push(glyph);
 	}
 
+	/// Returns the fontstack name, e.g. `"Fira Sans Regular"`.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use versatiles_glyphs::protobuf::PbfGlyphs;
+	///
+	/// let pbf = PbfGlyphs::new("MyFont".to_string(), "0-255".to_string());
+	/// assert_eq!(pbf.name(), Some("MyFont"));
+	/// ```
+	#[allow(dead_code)] // Public API; the CLI only writes fontstacks.
+	pub fn name(&self) -> Option<&str> {
+		self.stacks.first().map(|stack| stack.name.as_str())
+	}
+
+	/// Attaches the metrics of the font, so the block describes itself.
+	///
+	/// Decoders that don't know the field, like map renderers, ignore it.
+	pub fn set_metadata(&mut self, metadata: PbfFontMetadata) {
+		self.metadata = Some(metadata);
+	}
+
+	/// Returns the metrics of the font, if the block has them, see [`Self::set_metadata`].
+	#[allow(dead_code)] // Public API; the CLI only writes metadata.
+	pub fn metadata(&self) -> Option<&PbfFontMetadata> {
+		self.metadata.as_ref()
+	}
+
+	/// Decodes a protobuf-encoded `.pbf` glyph block, like one written by [`Self::into_vec`].
+	///
+	/// Re-encoding the result with [`Self::into_vec`] yields the original bytes
+	/// for every block this crate writes.
+	///
+	/// # Errors
+	///
+	/// Returns an [`anyhow::Error`] if `data` isn't a valid `glyphs` message.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use versatiles_glyphs::protobuf::{PbfGlyph, PbfGlyphs};
+	///
+	/// let mut pbf = PbfGlyphs::new("MyFont".to_string(), "0-255".to_string());
+	/// pbf.push(PbfGlyph::empty(42, 12));
+	/// let bytes = pbf.clone().into_vec().unwrap();
+	///
+	/// assert_eq!(PbfGlyphs::from_slice(&bytes).unwrap(), pbf);
+	/// ```
+	pub fn from_slice(data: &[u8]) -> Result<Self> {
+		Ok(Self::decode(data)?)
+	}
+
 	/// Consumes this instance, returning a protobuf-encoded representation
 	/// of the underlying data in a `Vec<u8>`.
 	///
@@ -80,6 +138,7 @@ impl From<Fontstack> for PbfGlyphs {
 	fn from(stack: Fontstack) -> Self {
 		PbfGlyphs {
 			stacks: vec![stack],
+			metadata: None,
 		}
 	}
 }
@@ -87,6 +146,10 @@ impl From<Fontstack> for PbfGlyphs {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{
+		font::{FontFileEntry, FontWrapper},
+		render::Renderer,
+	};
 
 	#[test]
 	fn test_glyphs_new() {
@@ -97,7 +160,7 @@ mod tests {
 
 		assert_eq!(
 			format!("{decoded_glyphs:?}"),
-			"PbfGlyphs { stacks: [Fontstack { name: \"TestFont\", range: \"0-255\", glyphs: [] }] }"
+			"PbfGlyphs { stacks: [Fontstack { name: \"TestFont\", range: \"0-255\", glyphs: [] }], metadata: None }"
 		);
 	}
 
@@ -135,6 +198,22 @@ mod tests {
 		let fs = &decoded.stacks[0];
 		assert_eq!(fs.glyphs[0], glyph_a);
 		assert_eq!(fs.glyphs[1], glyph_b);
-		assert_eq!(format!("{decoded:?}"), "PbfGlyphs { stacks: [Fontstack { name: \"MultiStack\", range: \"100-200\", glyphs: [PbfGlyph { id: 100, bitmap: Some([10, 20]), width: 15, height: 20, left: -2, top: 5, advance: 16 }, PbfGlyph { id: 101, bitmap: None, width: 9, height: 10, left: 0, top: 2, advance: 11 }] }] }");
+		assert_eq!(format!("{decoded:?}"), "PbfGlyphs { stacks: [Fontstack { name: \"MultiStack\", range: \"100-200\", glyphs: [PbfGlyph { id: 100, bitmap: Some([10, 20]), width: 15, height: 20, left: -2, top: 5, advance: 16 }, PbfGlyph { id: 101, bitmap: None, width: 9, height: 10, left: 0, top: 2, advance: 11 }] }], metadata: None }");
+	}
+
+	#[test]
+	fn test_decode_encode_is_byte_stable() -> Result<()> {
+		let font = FontWrapper::from(FontFileEntry::new(
+			include_bytes!("../../testdata/Fira Sans - Regular.ttf").to_vec(),
+		)?);
+		let blocks = font.get_blocks();
+		let block = blocks.iter().find(|b| b.start_index == 256).unwrap();
+		let original = block.render(String::from("Fira Sans Regular"), &Renderer::new_precise())?;
+
+		let decoded = PbfGlyphs::from_slice(&original)?;
+		assert_eq!(decoded.name(), Some("Fira Sans Regular"));
+		assert_eq!(decoded.clone().into_glyphs().len(), block.len());
+		assert_eq!(decoded.into_vec()?, original);
+		Ok(())
 	}
 }
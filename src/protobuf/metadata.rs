@@ -0,0 +1,43 @@
+use prost::{alloc, Message};
+
+/// Metrics of the font a glyph block was rendered from, so the block describes
+/// itself, see [`PbfGlyphs::set_metadata`](super::PbfGlyphs::set_metadata).
+///
+/// This isn't part of the Mapbox glyphs format. It is stored in the extension
+/// range of the `glyphs` message, so existing decoders skip it.
+#[derive(Clone, PartialEq, Message, Eq)]
+pub struct PbfFontMetadata {
+	/// The font family, e.g. `"Fira Sans"`.
+	#[prost(string, required, tag = "1")]
+	pub family: alloc::string::String,
+
+	/// The size the glyphs were rendered at, in pixels per EM.
+	#[prost(uint32, required, tag = "2")]
+	pub size: u32,
+
+	/// The number of font units per EM of the font.
+	#[prost(uint32, required, tag = "3")]
+	pub units_per_em: u32,
+
+	/// The ascender of the font in font units, the distance from the baseline
+	/// to the top of the line.
+	#[prost(sint32, required, tag = "4")]
+	pub ascender: i32,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_metadata_round_trip() {
+		let metadata = PbfFontMetadata {
+			family: "Fira Sans".to_string(),
+			size: 24,
+			units_per_em: 1000,
+			ascender: 935,
+		};
+		let decoded = PbfFontMetadata::decode(&metadata.encode_to_vec()[..]).unwrap();
+		assert_eq!(decoded, metadata);
+	}
+}
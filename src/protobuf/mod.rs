@@ -6,6 +6,8 @@
 mod fontstack;
 mod glyph;
 mod glyphs;
+mod metadata;
 
 pub use glyph::PbfGlyph;
 pub use glyphs::PbfGlyphs;
+pub use metadata::PbfFontMetadata;
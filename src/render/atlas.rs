@@ -0,0 +1,282 @@
+//! Packs rendered SDF glyphs into a single texture, a glyph atlas (or sprite
+//! sheet), for renderers that read one image instead of `.pbf` glyph blocks.
+
+use crate::protobuf::PbfGlyph;
+use anyhow::{ensure, Result};
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use std::io::Write;
+
+/// Pixels left empty between neighbouring glyphs, so texture filtering doesn't
+/// blend them.
+const PADDING: u32 = 1;
+
+/// Position and metrics of one glyph in an [`Atlas`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AtlasGlyph {
+	/// Codepoint of the glyph.
+	pub id: u32,
+	/// Left edge of the glyph's bitmap in the texture.
+	pub x: u32,
+	/// Top edge of the glyph's bitmap in the texture.
+	pub y: u32,
+	/// Width of the bitmap, including the buffer on both sides. `0` for glyphs
+	/// without a bitmap, like spaces.
+	pub width: u32,
+	/// Height of the bitmap, including the buffer on both sides.
+	pub height: u32,
+	/// Padding in pixels around the content area, see [`PbfGlyph::buffer`].
+	pub buffer: u32,
+	/// Horizontal offset of the content area, like [`PbfGlyph::left`].
+	pub left: i32,
+	/// Vertical offset of the content area, like [`PbfGlyph::top`].
+	pub top: i32,
+	/// Horizontal advance, like [`PbfGlyph::advance`].
+	pub advance: u32,
+}
+
+/// A grayscale texture with the SDF bitmaps of many glyphs, and where each
+/// glyph is in it.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct Atlas {
+	/// Width of the texture in pixels.
+	pub width: u32,
+	/// Height of the texture in pixels.
+	pub height: u32,
+	/// One byte per pixel, row by row from the top.
+	#[serde(skip)]
+	pub bitmap: Vec<u8>,
+	/// The glyphs in the texture, sorted by codepoint, then by input order.
+	pub glyphs: Vec<AtlasGlyph>,
+}
+
+impl Atlas {
+	/// Packs the bitmaps of `glyphs`, including their buffers, into one texture.
+	///
+	/// Glyphs are placed on shelves, tallest first, in a texture as wide as the
+	/// next power of two of the square root of their total area, so it's about
+	/// square. Glyphs without a bitmap get an empty rectangle, but keep their
+	/// metrics. Glyphs that share a codepoint each get their own rectangle.
+	pub fn pack(glyphs: &[PbfGlyph]) -> Self {
+		// Each entry keeps the index of its glyph, so ids don't have to be unique.
+		let mut entries = glyphs
+			.iter()
+			.enumerate()
+			.map(|(index, glyph)| {
+				let buffer = glyph.buffer().unwrap_or(0);
+				let (width, height) = glyph.bitmap_dimensions().unwrap_or((0, 0));
+				let entry = AtlasGlyph {
+					id: glyph.id,
+					x: 0,
+					y: 0,
+					width,
+					height,
+					buffer,
+					left: glyph.left,
+					top: glyph.top,
+					advance: glyph.advance,
+				};
+				(index, entry)
+			})
+			.collect::<Vec<_>>();
+
+		let area = entries
+			.iter()
+			.filter(|(_, e)| e.width > 0)
+			.map(|(_, e)| ((e.width + PADDING) * (e.height + PADDING)) as f64)
+			.sum::<f64>();
+		let widest = entries.iter().map(|(_, e)| e.width).max().unwrap_or(0);
+		let width = widest.max(area.sqrt().ceil() as u32).next_power_of_two();
+
+		// Shelf packing: fill rows from left to right, tallest glyphs first.
+		entries.sort_by_key(|(_, e)| (std::cmp::Reverse(e.height), e.id));
+		let (mut x, mut y, mut shelf) = (0, 0, 0);
+		for (_, entry) in entries.iter_mut().filter(|(_, e)| e.width > 0) {
+			if x > 0 && x + entry.width > width {
+				y += shelf + PADDING;
+				(x, shelf) = (0, 0);
+			}
+			(entry.x, entry.y) = (x, y);
+			x += entry.width + PADDING;
+			shelf = shelf.max(entry.height);
+		}
+		let height = y + shelf;
+		let width = if height == 0 { 0 } else { width };
+
+		let mut bitmap = vec![0; (width * height) as usize];
+		for (entry, data) in entries
+			.iter()
+			.filter(|(_, e)| e.width > 0)
+			.filter_map(|&(index, ref e)| Some((e, glyphs[index].bitmap.as_ref()?)))
+		{
+			for (row, pixels) in data.chunks(entry.width as usize).enumerate() {
+				let start = ((entry.y as usize + row) * width as usize) + entry.x as usize;
+				bitmap[start..start + pixels.len()].copy_from_slice(pixels);
+			}
+		}
+
+		entries.sort_by_key(|&(index, ref e)| (e.id, index));
+		Atlas {
+			width,
+			height,
+			bitmap,
+			glyphs: entries.into_iter().map(|(_, e)| e).collect(),
+		}
+	}
+
+	/// Encodes the texture as an 8-bit grayscale PNG.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the atlas has no pixels, since a PNG can't be empty.
+	pub fn to_png(&self) -> Result<Vec<u8>> {
+		ensure!(
+			self.width > 0 && self.height > 0,
+			"the atlas is empty, none of the glyphs has a bitmap"
+		);
+		let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+		let mut header = Vec::with_capacity(13);
+		header.extend(self.width.to_be_bytes());
+		header.extend(self.height.to_be_bytes());
+		// Bit depth 8, grayscale, deflate, standard filters, no interlacing.
+		header.extend([8, 0, 0, 0, 0]);
+		write_chunk(&mut png, b"IHDR", &header);
+
+		// Every row starts with its filter type, 0 for none.
+		let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+		for row in self.bitmap.chunks(self.width as usize) {
+			encoder.write_all(&[0])?;
+			encoder.write_all(row)?;
+		}
+		write_chunk(&mut png, b"IDAT", &encoder.finish()?);
+		write_chunk(&mut png, b"IEND", &[]);
+		Ok(png)
+	}
+
+	/// Encodes the size of the texture and every glyph's rectangle and metrics
+	/// as pretty-printed JSON.
+	pub fn to_json(&self) -> Result<Vec<u8>> {
+		Ok(serde_json::to_vec_pretty(self)?)
+	}
+}
+
+/// Appends a PNG chunk: its length, `kind`, `data` and the CRC of both.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+	png.extend((data.len() as u32).to_be_bytes());
+	png.extend(kind);
+	png.extend(data);
+	let mut crc = Crc::new();
+	crc.update(kind);
+	crc.update(data);
+	png.extend(crc.sum().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::render::Renderer;
+	use flate2::read::ZlibDecoder;
+	use std::io::Read;
+	use ttf_parser::Face;
+
+	const TEST_FONT: &[u8] = include_bytes!("../../testdata/Fira Sans - Regular.ttf");
+
+	fn render(text: &str) -> Vec<PbfGlyph> {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		Renderer::new_precise().render_glyphs_for_string(&face, text)
+	}
+
+	#[test]
+	fn test_pack() -> Result<()> {
+		let glyphs = render("Hello, World! gj@");
+		let atlas = Atlas::pack(&glyphs);
+		assert!(atlas.width.is_power_of_two());
+		assert_eq!(atlas.bitmap.len() as u32, atlas.width * atlas.height);
+
+		let json: serde_json::Value = serde_json::from_slice(&atlas.to_json()?)?;
+		assert_eq!(json["width"], atlas.width);
+		let entries: Vec<AtlasGlyph> = serde_json::from_value(json["glyphs"].clone())?;
+		assert_eq!(entries, atlas.glyphs);
+		assert_eq!(entries.len(), glyphs.len());
+
+		// Every rectangle is inside the texture, doesn't overlap any other, and
+		// holds the glyph's bitmap.
+		let mut sorted = glyphs.clone();
+		sorted.sort_by_key(|g| g.id);
+		let rects = entries
+			.iter()
+			.zip(&sorted)
+			.filter(|(e, _)| e.width > 0)
+			.collect::<Vec<_>>();
+		assert_eq!(rects.len(), glyphs.len() - 1, "all but the space");
+		for (i, &(a, glyph)) in rects.iter().enumerate() {
+			assert!(a.x + a.width <= atlas.width && a.y + a.height <= atlas.height);
+			for (b, _) in &rects[i + 1..] {
+				let apart = a.x + a.width <= b.x
+					|| b.x + b.width <= a.x
+					|| a.y + a.height <= b.y
+					|| b.y + b.height <= a.y;
+				assert!(apart, "{a:?} overlaps {b:?}");
+			}
+
+			assert_eq!(a.id, glyph.id);
+			assert_eq!(
+				(a.left, a.top, a.advance),
+				(glyph.left, glyph.top, glyph.advance)
+			);
+			let first_row = (a.y * atlas.width + a.x) as usize;
+			assert_eq!(
+				atlas.bitmap[first_row..first_row + a.width as usize],
+				glyph.bitmap.as_ref().unwrap()[..a.width as usize]
+			);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_pack_duplicate_ids() {
+		let mut glyphs = render("Aj");
+		glyphs[1].id = glyphs[0].id;
+		let atlas = Atlas::pack(&glyphs);
+
+		// Both glyphs keep their own bitmap, in their original order.
+		assert_eq!(atlas.glyphs.len(), 2);
+		for (entry, glyph) in atlas.glyphs.iter().zip(&glyphs) {
+			assert_eq!(
+				(entry.width, entry.height),
+				glyph.bitmap_dimensions().unwrap()
+			);
+			let first_row = (entry.y * atlas.width + entry.x) as usize;
+			assert_eq!(
+				atlas.bitmap[first_row..first_row + entry.width as usize],
+				glyph.bitmap.as_ref().unwrap()[..entry.width as usize]
+			);
+		}
+	}
+
+	#[test]
+	fn test_to_png() -> Result<()> {
+		let atlas = Atlas::pack(&render("Ag"));
+		let png = atlas.to_png()?;
+		assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+		assert_eq!(&png[12..16], b"IHDR");
+		assert_eq!(png[16..20], atlas.width.to_be_bytes());
+		assert_eq!(png[20..24], atlas.height.to_be_bytes());
+		assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+
+		// The pixel data decodes to the bitmap, with a filter byte per row.
+		let length = u32::from_be_bytes(png[33..37].try_into()?) as usize;
+		assert_eq!(&png[37..41], b"IDAT");
+		let mut pixels = Vec::new();
+		ZlibDecoder::new(&png[41..41 + length]).read_to_end(&mut pixels)?;
+		let rows = pixels
+			.chunks(atlas.width as usize + 1)
+			.inspect(|row| assert_eq!(row[0], 0))
+			.flat_map(|row| row[1..].to_vec())
+			.collect::<Vec<_>>();
+		assert_eq!(rows, atlas.bitmap);
+
+		assert!(Atlas::pack(&render(" ")).to_png().is_err());
+		Ok(())
+	}
+}
@@ -46,15 +46,35 @@
 //! `CUTOFF` = `0.25 * 256` is the SDF zero-crossing offset: the byte value
 //! `192 = 256 - 64` corresponds to "exactly on the outline", with values below
 //! falling off into the buffer and values above representing the interior.
+//!
+//! # Scaling the radius with the size
+//!
+//! `SDF_RADIUS` and `CUTOFF` are tuned for the default size of 24 pixels. At
+//! other sizes the halo keeps its width in pixels, so it gets relatively
+//! thinner on larger glyphs. With [`RenderOptions::auto_tune`] the radius
+//! scales with the size:
+//!
+//! ```text
+//! radius = SDF_RADIUS · size / 24
+//! value  = 255 − (distance · 256 / radius + CUTOFF)
+//! ```
+//!
+//! `CUTOFF` is a quarter of the radius, so the zero-crossing offset in
+//! pixels (`radius / 4`) scales along with it, while its byte value stays the
+//! same. A glyph at size 48 then has the same SDF as one at size 24, sampled
+//! twice as densely. [`RenderOptions::sdf_radius`] overrides the radius.
 
-/// Glyph height in pixels per EM. The renderer scales every outline so this
-/// many pixels represent one EM unit before flattening to the integer grid.
+/// Default glyph height in pixels per EM. The renderer scales every outline so
+/// this many pixels represent one EM unit before flattening to the integer grid.
+///
+/// Can be overridden per renderer with [`Renderer::with_size`].
 const GLYPH_SIZE: i32 = 24;
 
-/// Pixels of padding on every side of the glyph content area.
+/// Default pixels of padding on every side of the glyph content area.
 ///
 /// See the module-level docs for the relationship between this constant and
-/// the SDF gradient radius ([`SDF_RADIUS`]).
+/// the SDF gradient radius ([`SDF_RADIUS`]). Can be overridden per renderer
+/// with [`Renderer::with_buffer`].
 const BUFFER: i32 = 3;
 
 /// Maximum SDF gradient radius in pixels. `renderer_precise` computes signed
@@ -65,8 +85,20 @@ const BUFFER: i32 = 3;
 const SDF_RADIUS: f64 = 8.0;
 
 /// SDF zero-crossing offset, in the 0..=255 byte range used by the bitmap.
+/// This is a quarter of the radius, whatever the radius is.
 const CUTOFF: f64 = 0.25 * 256.0;
 
+/// Width in pixels of the widest halo that `--stats` expects clients to draw
+/// around a glyph. A glyph counts as clipped if its buffer can't hold a halo
+/// this wide, see [`RenderResult::is_clipped`].
+#[cfg(feature = "profiling")]
+const HALO_WIDTH: f64 = 2.0;
+
+mod atlas;
+mod options;
+#[cfg(feature = "profiling")]
+mod profiling;
+mod recorder;
 mod renderer;
 mod renderer_dummy;
 mod renderer_precise;
@@ -74,5 +106,17 @@ mod result;
 mod ring_builder;
 mod rtree_segments;
 
+pub use atlas::Atlas;
+#[allow(unused_imports)]
+pub use atlas::AtlasGlyph;
+pub use options::RenderOptions;
+#[cfg(feature = "profiling")]
+#[allow(unused_imports)]
+pub use profiling::{GlyphTiming, Profiler};
+#[allow(unused_imports)]
+pub use recorder::RenderRecorder;
+pub(crate) use renderer::glyph_advance;
 pub use renderer::Renderer;
+#[allow(unused_imports)]
+pub use renderer::RendererBuilder;
 pub use result::RenderResult;
@@ -0,0 +1,93 @@
+use super::{BUFFER, GLYPH_SIZE, SDF_RADIUS};
+use crate::error::GlyphError;
+use anyhow::anyhow;
+
+/// Settings that control how a [`Renderer`](super::Renderer) draws glyphs.
+///
+/// Build a renderer from them with [`RendererBuilder`](super::RendererBuilder).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+	/// Glyph height in pixels per EM. Defaults to 24.
+	pub size: u32,
+	/// Padding in pixels on every side of the glyph content area. Defaults to 3.
+	///
+	/// Note that maplibre/mapbox consumers assume a buffer of 3 pixels.
+	pub buffer: u32,
+	/// Offset of the baseline in pixels, relative to the default shift of `size`
+	/// pixels, which places the standard alphabetic baseline at the origin.
+	/// Positive values lower the glyphs. Defaults to 0.
+	pub baseline: i32,
+	/// Exponent applied to the normalized SDF values before quantization.
+	/// Defaults to `1.0`, the linear mapping. Must be finite and greater than
+	/// 0, see [`Self::validate`].
+	pub gamma: f64,
+	/// Whether to round the scaled outline to whole pixels before the SDF pass.
+	///
+	/// Outlines are unhinted, so a thin stem at a small size may cover two
+	/// pixels partially instead of one fully. Snapping aligns the edges with the
+	/// pixel grid, which keeps stems consistent, but makes curves slightly
+	/// angular. This is a simple alternative to hinting. Defaults to `false`.
+	pub grid_snap: bool,
+	/// Whether to trim the bitmap rows and columns that lie entirely outside
+	/// the halo.
+	///
+	/// The content area is the integer cell around the outline, so the outline
+	/// can sit up to 1 pixel inside each edge, and the outermost buffer pixels
+	/// can be farther than `buffer` pixels from it. Trimming those shrinks the
+	/// bitmap without changing how clients draw the glyph or its halo, since
+	/// the buffer stays the same. Defaults to `false`.
+	pub tight: bool,
+	/// Whether to scale the SDF radius with `size`, so glyphs of every size
+	/// have the same halo relative to the EM, see [`Self::radius`]. Defaults to
+	/// `false`, which keeps the radius of 8 pixels tuned for size 24.
+	pub auto_tune: bool,
+	/// SDF radius in pixels. Overrides both the default and `auto_tune`.
+	pub sdf_radius: Option<f64>,
+}
+
+impl Default for RenderOptions {
+	fn default() -> Self {
+		RenderOptions {
+			size: GLYPH_SIZE as u32,
+			buffer: BUFFER as u32,
+			baseline: 0,
+			gamma: 1.0,
+			grid_snap: false,
+			tight: false,
+			auto_tune: false,
+			sdf_radius: None,
+		}
+	}
+}
+
+impl RenderOptions {
+	/// Checks that the options can be rendered with.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `gamma` isn't finite and greater than 0: `0^0 = 1`
+	/// would fill every pixel outside the halo, and NaN would clear them all.
+	pub fn validate(&self) -> Result<(), GlyphError> {
+		if !(self.gamma.is_finite() && self.gamma > 0.0) {
+			return Err(GlyphError::Other(anyhow!(
+				"gamma must be finite and greater than 0, got {}",
+				self.gamma
+			)));
+		}
+		Ok(())
+	}
+
+	/// Returns the SDF radius in pixels: `sdf_radius` if set, otherwise
+	/// `8 · size / 24` with `auto_tune` and `8` without.
+	///
+	/// The zero-crossing offset `CUTOFF` is a quarter of the radius, so it
+	/// scales along with it: the outline stays at the byte value 191, and the
+	/// halo keeps its shape relative to the glyph.
+	pub fn radius(&self) -> f64 {
+		match self.sdf_radius {
+			Some(radius) => radius,
+			None if self.auto_tune => SDF_RADIUS * self.size as f64 / GLYPH_SIZE as f64,
+			None => SDF_RADIUS,
+		}
+	}
+}
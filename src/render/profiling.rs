@@ -0,0 +1,172 @@
+//! Per-glyph render timings and the number of clipped glyphs, collected with
+//! the `profiling` feature.
+//!
+//! Without the feature, this module isn't compiled and rendering doesn't
+//! measure anything.
+
+use std::{
+	collections::HashMap,
+	fmt::Write,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	time::Duration,
+};
+
+/// The time it took to render a single glyph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphTiming {
+	/// Fontstack name of the font, e.g. "Fira Sans Regular".
+	pub font: String,
+	/// Codepoint of the glyph.
+	pub codepoint: u32,
+	/// Time spent rendering the glyph.
+	pub duration: Duration,
+}
+
+/// Collects [`GlyphTiming`]s from all render threads.
+///
+/// Clones share the same timings, so every copy of a
+/// [`Renderer`](super::Renderer) records into the same profiler.
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+	timings: Arc<Mutex<Vec<GlyphTiming>>>,
+	/// Number of glyphs whose SDF was cut off by the buffer.
+	clipped: Arc<AtomicUsize>,
+}
+
+impl Profiler {
+	/// Records the render time of a single glyph.
+	pub fn record(&self, font: &str, codepoint: u32, duration: Duration) {
+		self.timings.lock().unwrap().push(GlyphTiming {
+			font: font.to_string(),
+			codepoint,
+			duration,
+		});
+	}
+
+	/// Counts a glyph whose SDF was cut off by the buffer, see
+	/// [`RenderResult::is_clipped`](super::RenderResult::is_clipped).
+	pub fn record_clipped(&self) {
+		self.clipped.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Returns the number of glyphs whose SDF was cut off by the buffer.
+	pub fn clipped(&self) -> usize {
+		self.clipped.load(Ordering::Relaxed)
+	}
+
+	/// Returns a copy of all timings recorded so far.
+	pub fn timings(&self) -> Vec<GlyphTiming> {
+		self.timings.lock().unwrap().clone()
+	}
+
+	/// Formats a report of the `n` slowest glyphs, the total render time per font
+	/// and the number of clipped glyphs, if any.
+	pub fn report(&self, n: usize) -> String {
+		let mut timings = self.timings();
+		timings.sort_by_key(|timing| std::cmp::Reverse(timing.duration));
+
+		let mut totals = HashMap::<&str, Duration>::new();
+		for timing in &timings {
+			*totals.entry(&timing.font).or_default() += timing.duration;
+		}
+		let mut totals = totals.into_iter().collect::<Vec<_>>();
+		totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+		let mut report = format!(
+			"Slowest {} of {} glyphs:\n",
+			n.min(timings.len()),
+			timings.len()
+		);
+		for timing in timings.iter().take(n) {
+			let GlyphTiming {
+				font,
+				codepoint,
+				duration,
+			} = timing;
+			writeln!(report, "  U+{codepoint:04X} {font}: {duration:.2?}").unwrap();
+		}
+		report.push_str("Render time per font:\n");
+		for (font, duration) in totals {
+			writeln!(report, "  {font}: {duration:.2?}").unwrap();
+		}
+		let clipped = self.clipped();
+		if clipped > 0 {
+			writeln!(
+				report,
+				"Warning: the buffer cuts off the SDF of {clipped} glyphs, increase it to keep the whole halo"
+			)
+			.unwrap();
+		}
+		report
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		font::{FontFileEntry, FontWrapper},
+		render::Renderer,
+	};
+
+	#[test]
+	fn test_render_block_records_timings() {
+		let font = FontWrapper::from(
+			FontFileEntry::new(include_bytes!("../../testdata/Fira Sans - Regular.ttf").to_vec())
+				.unwrap(),
+		);
+		let blocks = font.get_blocks();
+		let block = blocks.iter().find(|b| b.start_index == 0).unwrap();
+
+		let renderer = Renderer::new_dummy();
+		block
+			.render(String::from("Fira"), &renderer.clone())
+			.unwrap();
+
+		let timings = renderer.profiler().timings();
+		assert_eq!(timings.len(), block.len());
+		assert!(timings.iter().all(|t| t.font == "Fira"));
+		assert!(timings.iter().any(|t| t.codepoint == 65));
+
+		let report = renderer.profiler().report(3);
+		assert!(report.starts_with(&format!("Slowest 3 of {} glyphs:\n", block.len())));
+		assert!(report.contains("Render time per font:\n  Fira: "));
+	}
+
+	#[test]
+	fn test_render_counts_clipped_glyphs() {
+		let face =
+			ttf_parser::Face::parse(include_bytes!("../../testdata/Fira Sans - Regular.ttf"), 0)
+				.unwrap();
+		let clipped = |buffer: u32| {
+			let renderer = Renderer::builder().buffer(buffer).build();
+			renderer.render_glyph(&face, 65).unwrap();
+			renderer.render_glyph(&face, 32).unwrap();
+			renderer.profiler().clipped()
+		};
+
+		// The default buffer holds a halo of 2 pixels, a smaller one cuts it
+		// off. The space has no bitmap to clip.
+		assert_eq!(clipped(1), 1);
+		assert_eq!(clipped(2), 1);
+		assert_eq!(clipped(3), 0);
+		assert_eq!(clipped(7), 0);
+
+		let renderer = Renderer::builder().buffer(1).build();
+		renderer.render_glyph(&face, 65).unwrap();
+		assert!(renderer.profiler().report(1).ends_with(
+			"Warning: the buffer cuts off the SDF of 1 glyphs, increase it to keep the whole halo\n"
+		));
+	}
+
+	#[test]
+	fn test_report_without_timings() {
+		assert_eq!(
+			Profiler::default().report(10),
+			"Slowest 0 of 0 glyphs:\nRender time per font:\n"
+		);
+	}
+}
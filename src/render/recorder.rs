@@ -0,0 +1,50 @@
+//! Records which glyphs a renderer was asked to render, to test the block
+//! enumeration and filtering without depending on SDF bytes.
+
+use std::{
+	collections::BTreeSet,
+	sync::{Arc, Mutex},
+};
+
+/// Collects the `(font, codepoint)` pairs of all rendered glyphs from all
+/// render threads.
+///
+/// Clones share the same calls, so every copy of a
+/// [`Renderer`](super::Renderer) records into the same recorder.
+#[derive(Clone, Debug, Default)]
+pub struct RenderRecorder {
+	calls: Arc<Mutex<Vec<(String, u32)>>>,
+}
+
+impl RenderRecorder {
+	/// Records that the glyph `codepoint` of the fontstack `font`, e.g.
+	/// "Fira Sans Regular", was rendered.
+	pub fn record(&self, font: &str, codepoint: u32) {
+		self
+			.calls
+			.lock()
+			.unwrap()
+			.push((font.to_string(), codepoint));
+	}
+
+	/// Returns all recorded `(font, codepoint)` pairs, sorted, since blocks
+	/// may be rendered in any order.
+	#[allow(dead_code)] // Public API; for tests.
+	pub fn calls(&self) -> Vec<(String, u32)> {
+		let mut calls = self.calls.lock().unwrap().clone();
+		calls.sort_unstable();
+		calls
+	}
+
+	/// Returns the distinct codepoints rendered for any font.
+	#[allow(dead_code)] // Public API; for tests.
+	pub fn codepoints(&self) -> BTreeSet<u32> {
+		self
+			.calls
+			.lock()
+			.unwrap()
+			.iter()
+			.map(|(_, codepoint)| *codepoint)
+			.collect()
+	}
+}
@@ -1,12 +1,17 @@
 use super::{
-	renderer_dummy::renderer_dummy, renderer_precise::renderer_precise, ring_builder::RingBuilder,
-	RenderResult, BUFFER, GLYPH_SIZE,
+	renderer_dummy::renderer_dummy,
+	renderer_precise::{renderer_precise, sdf_value},
+	ring_builder::RingBuilder,
+	RenderOptions, RenderRecorder, RenderResult,
 };
 use crate::{
+	error::GlyphError,
+	font::FeatureSubstitution,
 	geometry::{Point, Rings},
 	protobuf::PbfGlyph,
 };
-use ttf_parser::Face;
+use std::collections::HashSet;
+use ttf_parser::{Face, GlyphId};
 
 #[derive(Debug, Clone)]
 enum RendererMode {
@@ -18,35 +23,128 @@ enum RendererMode {
 /// A renderer for creating signed distance fields (SDF) from glyph outlines.
 pub struct Renderer {
 	mode: RendererMode,
+	options: RenderOptions,
+	/// Collects the rendered glyphs, see [`RendererBuilder::record`].
+	recorder: Option<RenderRecorder>,
+	/// Collects the render time of every glyph.
+	#[cfg(feature = "profiling")]
+	profiler: super::Profiler,
+	/// Codepoint that fails to render, see [`RendererBuilder::fail_on`].
+	#[cfg(test)]
+	fail_on: Option<u32>,
 }
 
 impl Renderer {
 	/// Creates a new renderer with the specified mode.
 	pub fn new(dummy: bool) -> Self {
-		if dummy {
-			Renderer::new_dummy()
-		} else {
-			Renderer::new_precise()
-		}
+		Renderer::builder().dummy(dummy).build()
 	}
 	/// Creates a new renderer with the precise mode.
+	#[allow(dead_code)] // Public API; the CLI uses `Renderer::new`.
 	pub fn new_precise() -> Self {
-		Renderer {
-			mode: RendererMode::Precise,
-		}
+		Renderer::builder().build()
 	}
 	/// Creates a new renderer with the dummy mode. This mode generates empty bitmaps and is used for testing.
+	#[allow(dead_code)] // Public API; the CLI uses `Renderer::new`.
 	pub fn new_dummy() -> Self {
-		Renderer {
-			mode: RendererMode::Dummy,
-		}
+		Renderer::builder().dummy(true).build()
+	}
+
+	/// Creates a new renderer with the dummy mode that records every rendered
+	/// glyph, see [`Self::recorder`]. This is used for testing which glyphs are
+	/// rendered.
+	#[allow(dead_code)] // Public API; for tests.
+	pub fn new_recording() -> Self {
+		Renderer::builder().dummy(true).record(true).build()
+	}
+
+	/// Creates a new renderer with the dummy mode that fails to render `codepoint`,
+	/// to test how render errors are handled.
+	#[cfg(test)]
+	pub fn new_failing(codepoint: u32) -> Self {
+		Renderer::builder().dummy(true).fail_on(codepoint).build()
+	}
+
+	/// Returns a [`RendererBuilder`] with the default [`RenderOptions`] and the precise mode.
+	pub fn builder() -> RendererBuilder {
+		RendererBuilder::default()
+	}
+
+	/// Returns the options this renderer draws glyphs with.
+	#[allow(dead_code)] // Public API; the CLI never inspects a renderer.
+	pub fn options(&self) -> &RenderOptions {
+		&self.options
+	}
+
+	/// Returns the recorder of the glyphs rendered by this renderer and all its
+	/// copies, if it was built with [`RendererBuilder::record`].
+	///
+	/// Glyphs are recorded by [`GlyphBlock::render`](crate::font::GlyphBlock::render).
+	pub fn recorder(&self) -> Option<&RenderRecorder> {
+		self.recorder.as_ref()
+	}
+
+	/// Returns a copy of this renderer that scales glyphs to `size` pixels per EM
+	/// instead of the default 24.
+	pub fn with_size(&self, size: u32) -> Self {
+		let mut renderer = self.clone();
+		renderer.options.size = size;
+		renderer
+	}
+
+	/// Returns a copy of this renderer that pads every glyph bitmap with `buffer`
+	/// pixels instead of the default 3.
+	///
+	/// Note that maplibre/mapbox consumers assume a buffer of 3 pixels.
+	pub fn with_buffer(&self, buffer: u32) -> Self {
+		let mut renderer = self.clone();
+		renderer.options.buffer = buffer;
+		renderer
+	}
+
+	/// Returns a copy of this renderer that moves the baseline by `baseline`
+	/// pixels relative to `size`, e.g. for scripts that are aligned on an
+	/// ideographic baseline.
+	///
+	/// Every pixel lowers the `top` of each glyph by one pixel, negative values
+	/// raise it.
+	pub fn with_baseline(&self, baseline: i32) -> Self {
+		let mut renderer = self.clone();
+		renderer.options.baseline = baseline;
+		renderer
+	}
+
+	/// Returns the profiler that collects the render times of this renderer and
+	/// all its copies.
+	#[cfg(feature = "profiling")]
+	pub fn profiler(&self) -> &super::Profiler {
+		&self.profiler
+	}
+
+	/// Returns a copy of this renderer that applies a gamma curve to the SDF:
+	/// every value `v` is stored as `255 · (v / 255)^gamma`.
+	///
+	/// The default of `1.0` keeps the linear mapping. Values above `1.0` darken
+	/// the gradient for crisper edges, values below `1.0` soften it. Fully
+	/// outside (`0`) and fully inside (`255`) pixels are never changed.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `gamma` isn't finite and greater than 0, see
+	/// [`RenderOptions::validate`].
+	#[allow(dead_code)] // Public API; the CLI renders with the linear mapping.
+	pub fn with_gamma(&self, gamma: f64) -> Result<Self, GlyphError> {
+		let mut renderer = self.clone();
+		renderer.options.gamma = gamma;
+		renderer.options.validate()?;
+		Ok(renderer)
 	}
 
 	/// Prepares the geometry and compute bounding box data for rendering.
 	///
 	/// This method:
 	/// - Computes the bounding box for the given `rings`.
-	/// - Adjusts it by adding a `BUFFER` on all sides.
+	/// - Adjusts it by adding the buffer on all sides.
 	/// - Translates the outline to ensure it starts at `(0, 0)`.
 	/// - Produces a [`RenderResult`] with the computed width, height,
 	///   and coordinate offsets.
@@ -59,8 +157,8 @@ impl Renderer {
 	/// `min` and `ceil` on `max`, so the integer cell always *contains* the
 	/// float bbox. The trade-off is that the actual outline can sit up to 1
 	/// pixel inside each edge — see the [module-level docs](super) for the
-	/// full discussion of this rounding artifact and why `BUFFER` is only
-	/// 3 pixels even though the SDF gradient extends to 8.
+	/// full discussion of this rounding artifact and why the default buffer
+	/// is only 3 pixels even though the SDF gradient extends to 8.
 	fn prepare_glyph(&self, rings: &Rings) -> Option<RenderResult> {
 		let bbox = rings.get_bbox();
 
@@ -68,12 +166,12 @@ impl Renderer {
 			return None;
 		}
 
-		// floor/ceil + BUFFER: the bitmap's content area is the integer cell
-		// containing `bbox`, padded by BUFFER pixels on every side for the SDF.
-		let x0 = bbox.min.x.floor() as i32 - BUFFER;
-		let y0 = bbox.min.y.floor() as i32 - BUFFER;
-		let x1 = bbox.max.x.ceil() as i32 + BUFFER;
-		let y1 = bbox.max.y.ceil() as i32 + BUFFER;
+		// floor/ceil + buffer: the bitmap's content area is the integer cell
+		// containing `bbox`, padded by `buffer` pixels on every side for the SDF.
+		let buffer = self.options.buffer as i32;
+		let bounds = bbox.pad_snapped(buffer as f64);
+		let (x0, y0) = (bounds.min.x as i32, bounds.min.y as i32);
+		let (x1, y1) = (bounds.max.x as i32, bounds.max.y as i32);
 		let width = (x1 - x0) as usize;
 		let height = (y1 - y0) as usize;
 
@@ -84,6 +182,7 @@ impl Renderer {
 			y0,
 			width: width as u32,
 			height: height as u32,
+			buffer,
 			bitmap: None,
 		};
 
@@ -97,26 +196,119 @@ impl Renderer {
 	/// to create a signed distance field (SDF). The SDF is then converted
 	/// into a [`PbfGlyph`]. If no SDF is produced, an empty glyph is returned.
 	///
+	/// `face` can be any parsed [`Face`], so the outline follows its variation
+	/// coordinates, if set with [`Face::set_variation`].
+	///
 	/// # Return
 	///
 	/// Returns [`None`] if no corresponding glyph index can be found in `face`.
 	pub fn render_glyph(&self, face: &Face, index: u32) -> Option<PbfGlyph> {
-		let cp = char::from_u32(index)?;
+		let glyph_id = face.glyph_index(char::from_u32(index)?)?;
+		Some(self.render_glyph_id(face, glyph_id, index))
+	}
+
+	/// Renders the glyph for codepoint `index` of a glyph block: the `.notdef`
+	/// glyph if `notdef`, see [`Self::render_notdef`], or the glyph of `face`,
+	/// see [`Self::render_glyph`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if the glyph can't be rendered.
+	pub(crate) fn render_block_glyph(
+		&self,
+		face: &Face,
+		index: u32,
+		notdef: bool,
+	) -> Result<Option<PbfGlyph>, GlyphError> {
+		#[cfg(test)]
+		if self.fail_on == Some(index) {
+			return Err(GlyphError::Other(anyhow::anyhow!(
+				"failed to render glyph {index}"
+			)));
+		}
+		Ok(if notdef {
+			Some(self.render_notdef(face, index))
+		} else {
+			self.render_glyph(face, index)
+		})
+	}
+
+	/// Estimates the encoded size in bytes of the glyph for codepoint `index` of
+	/// a glyph block, see [`Self::render_block_glyph`], without rendering it.
+	///
+	/// The bitmap is assumed to cover the scaled bounding box of the outline,
+	/// padded by the buffer, so [`RenderOptions::tight`] and
+	/// [`RenderOptions::grid_snap`] are ignored. Returns `0` if `face` has no
+	/// glyph for the codepoint.
+	pub fn estimate_block_glyph_size(&self, face: &Face, index: u32, notdef: bool) -> u64 {
+		// Tags and varints of the glyph message and its fields, besides the bitmap.
+		const GLYPH_OVERHEAD: u64 = 20;
+
+		let glyph_id = if notdef {
+			Some(GlyphId(0))
+		} else {
+			char::from_u32(index).and_then(|c| face.glyph_index(c))
+		};
+		let Some(glyph_id) = glyph_id else {
+			return 0;
+		};
+		let Some(bbox) = face.glyph_bounding_box(glyph_id) else {
+			return GLYPH_OVERHEAD;
+		};
+		let scale = self.options.size as f64 / face.units_per_em() as f64;
+		let padding = 2 * self.options.buffer as u64;
+		let cells = |min: i16, max: i16| {
+			((max as f64 * scale).ceil() - (min as f64 * scale).floor()).max(0.0) as u64 + padding
+		};
+		GLYPH_OVERHEAD + cells(bbox.x_min, bbox.x_max) * cells(bbox.y_min, bbox.y_max)
+	}
+
+	/// Renders the `.notdef` glyph (glyph id 0, usually a box or "tofu") of `face`
+	/// as the glyph for codepoint `index`.
+	///
+	/// Used as a visible fallback for codepoints the font doesn't cover.
+	pub fn render_notdef(&self, face: &Face, index: u32) -> PbfGlyph {
+		self.render_glyph_id(face, GlyphId(0), index)
+	}
 
-		let glyph_id = face.glyph_index(cp)?;
-		let scale = GLYPH_SIZE as f64 / face.units_per_em() as f64;
+	/// Like [`Self::render_glyph`], but renders the glyph that `feature`
+	/// substitutes for the codepoint `index`, e.g. its small capital.
+	///
+	/// Only single substitutions apply to a lone codepoint. See
+	/// [`FeatureSubstitution`] for the limitations.
+	#[allow(dead_code)] // Public API; the CLI renders the default glyphs.
+	pub fn render_glyph_with_feature(
+		&self,
+		face: &Face,
+		index: u32,
+		feature: &FeatureSubstitution,
+	) -> Option<PbfGlyph> {
+		let glyph_id = face.glyph_index(char::from_u32(index)?)?;
+		Some(self.render_glyph_id(face, feature.substitute(glyph_id), index))
+	}
+
+	/// Renders the glyph `glyph_id` of `face` and stores it as codepoint `index`.
+	///
+	/// Renders any glyph, including those without a `cmap` entry, like the
+	/// results of [`FeatureSubstitution`].
+	///
+	/// Glyphs without a fillable outline, like spaces, control glyphs or
+	/// malformed glyphs that only emit `move_to` commands, render as an empty
+	/// [`PbfGlyph`] without bitmap that keeps the glyph's advance.
+	pub fn render_glyph_id(&self, face: &Face, glyph_id: GlyphId, index: u32) -> PbfGlyph {
+		// `Face::parse` rejects fonts with `units_per_em` outside 16..=16384, so
+		// the scale is always finite.
+		let scale = self.options.size as f64 / face.units_per_em() as f64;
 
 		let mut builder = RingBuilder::default();
 		face.outline_glyph(glyph_id, &mut builder);
 		let mut rings = builder.into_rings();
 
-		// `* 0.95` matches the empirical scale used by other Mapbox-spec glyph
-		// pipelines (e.g. fontnik) so renderings line up with existing tiles.
-		let advance_float = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f64 * scale * 0.95;
+		let advance_float = glyph_advance(face, glyph_id, self.options.size);
 		let advance = advance_float.round() as u32;
 
 		if rings.is_empty() {
-			return Some(PbfGlyph::empty(index, advance));
+			return PbfGlyph::empty(index, advance);
 		}
 
 		rings.scale(scale);
@@ -130,29 +322,204 @@ impl Renderer {
 		let dx = (advance as f64 - advance_float) / 2.0;
 		rings.translate(&Point::new(dx, 0.0));
 
+		if self.options.grid_snap {
+			rings.snap_to_grid();
+		}
+
+		// Degenerate outlines, e.g. snapped onto a line, have nothing to fill.
+		if !rings.has_renderable_area() {
+			return PbfGlyph::empty(index, advance);
+		}
+
 		let mut glyph = if let Some(g) = self.prepare_glyph(&rings) {
 			g
 		} else {
-			return Some(PbfGlyph::empty(index, advance));
+			return PbfGlyph::empty(index, advance);
 		};
 
 		// Render the SDF
 		match self.mode {
-			RendererMode::Precise => renderer_precise(&mut glyph, rings),
+			RendererMode::Precise => {
+				let radius = self.options.radius();
+				renderer_precise(&mut glyph, rings, radius, self.options.gamma);
+				#[cfg(feature = "profiling")]
+				if glyph.is_clipped(sdf_value(super::HALO_WIDTH, radius, self.options.gamma)) {
+					self.profiler.record_clipped();
+				}
+				if self.options.tight {
+					// Pixels below this value are farther than `buffer` pixels from
+					// the outline.
+					let buffer = self.options.buffer as f64;
+					glyph.trim(sdf_value(buffer, radius, self.options.gamma));
+				}
+			}
 			RendererMode::Dummy => renderer_dummy(&mut glyph),
 		}
 
 		// Shift the SDF output to re-base the glyph
-		glyph.y1 -= GLYPH_SIZE;
+		glyph.y1 -= self.options.size as i32 + self.options.baseline;
 
-		Some(glyph.into_pbf_glyph(index, advance))
+		glyph.into_pbf_glyph(index, advance)
+	}
+
+	/// Renders every distinct character of `text`, e.g. to preview a label or to
+	/// check that a font covers a phrase.
+	///
+	/// Glyphs are returned in order of first appearance in `text`. Characters
+	/// missing from `face` are skipped.
+	pub fn render_glyphs_for_string(&self, face: &Face, text: &str) -> Vec<PbfGlyph> {
+		let mut seen = HashSet::new();
+		text
+			.chars()
+			.filter(|c| seen.insert(*c))
+			.filter_map(|c| self.render_glyph(face, c as u32))
+			.collect()
+	}
+}
+
+/// Returns the horizontal advance of `glyph_id` in pixels at `size` pixels per
+/// EM, before rounding to the integer [`PbfGlyph::advance`].
+pub(crate) fn glyph_advance(face: &Face, glyph_id: GlyphId, size: u32) -> f64 {
+	let scale = size as f64 / face.units_per_em() as f64;
+	// `* 0.95` matches the empirical scale used by other Mapbox-spec glyph
+	// pipelines (e.g. fontnik) so renderings line up with existing tiles.
+	face.glyph_hor_advance(glyph_id).unwrap_or(0) as f64 * scale * 0.95
+}
+
+/// Builds a [`Renderer`] from chainable settings, starting with the default
+/// [`RenderOptions`] and the precise mode.
+///
+/// ```
+/// use versatiles_glyphs::render::Renderer;
+///
+/// let renderer = Renderer::builder().size(32).buffer(4).build();
+/// assert_eq!(renderer.options().size, 32);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RendererBuilder {
+	dummy: bool,
+	record: bool,
+	options: RenderOptions,
+	#[cfg(test)]
+	fail_on: Option<u32>,
+}
+
+impl RendererBuilder {
+	/// Uses the dummy mode, which generates empty bitmaps, if `dummy` is `true`.
+	pub fn dummy(mut self, dummy: bool) -> Self {
+		self.dummy = dummy;
+		self
+	}
+
+	/// Records every rendered glyph if `record` is `true`, see [`Renderer::recorder`].
+	#[allow(dead_code)] // Public API; for tests.
+	pub fn record(mut self, record: bool) -> Self {
+		self.record = record;
+		self
+	}
+
+	/// Makes every render of `codepoint` fail, see [`Renderer::new_failing`].
+	#[cfg(test)]
+	pub fn fail_on(mut self, codepoint: u32) -> Self {
+		self.fail_on = Some(codepoint);
+		self
+	}
+
+	/// Replaces all options at once.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the options are invalid, see [`RenderOptions::validate`].
+	#[allow(dead_code)] // Public API; the CLI only uses the default options.
+	pub fn options(mut self, options: RenderOptions) -> Result<Self, GlyphError> {
+		options.validate()?;
+		self.options = options;
+		Ok(self)
+	}
+
+	/// Sets the glyph height in pixels per EM, see [`Renderer::with_size`].
+	#[allow(dead_code)] // Public API; fonts.json sizes are applied with `Renderer::with_size`.
+	pub fn size(mut self, size: u32) -> Self {
+		self.options.size = size;
+		self
+	}
+
+	/// Sets the padding in pixels, see [`Renderer::with_buffer`].
+	#[allow(dead_code)] // Public API; fonts.json buffers are applied with `Renderer::with_buffer`.
+	pub fn buffer(mut self, buffer: u32) -> Self {
+		self.options.buffer = buffer;
+		self
+	}
+
+	/// Sets the baseline offset in pixels relative to the size, see [`Renderer::with_baseline`].
+	#[allow(dead_code)] // Public API; the CLI applies baselines with `Renderer::with_baseline`.
+	pub fn baseline(mut self, baseline: i32) -> Self {
+		self.options.baseline = baseline;
+		self
+	}
+
+	/// Sets the gamma curve applied to the SDF, see [`Renderer::with_gamma`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if `gamma` isn't finite and greater than 0.
+	#[allow(dead_code)] // Public API; the CLI renders with the linear mapping.
+	pub fn gamma(mut self, gamma: f64) -> Result<Self, GlyphError> {
+		self.options.gamma = gamma;
+		self.options.validate()?;
+		Ok(self)
+	}
+
+	/// Sets whether to snap outlines to the pixel grid, see [`RenderOptions::grid_snap`].
+	#[allow(dead_code)] // Public API; the CLI renders unsnapped outlines.
+	pub fn grid_snap(mut self, grid_snap: bool) -> Self {
+		self.options.grid_snap = grid_snap;
+		self
+	}
+
+	/// Sets whether to trim padding outside the halo, see [`RenderOptions::tight`].
+	#[allow(dead_code)] // Public API; the CLI writes untrimmed bitmaps.
+	pub fn tight(mut self, tight: bool) -> Self {
+		self.options.tight = tight;
+		self
+	}
+
+	/// Sets whether to scale the SDF radius with the size, see [`RenderOptions::radius`].
+	#[allow(dead_code)] // Public API; the CLI renders with the fixed radius.
+	pub fn auto_tune(mut self, auto_tune: bool) -> Self {
+		self.options.auto_tune = auto_tune;
+		self
+	}
+
+	/// Sets the SDF radius in pixels, overriding `auto_tune`, see [`RenderOptions::radius`].
+	#[allow(dead_code)] // Public API; the CLI renders with the fixed radius.
+	pub fn sdf_radius(mut self, radius: f64) -> Self {
+		self.options.sdf_radius = Some(radius);
+		self
+	}
+
+	/// Creates the [`Renderer`].
+	pub fn build(self) -> Renderer {
+		Renderer {
+			mode: if self.dummy {
+				RendererMode::Dummy
+			} else {
+				RendererMode::Precise
+			},
+			options: self.options,
+			recorder: self.record.then(RenderRecorder::default),
+			#[cfg(feature = "profiling")]
+			profiler: super::Profiler::default(),
+			#[cfg(test)]
+			fail_on: self.fail_on,
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::utils::bitmap_as_ascii_art;
+	use crate::utils::{bitmap_as_art, bitmap_as_ascii_art, ArtOptions};
 
 	const TEST_FONT: &[u8] = include_bytes!("../../testdata/Fira Sans - Regular.ttf");
 
@@ -172,6 +539,291 @@ mod tests {
 		bitmap_as_ascii_art(glyph.bitmap.as_ref().unwrap(), glyph.width as usize + 6)
 	}
 
+	#[test]
+	fn test_render_glyph_with_size_and_buffer() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let renderer = Renderer::new_precise().with_size(48).with_buffer(5);
+		let glyph = renderer.render_glyph(&face, 65).unwrap();
+
+		// Twice the default size: twice the default 14×17 content area.
+		assert_eq!(glyph.width, 28);
+		assert_eq!(glyph.height, 34);
+		assert_eq!(glyph.advance, 26);
+		assert_eq!(
+			glyph.bitmap.unwrap().len() as u32,
+			(glyph.width + 10) * (glyph.height + 10)
+		);
+	}
+
+	#[test]
+	fn test_builder_with_size_and_buffer() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let default = Renderer::new_precise().render_glyph(&face, 65).unwrap();
+		let renderer = Renderer::builder().size(32).buffer(4).build();
+		assert_eq!(
+			renderer.options(),
+			&RenderOptions {
+				size: 32,
+				buffer: 4,
+				..Default::default()
+			}
+		);
+
+		let glyph = renderer.render_glyph(&face, 65).unwrap();
+		assert!(glyph.width > default.width);
+		assert!(glyph.height > default.height);
+		assert_eq!(glyph.buffer(), Some(4));
+		assert_eq!(
+			glyph.bitmap.unwrap().len() as u32,
+			(glyph.width + 8) * (glyph.height + 8)
+		);
+	}
+
+	#[test]
+	fn test_render_glyph_with_grid_snap() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let render = |grid_snap: bool| {
+			let renderer = Renderer::builder().size(12).grid_snap(grid_snap).build();
+			renderer.render_glyph(&face, 'l' as u32).unwrap()
+		};
+		let (plain, snapped) = (render(false), render(true));
+
+		// Edges move by at most half a pixel, so the snapped glyph stays within the
+		// integer cell of the unsnapped one, but the pixels of the thin stem change.
+		let cell = |g: &PbfGlyph| {
+			(
+				g.left,
+				g.left + g.width as i32,
+				g.top - g.height as i32,
+				g.top,
+			)
+		};
+		let (outer, inner) = (cell(&plain), cell(&snapped));
+		assert!(
+			outer.0 <= inner.0 && inner.1 <= outer.1,
+			"{outer:?} {inner:?}"
+		);
+		assert!(
+			outer.2 <= inner.2 && inner.3 <= outer.3,
+			"{outer:?} {inner:?}"
+		);
+		assert!(outer.1 - outer.0 - (inner.1 - inner.0) <= 2);
+		assert!(outer.3 - outer.2 - (inner.3 - inner.2) <= 2);
+		assert_ne!(snapped.bitmap, plain.bitmap);
+		assert_eq!(snapped.advance, plain.advance);
+	}
+
+	#[test]
+	fn test_render_glyph_with_tight() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let threshold = sdf_value(3.0, 8.0, 1.0);
+
+		// Maps every pixel to its position, with y pointing up.
+		let pixels = |g: &PbfGlyph| {
+			let width = g.width as i32 + 6;
+			g.bitmap
+				.iter()
+				.flatten()
+				.enumerate()
+				.map(|(i, &v)| {
+					let (col, row) = (i as i32 % width, i as i32 / width);
+					((g.left - 3 + col, g.top + 3 - row), v)
+				})
+				.collect::<std::collections::HashMap<_, _>>()
+		};
+
+		let mut trimmed = 0;
+		for c in ['.', 'o', 'A', 'g'] {
+			let plain = Renderer::new_precise()
+				.render_glyph(&face, c as u32)
+				.unwrap();
+			let tight = Renderer::builder()
+				.tight(true)
+				.build()
+				.render_glyph(&face, c as u32)
+				.unwrap();
+			assert!(tight.width <= plain.width && tight.height <= plain.height);
+			assert_eq!(tight.advance, plain.advance);
+			assert_eq!(
+				tight.bitmap.as_ref().unwrap().len() as u32,
+				(tight.width + 6) * (tight.height + 6)
+			);
+
+			// Same values where both have pixels, and only pixels outside the halo
+			// are dropped.
+			let (plain, tight) = (pixels(&plain), pixels(&tight));
+			for (position, value) in &plain {
+				match tight.get(position) {
+					Some(v) => assert_eq!(v, value, "{c:?} {position:?}"),
+					None => assert!(*value < threshold, "{c:?} {position:?}"),
+				}
+			}
+			trimmed += plain.len() - tight.len();
+		}
+		assert!(trimmed > 0);
+	}
+
+	#[test]
+	fn test_render_glyph_with_auto_tune() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+
+		// Mean difference between horizontal neighbours on the gradient, i.e.
+		// the slope of the SDF in bytes per pixel.
+		let slope = |renderer: Renderer| {
+			let glyph = renderer.render_glyph(&face, 65).unwrap();
+			let bitmap = glyph.bitmap.unwrap();
+			let width = glyph.width as usize + 6;
+			let steps = bitmap
+				.chunks(width)
+				.flat_map(|row| row.windows(2))
+				.filter(|pair| pair.iter().all(|v| (1..255).contains(v)))
+				.map(|pair| pair[0].abs_diff(pair[1]) as f64)
+				.collect::<Vec<_>>();
+			steps.iter().sum::<f64>() / steps.len() as f64
+		};
+
+		let base = slope(Renderer::new_precise());
+		let fixed = slope(Renderer::builder().size(48).build());
+		let tuned = slope(Renderer::builder().size(48).auto_tune(true).build());
+
+		// Twice the size and twice the radius: the halo covers twice as many
+		// pixels, so the slope per pixel halves and stays the same per EM.
+		let ratio = tuned / base;
+		assert!((0.4..0.6).contains(&ratio), "{tuned} / {base}");
+		let ratio = fixed / base;
+		assert!((0.8..1.2).contains(&ratio), "{fixed} / {base}");
+
+		// Size 24 is unchanged, and an explicit radius overrides auto-tuning.
+		assert_eq!(
+			Renderer::builder()
+				.auto_tune(true)
+				.build()
+				.render_glyph(&face, 65),
+			Renderer::new_precise().render_glyph(&face, 65)
+		);
+		assert_eq!(
+			Renderer::builder()
+				.size(48)
+				.auto_tune(true)
+				.sdf_radius(8.0)
+				.build()
+				.render_glyph(&face, 65),
+			Renderer::builder().size(48).build().render_glyph(&face, 65)
+		);
+	}
+
+	#[test]
+	fn test_render_glyph_with_small_caps() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let smcp = FeatureSubstitution::new(&face, ttf_parser::Tag::from_bytes(b"smcp")).unwrap();
+		let renderer = Renderer::new_precise();
+
+		let base = renderer.render_glyph(&face, 'a' as u32).unwrap();
+		let small_cap = renderer
+			.render_glyph_with_feature(&face, 'a' as u32, &smcp)
+			.unwrap();
+
+		assert_eq!(small_cap.id, 'a' as u32);
+		assert_ne!(small_cap.bitmap, base.bitmap);
+		// The small capital is as tall as the x-height, but shaped like an "A",
+		// which is wider at the base and has no bowl.
+		assert_ne!(
+			(small_cap.width, small_cap.advance),
+			(base.width, base.advance)
+		);
+		assert_eq!(as_art(&small_cap).len(), as_art(&base).len());
+	}
+
+	#[test]
+	fn test_render_glyph_with_baseline() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let renderer = Renderer::new_precise();
+		let glyph = renderer.render_glyph(&face, 65).unwrap();
+		let lowered = renderer.with_baseline(6).render_glyph(&face, 65).unwrap();
+		let raised = renderer.with_baseline(-4).render_glyph(&face, 65).unwrap();
+
+		// The offset is relative to the default baseline, so 6 pixels lower the glyph by 6.
+		assert_eq!(glyph.top, -7);
+		assert_eq!(lowered.top, -13);
+		assert_eq!(raised.top, -3);
+		assert_eq!(lowered.bitmap, glyph.bitmap);
+		assert_eq!(
+			renderer.with_baseline(0).render_glyph(&face, 65),
+			Some(glyph)
+		);
+	}
+
+	#[test]
+	fn test_render_glyph_with_gamma() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let renderer = Renderer::new_precise();
+		let linear = renderer.render_glyph(&face, 65).unwrap();
+		assert_eq!(
+			renderer.with_gamma(1.0).unwrap().render_glyph(&face, 65),
+			Some(linear.clone())
+		);
+
+		let curved = renderer
+			.with_gamma(2.0)
+			.unwrap()
+			.render_glyph(&face, 65)
+			.unwrap();
+		let linear = linear.bitmap.unwrap();
+		let curved = curved.bitmap.unwrap();
+		assert_eq!(linear.len(), curved.len());
+
+		let mut midtones = 0;
+		for (&l, &c) in linear.iter().zip(&curved) {
+			match l {
+				0 | 255 => assert_eq!(c, l),
+				_ => {
+					assert!(c <= l);
+					midtones += usize::from(c < l);
+				}
+			}
+		}
+		assert!(midtones > 100, "{midtones}");
+	}
+
+	#[test]
+	fn test_invalid_gamma_is_rejected() {
+		let renderer = Renderer::new_precise();
+		for gamma in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+			assert!(renderer.with_gamma(gamma).is_err(), "{gamma}");
+			assert!(Renderer::builder().gamma(gamma).is_err(), "{gamma}");
+			let options = RenderOptions {
+				gamma,
+				..RenderOptions::default()
+			};
+			assert!(Renderer::builder().options(options).is_err(), "{gamma}");
+		}
+		assert!(Renderer::builder().gamma(0.5).is_ok());
+	}
+
+	#[test]
+	fn test_render_glyphs_for_string() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let glyphs = Renderer::new_precise().render_glyphs_for_string(&face, "AéA\u{E000}é");
+
+		// Duplicates and the unsupported private-use character are skipped.
+		assert_eq!(glyphs.iter().map(|g| g.id).collect::<Vec<_>>(), [65, 233]);
+		assert!(glyphs.iter().all(|g| g.advance > 0));
+	}
+
+	#[test]
+	fn test_render_notdef() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let renderer = Renderer::new_precise();
+		assert!(renderer.render_glyph(&face, 0xE000).is_none());
+
+		let glyph = renderer.render_notdef(&face, 0xE000);
+		assert_eq!(glyph.id, 0xE000);
+		assert!(glyph.width > 0 && glyph.height > 0 && glyph.advance > 0);
+		let bitmap = glyph.bitmap.unwrap();
+		assert_eq!(bitmap.len() as u32, (glyph.width + 6) * (glyph.height + 6));
+		assert!(bitmap.iter().any(|&v| v > 0));
+	}
+
 	#[test]
 	fn test_render_glyph_32() {
 		let glyph = get_glyph(32);
@@ -184,6 +836,22 @@ mod tests {
 		assert!(glyph.bitmap.is_none());
 	}
 
+	#[test]
+	fn test_render_empty_outlines() {
+		let face = Face::parse(TEST_FONT, 0).unwrap();
+		let renderer = Renderer::new_precise();
+		// No-break space, figure space and zero-width space have no outline.
+		for (index, advance) in [(0xA0, 6), (0x2007, 13), (0x200B, 0)] {
+			let glyph_id = face.glyph_index(char::from_u32(index).unwrap()).unwrap();
+			let glyph = renderer.render_glyph(&face, index).unwrap();
+			assert_eq!(glyph, PbfGlyph::empty(index, advance));
+			assert_eq!(
+				glyph.advance,
+				glyph_advance(&face, glyph_id, renderer.options().size).round() as u32
+			);
+		}
+	}
+
 	#[test]
 	fn test_render_glyph_65() {
 		let glyph = get_glyph(65);
@@ -223,6 +891,24 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_art_cell_width() {
+		let glyph = get_glyph(65);
+		let options = ArtOptions {
+			cell_width: 1,
+			..Default::default()
+		};
+		let single = bitmap_as_art(glyph.bitmap.as_ref().unwrap(), 20, &options);
+
+		// The default art is the same picture with every character doubled.
+		let halved = as_art(&glyph)
+			.iter()
+			.map(|row| row.chars().step_by(2).collect::<String>())
+			.collect::<Vec<_>>();
+		assert_eq!(single, halved);
+		assert_eq!(single[0], "      ░░░░░░░░      ");
+	}
+
 	#[test]
 	fn test_render_glyph_230() {
 		let glyph = get_glyph(230);
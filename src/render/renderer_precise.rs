@@ -1,11 +1,21 @@
 use super::{
 	rtree_segments::{min_distance_to_line_segment, SegmentValue},
-	RenderResult, CUTOFF, SDF_RADIUS,
+	RenderResult, CUTOFF,
 };
 use crate::geometry::{Point, Rings};
 use rstar::RTree;
 
-pub fn renderer_precise(glyph: &mut RenderResult, rings: Rings) {
+/// Renders the SDF of `rings` into `glyph.bitmap`, with distances up to
+/// `radius` pixels, see [`sdf_value`].
+///
+/// Each value is normalized to `0.0..=1.0` and raised to the power of `gamma`
+/// before quantization, so `1.0` keeps the linear mapping.
+///
+/// Pixels are inside if their winding number is non-zero. Unlike the sign of
+/// the winding number, this doesn't depend on the direction of the contours,
+/// so TrueType outlines (clockwise outer contours) and CFF outlines
+/// (counter-clockwise outer contours) render the same.
+pub fn renderer_precise(glyph: &mut RenderResult, rings: Rings, radius: f64, gamma: f64) {
 	let width = glyph.width as usize;
 	let height = glyph.height as usize;
 
@@ -22,8 +32,6 @@ pub fn renderer_precise(glyph: &mut RenderResult, rings: Rings) {
 
 	let mut bitmap = vec![0; width * height];
 
-	let radius_by_256 = 256.0 / SDF_RADIUS;
-
 	let x0 = glyph.x0 as f64 + 0.5;
 	let y0 = glyph.y0 as f64 + 0.5;
 
@@ -39,8 +47,8 @@ pub fn renderer_precise(glyph: &mut RenderResult, rings: Rings) {
 		//   downward crossing (s.y >  py >= e.y) → -1
 		crossings.clear();
 		for seg in &segments {
-			let s = seg.start;
-			let e = seg.end;
+			let s = &seg.start;
+			let e = &seg.end;
 			if s.y <= py && e.y > py {
 				let t = (py - s.y) / (e.y - s.y);
 				crossings.push((s.x + t * (e.x - s.x), 1));
@@ -67,22 +75,33 @@ pub fn renderer_precise(glyph: &mut RenderResult, rings: Rings) {
 			let inside = wn != 0;
 
 			let sample_pt = Point::new(px, py);
-			let mut d = min_distance_to_line_segment(&rtree, &sample_pt, &SDF_RADIUS);
+			let mut d = min_distance_to_line_segment(&rtree, &sample_pt, &radius);
 			if inside {
 				d = -d;
 			}
 
-			d = d * radius_by_256 + CUTOFF;
-			let n = (255.0 - d).clamp(0.0, 255.0);
-
 			let i = (height - 1 - y) * width + x; // Invert Y axis
-			bitmap[i] = n.round() as u8;
+			bitmap[i] = sdf_value(d, radius, gamma);
 		}
 	}
 
 	glyph.bitmap = Some(bitmap);
 }
 
+/// Returns the bitmap value of a pixel `distance` pixels outside the outline,
+/// or inside it if `distance` is negative.
+///
+/// `radius` pixels map to the whole byte range, so the value falls by
+/// `256 / radius` per pixel, and is 255 at `radius / 4` pixels inside.
+pub fn sdf_value(distance: f64, radius: f64, gamma: f64) -> u8 {
+	let d = distance * 256.0 / radius + CUTOFF;
+	let mut n = (255.0 - d).clamp(0.0, 255.0);
+	if gamma != 1.0 {
+		n = (n / 255.0).powf(gamma) * 255.0;
+	}
+	n.round() as u8
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -92,6 +111,39 @@ mod tests {
 		Rings::from(vec![vec![(1, 2), (5, 2), (5, 6), (1, 6), (1, 2)]])
 	}
 
+	#[test]
+	fn test_render_sdf_independent_of_orientation() {
+		let render = |rings: Rings| {
+			let mut glyph = RenderResult {
+				width: 18,
+				height: 18,
+				x0: -3,
+				x1: 15,
+				y0: -3,
+				y1: 15,
+				buffer: 3,
+				bitmap: None,
+			};
+			renderer_precise(&mut glyph, rings, 8.0, 1.0);
+			glyph.bitmap.unwrap()
+		};
+		let outer = vec![(0, 0), (0, 12), (12, 12), (12, 0), (0, 0)];
+		let hole = vec![(4, 4), (8, 4), (8, 8), (4, 8), (4, 4)];
+		let reversed = |ring: &Vec<(i32, i32)>| ring.iter().rev().copied().collect::<Vec<_>>();
+
+		// TrueType: clockwise outer contour, counter-clockwise hole. CFF: the opposite.
+		let truetype = render(Rings::from(vec![outer.clone(), hole.clone()]));
+		let cff = render(Rings::from(vec![reversed(&outer), reversed(&hole)]));
+		assert_eq!(truetype, cff);
+
+		// Pixel centers are at `x0 + 0.5 + column` and `y1 - 0.5 - row`.
+		let pixel = |x: f64, y: f64| truetype[((14.5 - y) * 18.0 + (x + 2.5)) as usize];
+		assert!(pixel(1.5, 6.5) > 192, "filled frame");
+		assert!(pixel(6.5, 6.5) < 192, "empty hole");
+		assert_eq!(pixel(-2.5, -2.5), pixel(14.5, 14.5), "background");
+		assert!(pixel(-2.5, -2.5) < 192, "empty background");
+	}
+
 	#[test]
 	fn test_render_sdf_simple_square() {
 		let rings = make_square_rings();
@@ -102,9 +154,10 @@ mod tests {
 			x1: 8,
 			y0: -1,
 			y1: 9,
+			buffer: 3,
 			bitmap: None,
 		};
-		renderer_precise(&mut glyph, rings);
+		renderer_precise(&mut glyph, rings, 8.0, 1.0);
 
 		assert_eq!(glyph.width, 10);
 		assert_eq!(glyph.height, 10);
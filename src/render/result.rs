@@ -1,4 +1,3 @@
-use super::BUFFER;
 use crate::protobuf::PbfGlyph;
 
 /// Holds intermediate results of the glyph rendering process,
@@ -24,20 +23,90 @@ pub struct RenderResult {
 	/// The height of the rendered bitmap, including any buffer or padding.
 	pub height: u32,
 
+	/// The padding in pixels on every side of the content area, included in
+	/// `width` and `height`.
+	pub buffer: i32,
+
 	/// The rendered bitmap data, if available.
 	pub bitmap: Option<Vec<u8>>,
 }
 
 impl RenderResult {
+	/// Removes the outermost rows and columns of the bitmap while all of their
+	/// pixels are below `threshold`, and moves the bounds accordingly.
+	///
+	/// The content area keeps at least one pixel on each axis.
+	pub fn trim(&mut self, threshold: u8) {
+		let Some(bitmap) = &self.bitmap else {
+			return;
+		};
+		let width = self.width as usize;
+		let min_size = 2 * self.buffer as u32 + 1;
+		let below = |x: usize, y: usize| bitmap[y * width + x] < threshold;
+
+		// Bitmap rows run from top to bottom.
+		let (mut top, mut bottom) = (0, self.height as usize);
+		let (mut left, mut right) = (0, width);
+		while bottom - top > min_size as usize && (left..right).all(|x| below(x, top)) {
+			top += 1;
+		}
+		while bottom - top > min_size as usize && (left..right).all(|x| below(x, bottom - 1)) {
+			bottom -= 1;
+		}
+		while right - left > min_size as usize && (top..bottom).all(|y| below(left, y)) {
+			left += 1;
+		}
+		while right - left > min_size as usize && (top..bottom).all(|y| below(right - 1, y)) {
+			right -= 1;
+		}
+
+		let trimmed = (top..bottom)
+			.flat_map(|y| bitmap[y * width + left..y * width + right].iter().copied())
+			.collect();
+
+		self.x0 += left as i32;
+		self.x1 -= (width - right) as i32;
+		self.y0 += (self.height as usize - bottom) as i32;
+		self.y1 -= top as i32;
+		self.width = (right - left) as u32;
+		self.height = (bottom - top) as u32;
+		self.bitmap = Some(trimmed);
+	}
+
+	/// Returns `true` if any pixel in the outermost rows or columns of the
+	/// bitmap is at least `threshold`, i.e. the buffer is too small to hold the
+	/// part of the SDF that clients draw, and it is cut off at the edges.
+	///
+	/// For a halo `halo` pixels wide, `threshold` is the SDF value at that
+	/// distance from the outline, about `192 - halo · 256 / radius`. Values below
+	/// it are never drawn, so the SDF beyond the buffer doesn't matter. A
+	/// threshold of `1` checks whether the whole SDF fits, which it nearly never
+	/// does with the default buffer, see the [`render` module docs](crate::render).
+	#[allow(dead_code)] // Public API; the CLI only counts clipped glyphs with the "profiling" feature.
+	pub fn is_clipped(&self, threshold: u8) -> bool {
+		let Some(bitmap) = &self.bitmap else {
+			return false;
+		};
+		let (width, height) = (self.width as usize, self.height as usize);
+		if width == 0 || height == 0 {
+			return false;
+		}
+		let rows = [0, height - 1]
+			.into_iter()
+			.flat_map(|y| &bitmap[y * width..(y + 1) * width]);
+		let columns = (0..height).flat_map(|y| [bitmap[y * width], bitmap[y * width + width - 1]]);
+		rows.copied().chain(columns).any(|value| value >= threshold)
+	}
+
 	/// Consumes this rendering result and produces a [`PbfGlyph`].
 	///
-	/// The bitmap stored on disk is `(width + 2·BUFFER) × (height + 2·BUFFER)`
-	/// pixels: a content area surrounded by `BUFFER` pixels of SDF
+	/// The bitmap stored on disk is `(width + 2·buffer) × (height + 2·buffer)`
+	/// pixels: a content area surrounded by `buffer` pixels of SDF
 	/// padding on every side. The PBF metrics report only the *content area*
 	/// (`width`, `height`, `left`, `top`) — consumers reconstruct the full
-	/// bitmap dimensions by adding back `2·BUFFER` on each axis.
+	/// bitmap dimensions by adding back `2·buffer` on each axis.
 	///
-	/// `left = x0 + BUFFER` and `top = y1 - BUFFER` therefore correspond to
+	/// `left = x0 + buffer` and `top = y1 - buffer` therefore correspond to
 	/// `floor(min.x)` and `ceil(max.y)` of the float bbox computed in
 	/// [`Renderer::prepare_glyph`](crate::render::Renderer). See the
 	/// [`render` module docs](crate::render) for why those `floor`/`ceil`
@@ -56,6 +125,7 @@ impl RenderResult {
 	///     y1: 10,
 	///     width: 20,
 	///     height: 24,
+	///     buffer: 3,
 	///     bitmap: Some(vec![0; 20 * 24]),
 	/// };
 	///
@@ -67,10 +137,10 @@ impl RenderResult {
 		PbfGlyph {
 			id,
 			bitmap: self.bitmap,
-			width: self.width - 2 * BUFFER as u32,
-			height: self.height - 2 * BUFFER as u32,
-			left: self.x0 + BUFFER,
-			top: self.y1 - BUFFER,
+			width: self.width - 2 * self.buffer as u32,
+			height: self.height - 2 * self.buffer as u32,
+			left: self.x0 + self.buffer,
+			top: self.y1 - self.buffer,
 			advance,
 		}
 	}
@@ -270,4 +270,19 @@ mod tests {
 		let segments = rings.get_segments();
 		assert_eq!(segments.len(), 3);
 	}
+
+	#[test]
+	fn test_move_to_only_outline_has_no_rings() {
+		let mut builder = RingBuilder::default();
+		builder.move_to(0.0, 0.0);
+		builder.move_to(5.0, 5.0);
+		builder.close();
+		// A single-point ring, closed onto itself.
+		builder.move_to(1.0, 1.0);
+		builder.line_to(1.0, 1.0);
+		builder.close();
+		builder.move_to(2.0, 2.0);
+
+		assert!(builder.into_rings().is_empty());
+	}
 }
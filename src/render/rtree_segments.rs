@@ -75,10 +75,7 @@ mod tests {
 	fn test_envelope_computation() {
 		let start = Point { x: 1.0, y: 4.0 };
 		let end = Point { x: 3.0, y: 2.0 };
-		let seg = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg = Segment::new(&start, &end);
 		let value = SegmentValue::new(seg);
 
 		let envelope = value.envelope();
@@ -94,10 +91,7 @@ mod tests {
 		// Create one segment
 		let start = Point { x: 0.0, y: 0.0 };
 		let end = Point { x: 4.0, y: 0.0 };
-		let seg = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg = Segment::new(&start, &end);
 		let rtree = RTree::bulk_load(vec![SegmentValue::new(seg)]);
 
 		// Query near the segment
@@ -113,10 +107,7 @@ mod tests {
 		// Single horizontal segment from (0,0) to (4,0)
 		let start = Point { x: 0.0, y: 0.0 };
 		let end = Point { x: 4.0, y: 0.0 };
-		let seg = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg = Segment::new(&start, &end);
 		let rtree = RTree::bulk_load(vec![SegmentValue::new(seg)]);
 
 		// The query point is far away, with a small radius
@@ -135,22 +126,13 @@ mod tests {
 		// Create multiple segments
 		let start = Point { x: 0.0, y: 0.0 };
 		let end = Point { x: 4.0, y: 0.0 };
-		let seg1 = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg1 = Segment::new(&start, &end);
 		let start = Point { x: 2.0, y: 2.0 };
 		let end = Point { x: 2.0, y: 6.0 };
-		let seg2 = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg2 = Segment::new(&start, &end);
 		let start = Point { x: -1.0, y: -1.0 };
 		let end = Point { x: -1.0, y: -5.0 };
-		let seg3 = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg3 = Segment::new(&start, &end);
 
 		// Build RTree
 		let rtree = RTree::bulk_load(vec![
@@ -182,10 +164,7 @@ mod tests {
 		// Create a simple segment
 		let start = Point { x: 1.0, y: 1.0 };
 		let end = Point { x: 5.0, y: 1.0 };
-		let seg = Segment {
-			start: &start,
-			end: &end,
-		};
+		let seg = Segment::new(&start, &end);
 		let rtree = RTree::bulk_load(vec![SegmentValue::new(seg)]);
 
 		// Query with a point that lies exactly on the segment
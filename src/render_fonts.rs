@@ -0,0 +1,105 @@
+//! A single entry point that renders font files with the common defaults.
+
+use crate::{
+	error::GlyphError,
+	font::{FontLayout, FontManager, GLYPH_BLOCK_SIZE},
+	render::{RenderOptions, Renderer},
+	writer::Writer,
+};
+use std::{collections::HashSet, path::PathBuf};
+
+/// Options for [`render_fonts`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderFontsOptions {
+	/// How the glyphs are drawn, like their size and buffer.
+	pub render: RenderOptions,
+	/// If set, only these codepoints are rendered and indexed.
+	pub charset: Option<HashSet<u32>>,
+	/// Directory naming scheme for the rendered fonts.
+	pub layout: FontLayout,
+	/// Number of codepoints per glyph block, [`GLYPH_BLOCK_SIZE`] by default.
+	pub block_size: u32,
+	/// Whether to read and render in parallel. Defaults to `true`.
+	pub parallel: bool,
+	/// Whether to write `index.json` and `font_families.json`. Defaults to `true`.
+	pub write_index: bool,
+}
+
+impl Default for RenderFontsOptions {
+	fn default() -> Self {
+		RenderFontsOptions {
+			render: RenderOptions::default(),
+			charset: None,
+			layout: FontLayout::default(),
+			block_size: GLYPH_BLOCK_SIZE,
+			parallel: true,
+			write_index: true,
+		}
+	}
+}
+
+/// Renders the font files at `paths` into `writer`: all glyph blocks of every
+/// font and, unless disabled, the `index.json` and `font_families.json`.
+///
+/// Files with the same font name, like the Latin and the Arabic file of a
+/// font, are merged into one font. This wraps [`FontManager`] for the common
+/// case; use it directly for more control. The writer isn't finished, so call
+/// [`Writer::finish`] afterwards.
+///
+/// # Errors
+///
+/// Returns an error if a font can't be read or parsed, or if writing fails.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::PathBuf;
+/// use versatiles_glyphs::{render_fonts, writer::Writer, RenderFontsOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut tar = Vec::new();
+/// let mut writer = Writer::new_tar(&mut tar);
+/// let options = RenderFontsOptions {
+///     charset: Some(('A'..='Z').map(u32::from).collect()),
+///     ..Default::default()
+/// };
+/// render_fonts(&[PathBuf::from("testdata/Fira Sans - Regular.ttf")], &options, &mut writer)?;
+/// writer.finish()?;
+/// drop(writer);
+///
+/// let mut archive = tar::Archive::new(tar.as_slice());
+/// let mut files = archive
+///     .entries()?
+///     .map(|entry| Ok(entry?.path()?.display().to_string()))
+///     .collect::<Result<Vec<_>, std::io::Error>>()?;
+/// files.sort();
+/// assert_eq!(
+///     files,
+///     ["fira_sans_regular/", "fira_sans_regular/0-255.pbf", "font_families.json", "index.json"]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_fonts(
+	paths: &[PathBuf],
+	options: &RenderFontsOptions,
+	writer: &mut Writer,
+) -> Result<(), GlyphError> {
+	let mut manager = FontManager::new(options.parallel);
+	manager.layout = options.layout;
+	manager.block_size = options.block_size;
+	manager.add_paths(paths)?;
+	if let Some(charset) = &options.charset {
+		for font in manager.fonts.values_mut() {
+			font.settings.charset = Some(charset.clone());
+		}
+	}
+
+	let renderer = Renderer::builder().options(options.render.clone())?.build();
+	manager.render_glyphs(writer, &renderer)?;
+	if options.write_index {
+		manager.write_index_json(writer)?;
+		manager.write_families_json(writer)?;
+	}
+	Ok(())
+}
@@ -12,6 +12,7 @@
 /// # Returns
 /// A vector of strings, where each string represents one row of the image
 /// (with each pixel replaced by two digits and separated by spaces).
+#[allow(dead_code)] // Public API; only the tests print digit art.
 pub fn bitmap_as_digit_art(bitmap: &[u8], width: usize) -> Vec<String> {
 	bitmap
 		.chunks(width)
@@ -27,10 +28,45 @@ pub fn bitmap_as_digit_art(bitmap: &[u8], width: usize) -> Vec<String> {
 		.collect()
 }
 
+/// Options for [`bitmap_as_art`].
+#[derive(Clone, Debug)]
+pub struct ArtOptions {
+	/// Characters for the intensities between blank and full, from light to dark.
+	///
+	/// Pixels `0..=60` are always blank and `241..=255` always `'█'`; the range
+	/// in between is split evenly across the ramp.
+	pub ramp: Vec<char>,
+	/// How often each pixel's character is repeated. Two characters per pixel
+	/// keep the aspect ratio of most terminal fonts. Full pixels (`241..=255`)
+	/// stay a single `'█'`, like in the original ASCII art.
+	pub cell_width: usize,
+}
+
+impl Default for ArtOptions {
+	fn default() -> Self {
+		ArtOptions {
+			ramp: vec!['░', '▒', '▓'],
+			cell_width: 2,
+		}
+	}
+}
+
+impl ArtOptions {
+	/// Returns the character representing the pixel value `x`.
+	fn char_for(&self, x: u8) -> char {
+		match x {
+			0..=60 => ' ',
+			241..=255 => '█',
+			_ if self.ramp.is_empty() => '█',
+			_ => self.ramp[(x as usize - 61) * self.ramp.len() / 180],
+		}
+	}
+}
+
 /// Converts a grayscale bitmap into rows of ASCII "art," using various Unicode
 /// characters to represent ranges of pixel intensity.
 ///
-/// The conversion uses these thresholds (inclusively):
+/// With the default [`ArtOptions`], the conversion uses these thresholds (inclusively):
 ///
 /// - `0..=60` -> `"  "` (2 spaces)  
 /// - `61..=120` -> `"░░"`  
@@ -41,38 +77,73 @@ pub fn bitmap_as_digit_art(bitmap: &[u8], width: usize) -> Vec<String> {
 /// # Arguments
 /// * `bitmap` - A slice of 8-bit grayscale pixel data.
 /// * `width` - The number of pixels per row.
+/// * `options` - The character ramp and the number of characters per pixel.
 ///
 /// # Returns
-/// A vector of strings, where each string represents one row of the image
-/// as a sequence of 2-character intensity symbols.
+/// A vector of strings, where each string represents one row of the image.
 ///
 /// # Example
 /// ```
+/// use versatiles_glyphs::utils::{bitmap_as_art, ArtOptions};
+///
 /// let bitmap = vec![0, 64, 128, 192, 255];
-/// let rows = bitmap_as_ascii_art(&bitmap, 5);
-/// assert_eq!(
-///     rows,
-///     vec![
-///         "  ░░▒▒▓▓█"
-///     ]
-/// );
+/// let options = ArtOptions { cell_width: 1, ..Default::default() };
+/// assert_eq!(bitmap_as_art(&bitmap, 5, &options), vec![" ░▒▓█"]);
 /// ```
-pub fn bitmap_as_ascii_art(bitmap: &[u8], width: usize) -> Vec<String> {
+pub fn bitmap_as_art(bitmap: &[u8], width: usize, options: &ArtOptions) -> Vec<String> {
 	bitmap
 		.chunks(width)
 		.map(|row| {
 			row.iter()
-				.map(|&x| {
-					String::from(match x {
-						0..=60 => "  ",
-						61..=120 => "░░",
-						121..=180 => "▒▒",
-						181..=240 => "▓▓",
-						_ => "█",
-					})
+				.flat_map(|&x| {
+					let count = if x > 240 { 1 } else { options.cell_width };
+					std::iter::repeat_n(options.char_for(x), count)
 				})
-				.collect::<Vec<String>>()
-				.join("")
+				.collect::<String>()
 		})
 		.collect()
 }
+
+/// Shorthand for [`bitmap_as_art`] with the default [`ArtOptions`], i.e. two
+/// characters per pixel.
+///
+/// # Example
+/// ```
+/// use versatiles_glyphs::utils::bitmap_as_ascii_art;
+///
+/// let bitmap = vec![0, 64, 128, 192, 255];
+/// assert_eq!(bitmap_as_ascii_art(&bitmap, 5), vec!["  ░░▒▒▓▓█"]);
+/// ```
+pub fn bitmap_as_ascii_art(bitmap: &[u8], width: usize) -> Vec<String> {
+	bitmap_as_art(bitmap, width, &ArtOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RAMP: [u8; 10] = [0, 60, 61, 120, 121, 180, 181, 240, 241, 255];
+
+	#[test]
+	fn test_double_width() {
+		assert_eq!(bitmap_as_ascii_art(&RAMP, 5), ["    ░░░░▒▒", "▒▒▓▓▓▓██"]);
+	}
+
+	#[test]
+	fn test_single_width() {
+		let options = ArtOptions {
+			cell_width: 1,
+			..Default::default()
+		};
+		assert_eq!(bitmap_as_art(&RAMP, 10, &options), ["  ░░▒▒▓▓██"]);
+	}
+
+	#[test]
+	fn test_custom_ramp() {
+		let options = ArtOptions {
+			ramp: vec!['.', '+'],
+			cell_width: 1,
+		};
+		assert_eq!(bitmap_as_art(&RAMP, 10, &options), ["  ...+++██"]);
+	}
+}
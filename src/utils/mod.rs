@@ -1,11 +1,11 @@
 //! Utility functions and structures for file paths, progress bars, etc.
 
-#[cfg(test)]
 mod decode_bitmap;
 mod output_directory;
+mod progress;
 mod progress_bar;
 
-#[cfg(test)]
 pub use decode_bitmap::*;
 pub use output_directory::*;
+pub use progress::*;
 pub use progress_bar::*;
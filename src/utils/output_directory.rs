@@ -46,6 +46,19 @@ pub fn prepare_output_directory(output_directory: &str) -> Result<PathBuf> {
 	Ok(output_directory)
 }
 
+/// Creates the output directory at `output_directory` if it doesn't exist,
+/// keeping any existing contents, e.g. to resume an earlier run.
+///
+/// # Errors
+///
+/// Returns an error if the directory could not be created.
+pub fn ensure_output_directory(output_directory: &str) -> Result<PathBuf> {
+	let output_directory: PathBuf = PathBuf::from(output_directory);
+	fs::create_dir_all(&output_directory)
+		.with_context(|| format!("creating directory \"{output_directory:?}\""))?;
+	Ok(output_directory)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -107,4 +120,17 @@ mod tests {
 		let entries: Vec<_> = fs::read_dir(&prepared_dir).unwrap().collect();
 		assert!(entries.is_empty(),);
 	}
+
+	#[test]
+	fn test_ensure_output_directory_keeps_contents() {
+		let temp_dir = tempdir().unwrap();
+		let output_dir = temp_dir.path().join("a/b");
+		let output_dir_str = output_dir.to_str().unwrap();
+
+		ensure_output_directory(output_dir_str).unwrap();
+		fs::write(output_dir.join("0-255.pbf"), b"old").unwrap();
+		ensure_output_directory(output_dir_str).unwrap();
+
+		assert_eq!(fs::read(output_dir.join("0-255.pbf")).unwrap(), b"old");
+	}
 }
@@ -0,0 +1,37 @@
+use indicatif::ProgressBar;
+
+/// Receives progress updates while glyphs are rendered, e.g. to drive a
+/// custom progress UI when the library is embedded.
+///
+/// Methods are called from multiple render threads at once.
+pub trait ProgressSink: Send + Sync {
+	/// Sets the total amount of work, in glyphs.
+	fn set_total(&self, total: u64);
+	/// Reports that `n` more glyphs have been processed.
+	fn inc(&self, n: u64);
+	/// Reports that all work is done.
+	fn finish(&self);
+}
+
+/// A [`ProgressSink`] that ignores all updates.
+#[derive(Clone, Copy, Debug, Default)]
+#[allow(dead_code)] // Public API; the CLI always shows a progress bar.
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+	fn set_total(&self, _total: u64) {}
+	fn inc(&self, _n: u64) {}
+	fn finish(&self) {}
+}
+
+impl ProgressSink for ProgressBar {
+	fn set_total(&self, total: u64) {
+		self.set_length(total);
+	}
+	fn inc(&self, n: u64) {
+		ProgressBar::inc(self, n);
+	}
+	fn finish(&self) {
+		ProgressBar::finish(self);
+	}
+}
@@ -0,0 +1,148 @@
+//! Bundles all glyph blocks of a font into a single container file.
+//!
+//! # Container format
+//!
+//! For every font directory, the blocks `{start}-{end}.pbf` are replaced by two files:
+//!
+//! - `glyphs.pbf`: all blocks concatenated in ascending codepoint order. Since
+//!   concatenated protobuf messages merge, the file itself is a valid `glyphs`
+//!   message with one `stacks` entry per range.
+//! - `glyphs.json`: the offset table, an array of
+//!   `{ "range": "0-255", "offset": 0, "length": 80022 }` objects in the same
+//!   order, so a server can seek to the bytes of a single range.
+
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+/// File name of the concatenated blocks inside each font directory.
+pub const BUNDLE_FILENAME: &str = "glyphs.pbf";
+/// File name of the offset table inside each font directory.
+pub const BUNDLE_INDEX_FILENAME: &str = "glyphs.json";
+
+/// Position of a single block inside a `glyphs.pbf` bundle.
+#[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BundleEntry {
+	/// Codepoint range of the block, like `"0-255"`.
+	pub range: String,
+	/// Byte offset of the block within the bundle.
+	pub offset: u64,
+	/// Byte length of the block.
+	pub length: u64,
+}
+
+/// Collects the glyph blocks of every font until they are bundled.
+#[derive(Debug, Default)]
+pub struct Bundler {
+	/// Blocks by font directory, each keyed by the first codepoint of its range.
+	fonts: BTreeMap<String, BTreeMap<u32, (String, Vec<u8>)>>,
+}
+
+impl Bundler {
+	/// Keeps `bytes` for bundling if `filename` is a glyph block like
+	/// `"font_id/0-255.pbf"`. Returns [`None`] for any other file.
+	pub fn add(&mut self, filename: &str, bytes: &[u8]) -> Option<()> {
+		let (dir, file) = filename.rsplit_once('/')?;
+		let range = file.strip_suffix(".pbf")?;
+		let (start, end) = range.split_once('-')?;
+		let start = start.parse::<u32>().ok()?;
+		end.parse::<u32>().ok()?;
+
+		self
+			.fonts
+			.entry(dir.to_string())
+			.or_default()
+			.insert(start, (range.to_string(), bytes.to_vec()));
+		Some(())
+	}
+
+	/// Returns the bundle and offset table files of every font, as `(filename, bytes)`.
+	pub fn into_files(self) -> Result<Vec<(String, Vec<u8>)>> {
+		let mut files = Vec::new();
+		for (dir, blocks) in self.fonts {
+			let mut bundle = Vec::new();
+			let mut index = Vec::new();
+			for (range, bytes) in blocks.into_values() {
+				index.push(BundleEntry {
+					range,
+					offset: bundle.len() as u64,
+					length: bytes.len() as u64,
+				});
+				bundle.extend_from_slice(&bytes);
+			}
+			files.push((format!("{dir}/{BUNDLE_FILENAME}"), bundle));
+			files.push((
+				format!("{dir}/{BUNDLE_INDEX_FILENAME}"),
+				serde_json::to_vec_pretty(&index)?,
+			));
+		}
+		Ok(files)
+	}
+}
+
+/// Splits a `glyphs.pbf` bundle into its blocks, using the offset table from `glyphs.json`.
+///
+/// Returns `(range, bytes)` pairs, like `("0-255", …)`.
+///
+/// # Errors
+///
+/// Returns an error if the offset table can't be parsed or points outside `bundle`.
+#[allow(dead_code)] // Public API; the CLI only writes bundles.
+pub fn split_bundle<'b>(bundle: &'b [u8], index: &[u8]) -> Result<Vec<(String, &'b [u8])>> {
+	let entries = serde_json::from_slice::<Vec<BundleEntry>>(index)?;
+	entries
+		.into_iter()
+		.map(|entry| {
+			let bytes = entry.offset.checked_add(entry.length).and_then(|end| {
+				bundle.get(usize::try_from(entry.offset).ok()?..usize::try_from(end).ok()?)
+			});
+			let Some(bytes) = bytes else {
+				bail!("range \"{}\" exceeds the bundle size", entry.range);
+			};
+			Ok((entry.range, bytes))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bundle_and_split() -> Result<()> {
+		let mut bundler = Bundler::default();
+		assert!(bundler.add("font/256-511.pbf", b"second").is_some());
+		assert!(bundler.add("font/0-255.pbf", b"first").is_some());
+		assert!(bundler.add("index.json", b"[]").is_none());
+		assert!(bundler.add("font/readme.pbf", b"").is_none());
+
+		let files = bundler.into_files()?;
+		assert_eq!(files.len(), 2);
+		assert_eq!(files[0].0, "font/glyphs.pbf");
+		assert_eq!(files[0].1, b"firstsecond");
+		assert_eq!(files[1].0, "font/glyphs.json");
+
+		let blocks = split_bundle(&files[0].1, &files[1].1)?;
+		assert_eq!(
+			blocks,
+			[
+				(String::from("0-255"), &b"first"[..]),
+				(String::from("256-511"), &b"second"[..])
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_split_rejects_invalid_offsets() {
+		let index = br#"[{ "range": "0-255", "offset": 2, "length": 8 }]"#;
+		assert!(split_bundle(b"short", index).is_err());
+
+		// Lengths that overflow the end offset are rejected, not wrapped.
+		let index = format!(
+			r#"[{{ "range": "0-255", "offset": 2, "length": {} }}]"#,
+			u64::MAX
+		);
+		let err = split_bundle(b"short", index.as_bytes()).unwrap_err();
+		assert_eq!(err.to_string(), "range \"0-255\" exceeds the bundle size");
+	}
+}
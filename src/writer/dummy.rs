@@ -1,6 +1,7 @@
 use super::WriterTrait;
 use anyhow::Result;
 use regex_lite::Regex;
+use std::collections::HashMap;
 
 /// A dummy writer that captures written files and directories in memory
 /// for testing or debugging purposes.
@@ -56,6 +57,48 @@ impl WriterTrait for DummyWriter {
 	}
 }
 
+/// A writer that keeps files in memory and can read them back, but cuts
+/// every `.pbf` file in half, to test that corrupted output is detected.
+#[derive(Default)]
+pub struct CorruptingWriter {
+	files: HashMap<String, Vec<u8>>,
+}
+
+impl WriterTrait for CorruptingWriter {
+	fn write_file(&mut self, file_name: &str, bytes: &[u8]) -> Result<()> {
+		let length = if file_name.ends_with(".pbf") {
+			bytes.len() / 2
+		} else {
+			bytes.len()
+		};
+		self
+			.files
+			.insert(file_name.to_string(), bytes[..length].to_vec());
+		Ok(())
+	}
+
+	fn write_directory(&mut self, _dir_name: &str) -> Result<()> {
+		Ok(())
+	}
+
+	fn file_exists(&self, file_name: &str) -> bool {
+		self.files.contains_key(file_name)
+	}
+
+	fn read_file(&self, file_name: &str) -> Result<Option<Vec<u8>>> {
+		Ok(self.files.get(file_name).cloned())
+	}
+
+	fn finish(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	#[cfg(test)]
+	fn get_inner(&self) -> Option<&[String]> {
+		None
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1,6 +1,12 @@
-use super::WriterTrait;
-use anyhow::{Context, Result};
-use std::{fs::create_dir_all, path::PathBuf};
+use super::{copy_exact, WriterTrait};
+use anyhow::{bail, ensure, Context, Result};
+use std::{
+	collections::HashSet,
+	ffi::OsString,
+	fs::{create_dir_all, remove_file, rename, File},
+	io::{BufWriter, Read, Write},
+	path::{Component, Path, PathBuf},
+};
 
 /// Writes data directly to the filesystem.
 ///
@@ -10,27 +16,130 @@ use std::{fs::create_dir_all, path::PathBuf};
 pub struct FileWriter {
 	/// The root folder where files and subdirectories are written.
 	folder: PathBuf,
+	/// Directories that are known to exist, so each is only created once.
+	created_dirs: HashSet<PathBuf>,
 }
 
 impl FileWriter {
 	/// Creates a new [`FileWriter`] that will use the given `folder`
 	/// as its root.
 	pub fn new(folder: PathBuf) -> Self {
-		Self { folder }
+		Self {
+			folder,
+			created_dirs: HashSet::new(),
+		}
+	}
+
+	/// Creates `dir_path` and its parents, unless that was already done.
+	fn create_dir(&mut self, dir_path: &Path) -> Result<()> {
+		if !self.created_dirs.contains(dir_path) {
+			create_dir_all(dir_path)
+				.with_context(|| format!("creating directory \"{dir_path:?}\""))?;
+			self.created_dirs.insert(dir_path.to_path_buf());
+		}
+		Ok(())
+	}
+
+	/// Creates the parent directory of `file_path`, see [`Self::create_dir`].
+	fn create_parent(&mut self, file_path: &Path) -> Result<()> {
+		match file_path.parent() {
+			Some(parent) => self.create_dir(parent),
+			None => Ok(()),
+		}
+	}
+
+	/// Resolves `name` below the root folder.
+	///
+	/// # Errors
+	///
+	/// Fails if `name` contains a NUL byte, is absolute or contains a `..`
+	/// component, since it could then point outside the root folder.
+	fn resolve(&self, name: &str) -> Result<PathBuf> {
+		ensure!(
+			!name.contains('\0'),
+			"invalid path {name:?}: contains a NUL byte"
+		);
+		for component in Path::new(name).components() {
+			match component {
+				Component::Normal(_) | Component::CurDir => {}
+				Component::ParentDir => {
+					bail!("invalid path {name:?}: must not contain \"..\"")
+				}
+				Component::RootDir | Component::Prefix(_) => {
+					bail!("invalid path {name:?}: must be relative")
+				}
+			}
+		}
+		Ok(self.folder.join(name))
 	}
 }
 
+/// Writes `file_path` atomically: `write` fills a temporary file in the same
+/// directory, which then replaces `file_path`. An interrupted run never leaves
+/// a truncated file behind, which a later run with `--skip-existing` would keep.
+///
+/// The temporary file is removed if writing fails.
+fn write_atomically(
+	file_path: &Path,
+	write: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+	let mut temp_name = OsString::from(".");
+	temp_name.push(file_path.file_name().unwrap_or_default());
+	temp_name.push(".tmp");
+	let temp_path = file_path.with_file_name(temp_name);
+
+	let result = File::create(&temp_path)
+		.with_context(|| format!("creating file \"{temp_path:?}\""))
+		.and_then(|file| {
+			let mut file = BufWriter::new(file);
+			write(&mut file)?;
+			file.flush()?;
+			Ok(())
+		})
+		.and_then(|()| {
+			rename(&temp_path, file_path)
+				.with_context(|| format!("renaming {temp_path:?} to {file_path:?}"))
+		});
+	if result.is_err() {
+		let _ = remove_file(&temp_path);
+	}
+	result
+}
+
 impl WriterTrait for FileWriter {
 	/// Creates (or overwrites) a file with the given `file_name`
 	/// under the writer's root folder, writing the provided `bytes`.
 	///
+	/// The file is replaced atomically, so it is either complete or missing.
+	/// Missing parent directories are created, so no prior
+	/// [`write_directory`](WriterTrait::write_directory) call is needed.
+	///
 	/// # Errors
 	///
-	/// Fails if the file cannot be created or written.
+	/// Fails if `file_name` would leave the root folder, or if the file cannot
+	/// be created or written.
 	fn write_file(&mut self, file_name: &str, bytes: &[u8]) -> Result<()> {
-		let file_path = self.folder.join(file_name);
-		std::fs::write(file_path, bytes)?;
-		Ok(())
+		let file_path = self.resolve(file_name)?;
+		self.create_parent(&file_path)?;
+		write_atomically(&file_path, |file| Ok(file.write_all(bytes)?))
+	}
+
+	/// Creates (or overwrites) a file and streams exactly `size` bytes from `reader` into it.
+	/// Missing parent directories are created.
+	///
+	/// # Errors
+	///
+	/// Fails if `file_name` would leave the root folder, if the file cannot be
+	/// created or written, or if `reader` ends early.
+	fn write_file_from_reader(
+		&mut self,
+		file_name: &str,
+		size: u64,
+		reader: &mut dyn Read,
+	) -> Result<()> {
+		let file_path = self.resolve(file_name)?;
+		self.create_parent(&file_path)?;
+		write_atomically(&file_path, |file| copy_exact(reader, file, size))
 	}
 
 	/// Creates a directory with the given name under the writer's root folder,
@@ -38,11 +147,31 @@ impl WriterTrait for FileWriter {
 	///
 	/// # Errors
 	///
-	/// Fails if the directory cannot be created.
+	/// Fails if `dir_name` would leave the root folder, or if the directory
+	/// cannot be created.
 	fn write_directory(&mut self, dir_name: &str) -> Result<()> {
-		let dir_path = self.folder.join(dir_name);
-		create_dir_all(&dir_path).with_context(|| format!("creating directory \"{dir_path:?}\""))?;
-		Ok(())
+		let dir_path = self.resolve(dir_name)?;
+		self.create_dir(&dir_path)
+	}
+
+	/// Returns `true` if `file_name` is an existing file under the root folder.
+	fn file_exists(&self, file_name: &str) -> bool {
+		self.resolve(file_name).is_ok_and(|path| path.is_file())
+	}
+
+	/// Reads `file_name` under the root folder, or returns `None` if it isn't a file.
+	///
+	/// # Errors
+	///
+	/// Fails if `file_name` would leave the root folder, or if the file can't be read.
+	fn read_file(&self, file_name: &str) -> Result<Option<Vec<u8>>> {
+		let file_path = self.resolve(file_name)?;
+		if !file_path.is_file() {
+			return Ok(None);
+		}
+		let bytes =
+			std::fs::read(&file_path).with_context(|| format!("reading file \"{file_path:?}\""))?;
+		Ok(Some(bytes))
 	}
 
 	/// Concludes writing. For a [`FileWriter`] this is a no-op.
@@ -78,6 +207,64 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_write_file_from_reader() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let mut writer = FileWriter::new(temp_dir.path().to_path_buf());
+
+		let data = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+		writer.write_file_from_reader("stream.bin", 10_000, &mut std::io::Cursor::new(&data))?;
+
+		assert_eq!(fs::read(temp_dir.path().join("stream.bin"))?, data);
+		Ok(())
+	}
+
+	#[test]
+	fn test_failed_write_keeps_the_old_file() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let mut writer = FileWriter::new(temp_dir.path().to_path_buf());
+		writer.write_file("font/0-255.pbf", b"old")?;
+
+		// The reader ends early, like an interrupted write.
+		let result = writer.write_file_from_reader("font/0-255.pbf", 10, &mut &b"new"[..]);
+		assert!(result.is_err());
+		assert_eq!(fs::read(temp_dir.path().join("font/0-255.pbf"))?, b"old");
+
+		// Neither write leaves a temporary file behind.
+		writer.write_file("font/0-255.pbf", b"new")?;
+		assert_eq!(fs::read(temp_dir.path().join("font/0-255.pbf"))?, b"new");
+		let names = fs::read_dir(temp_dir.path().join("font"))?
+			.map(|entry| Ok(entry?.file_name()))
+			.collect::<Result<Vec<_>>>()?;
+		assert_eq!(names, ["0-255.pbf"]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_exists() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let mut writer = FileWriter::new(temp_dir.path().to_path_buf());
+
+		assert!(!writer.file_exists("font/0-255.pbf"));
+		writer.write_file("font/0-255.pbf", b"data")?;
+		assert!(writer.file_exists("font/0-255.pbf"));
+		assert!(!writer.file_exists("font"));
+		assert!(!writer.file_exists("../font/0-255.pbf"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_read_file() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let mut writer = FileWriter::new(temp_dir.path().to_path_buf());
+
+		assert_eq!(writer.read_file("index.json")?, None);
+		writer.write_file("index.json", b"[]")?;
+		assert_eq!(writer.read_file("index.json")?, Some(b"[]".to_vec()));
+		assert!(writer.read_file("../index.json").is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn test_write_directory() -> Result<()> {
 		let temp_dir = tempdir()?;
@@ -93,6 +280,47 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_write_file_creates_parent_directories() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let mut writer = FileWriter::new(temp_dir.path().to_path_buf());
+
+		writer.write_file("a/b/c.pbf", b"abc")?;
+		writer.write_file("a/b/d.pbf", b"abd")?;
+		writer.write_file_from_reader("a/e/f.pbf", 3, &mut &b"aef"[..])?;
+
+		assert_eq!(fs::read(temp_dir.path().join("a/b/c.pbf"))?, b"abc");
+		assert_eq!(fs::read(temp_dir.path().join("a/b/d.pbf"))?, b"abd");
+		assert_eq!(fs::read(temp_dir.path().join("a/e/f.pbf"))?, b"aef");
+		Ok(())
+	}
+
+	#[test]
+	fn test_reject_paths_outside_root() -> Result<()> {
+		let temp_dir = tempdir()?;
+		let root = temp_dir.path().join("root");
+		fs::create_dir(&root)?;
+		let mut writer = FileWriter::new(root.clone());
+
+		let err = writer.write_file("../evil.pbf", b"evil").unwrap_err();
+		assert!(err.to_string().contains("must not contain \"..\""), "{err}");
+		assert!(!temp_dir.path().join("evil.pbf").exists());
+
+		assert!(writer.write_file("font/../../evil.pbf", b"evil").is_err());
+		assert!(writer.write_file("/tmp/evil.pbf", b"evil").is_err());
+		assert!(writer.write_file("evil\0.pbf", b"evil").is_err());
+		assert!(writer
+			.write_file_from_reader("../evil.pbf", 4, &mut &b"evil"[..])
+			.is_err());
+		assert!(writer.write_directory("../evil/").is_err());
+		assert!(!temp_dir.path().join("evil").exists());
+
+		// Names that merely contain dots are fine.
+		writer.write_file("font..name.pbf", b"ok")?;
+		assert_eq!(fs::read_dir(&root)?.count(), 1);
+		Ok(())
+	}
+
 	#[test]
 	fn test_finish() -> Result<()> {
 		let temp_dir = tempdir()?;
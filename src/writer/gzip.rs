@@ -0,0 +1,151 @@
+//! Gzip compression of glyph blocks, so a static server or CDN can serve them
+//! with `Content-Encoding: gzip` without compressing at runtime.
+
+use anyhow::{bail, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+	borrow::Cow,
+	fmt,
+	io::{Read, Write},
+	str::FromStr,
+};
+
+/// How gzip-compressed glyph blocks are named, see
+/// [`Writer::with_gzip_blocks`](super::Writer::with_gzip_blocks).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GzipNaming {
+	/// Keep the `.pbf` name. The server has to send `Content-Encoding: gzip`.
+	#[default]
+	Pbf,
+	/// Append `.gz`, like `0-255.pbf.gz`, as expected by e.g. nginx `gzip_static`.
+	PbfGz,
+}
+
+impl GzipNaming {
+	/// Returns the name of the compressed block `filename`.
+	pub fn filename<'a>(&self, filename: &'a str) -> Cow<'a, str> {
+		match self {
+			GzipNaming::Pbf => Cow::Borrowed(filename),
+			GzipNaming::PbfGz => Cow::Owned(format!("{filename}.gz")),
+		}
+	}
+}
+
+impl fmt::Display for GzipNaming {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			GzipNaming::Pbf => "pbf",
+			GzipNaming::PbfGz => "pbf.gz",
+		})
+	}
+}
+
+impl FromStr for GzipNaming {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		Ok(match s.to_ascii_lowercase().as_str() {
+			"pbf" => GzipNaming::Pbf,
+			"pbf.gz" | "gz" => GzipNaming::PbfGz,
+			_ => bail!("unknown gzip naming \"{s}\", expected \"pbf\" or \"pbf.gz\""),
+		})
+	}
+}
+
+/// Compresses `bytes` with gzip at the best compression level, since every
+/// block is compressed once and served many times.
+pub fn gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+	let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 2), Compression::best());
+	encoder.write_all(bytes)?;
+	Ok(encoder.finish()?)
+}
+
+/// Returns whether `bytes` start with the gzip magic bytes.
+pub fn is_gzipped(bytes: &[u8]) -> bool {
+	bytes.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses `bytes` written by [`gzip`].
+pub fn gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
+	let mut decompressed = Vec::with_capacity(bytes.len() * 2);
+	GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+	Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_gzip_round_trip() -> Result<()> {
+		let bytes = (0..10_000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+		let compressed = gzip(&bytes)?;
+		assert!(compressed.len() < bytes.len());
+		assert!(is_gzipped(&compressed));
+		assert!(!is_gzipped(&bytes));
+
+		assert_eq!(gunzip(&compressed)?, bytes);
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_naming() -> Result<()> {
+		assert_eq!("pbf".parse::<GzipNaming>()?, GzipNaming::Pbf);
+		assert_eq!("PBF.GZ".parse::<GzipNaming>()?, GzipNaming::PbfGz);
+		assert!("zip".parse::<GzipNaming>().is_err());
+		assert_eq!(GzipNaming::PbfGz.filename("a/0-255.pbf"), "a/0-255.pbf.gz");
+		assert_eq!(GzipNaming::Pbf.filename("a/0-255.pbf"), "a/0-255.pbf");
+		Ok(())
+	}
+	#[test]
+	fn test_write_compressed_block() -> Result<()> {
+		use crate::writer::{ChecksumAlgorithm, Writer};
+		let temp = tempfile::tempdir()?;
+		let bytes = (0..10_000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+
+		// Compressed beforehand, the block is written like an uncompressed one.
+		let mut writer = Writer::new_file(temp.path().to_path_buf())
+			.with_gzip_blocks(GzipNaming::PbfGz)
+			.with_checksums(ChecksumAlgorithm::Sha256);
+		assert!(writer.compresses_blocks());
+		writer.write_file("a/0-255.pbf", &bytes)?;
+		writer.write_compressed_block("b/0-255.pbf", &Writer::compress(&bytes)?)?;
+		assert!(writer
+			.write_compressed_block("b/index.json", b"{}")
+			.is_err());
+		writer.finish()?;
+		assert_eq!(
+			std::fs::read(temp.path().join("a/0-255.pbf.gz"))?,
+			std::fs::read(temp.path().join("b/0-255.pbf.gz"))?
+		);
+		let manifest = std::fs::read_to_string(temp.path().join("manifest.json"))?;
+		assert!(manifest.contains("\"b/0-255.pbf.gz\""));
+
+		// Bundles are compressed as a whole.
+		let writer = Writer::new_file(temp.path().to_path_buf())
+			.with_gzip_blocks(GzipNaming::Pbf)
+			.with_bundles();
+		assert!(!writer.compresses_blocks());
+		Ok(())
+	}
+
+	#[test]
+	fn test_read_file_with_other_compression() -> Result<()> {
+		use crate::writer::Writer;
+		let temp = tempfile::tempdir()?;
+		std::fs::write(temp.path().join("index.json"), b"[]")?;
+		std::fs::write(temp.path().join("plain.pbf"), b"\x0a\x00")?;
+		std::fs::write(temp.path().join("gzipped.json"), gzip(b"{}")?)?;
+
+		// An output written without compression can be read with it, and vice versa.
+		let writer = Writer::new_file(temp.path().to_path_buf())
+			.with_gzip_blocks(GzipNaming::PbfGz)
+			.with_gzip_json();
+		assert_eq!(writer.read_file("index.json")?, Some(b"[]".to_vec()));
+		assert_eq!(writer.read_file("plain.pbf")?, Some(b"\x0a\x00".to_vec()));
+		assert_eq!(writer.read_file("missing.json")?, None);
+		let writer = Writer::new_file(temp.path().to_path_buf());
+		assert_eq!(writer.read_file("gzipped.json")?, Some(b"{}".to_vec()));
+		Ok(())
+	}
+}
@@ -0,0 +1,244 @@
+//! Content checksums for every file passed through a [`Writer`](super::Writer).
+
+use anyhow::{bail, ensure, Result};
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr};
+
+/// File name of the manifest written by [`Writer::finish`](super::Writer::finish).
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// Default number of hex digits in a [`content_filename`]: 64 bits, so a
+/// collision is unlikely even among billions of files.
+#[allow(dead_code)] // Public API; see `content_filename`.
+pub const CONTENT_HASH_LENGTH: usize = 16;
+
+/// Hash algorithm used for the checksums in the manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+	/// SHA-256, as produced by `sha256sum`.
+	Sha256,
+	/// BLAKE3 with 256-bit output, as produced by `b3sum`.
+	Blake3,
+}
+
+impl ChecksumAlgorithm {
+	/// Returns the lowercase hex digest of `bytes`.
+	pub fn hash(&self, bytes: &[u8]) -> String {
+		match self {
+			ChecksumAlgorithm::Sha256 => to_hex(&Sha256::digest(bytes)),
+			ChecksumAlgorithm::Blake3 => blake3::hash(bytes).to_hex().to_string(),
+		}
+	}
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ChecksumAlgorithm::Sha256 => "sha256",
+			ChecksumAlgorithm::Blake3 => "blake3",
+		})
+	}
+}
+
+impl FromStr for ChecksumAlgorithm {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		Ok(match s.to_ascii_lowercase().as_str() {
+			"sha256" | "sha-256" => ChecksumAlgorithm::Sha256,
+			"blake3" => ChecksumAlgorithm::Blake3,
+			_ => bail!("unknown checksum algorithm \"{s}\", expected \"sha256\" or \"blake3\""),
+		})
+	}
+}
+
+/// A single file listed in the manifest.
+#[derive(Debug, serde::Serialize)]
+struct ManifestEntry {
+	path: String,
+	size: u64,
+	hash: String,
+}
+
+/// Collects the checksum of every written file.
+#[derive(Debug)]
+pub struct Manifest {
+	algorithm: ChecksumAlgorithm,
+	files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+	/// Creates an empty manifest using `algorithm`.
+	pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+		Self {
+			algorithm,
+			files: Vec::new(),
+		}
+	}
+
+	/// Hashes `bytes` and records them under `path`.
+	pub fn add_file(&mut self, path: &str, bytes: &[u8]) {
+		self.files.push(ManifestEntry {
+			path: path.to_string(),
+			size: bytes.len() as u64,
+			hash: self.algorithm.hash(bytes),
+		});
+	}
+
+	/// Serializes the manifest as pretty-printed JSON.
+	///
+	/// Files are sorted by path, so the output doesn't depend on the order in
+	/// which (parallel) rendering finished. The overall `digest` is the hash of
+	/// the sorted list in `sha256sum`/`b3sum` format (`"{hash}  {path}\n"` per file).
+	pub fn to_json(&self) -> Result<Vec<u8>> {
+		#[derive(serde::Serialize)]
+		struct Json<'a> {
+			algorithm: String,
+			digest: String,
+			files: Vec<&'a ManifestEntry>,
+		}
+
+		let mut files = self.files.iter().collect::<Vec<_>>();
+		files.sort_by(|a, b| a.path.cmp(&b.path));
+		let list = files
+			.iter()
+			.map(|f| format!("{}  {}\n", f.hash, f.path))
+			.collect::<String>();
+
+		Ok(serde_json::to_vec_pretty(&Json {
+			algorithm: self.algorithm.to_string(),
+			digest: self.algorithm.hash(list.as_bytes()),
+			files,
+		})?)
+	}
+}
+
+/// Returns the content-addressed name of a file with `bytes`: the first
+/// `length` lowercase hex digits of their SHA-256, followed by `extension`,
+/// like `"ba7816bf8f01cfea.pbf"` for `b"abc"` and `".pbf"`.
+///
+/// The hash function is fixed and unseeded, unlike [`std::hash`], so identical
+/// content gets the same name across runs, machines and versions, e.g. to store
+/// identical glyph blocks once in a shared file.
+///
+/// # Errors
+///
+/// Returns an error if `length` isn't between 1 and 64.
+#[allow(dead_code)] // Public API; the CLI writes every block under its own path.
+pub fn content_filename(bytes: &[u8], length: usize, extension: &str) -> Result<String> {
+	ensure!(
+		(1..=64).contains(&length),
+		"hash length must be between 1 and 64 hex digits, got {length}"
+	);
+	let mut name = ChecksumAlgorithm::Sha256.hash(bytes);
+	name.truncate(length);
+	name.push_str(extension);
+	Ok(name)
+}
+
+/// Formats bytes as a lowercase hex string.
+fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_hash_known_values() {
+		assert_eq!(
+			ChecksumAlgorithm::Sha256.hash(b"abc"),
+			"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+		);
+		assert_eq!(
+			ChecksumAlgorithm::Blake3.hash(b"abc"),
+			"6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+		);
+	}
+
+	#[test]
+	fn test_content_filename() -> Result<()> {
+		assert_eq!(
+			content_filename(b"abc", CONTENT_HASH_LENGTH, ".pbf")?,
+			"ba7816bf8f01cfea.pbf"
+		);
+		assert_eq!(content_filename(b"abc", 4, "")?, "ba78");
+		assert_eq!(
+			content_filename(b"abc", 64, "")?,
+			ChecksumAlgorithm::Sha256.hash(b"abc")
+		);
+		assert!(content_filename(b"abc", 0, "").is_err());
+		assert!(content_filename(b"abc", 65, "").is_err());
+
+		// Rendering the same block twice gives the same name, and another block another one.
+		let render = |index: usize| -> Result<Vec<u8>> {
+			let font = crate::font::FontWrapper::from(crate::font::FontFileEntry::new(
+				include_bytes!("../../testdata/Fira Sans - Regular.ttf").to_vec(),
+			)?);
+			let renderer = crate::render::Renderer::new_precise();
+			Ok(font.get_blocks()[index].render(String::from("Fira Sans Regular"), &renderer)?)
+		};
+		let name = content_filename(&render(0)?, CONTENT_HASH_LENGTH, ".pbf")?;
+		assert_eq!(
+			name,
+			content_filename(&render(0)?, CONTENT_HASH_LENGTH, ".pbf")?
+		);
+		assert_ne!(
+			name,
+			content_filename(&render(1)?, CONTENT_HASH_LENGTH, ".pbf")?
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_algorithm() {
+		assert_eq!(
+			"sha256".parse::<ChecksumAlgorithm>().unwrap(),
+			ChecksumAlgorithm::Sha256
+		);
+		assert_eq!(
+			"BLAKE3".parse::<ChecksumAlgorithm>().unwrap(),
+			ChecksumAlgorithm::Blake3
+		);
+		assert!("md5".parse::<ChecksumAlgorithm>().is_err());
+	}
+
+	#[test]
+	fn test_manifest_is_sorted() -> Result<()> {
+		let mut manifest = Manifest::new(ChecksumAlgorithm::Sha256);
+		manifest.add_file("b.pbf", b"abc");
+		manifest.add_file("a.pbf", b"");
+
+		let json = String::from_utf8(manifest.to_json()?)?;
+		let value: serde_json::Value = serde_json::from_str(&json)?;
+		assert_eq!(value["algorithm"], "sha256");
+		assert_eq!(value["files"][0]["path"], "a.pbf");
+		assert_eq!(value["files"][1]["size"], 3);
+
+		let list = format!(
+			"{}  a.pbf\n{}  b.pbf\n",
+			ChecksumAlgorithm::Sha256.hash(b""),
+			ChecksumAlgorithm::Sha256.hash(b"abc")
+		);
+		assert_eq!(
+			value["digest"],
+			ChecksumAlgorithm::Sha256.hash(list.as_bytes())
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_dropped_writer_writes_no_manifest() -> Result<()> {
+		use crate::writer::Writer;
+		let temp = tempfile::tempdir()?;
+		{
+			let mut writer =
+				Writer::new_file(temp.path().to_path_buf()).with_checksums(ChecksumAlgorithm::Sha256);
+			writer.write_file("a.pbf", b"abc")?;
+		}
+		assert!(temp.path().join("a.pbf").exists());
+		assert!(!temp.path().join(MANIFEST_FILENAME).exists());
+		Ok(())
+	}
+}
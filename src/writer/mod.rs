@@ -1,18 +1,65 @@
 //! Writers for storing glyph data in files or tar archives.
 
+mod bundle;
 #[cfg(test)]
 mod dummy;
 mod file;
+mod gzip;
+mod manifest;
+mod retry;
 mod tar;
 
-use anyhow::Result;
+use crate::error::GlyphError;
+use anyhow::{anyhow, ensure, Result};
+use bundle::Bundler;
+#[allow(unused_imports)]
+pub use bundle::{split_bundle, BundleEntry, BUNDLE_FILENAME, BUNDLE_INDEX_FILENAME};
+pub use gzip::GzipNaming;
+use manifest::Manifest;
+#[allow(unused_imports)]
+pub use manifest::{content_filename, CONTENT_HASH_LENGTH};
+pub use manifest::{ChecksumAlgorithm, MANIFEST_FILENAME};
+pub use retry::RetryPolicy;
+use retry::RetryWriter;
+use std::{
+	borrow::Cow,
+	io::{ErrorKind, Read, Write},
+};
 
 trait WriterTrait
 where
 	Self: Send + Sync,
 {
 	fn write_file(&mut self, filename: &str, bytes: &[u8]) -> Result<()>;
+	/// Writes exactly `size` bytes from `reader`. By default, they are buffered
+	/// and passed to [`write_file`](WriterTrait::write_file).
+	fn write_file_from_reader(
+		&mut self,
+		filename: &str,
+		size: u64,
+		reader: &mut dyn Read,
+	) -> Result<()> {
+		self.write_file(filename, &read_exact_to_vec(reader, size)?)
+	}
 	fn write_directory(&mut self, dirname: &str) -> Result<()>;
+	/// Returns `true` if `filename` was already written, e.g. by an earlier run.
+	/// Only outputs that can be read back know this, so by default it's `false`.
+	fn file_exists(&self, _filename: &str) -> bool {
+		false
+	}
+	/// Returns the content of `filename` if it was already written, e.g. by an
+	/// earlier run. Like [`file_exists`](WriterTrait::file_exists), it's `None`
+	/// by default.
+	fn read_file(&self, _filename: &str) -> Result<Option<Vec<u8>>> {
+		Ok(None)
+	}
+	/// Makes the writer retry the writes to its sink itself, as described by
+	/// `policy`, if a failed call may have left part of its data in the output,
+	/// so repeating the whole call would corrupt it. Returns `false` if it
+	/// doesn't, so [`RetryWriter`] repeats whole calls instead.
+	fn retry_writes(&mut self, _policy: RetryPolicy) -> bool {
+		false
+	}
 	fn finish(&mut self) -> Result<()>;
 	#[cfg(test)]
 	fn get_inner(&self) -> Option<&[String]>;
@@ -20,44 +67,333 @@ where
 
 /// A struct for writing files and directories to various output targets.
 pub struct Writer<'a> {
-	writer: Box<dyn WriterTrait + 'a>,
+	/// The output. It is only `None` while [`Self::with_retries`] wraps it.
+	writer: Option<Box<dyn WriterTrait + 'a>>,
 	finished: bool,
+	manifest: Option<Manifest>,
+	bundler: Option<Bundler>,
+	/// Whether `.pbf` files are gzip-compressed, and how they are named then.
+	gzip: Option<GzipNaming>,
+	/// Whether `.json` files are compressed and named like the `.pbf` files.
+	gzip_json: bool,
+	/// Prepended to every written path, either empty or ending with `/`.
+	prefix: String,
 }
 
 impl<'a> Writer<'a> {
 	/// Creates a new `Writer` that writes to a tar archive.
 	pub fn new_tar<W: std::io::Write + Send + Sync + 'static>(writer: &'a mut W) -> Self {
-		Self {
-			writer: Box::new(tar::TarWriter::new(writer)),
-			finished: false,
-		}
+		Self::from_inner(Box::new(tar::TarWriter::new(writer)))
+	}
+
+	/// Like [`Self::new_tar`], but sets the modification time of every entry to
+	/// `mtime` seconds since the Unix epoch, so the same files always produce
+	/// the same archive.
+	pub fn new_tar_with_mtime<W: std::io::Write + Send + Sync + 'static>(
+		writer: &'a mut W,
+		mtime: u64,
+	) -> Self {
+		Self::from_inner(Box::new(tar::TarWriter::new(writer).with_mtime(mtime)))
 	}
 
 	/// Creates a new `Writer` that writes to a directory on the filesystem.
 	pub fn new_file(folder: std::path::PathBuf) -> Self {
-		Self {
-			writer: Box::new(file::FileWriter::new(folder)),
-			finished: false,
-		}
+		Self::from_inner(Box::new(file::FileWriter::new(folder)))
 	}
 
 	#[cfg(test)]
 	/// Creates a new `Writer` that writes to an in-memory buffer.
 	pub fn new_dummy() -> Self {
+		Self::from_inner(Box::new(dummy::DummyWriter::default()))
+	}
+
+	#[cfg(test)]
+	/// Creates a new `Writer` that keeps files in memory, but cuts every `.pbf`
+	/// file in half, like a truncated write.
+	pub fn new_corrupting() -> Self {
+		Self::from_inner(Box::new(dummy::CorruptingWriter::default()))
+	}
+
+	/// Creates a new `Writer` around `writer`, without any options.
+	fn from_inner(writer: Box<dyn WriterTrait + 'a>) -> Self {
 		Self {
-			writer: Box::new(dummy::DummyWriter::default()),
+			writer: Some(writer),
 			finished: false,
+			manifest: None,
+			bundler: None,
+			gzip: None,
+			gzip_json: false,
+			prefix: String::new(),
+		}
+	}
+
+	/// Enables checksums: every file written from now on is hashed with
+	/// `algorithm`, and [`Self::finish`] adds a [`MANIFEST_FILENAME`] listing
+	/// the per-file hashes and an overall digest.
+	pub fn with_checksums(mut self, algorithm: ChecksumAlgorithm) -> Self {
+		self.manifest = Some(Manifest::new(algorithm));
+		self
+	}
+
+	/// Enables bundling: instead of separate `{start}-{end}.pbf` files, each font
+	/// directory gets a single [`BUNDLE_FILENAME`] plus a [`BUNDLE_INDEX_FILENAME`]
+	/// offset table, written by [`Self::finish`].
+	///
+	/// All blocks are kept in memory until then.
+	pub fn with_bundles(mut self) -> Self {
+		self.bundler = Some(Bundler::default());
+		self
+	}
+
+	/// Compresses every `.pbf` file with gzip and names it as described by
+	/// `naming`, so a static server can serve the blocks with
+	/// `Content-Encoding: gzip`. JSON files stay uncompressed, unless
+	/// [`Self::with_gzip_json`] is set.
+	///
+	/// With bundling enabled, each bundle is compressed as a whole, so its
+	/// offset table refers to the uncompressed bytes.
+	pub fn with_gzip_blocks(mut self, naming: GzipNaming) -> Self {
+		self.gzip = Some(naming);
+		self
+	}
+
+	/// Also compresses every `.json` file, like `index.json` and
+	/// `font_families.json`, and names it like the blocks, so the whole output
+	/// is encoded the same way. Only takes effect with [`Self::with_gzip_blocks`].
+	///
+	/// The [`MANIFEST_FILENAME`] stays uncompressed.
+	pub fn with_gzip_json(mut self) -> Self {
+		self.gzip_json = true;
+		self
+	}
+
+	/// Returns how `filename` is named if it is compressed, or `None` if it isn't.
+	fn gzip_naming(&self, filename: &str) -> Option<GzipNaming> {
+		self
+			.gzip
+			.filter(|_| filename.ends_with(".pbf") || (self.gzip_json && filename.ends_with(".json")))
+	}
+
+	/// Returns the name under which `filename` is written, without the prefix.
+	fn output_filename<'n>(&self, filename: &'n str) -> Cow<'n, str> {
+		match self.gzip_naming(filename) {
+			Some(naming) => naming.filename(filename),
+			None => Cow::Borrowed(filename),
+		}
+	}
+
+	/// Retries writes that fail with a transient I/O error, like
+	/// [`std::io::ErrorKind::Interrupted`], as described by `policy`.
+	///
+	/// Useful for a directory on a flaky network share. Other errors still fail
+	/// immediately.
+	///
+	/// Tar outputs, e.g. streamed to stdout over the network, retry every
+	/// single write to their sink instead: a failed entry may already be partly
+	/// written to the archive, and repeating it would corrupt the archive.
+	pub fn with_retries(mut self, policy: RetryPolicy) -> Self {
+		if let Some(mut inner) = self.writer.take() {
+			if !inner.retry_writes(policy) {
+				inner = Box::new(RetryWriter::new(inner, policy));
+			}
+			self.writer = Some(inner);
 		}
+		self
+	}
+
+	/// Returns the output.
+	fn inner(&self) -> &(dyn WriterTrait + 'a) {
+		self.writer.as_deref().expect("the output is always set")
+	}
+
+	/// Returns the output for writing.
+	fn inner_mut(&mut self) -> &mut (dyn WriterTrait + 'a) {
+		self
+			.writer
+			.as_deref_mut()
+			.expect("the output is always set")
+	}
+
+	/// Writes every file and directory below `prefix`, like `"fonts"` or
+	/// `"assets/fonts/"`, and writes the directory entries of the prefix itself.
+	///
+	/// Paths in the manifest and the bundle offset tables stay relative to the prefix.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `prefix` is empty, absolute, or contains empty, `.` or
+	/// `..` segments, backslashes or NUL bytes, or if writing the directories fails.
+	pub fn with_prefix(mut self, prefix: &str) -> Result<Self, GlyphError> {
+		let trimmed = prefix.strip_suffix('/').unwrap_or(prefix);
+		for segment in trimmed.split('/') {
+			if matches!(segment, "" | "." | "..") || segment.contains(['\\', '\0']) {
+				return Err(GlyphError::Other(anyhow!(
+					"invalid prefix {prefix:?}: expected a relative path like \"fonts/\""
+				)));
+			}
+			self.prefix = format!("{}{segment}/", self.prefix);
+			let prefix = self.prefix.clone();
+			self.inner_mut().write_directory(&prefix)?;
+		}
+		Ok(self)
 	}
 
 	/// Writes the given bytes to a file with the given filename.
-	pub fn write_file(&mut self, filename: &str, bytes: &[u8]) -> Result<()> {
-		self.writer.write_file(filename, bytes)
+	///
+	/// # Errors
+	///
+	/// Returns [`GlyphError::Io`] if the output can't be written.
+	pub fn write_file(&mut self, filename: &str, bytes: &[u8]) -> Result<(), GlyphError> {
+		if let Some(bundler) = &mut self.bundler {
+			if bundler.add(filename, bytes).is_some() {
+				return Ok(());
+			}
+		}
+		let bytes = match self.gzip_naming(filename) {
+			Some(_) => Cow::Owned(gzip::gzip(bytes)?),
+			None => Cow::Borrowed(bytes),
+		};
+		self.write_output(filename, &bytes)
+	}
+
+	/// Returns `true` if every `.pbf` file is compressed on its own when it is
+	/// written, i.e. gzip is enabled without bundling. Then the blocks can be
+	/// compressed with [`Self::compress`] in parallel beforehand, and written
+	/// with [`Self::write_compressed_block`].
+	pub fn compresses_blocks(&self) -> bool {
+		self.gzip.is_some() && self.bundler.is_none()
+	}
+
+	/// Compresses a glyph block like [`Self::write_file`] does if
+	/// [`Self::compresses_blocks`], at the best compression level.
+	///
+	/// # Errors
+	///
+	/// Returns an error if compressing fails.
+	pub fn compress(bytes: &[u8]) -> Result<Vec<u8>, GlyphError> {
+		Ok(gzip::gzip(bytes)?)
+	}
+
+	/// Writes the `.pbf` file `filename` that was already compressed with
+	/// [`Self::compress`], like [`Self::write_file`] writes the uncompressed one.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the writer doesn't [compress blocks](Self::compresses_blocks),
+	/// `filename` isn't a `.pbf` file, or the output can't be written.
+	pub fn write_compressed_block(
+		&mut self,
+		filename: &str,
+		compressed: &[u8],
+	) -> Result<(), GlyphError> {
+		if !self.compresses_blocks() || !filename.ends_with(".pbf") {
+			return Err(GlyphError::Other(anyhow!(
+				"{filename:?} isn't written as a compressed glyph block"
+			)));
+		}
+		self.write_output(filename, compressed)
+	}
+
+	/// Writes the final `bytes` of `filename`, adding them to the manifest.
+	fn write_output(&mut self, filename: &str, bytes: &[u8]) -> Result<(), GlyphError> {
+		let filename = self.output_filename(filename);
+		if let Some(manifest) = &mut self.manifest {
+			manifest.add_file(&filename, bytes);
+		}
+		let path = format!("{}{filename}", self.prefix);
+		self
+			.inner_mut()
+			.write_file(&path, bytes)
+			.map_err(|err| GlyphError::from_io(err, || format!("writing file {path:?}")))
+	}
+
+	/// Writes a file with the given filename, streaming exactly `size` bytes from `reader`.
+	///
+	/// Tar and file outputs copy the data in chunks without holding it in memory.
+	/// With checksums, bundling or gzip enabled, the data is buffered and passed
+	/// to [`Self::write_file`].
+	///
+	/// # Errors
+	///
+	/// Returns an error if `reader` fails or ends before `size` bytes were read.
+	#[allow(dead_code)] // Public API; rendered blocks are always in memory.
+	pub fn write_file_from_reader(
+		&mut self,
+		filename: &str,
+		size: u64,
+		reader: &mut dyn Read,
+	) -> Result<(), GlyphError> {
+		if self.manifest.is_some() || self.bundler.is_some() || self.gzip.is_some() {
+			return self.write_file(filename, &read_exact_to_vec(reader, size)?);
+		}
+		let path = format!("{}{filename}", self.prefix);
+		self
+			.inner_mut()
+			.write_file_from_reader(&path, size, reader)
+			.map_err(|err| GlyphError::from_io(err, || format!("writing file {path:?}")))
+	}
+
+	/// Returns `true` if the file `filename` already exists in the output, so
+	/// writing it again can be skipped.
+	///
+	/// Only directory outputs report existing files. With checksums or bundling
+	/// enabled this is always `false`, since every file has to pass through the
+	/// manifest or the bundle.
+	pub fn file_exists(&self, filename: &str) -> bool {
+		self.manifest.is_none()
+			&& self.bundler.is_none()
+			&& self.inner().file_exists(&format!(
+				"{}{}",
+				self.prefix,
+				self.output_filename(filename)
+			))
+	}
+
+	/// Returns the content of the file `filename` if it already exists in the
+	/// output, e.g. an `index.json` to merge new fonts into, decompressed if it
+	/// was written compressed.
+	///
+	/// The output may have been written with other compression settings, so
+	/// this falls back to the uncompressed name, and only decompresses files
+	/// that start with the gzip magic bytes.
+	///
+	/// Only directory outputs can be read back, so this is `None` otherwise.
+	pub fn read_file(&self, filename: &str) -> Result<Option<Vec<u8>>, GlyphError> {
+		let read = |name: &str| self.inner().read_file(&format!("{}{}", self.prefix, name));
+		let output_filename = self.output_filename(filename);
+		let mut bytes = read(&output_filename)?;
+		if bytes.is_none() && output_filename != filename {
+			bytes = read(filename)?;
+		}
+		Ok(match bytes {
+			Some(bytes) if gzip::is_gzipped(&bytes) => Some(gzip::gunzip(&bytes)?),
+			bytes => bytes,
+		})
+	}
+
+	/// Returns the glyph block `filename` as it was written, decompressed if
+	/// gzip is enabled, e.g. to check that it decodes.
+	///
+	/// This is `None` if the output can't be read back, like a tar archive, or
+	/// with bundling, since bundles are only written by [`Self::finish`].
+	pub fn read_block(&self, filename: &str) -> Result<Option<Vec<u8>>, GlyphError> {
+		if self.bundler.is_some() {
+			return Ok(None);
+		}
+		self.read_file(filename)
 	}
 
 	/// Writes an empty directory with the given name.
-	pub fn write_directory(&mut self, dirname: &str) -> Result<()> {
-		self.writer.write_directory(dirname)
+	///
+	/// # Errors
+	///
+	/// Returns [`GlyphError::Io`] if the output can't be written.
+	pub fn write_directory(&mut self, dirname: &str) -> Result<(), GlyphError> {
+		let path = format!("{}{dirname}", self.prefix);
+		self
+			.inner_mut()
+			.write_directory(&path)
+			.map_err(|err| GlyphError::from_io(err, || format!("writing directory {path:?}")))
 	}
 
 	/// Finishes writing to the output target.
@@ -65,18 +401,40 @@ impl<'a> Writer<'a> {
 	/// Idempotent: subsequent calls (including the implicit one in [`Drop`])
 	/// are no-ops, so explicitly calling `finish()` will not produce duplicate
 	/// trailers (e.g. extra zero-padding in a tar archive).
-	pub fn finish(&mut self) -> Result<()> {
+	///
+	/// If bundling is enabled, the bundles are written first, followed by the
+	/// manifest if checksums are enabled.
+	///
+	/// # Errors
+	///
+	/// Returns [`GlyphError::Io`] if the output can't be written or flushed.
+	pub fn finish(&mut self) -> Result<(), GlyphError> {
 		if self.finished {
 			return Ok(());
 		}
 		self.finished = true;
-		self.writer.finish()
+		if let Some(bundler) = self.bundler.take() {
+			for (filename, bytes) in bundler.into_files()? {
+				self.write_file(&filename, &bytes)?;
+			}
+		}
+		if let Some(manifest) = self.manifest.take() {
+			let path = format!("{}{MANIFEST_FILENAME}", self.prefix);
+			self
+				.inner_mut()
+				.write_file(&path, &manifest.to_json()?)
+				.map_err(|err| GlyphError::from_io(err, || format!("writing file {path:?}")))?;
+		}
+		self
+			.inner_mut()
+			.finish()
+			.map_err(|err| GlyphError::from_io(err, || String::from("finishing the output")))
 	}
 
 	#[cfg(test)]
 	/// Returns the inner buffer of the writer, if available.
 	pub fn get_inner(&self) -> Option<&[String]> {
-		self.writer.get_inner()
+		self.inner().get_inner()
 	}
 }
 
@@ -85,13 +443,58 @@ impl Drop for Writer<'_> {
 	/// here is logged to stderr and the underlying I/O error is otherwise
 	/// dropped. Callers that care about finalize errors should call
 	/// [`Writer::finish`] explicitly.
+	///
+	/// Only the inner writer is finalized: the bundles and the manifest are
+	/// written by an explicit [`Writer::finish`] alone, so an output abandoned
+	/// by an error never gets a manifest that vouches for it.
 	fn drop(&mut self) {
 		if self.finished {
 			return;
 		}
-		if let Err(e) = self.writer.finish() {
+		self.finished = true;
+		if let Err(e) = self.inner_mut().finish() {
 			eprintln!("warning: writer finalize failed during drop: {e:#}");
 		}
-		self.finished = true;
 	}
 }
+
+/// Size of the chunks in which [`copy_exact`] copies data.
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Upper limit for the memory [`read_exact_to_vec`] reserves up front, so a
+/// wrong `size` can't allocate more than the reader actually delivers.
+const MAX_PREALLOCATION: u64 = 16 * 1024 * 1024;
+
+/// Copies exactly `size` bytes from `reader` to `writer` in chunks of [`COPY_CHUNK_SIZE`].
+/// Reads interrupted by a signal ([`ErrorKind::Interrupted`]) are retried.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails, or if `reader` ends early.
+fn copy_exact(reader: &mut dyn Read, writer: &mut dyn Write, size: u64) -> Result<()> {
+	let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+	let mut remaining = size;
+	while remaining > 0 {
+		let chunk = remaining.min(COPY_CHUNK_SIZE as u64) as usize;
+		let read = match reader.read(&mut buffer[..chunk]) {
+			Ok(read) => read,
+			Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+			Err(err) => return Err(err.into()),
+		};
+		ensure!(
+			read > 0,
+			"unexpected end of data: expected {size} bytes, got {}",
+			size - remaining
+		);
+		writer.write_all(&buffer[..read])?;
+		remaining -= read as u64;
+	}
+	Ok(())
+}
+
+/// Reads exactly `size` bytes from `reader` into memory, see [`copy_exact`].
+fn read_exact_to_vec(reader: &mut dyn Read, size: u64) -> Result<Vec<u8>> {
+	let mut bytes = Vec::with_capacity(size.min(MAX_PREALLOCATION) as usize);
+	copy_exact(reader, &mut bytes, size)?;
+	Ok(bytes)
+}
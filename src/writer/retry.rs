@@ -0,0 +1,280 @@
+//! Retries writes to outputs that fail transiently, e.g. a directory on a network share.
+
+use super::WriterTrait;
+use anyhow::Result;
+use std::{
+	io::{self, Write},
+	thread,
+	time::Duration,
+};
+
+/// How often and how patiently [`RetryWriter`] retries a failed write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// Number of retries after the first attempt.
+	pub retries: u32,
+	/// Delay before the first retry. It doubles with every further retry.
+	pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+	/// Three retries, after 100, 200 and 400 milliseconds.
+	fn default() -> Self {
+		RetryPolicy {
+			retries: 3,
+			backoff: Duration::from_millis(100),
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Calls `op` until it succeeds, fails with an error that `is_transient`
+	/// rejects, or runs out of retries.
+	fn run<T, E>(
+		&self,
+		is_transient: impl Fn(&E) -> bool,
+		mut op: impl FnMut() -> Result<T, E>,
+	) -> Result<T, E> {
+		let mut delay = self.backoff;
+		let mut retries = self.retries;
+		loop {
+			match op() {
+				Err(err) if retries > 0 && is_transient(&err) => {
+					thread::sleep(delay);
+					delay = delay.saturating_mul(2);
+					retries -= 1;
+				}
+				result => return result,
+			}
+		}
+	}
+}
+
+/// Wraps another writer and retries `write_file`, `write_directory` and
+/// `finish` if they fail with [`io::ErrorKind::Interrupted`] or
+/// [`io::ErrorKind::WouldBlock`]. Any other error is returned immediately.
+///
+/// A retry repeats the whole call, so the inner writer must not keep any part
+/// of a failed write, see [`WriterTrait::retry_writes`]. Streamed writes are
+/// buffered by the default [`WriterTrait::write_file_from_reader`], so they
+/// can be repeated.
+pub struct RetryWriter<'a> {
+	inner: Box<dyn WriterTrait + 'a>,
+	policy: RetryPolicy,
+}
+
+impl<'a> RetryWriter<'a> {
+	/// Creates a new `RetryWriter` around `inner`.
+	pub fn new(inner: Box<dyn WriterTrait + 'a>, policy: RetryPolicy) -> Self {
+		RetryWriter { inner, policy }
+	}
+
+	/// Calls `op` until it succeeds, fails permanently or runs out of retries.
+	fn retry(&mut self, mut op: impl FnMut(&mut dyn WriterTrait) -> Result<()>) -> Result<()> {
+		let inner = &mut self.inner;
+		self.policy.run(is_transient, || op(inner.as_mut()))
+	}
+}
+
+/// Wraps an [`io::Write`] sink, like the one of a tar archive, and retries
+/// `write` and `flush` calls that fail with [`io::ErrorKind::Interrupted`] or
+/// [`io::ErrorKind::WouldBlock`], if it has a [`RetryPolicy`].
+///
+/// A failed `write` call consumes no bytes, so repeating it is safe in the
+/// middle of an entry, unlike repeating a whole [`WriterTrait`] call.
+pub struct RetrySink<W: Write> {
+	inner: W,
+	policy: Option<RetryPolicy>,
+}
+
+impl<W: Write> RetrySink<W> {
+	/// Creates a new `RetrySink` around `inner` that doesn't retry yet.
+	pub fn new(inner: W) -> Self {
+		RetrySink {
+			inner,
+			policy: None,
+		}
+	}
+
+	/// Retries failed writes as described by `policy`.
+	pub fn set_policy(&mut self, policy: RetryPolicy) {
+		self.policy = Some(policy);
+	}
+
+	/// Calls `op` on the sink, retrying it if there is a policy.
+	fn retry<T>(&mut self, mut op: impl FnMut(&mut W) -> io::Result<T>) -> io::Result<T> {
+		let inner = &mut self.inner;
+		match &self.policy {
+			Some(policy) => policy.run(is_transient_io, || op(inner)),
+			None => op(inner),
+		}
+	}
+}
+
+impl<W: Write> Write for RetrySink<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.retry(|inner| inner.write(buf))
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.retry(|inner| inner.flush())
+	}
+}
+
+/// Returns `true` if `err` is caused by an I/O error that may go away on its own.
+fn is_transient(err: &anyhow::Error) -> bool {
+	err.chain().any(|cause| {
+		cause
+			.downcast_ref::<io::Error>()
+			.is_some_and(is_transient_io)
+	})
+}
+
+/// Returns `true` if `err` may go away on its own.
+fn is_transient_io(err: &io::Error) -> bool {
+	matches!(
+		err.kind(),
+		io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+	)
+}
+
+impl WriterTrait for RetryWriter<'_> {
+	fn write_file(&mut self, filename: &str, bytes: &[u8]) -> Result<()> {
+		self.retry(|inner| inner.write_file(filename, bytes))
+	}
+
+	fn write_directory(&mut self, dirname: &str) -> Result<()> {
+		self.retry(|inner| inner.write_directory(dirname))
+	}
+
+	fn file_exists(&self, filename: &str) -> bool {
+		self.inner.file_exists(filename)
+	}
+
+	fn read_file(&self, filename: &str) -> Result<Option<Vec<u8>>> {
+		self.inner.read_file(filename)
+	}
+
+	fn finish(&mut self) -> Result<()> {
+		self.retry(|inner| inner.finish())
+	}
+
+	#[cfg(test)]
+	fn get_inner(&self) -> Option<&[String]> {
+		self.inner.get_inner()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{
+		atomic::{AtomicU32, Ordering},
+		Arc,
+	};
+
+	/// Fails the first `failures` calls with an error of `kind`.
+	struct FlakyWriter {
+		failures: u32,
+		kind: io::ErrorKind,
+		attempts: Arc<AtomicU32>,
+	}
+
+	impl WriterTrait for FlakyWriter {
+		fn write_file(&mut self, _filename: &str, _bytes: &[u8]) -> Result<()> {
+			if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+				Err(io::Error::from(self.kind))?;
+			}
+			Ok(())
+		}
+
+		fn write_directory(&mut self, _dirname: &str) -> Result<()> {
+			Ok(())
+		}
+
+		fn finish(&mut self) -> Result<()> {
+			Ok(())
+		}
+
+		fn get_inner(&self) -> Option<&[String]> {
+			None
+		}
+	}
+
+	fn flaky(
+		failures: u32,
+		kind: io::ErrorKind,
+		retries: u32,
+	) -> (RetryWriter<'static>, Arc<AtomicU32>) {
+		let attempts = Arc::new(AtomicU32::new(0));
+		let inner = FlakyWriter {
+			failures,
+			kind,
+			attempts: attempts.clone(),
+		};
+		let policy = RetryPolicy {
+			retries,
+			backoff: Duration::from_millis(1),
+		};
+		(RetryWriter::new(Box::new(inner), policy), attempts)
+	}
+
+	#[test]
+	fn test_retries_transient_errors() -> Result<()> {
+		let (mut writer, attempts) = flaky(2, io::ErrorKind::Interrupted, 3);
+		writer.write_file("font/0-255.pbf", b"data")?;
+		assert_eq!(attempts.load(Ordering::SeqCst), 3);
+		Ok(())
+	}
+
+	#[test]
+	fn test_gives_up_after_retries() {
+		let (mut writer, attempts) = flaky(5, io::ErrorKind::WouldBlock, 2);
+		assert!(writer.write_file("font/0-255.pbf", b"data").is_err());
+		assert_eq!(attempts.load(Ordering::SeqCst), 3);
+	}
+
+	#[test]
+	fn test_does_not_retry_other_errors() {
+		let (mut writer, attempts) = flaky(1, io::ErrorKind::PermissionDenied, 3);
+		assert!(writer.write_file("font/0-255.pbf", b"data").is_err());
+		assert_eq!(attempts.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn test_sink_retries_single_writes() -> Result<()> {
+		/// Fails every other call with `WouldBlock`, like a full socket buffer.
+		struct FlakySink {
+			calls: u32,
+			data: Vec<u8>,
+		}
+		impl Write for FlakySink {
+			fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+				self.calls += 1;
+				if self.calls % 2 == 1 {
+					return Err(io::ErrorKind::WouldBlock.into());
+				}
+				// Partial writes are completed by `write_all`.
+				let len = buf.len().min(3);
+				self.data.extend_from_slice(&buf[..len]);
+				Ok(len)
+			}
+			fn flush(&mut self) -> io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let mut sink = RetrySink::new(FlakySink {
+			calls: 0,
+			data: Vec::new(),
+		});
+		assert!(sink.write_all(b"abcdefg").is_err());
+		sink.set_policy(RetryPolicy {
+			retries: 1,
+			backoff: Duration::from_millis(1),
+		});
+		sink.write_all(b"abcdefg")?;
+		assert_eq!(sink.inner.data, b"abcdefg");
+		Ok(())
+	}
+}
@@ -1,7 +1,11 @@
-use super::WriterTrait;
+use super::{
+	copy_exact,
+	retry::{RetryPolicy, RetrySink},
+	WriterTrait,
+};
 use anyhow::{bail, ensure, Result};
 use std::{
-	io::{BufWriter, Write},
+	io::{BufWriter, Read, Write},
 	time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -27,18 +31,65 @@ const ZEROS_1K: [u8; 1024] = [0; 1024];
 ///   tar features beyond POSIX.1-1988.
 /// - Directories must end with a slash (`"/"`).
 pub struct TarWriter<W: Write> {
-	/// A buffered writer that collects and writes tar data.
-	writer: BufWriter<W>,
+	/// A buffered writer that collects and writes tar data, retrying failed
+	/// writes once [`WriterTrait::retry_writes`] set a policy.
+	writer: BufWriter<RetrySink<W>>,
+	/// Number of bytes written to `writer` so far, including headers and padding.
+	bytes_written: u64,
+	/// Modification time of every entry in seconds since the Unix epoch, or the
+	/// current time if `None`.
+	mtime: Option<u64>,
 }
 
 impl<W: Write> TarWriter<W> {
 	/// Creates a new [`TarWriter`] wrapping the provided `writer`.
 	pub fn new(writer: W) -> Self {
 		Self {
-			writer: BufWriter::new(writer),
+			writer: BufWriter::new(RetrySink::new(writer)),
+			bytes_written: 0,
+			mtime: None,
 		}
 	}
 
+	/// Sets the modification time of every entry to `mtime` seconds since the
+	/// Unix epoch instead of the current time, e.g. for reproducible archives.
+	pub fn with_mtime(mut self, mtime: u64) -> Self {
+		self.mtime = Some(mtime);
+		self
+	}
+
+	/// Returns the number of bytes of the archive written so far, including
+	/// headers, padding and, after [`finish`](WriterTrait::finish), the end marker.
+	///
+	/// Buffered bytes are counted, even if they haven't reached the wrapped
+	/// writer yet.
+	#[allow(dead_code)] // Public API; lets tests check archives for consumers.
+	pub fn bytes_written(&self) -> u64 {
+		self.bytes_written
+	}
+
+	/// Checks that the archive written so far ends on a 512-byte block boundary,
+	/// as it does after every complete entry.
+	///
+	/// # Errors
+	///
+	/// Returns an error with the byte count if it isn't a multiple of 512.
+	pub fn verify_alignment(&self) -> Result<()> {
+		ensure!(
+			self.bytes_written % 512 == 0,
+			"tar archive is not aligned to 512-byte blocks: {} bytes written",
+			self.bytes_written
+		);
+		Ok(())
+	}
+
+	/// Writes `bytes` and counts them in [`Self::bytes_written`].
+	fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+		self.writer.write_all(bytes)?;
+		self.bytes_written += bytes.len() as u64;
+		Ok(())
+	}
+
 	/// Builds and writes a 512-byte tar header for a file or directory.
 	///
 	/// # Parameters
@@ -65,10 +116,12 @@ impl<W: Write> TarWriter<W> {
 		write_octal(&mut header[124..136], size);
 
 		// Last modification time in numeric Unix time (octal, bytes 136..148)
-		let mtime = SystemTime::now()
-			.duration_since(UNIX_EPOCH)
-			.unwrap_or_default()
-			.as_secs();
+		let mtime = self.mtime.unwrap_or_else(|| {
+			SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs()
+		});
 		write_octal(&mut header[136..148], mtime);
 
 		// Type flag (file= '0', directory= '5'), byte 156
@@ -85,7 +138,15 @@ impl<W: Write> TarWriter<W> {
 		let csum: u32 = header.iter().map(|&b| b as u32).sum();
 		write_octal(&mut header[148..156], csum as u64);
 
-		self.writer.write_all(&header)?;
+		self.write_all(&header)
+	}
+
+	/// Pads file contents of `size` bytes to the next 512-byte boundary.
+	fn write_padding(&mut self, size: u64) -> Result<()> {
+		let remainder = size % 512;
+		if remainder != 0 {
+			self.write_all(&ZEROS_1K[0..(512 - remainder as usize)])?;
+		}
 		Ok(())
 	}
 }
@@ -101,16 +162,27 @@ impl<W: Write + Send + Sync> WriterTrait for TarWriter<W> {
 	fn write_file(&mut self, filename: &str, bytes: &[u8]) -> Result<()> {
 		let size = bytes.len() as u64;
 		self.write_header(filename, size, 0o644, b'0')?;
-		self.writer.write_all(bytes)?;
+		self.write_all(bytes)?;
+		self.write_padding(size)
+	}
 
-		// Pad file contents to a 512-byte boundary
-		let remainder = size % 512;
-		if remainder != 0 {
-			self
-				.writer
-				.write_all(&ZEROS_1K[0..(512 - remainder as usize)])?;
-		}
-		Ok(())
+	/// Streams exactly `size` bytes from `reader` as a file entry, copying them
+	/// in chunks after writing the header.
+	///
+	/// # Errors
+	///
+	/// Returns an error if writing fails or `reader` ends early, which leaves
+	/// the archive incomplete.
+	fn write_file_from_reader(
+		&mut self,
+		filename: &str,
+		size: u64,
+		reader: &mut dyn Read,
+	) -> Result<()> {
+		self.write_header(filename, size, 0o644, b'0')?;
+		copy_exact(reader, &mut self.writer, size)?;
+		self.bytes_written += size;
+		self.write_padding(size)
 	}
 
 	/// Writes a directory entry in the tar archive. The `dirname` must end with a slash.
@@ -125,13 +197,22 @@ impl<W: Write + Send + Sync> WriterTrait for TarWriter<W> {
 		Ok(())
 	}
 
+	/// Retries every single write to the sink, since a failed entry may already
+	/// be partly written to the archive, and repeating it would corrupt it.
+	fn retry_writes(&mut self, policy: RetryPolicy) -> bool {
+		self.writer.get_mut().set_policy(policy);
+		true
+	}
+
 	/// Finalizes the tar archive by writing an extra 1024 bytes of zeros.
 	///
 	/// # Errors
 	///
-	/// Returns an error if the padding write fails.
+	/// Returns an error if the padding write fails, or if the archive isn't
+	/// aligned to 512-byte blocks (see [`TarWriter::verify_alignment`]).
 	fn finish(&mut self) -> Result<()> {
-		self.writer.write_all(&ZEROS_1K)?;
+		self.write_all(&ZEROS_1K)?;
+		self.verify_alignment()?;
 		self.writer.flush()?;
 		Ok(())
 	}
@@ -173,6 +254,10 @@ fn write_string(dest: &mut [u8], s: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{
+		error::GlyphError,
+		writer::{GzipNaming, RetryPolicy, Writer},
+	};
 	use tar::{Archive, Entry};
 
 	#[test]
@@ -249,6 +334,41 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_bytes_written() -> Result<()> {
+		let mut output = Vec::new();
+		let written = {
+			let mut tar = TarWriter::new(&mut output);
+			assert_eq!(tar.bytes_written(), 0);
+			tar.write_file("a.txt", b"a")?;
+			assert_eq!(tar.bytes_written(), 1024);
+			tar.write_directory("dir/")?;
+			tar.write_file("dir/b.bin", &[1; 512])?;
+			tar.write_file_from_reader("dir/c.bin", 1000, &mut &[2; 1000][..])?;
+			tar.write_file("empty.txt", b"")?;
+			tar.verify_alignment()?;
+			tar.finish()?;
+			tar.bytes_written()
+		};
+
+		assert_eq!(written, output.len() as u64);
+		assert_eq!(written, 1024 + 512 + 1024 + 1536 + 512 + 1024);
+		assert_eq!(written % 512, 0);
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_alignment() {
+		let mut tar = TarWriter::new(Vec::new());
+		assert!(tar.verify_alignment().is_ok());
+		tar.write_all(b"partial").unwrap();
+		let err = tar.verify_alignment().unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"tar archive is not aligned to 512-byte blocks: 7 bytes written"
+		);
+	}
+
 	#[test]
 	fn test_real_decoder() -> Result<()> {
 		let mut output = Vec::new();
@@ -284,6 +404,169 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_write_file_from_reader() -> Result<()> {
+		let data = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+		let mut output = Vec::new();
+		{
+			let mut tar = TarWriter::new(&mut output);
+			tar.write_file_from_reader("stream.bin", 10_000, &mut std::io::Cursor::new(&data))?;
+			tar.write_file("after.txt", b"ok")?;
+			tar.finish()?;
+		}
+
+		let mut archive = Archive::new(&output[..]);
+		let mut entries = archive.entries()?;
+		let mut entry = entries.next().unwrap()?;
+		assert_eq!(entry.path()?.to_str(), Some("stream.bin"));
+		let mut content = Vec::new();
+		entry.read_to_end(&mut content)?;
+		assert_eq!(content, data);
+
+		// The padding is correct, so the next entry decodes as well.
+		let entry = entries.next().unwrap()?;
+		assert_eq!(entry.path()?.to_str(), Some("after.txt"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_write_file_from_short_reader_errors() {
+		let mut output = Vec::new();
+		let mut tar = TarWriter::new(&mut output);
+		let err = tar
+			.write_file_from_reader("short.bin", 100, &mut &b"only 13 bytes"[..])
+			.unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"unexpected end of data: expected 100 bytes, got 13"
+		);
+	}
+
+	#[test]
+	fn test_write_file_from_interrupted_reader() -> Result<()> {
+		/// Fails every other read with [`std::io::ErrorKind::Interrupted`].
+		struct InterruptingReader<'a> {
+			data: &'a [u8],
+			interrupt: bool,
+		}
+		impl Read for InterruptingReader<'_> {
+			fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+				self.interrupt = !self.interrupt;
+				if self.interrupt {
+					return Err(std::io::ErrorKind::Interrupted.into());
+				}
+				let len = buf.len().min(self.data.len()).min(7);
+				buf[..len].copy_from_slice(&self.data[..len]);
+				self.data = &self.data[len..];
+				Ok(len)
+			}
+		}
+
+		let mut output = Vec::new();
+		{
+			let mut tar = TarWriter::new(&mut output);
+			let mut reader = InterruptingReader {
+				data: b"interrupted content",
+				interrupt: false,
+			};
+			tar.write_file_from_reader("file.txt", 19, &mut reader)?;
+			tar.finish()?;
+		}
+		let mut archive = Archive::new(&output[..]);
+		let mut content = String::new();
+		archive
+			.entries()?
+			.next()
+			.unwrap()?
+			.read_to_string(&mut content)?;
+		assert_eq!(content, "interrupted content");
+		Ok(())
+	}
+
+	#[test]
+	fn test_write_file_from_reader_with_oversized_size() {
+		// The buffered path doesn't reserve the claimed size up front.
+		let mut writer = Writer::new_dummy().with_gzip_blocks(GzipNaming::Pbf);
+		let err = writer
+			.write_file_from_reader("font/0-255.pbf", u64::MAX, &mut &b"tiny"[..])
+			.unwrap_err();
+		assert!(err.to_string().contains("unexpected end of data"));
+	}
+
+	#[test]
+	fn test_failing_output_returns_io_errors() {
+		/// Fails every write, like a closed pipe.
+		struct FailingWrite;
+		impl Write for FailingWrite {
+			fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+				Err(std::io::ErrorKind::BrokenPipe.into())
+			}
+			fn flush(&mut self) -> std::io::Result<()> {
+				Err(std::io::ErrorKind::BrokenPipe.into())
+			}
+		}
+		let is_broken_pipe = |err: &GlyphError| matches!(err, GlyphError::Io { source, .. } if source.kind() == std::io::ErrorKind::BrokenPipe);
+
+		let mut output = FailingWrite;
+		let mut writer = Writer::new_tar(&mut output);
+		// Larger than the buffer, so it reaches the output right away.
+		let err = writer
+			.write_file("font/0-255.pbf", &[0; 16384])
+			.unwrap_err();
+		assert!(is_broken_pipe(&err), "{err:?}");
+		assert_eq!(err.to_string(), "writing file \"font/0-255.pbf\"");
+
+		// Small entries are buffered until the archive is finished.
+		writer.write_directory("font/").unwrap();
+		let err = writer.finish().unwrap_err();
+		assert!(is_broken_pipe(&err), "{err:?}");
+	}
+
+	#[test]
+	fn test_tar_writes_are_retried_per_write() -> Result<()> {
+		/// Fails every other write, like a full socket buffer.
+		struct FlakyWrite {
+			calls: u32,
+			data: Vec<u8>,
+		}
+		impl Write for FlakyWrite {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.calls += 1;
+				if self.calls % 2 == 1 {
+					return Err(std::io::ErrorKind::WouldBlock.into());
+				}
+				self.data.extend_from_slice(buf);
+				Ok(buf.len())
+			}
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		// Only the failed write is repeated, not the whole entry, so the
+		// archive has no partial entries.
+		let mut output = FlakyWrite {
+			calls: 0,
+			data: Vec::new(),
+		};
+		let mut writer = Writer::new_tar(&mut output).with_retries(RetryPolicy {
+			retries: 3,
+			backoff: std::time::Duration::from_millis(1),
+		});
+		writer.write_file("font/0-255.pbf", &[1; 16384])?;
+		writer.write_file("font/index.json", b"[]")?;
+		writer.finish()?;
+		drop(writer);
+
+		let mut archive = Archive::new(output.data.as_slice());
+		let sizes = archive
+			.entries()?
+			.map(|entry| Ok(entry?.size()))
+			.collect::<Result<Vec<_>>>()?;
+		assert_eq!(sizes, [16384, 2]);
+		Ok(())
+	}
+
 	fn bytes_until_null(buf: &[u8]) -> &str {
 		if let Some(pos) = buf.iter().position(|&b| b == 0) {
 			std::str::from_utf8(&buf[..pos]).unwrap_or("")
@@ -0,0 +1,194 @@
+//! Checks rendered glyph blocks against an independent reader of the glyph
+//! `.pbf` format, written after MapLibre GL JS's `readFontstacks`, so changes to
+//! the crate's own protobuf code can't silently break clients.
+
+use versatiles_glyphs::{
+	font::{FontFileEntry, FontWrapper},
+	render::Renderer,
+};
+
+/// The SDF padding MapLibre assumes around every bitmap.
+const BORDER: u32 = 3;
+
+#[derive(Debug, Default, PartialEq)]
+struct Fontstack {
+	name: String,
+	range: String,
+	glyphs: Vec<Glyph>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Glyph {
+	id: u32,
+	bitmap: Option<Vec<u8>>,
+	width: u32,
+	height: u32,
+	left: i32,
+	top: i32,
+	advance: u32,
+}
+
+/// A minimal protobuf reader: varints, zigzag-encoded signed varints and
+/// length-delimited fields, which is all the glyph format uses.
+struct Reader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn new(data: &'a [u8]) -> Self {
+		Reader { data, pos: 0 }
+	}
+
+	fn is_done(&self) -> bool {
+		self.pos >= self.data.len()
+	}
+
+	fn varint(&mut self) -> u64 {
+		let mut value = 0;
+		for shift in (0..64).step_by(7) {
+			let byte = self.data[self.pos];
+			self.pos += 1;
+			value |= u64::from(byte & 0x7F) << shift;
+			if byte & 0x80 == 0 {
+				return value;
+			}
+		}
+		panic!("varint longer than 10 bytes at {}", self.pos);
+	}
+
+	fn svarint(&mut self) -> i64 {
+		let value = self.varint();
+		(value >> 1) as i64 ^ -((value & 1) as i64)
+	}
+
+	fn bytes(&mut self) -> &'a [u8] {
+		let length = self.varint() as usize;
+		let bytes = &self.data[self.pos..self.pos + length];
+		self.pos += length;
+		bytes
+	}
+
+	/// Returns the field number and wire type of the next field.
+	fn tag(&mut self) -> (u64, u64) {
+		let tag = self.varint();
+		(tag >> 3, tag & 7)
+	}
+
+	fn skip(&mut self, wire_type: u64) {
+		match wire_type {
+			0 => {
+				self.varint();
+			}
+			1 => self.pos += 8,
+			2 => {
+				self.bytes();
+			}
+			5 => self.pos += 4,
+			_ => panic!("unknown wire type {wire_type}"),
+		}
+	}
+}
+
+fn read_fontstacks(data: &[u8]) -> Vec<Fontstack> {
+	let mut reader = Reader::new(data);
+	let mut stacks = Vec::new();
+	while !reader.is_done() {
+		match reader.tag() {
+			(1, 2) => stacks.push(read_fontstack(reader.bytes())),
+			(_, wire_type) => reader.skip(wire_type),
+		}
+	}
+	stacks
+}
+
+fn read_fontstack(data: &[u8]) -> Fontstack {
+	let mut reader = Reader::new(data);
+	let mut stack = Fontstack::default();
+	while !reader.is_done() {
+		match reader.tag() {
+			(1, 2) => stack.name = String::from_utf8(reader.bytes().to_vec()).unwrap(),
+			(2, 2) => stack.range = String::from_utf8(reader.bytes().to_vec()).unwrap(),
+			(3, 2) => stack.glyphs.push(read_glyph(reader.bytes())),
+			(_, wire_type) => reader.skip(wire_type),
+		}
+	}
+	stack
+}
+
+fn read_glyph(data: &[u8]) -> Glyph {
+	let mut reader = Reader::new(data);
+	let mut glyph = Glyph::default();
+	while !reader.is_done() {
+		match reader.tag() {
+			(1, 0) => glyph.id = reader.varint() as u32,
+			(2, 2) => glyph.bitmap = Some(reader.bytes().to_vec()),
+			(3, 0) => glyph.width = reader.varint() as u32,
+			(4, 0) => glyph.height = reader.varint() as u32,
+			(5, 0) => glyph.left = reader.svarint() as i32,
+			(6, 0) => glyph.top = reader.svarint() as i32,
+			(7, 0) => glyph.advance = reader.varint() as u32,
+			(_, wire_type) => reader.skip(wire_type),
+		}
+	}
+	glyph
+}
+
+#[test]
+fn test_rendered_block_matches_independent_reader() {
+	let data = std::fs::read(concat!(
+		env!("CARGO_MANIFEST_DIR"),
+		"/testdata/Fira Sans - Regular.ttf"
+	))
+	.unwrap();
+	let font = FontWrapper::from(FontFileEntry::new(data).unwrap());
+	let renderer = Renderer::new_precise();
+
+	let blocks = font.get_blocks();
+	let block = blocks.iter().find(|block| block.start_index == 0).unwrap();
+	let pbf = block
+		.render(String::from("Fira Sans Regular"), &renderer)
+		.unwrap();
+
+	let stacks = read_fontstacks(&pbf);
+	assert_eq!(stacks.len(), 1);
+	let stack = &stacks[0];
+	assert_eq!(stack.name, "Fira Sans Regular");
+	assert_eq!(stack.range, "0-255");
+
+	for id in [65, 96, 230] {
+		let glyph = stack.glyphs.iter().find(|glyph| glyph.id == id).unwrap();
+		let expected = renderer
+			.render_glyph(&font.files[0].face, id)
+			.unwrap_or_else(|| panic!("glyph {id} is missing"));
+
+		assert_eq!(
+			(
+				glyph.id,
+				glyph.width,
+				glyph.height,
+				glyph.left,
+				glyph.top,
+				glyph.advance
+			),
+			(
+				expected.id,
+				expected.width,
+				expected.height,
+				expected.left,
+				expected.top,
+				expected.advance
+			),
+			"glyph {id}"
+		);
+		assert!(glyph.width > 0 && glyph.height > 0, "glyph {id}");
+
+		let bitmap = glyph.bitmap.as_ref().unwrap();
+		assert_eq!(
+			bitmap.len() as u32,
+			(glyph.width + 2 * BORDER) * (glyph.height + 2 * BORDER),
+			"glyph {id}"
+		);
+		assert_eq!(Some(bitmap), expected.bitmap.as_ref(), "glyph {id}");
+	}
+}